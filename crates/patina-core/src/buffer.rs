@@ -5,6 +5,14 @@
 
 use ropey::Rope;
 use std::str::FromStr;
+use std::sync::atomic::{AtomicU64, Ordering};
+
+/// Source of revisions, shared so no two buffers' contents get the same one
+static NEXT_REVISION: AtomicU64 = AtomicU64::new(1);
+
+fn next_revision() -> u64 {
+    NEXT_REVISION.fetch_add(1, Ordering::Relaxed)
+}
 
 /// A text buffer backed by a rope data structure.
 #[derive(Debug, Clone)]
@@ -13,6 +21,8 @@ pub struct Buffer {
     rope: Rope,
     /// Whether the buffer has been modified since last save
     modified: bool,
+    /// Changes with every edit, for caches of anything derived from the text
+    revision: u64,
 }
 
 impl Buffer {
@@ -21,6 +31,7 @@ impl Buffer {
         Self {
             rope: Rope::new(),
             modified: false,
+            revision: next_revision(),
         }
     }
 
@@ -29,6 +40,7 @@ impl Buffer {
         Self {
             rope: Rope::from_str(text),
             modified: false,
+            revision: next_revision(),
         }
     }
 }
@@ -75,12 +87,14 @@ impl Buffer {
     pub fn insert(&mut self, char_idx: usize, text: &str) {
         self.rope.insert(char_idx, text);
         self.modified = true;
+        self.revision = next_revision();
     }
 
     /// Delete a range of characters
     pub fn delete(&mut self, start: usize, end: usize) {
         self.rope.remove(start..end);
         self.modified = true;
+        self.revision = next_revision();
     }
 
     /// Replace a range with new text
@@ -88,6 +102,7 @@ impl Buffer {
         self.rope.remove(start..end);
         self.rope.insert(start, text);
         self.modified = true;
+        self.revision = next_revision();
     }
 
     /// Convert a line and column to a character index
@@ -114,6 +129,12 @@ impl Buffer {
         self.modified
     }
 
+    /// Identifies the buffer's current text: it changes on every edit and is
+    /// never shared with another buffer's text
+    pub fn revision(&self) -> u64 {
+        self.revision
+    }
+
     /// Mark buffer as saved (not modified)
     pub fn mark_saved(&mut self) {
         self.modified = false;
@@ -165,6 +186,16 @@ mod tests {
         assert!(buf.is_modified());
     }
 
+    #[test]
+    fn test_revision() {
+        let mut buf = Buffer::from_text("Hello");
+        let first = buf.revision();
+        assert_ne!(Buffer::from_text("Hello").revision(), first);
+        assert_eq!(buf.clone().revision(), first);
+        buf.insert(5, "!");
+        assert_ne!(buf.revision(), first);
+    }
+
     #[test]
     fn test_slice() {
         let buf = Buffer::from_text("Hello World");
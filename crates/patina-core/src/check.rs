@@ -0,0 +1,335 @@
+//! Validation of markdown files: frontmatter, links and anchors.
+
+use crate::navigation::heading_slugs;
+use crate::{Document, Frontmatter};
+use comrak::nodes::{AstNode, NodeValue};
+use comrak::Arena;
+use std::fmt;
+use std::path::{Path, PathBuf};
+
+/// How serious a diagnostic is
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Severity {
+    Warning,
+    Error,
+}
+
+impl fmt::Display for Severity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Severity::Warning => write!(f, "warning"),
+            Severity::Error => write!(f, "error"),
+        }
+    }
+}
+
+/// A problem found in a document
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Diagnostic {
+    /// Line (1-indexed)
+    pub line: usize,
+    /// Column in chars (1-indexed)
+    pub column: usize,
+    /// Last column the problem covers, on the same line
+    pub end_column: usize,
+    pub severity: Severity,
+    /// Name of the check, like `broken-link`
+    pub rule: &'static str,
+    pub message: String,
+}
+
+impl Diagnostic {
+    /// Create an error
+    pub fn error(line: usize, column: usize, rule: &'static str, message: String) -> Self {
+        Self {
+            line,
+            column,
+            end_column: column,
+            severity: Severity::Error,
+            rule,
+            message,
+        }
+    }
+
+    /// Create a warning
+    pub fn warning(line: usize, column: usize, rule: &'static str, message: String) -> Self {
+        Self {
+            severity: Severity::Warning,
+            ..Self::error(line, column, rule, message)
+        }
+    }
+
+    /// Extend the diagnostic to `end_column` (inclusive)
+    pub fn until(mut self, end_column: usize) -> Self {
+        self.end_column = end_column.max(self.column);
+        self
+    }
+}
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}:{}: {}: {} [{}]",
+            self.line, self.column, self.severity, self.message, self.rule
+        )
+    }
+}
+
+/// Check a file's content; `path` locates relative links.
+///
+/// Lines are counted in `content`, including its frontmatter.
+pub fn check(content: &str, path: Option<&Path>) -> Vec<Diagnostic> {
+    let mut diagnostics = check_frontmatter(content);
+
+    let mut doc = Document::from_content(content);
+    doc.path = path.map(Path::to_path_buf);
+    let offset = body_line_offset(content);
+    diagnostics.extend(check_links(&doc).into_iter().map(|mut diagnostic| {
+        diagnostic.line += offset;
+        diagnostic
+    }));
+
+    diagnostics.sort_by_key(|d| (d.line, d.column));
+    diagnostics
+}
+
+/// Number of lines before the body of `content`, which `Document` leaves out
+/// when it splits off the frontmatter
+pub fn body_line_offset(content: &str) -> usize {
+    let (_, body) = Frontmatter::extract(content);
+    content[..content.len() - body.len()].matches('\n').count()
+}
+
+/// Frontmatter that is delimited but can't be parsed, at the parser's
+/// position when it gives one and the opening delimiter otherwise
+pub fn check_frontmatter(content: &str) -> Vec<Diagnostic> {
+    let Some(error) = Frontmatter::error(content) else {
+        return Vec::new();
+    };
+    let (line, column) = Frontmatter::error_location(content).unwrap_or_else(|| {
+        let delimiter = content.len() - content.trim_start().len();
+        (content[..delimiter].matches('\n').count() + 1, 1)
+    });
+    vec![Diagnostic::error(
+        line,
+        column,
+        "frontmatter",
+        format!("invalid frontmatter: {}", error),
+    )]
+}
+
+/// Relative links to missing files and `#anchors` matching no heading.
+///
+/// Links resolve against the document's directory, or the current directory
+/// for an unsaved document. Lines are in the document's body.
+pub fn check_links(doc: &Document) -> Vec<Diagnostic> {
+    let base = doc
+        .path
+        .as_deref()
+        .and_then(Path::parent)
+        .map_or_else(|| PathBuf::from("."), Path::to_path_buf);
+    let own_slugs = heading_slugs(&doc.headings());
+
+    let arena = Arena::new();
+    let root = doc.parse(&arena);
+    let mut diagnostics = Vec::new();
+    for node in root.descendants() {
+        let Some((url, kind)) = link_target(node) else {
+            continue;
+        };
+        let sourcepos = node.data.borrow().sourcepos;
+        let text = doc
+            .buffer
+            .line(sourcepos.start.line.saturating_sub(1))
+            .unwrap_or_default();
+        let line = sourcepos.start.line;
+        let column = char_column(&text, sourcepos.start.column);
+        let end_column = if sourcepos.end.line == line {
+            char_column(&text, sourcepos.end.column)
+        } else {
+            text.trim_end().chars().count()
+        };
+
+        let (target, anchor) = match url.split_once('#') {
+            Some((target, anchor)) => (target, Some(percent_decode(anchor))),
+            None => (url.as_str(), None),
+        };
+        let target = target.split('?').next().unwrap_or(target);
+
+        if target.is_empty() {
+            if let Some(anchor) = anchor.filter(|a| !own_slugs.contains(&a.to_lowercase())) {
+                diagnostics.push(
+                    Diagnostic::error(
+                        line,
+                        column,
+                        "broken-anchor",
+                        format!("no heading for anchor #{}", anchor),
+                    )
+                    .until(end_column),
+                );
+            }
+            continue;
+        }
+        if is_external(target) {
+            continue;
+        }
+
+        let file = base.join(percent_decode(target));
+        if !file.exists() {
+            diagnostics.push(
+                Diagnostic::error(
+                    line,
+                    column,
+                    "broken-link",
+                    format!("{} target not found: {}", kind, target),
+                )
+                .until(end_column),
+            );
+            continue;
+        }
+        let Some(anchor) = anchor.filter(|a| !a.is_empty()) else {
+            continue;
+        };
+        if !is_markdown(&file) {
+            continue;
+        }
+        let slugs = Document::from_file(file)
+            .map(|target| heading_slugs(&target.headings()))
+            .unwrap_or_default();
+        if !slugs.contains(&anchor.to_lowercase()) {
+            diagnostics.push(
+                Diagnostic::error(
+                    line,
+                    column,
+                    "broken-anchor",
+                    format!("no heading for anchor #{} in {}", anchor, target),
+                )
+                .until(end_column),
+            );
+        }
+    }
+    diagnostics
+}
+
+/// Char column of a 1-indexed byte column in `line`, as comrak reports them
+pub(crate) fn char_column(line: &str, byte_column: usize) -> usize {
+    let mut end = byte_column.saturating_sub(1).min(line.len());
+    while !line.is_char_boundary(end) {
+        end -= 1;
+    }
+    line[..end].chars().count() + 1
+}
+
+/// URL of a link or image node, with what to call it in messages
+fn link_target<'a>(node: &'a AstNode<'a>) -> Option<(String, &'static str)> {
+    match &node.data.borrow().value {
+        NodeValue::Link(link) => Some((link.url.clone(), "link")),
+        NodeValue::Image(link) => Some((link.url.clone(), "image")),
+        _ => None,
+    }
+}
+
+/// Whether a link has a scheme (`https:`, `mailto:`) or is protocol-relative
+fn is_external(url: &str) -> bool {
+    if url.starts_with("//") {
+        return true;
+    }
+    let Some((scheme, _)) = url.split_once(':') else {
+        return false;
+    };
+    // A one-letter "scheme" is a Windows drive
+    scheme.len() > 1
+        && scheme.starts_with(|c: char| c.is_ascii_alphabetic())
+        && scheme
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '+' | '-' | '.'))
+}
+
+fn is_markdown(path: &Path) -> bool {
+    path.extension()
+        .is_some_and(|ext| ext == "md" || ext == "markdown")
+}
+
+/// Decode `%XX` escapes, keeping invalid ones as written
+fn percent_decode(text: &str) -> String {
+    let bytes = text.as_bytes();
+    let mut out = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        let hex = bytes
+            .get(i + 1..i + 3)
+            .and_then(|hex| std::str::from_utf8(hex).ok())
+            .and_then(|hex| u8::from_str_radix(hex, 16).ok());
+        match (bytes[i], hex) {
+            (b'%', Some(byte)) => {
+                out.push(byte);
+                i += 3;
+            }
+            (byte, _) => {
+                out.push(byte);
+                i += 1;
+            }
+        }
+    }
+    String::from_utf8_lossy(&out).into_owned()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_anchors() {
+        let content = "# Intro\n\nSee [setup](#setup) and [intro](#Intro).\n";
+        let diagnostics = check(content, None);
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(diagnostics[0].rule, "broken-anchor");
+        assert_eq!((diagnostics[0].line, diagnostics[0].column), (3, 5));
+        assert_eq!(
+            diagnostics[0].to_string(),
+            "3:5: error: no heading for anchor #setup [broken-anchor]"
+        );
+    }
+
+    #[test]
+    fn test_files_and_frontmatter_offset() {
+        let dir = std::env::temp_dir().join(format!("patina-check-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(dir.join("other file.md"), "# Usage\n").unwrap();
+        let content = "---\ntitle: Links\n---\n\n[ok](other%20file.md#usage)\n\
+                       [bad anchor](other%20file.md#nope)\n![gone](img/missing.png)\n\
+                       <https://example.com> [mail](mailto:a@b.c)\n";
+        let diagnostics = check(content, Some(&dir.join("doc.md")));
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        let found: Vec<(usize, &str)> = diagnostics.iter().map(|d| (d.line, d.rule)).collect();
+        assert_eq!(found, vec![(6, "broken-anchor"), (7, "broken-link")]);
+        assert_eq!(
+            diagnostics[1].message,
+            "image target not found: img/missing.png"
+        );
+    }
+
+    #[test]
+    fn test_invalid_frontmatter() {
+        let diagnostics = check("\n---\ntitle: [oops\n---\n# Body\n", None);
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(
+            (
+                diagnostics[0].line,
+                diagnostics[0].column,
+                diagnostics[0].rule
+            ),
+            (4, 1, "frontmatter")
+        );
+        assert_eq!(diagnostics[0].severity, Severity::Error);
+
+        let diagnostics = check("---\ntitle: x\n  bad: indent\n---\n", None);
+        assert_eq!((diagnostics[0].line, diagnostics[0].column), (3, 6));
+
+        // Without a position from the parser the delimiter line is used
+        let diagnostics = check("\n---\n- a list\n---\n", None);
+        assert_eq!((diagnostics[0].line, diagnostics[0].column), (2, 1));
+    }
+}
@@ -1,6 +1,6 @@
 //! Document model combining buffer, frontmatter, and file metadata.
 
-use crate::{Buffer, Frontmatter, History, MarkdownParser};
+use crate::{Buffer, Frontmatter, History, MarkdownParser, SourceHighlighter};
 use comrak::{nodes::AstNode, Arena};
 use std::path::PathBuf;
 use std::str::FromStr;
@@ -26,6 +26,8 @@ pub struct Document {
     cached_html: Option<String>,
     /// Whether the cached HTML is stale
     html_dirty: bool,
+    /// Source highlighting state cache for the editor
+    highlighter: SourceHighlighter,
 }
 
 impl Document {
@@ -41,6 +43,7 @@ impl Document {
             parser: MarkdownParser::new(),
             cached_html: None,
             html_dirty: true,
+            highlighter: SourceHighlighter::new(),
         }
     }
 
@@ -66,6 +69,7 @@ impl Document {
             parser: MarkdownParser::new(),
             cached_html: None,
             html_dirty: true,
+            highlighter: SourceHighlighter::new(),
         }
     }
 }
@@ -135,6 +139,18 @@ impl Document {
     /// Mark the document as needing a re-parse
     pub fn invalidate_cache(&mut self) {
         self.html_dirty = true;
+        self.highlighter.clear();
+    }
+
+    /// Mark the document as edited at `line` (cached state before it stays valid)
+    pub fn invalidate_from_line(&mut self, line: usize) {
+        self.html_dirty = true;
+        self.highlighter.invalidate_from(line);
+    }
+
+    /// Source highlighter for the editor pane
+    pub fn source_highlighter(&self) -> &SourceHighlighter {
+        &self.highlighter
     }
 
     /// Get the rendered HTML (cached, updates if dirty)
@@ -153,6 +169,11 @@ impl Document {
         self.parser.parse(arena, &self.buffer.text())
     }
 
+    /// The parser used for the document
+    pub fn parser(&self) -> &MarkdownParser {
+        &self.parser
+    }
+
     /// Extract headings from the document for outline
     pub fn headings(&self) -> Vec<crate::parser::Heading> {
         self.parser.extract_headings(&self.buffer.text())
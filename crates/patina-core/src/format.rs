@@ -0,0 +1,641 @@
+//! Markdown formatter that normalizes style in place.
+//!
+//! Rather than printing the AST back out, which would rewrite every construct
+//! it touches, the formatter edits the source at positions the parser reports.
+//! Frontmatter, code blocks and HTML blocks are left exactly as written.
+
+use crate::table::Table;
+use crate::{Frontmatter, MarkdownParser};
+use comrak::nodes::{AstNode, ListType, NodeValue};
+use comrak::Arena;
+use std::collections::HashMap;
+use unicode_width::UnicodeWidthStr;
+
+/// How headings are written
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum HeadingStyle {
+    /// `# Title`
+    #[default]
+    Atx,
+    /// `Title` underlined with `=` or `-` for levels 1 and 2, ATX below
+    Setext,
+}
+
+/// Normalizes list markers, emphasis, headings, tables and paragraph wrapping
+#[derive(Debug, Clone)]
+pub struct Formatter {
+    width: usize,
+    list_marker: char,
+    emphasis: char,
+    strong: char,
+    heading_style: HeadingStyle,
+}
+
+/// What a line of output is, for the whitespace cleanup after rewriting
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Kind {
+    /// Code or HTML, kept byte for byte
+    Verbatim,
+    /// Paragraph text, whose trailing spaces may be a line break
+    Paragraph,
+    Other,
+}
+
+/// Lines `start..=end` (0-indexed) replaced by `lines`
+struct Rewrite {
+    start: usize,
+    end: usize,
+    lines: Vec<String>,
+    kind: Kind,
+}
+
+impl Formatter {
+    /// Create a formatter with `-` bullets, `*` emphasis, ATX headings and no wrapping
+    pub fn new() -> Self {
+        Self {
+            width: 0,
+            list_marker: '-',
+            emphasis: '*',
+            strong: '*',
+            heading_style: HeadingStyle::Atx,
+        }
+    }
+
+    /// Wrap paragraphs at `width` columns (0 leaves line breaks alone)
+    pub fn width(mut self, width: usize) -> Self {
+        self.width = width;
+        self
+    }
+
+    /// Bullet list marker: `-`, `*` or `+`
+    pub fn list_marker(mut self, marker: char) -> Self {
+        if matches!(marker, '-' | '*' | '+') {
+            self.list_marker = marker;
+        }
+        self
+    }
+
+    /// Emphasis delimiter: `*` or `_`
+    pub fn emphasis(mut self, delimiter: char) -> Self {
+        if matches!(delimiter, '*' | '_') {
+            self.emphasis = delimiter;
+        }
+        self
+    }
+
+    /// Strong emphasis delimiter, doubled: `*` or `_`
+    pub fn strong(mut self, delimiter: char) -> Self {
+        if matches!(delimiter, '*' | '_') {
+            self.strong = delimiter;
+        }
+        self
+    }
+
+    /// How headings are written
+    pub fn heading_style(mut self, style: HeadingStyle) -> Self {
+        self.heading_style = style;
+        self
+    }
+
+    /// Format markdown, keeping any frontmatter as written
+    pub fn format(&self, content: &str) -> String {
+        let (_, body) = Frontmatter::extract(content);
+        let frontmatter = &content[..content.len() - body.len()];
+        format!("{}{}", frontmatter, self.format_body(body))
+    }
+
+    /// Format markdown without frontmatter, like a document's buffer
+    pub fn format_body(&self, body: &str) -> String {
+        if body.trim().is_empty() {
+            return String::new();
+        }
+        let parser = MarkdownParser::new();
+        let arena = Arena::new();
+        let root = parser.parse(&arena, body);
+        let mut lines: Vec<String> = body.lines().map(String::from).collect();
+
+        // Marker and delimiter changes keep every byte offset in place, so they
+        // go first and the block rewrites below see their result
+        for (line, byte, replacement) in self.inline_edits(root, &lines) {
+            if let Some(text) = lines.get_mut(line) {
+                if text.is_char_boundary(byte) && text.is_char_boundary(byte + 1) {
+                    text.replace_range(byte..byte + 1, replacement);
+                }
+            }
+        }
+
+        let mut verbatim = vec![false; lines.len()];
+        let mut rewrites = Vec::new();
+        for node in root.descendants() {
+            let ast = node.data.borrow();
+            let sourcepos = ast.sourcepos;
+            let start = sourcepos.start.line.saturating_sub(1);
+            let end = sourcepos.end.line.saturating_sub(1).min(lines.len() - 1);
+            let prefix_len = sourcepos.start.column.saturating_sub(1);
+            match &ast.value {
+                NodeValue::CodeBlock(_) | NodeValue::HtmlBlock(_) => {
+                    for slot in &mut verbatim[start..=end] {
+                        *slot = true;
+                    }
+                }
+                NodeValue::Heading(heading) => {
+                    rewrites.extend(self.heading(&lines, start, prefix_len, heading.level));
+                }
+                NodeValue::Table(_) => rewrites.extend(table(&lines, start, end, prefix_len)),
+                NodeValue::Paragraph if !in_table(node) => {
+                    // Link reference definitions the parser took out of the
+                    // paragraph are still in its lines, and must stay on their own
+                    let definitions = lines[start]
+                        .get(prefix_len..)
+                        .is_some_and(is_link_definition);
+                    let reflow = self.width > 0 && !has_raw_inlines(node) && !definitions;
+                    rewrites.push(self.paragraph(&lines, start, end, prefix_len, reflow));
+                }
+                _ => {}
+            }
+        }
+        rewrites.sort_by_key(|rewrite| rewrite.start);
+
+        // Splice the rewrites in, remembering what each output line is
+        let mut out: Vec<(String, Kind)> = Vec::new();
+        let mut rewrites = rewrites.into_iter().peekable();
+        let mut idx = 0;
+        while idx < lines.len() {
+            // A rewrite inside an earlier one's lines is dropped
+            while rewrites.next_if(|rewrite| rewrite.start < idx).is_some() {}
+            match rewrites.next_if(|rewrite| rewrite.start == idx) {
+                Some(rewrite) => {
+                    out.extend(rewrite.lines.into_iter().map(|line| (line, rewrite.kind)));
+                    idx = rewrite.end + 1;
+                }
+                None => {
+                    let kind = if verbatim[idx] {
+                        Kind::Verbatim
+                    } else {
+                        Kind::Other
+                    };
+                    out.push((lines[idx].clone(), kind));
+                    idx += 1;
+                }
+            }
+        }
+
+        // Trailing whitespace goes, and runs of blank lines become one
+        let mut text = String::new();
+        let mut blank_run = true;
+        for (line, kind) in out {
+            let line = match kind {
+                Kind::Other => line.trim_end().to_string(),
+                Kind::Verbatim | Kind::Paragraph => line,
+            };
+            let blank = kind != Kind::Verbatim && line.trim().is_empty();
+            if blank && blank_run {
+                continue;
+            }
+            blank_run = blank;
+            text.push_str(if blank { "" } else { &line });
+            text.push('\n');
+        }
+        while text.ends_with("\n\n") {
+            text.pop();
+        }
+        text
+    }
+
+    /// Length-preserving replacements of list markers and emphasis delimiters,
+    /// as `(line, byte, replacement)` with 0-indexed lines
+    fn inline_edits<'a>(
+        &self,
+        root: &'a AstNode<'a>,
+        lines: &[String],
+    ) -> Vec<(usize, usize, &'static str)> {
+        let mut edits = Vec::new();
+        // Delimiters of `***both***` are shared by nested nodes with the same
+        // position; count how many each start has handed out
+        let mut claimed: HashMap<(usize, usize), usize> = HashMap::new();
+        for node in root.descendants() {
+            let ast = node.data.borrow();
+            let (start, end) = (ast.sourcepos.start, ast.sourcepos.end);
+            if start.line == 0 {
+                continue;
+            }
+            let byte_at = |line: usize, column: usize| {
+                lines
+                    .get(line - 1)
+                    .and_then(|text| text.as_bytes().get(column.wrapping_sub(1)).copied())
+            };
+            match &ast.value {
+                // Lists told apart only by their markers would merge into one
+                NodeValue::Item(item)
+                    if item.list_type == ListType::Bullet
+                        && !node.parent().is_some_and(beside_bullet_list) =>
+                {
+                    let marker = byte_at(start.line, start.column).map(char::from);
+                    if marker.is_some_and(|c| c != self.list_marker && matches!(c, '-' | '*' | '+'))
+                    {
+                        edits.push((
+                            start.line - 1,
+                            start.column - 1,
+                            marker_str(self.list_marker),
+                        ));
+                    }
+                }
+                NodeValue::Emph | NodeValue::Strong => {
+                    let (count, wanted) = match ast.value {
+                        NodeValue::Emph => (1, self.emphasis),
+                        _ => (2, self.strong),
+                    };
+                    let inset = claimed.entry((start.line, start.column)).or_default();
+                    let open = start.column + *inset;
+                    let close = end.column.saturating_sub(*inset + count - 1);
+                    *inset += count;
+                    let current = byte_at(start.line, open).map(char::from);
+                    if current == Some(wanted) || !matches!(current, Some('*' | '_')) {
+                        continue;
+                    }
+                    // `_` only delimits at word boundaries
+                    if wanted == '_' {
+                        let before = open
+                            .checked_sub(2)
+                            .and_then(|col| byte_at(start.line, col + 1));
+                        let after = byte_at(end.line, close + count);
+                        if [before, after]
+                            .into_iter()
+                            .flatten()
+                            .any(|b| b.is_ascii_alphanumeric())
+                        {
+                            continue;
+                        }
+                    }
+                    for offset in 0..count {
+                        edits.push((start.line - 1, open - 1 + offset, marker_str(wanted)));
+                        edits.push((end.line - 1, close - 1 + offset, marker_str(wanted)));
+                    }
+                }
+                _ => {}
+            }
+        }
+        edits
+    }
+
+    /// A heading in the configured style; setext headings inside containers
+    /// are left alone
+    fn heading(
+        &self,
+        lines: &[String],
+        start: usize,
+        prefix_len: usize,
+        level: u8,
+    ) -> Option<Rewrite> {
+        let first = lines.get(start)?;
+        let prefix = first.get(..prefix_len)?;
+        let content = &first[prefix_len..];
+
+        let (text, end) = if content.starts_with('#') {
+            (atx_text(content), start)
+        } else {
+            if !prefix.is_empty() {
+                return None;
+            }
+            // Setext content runs until its `===` or `---` underline
+            let underline =
+                (start + 1..lines.len()).find(|&idx| is_setext_underline(&lines[idx]))?;
+            let text: Vec<&str> = lines[start..underline].iter().map(|l| l.trim()).collect();
+            (text.join(" "), underline)
+        };
+
+        let lines = match (self.heading_style, level) {
+            (HeadingStyle::Setext, 1 | 2) if prefix.is_empty() && !text.is_empty() => {
+                let underline = if level == 1 { "=" } else { "-" };
+                vec![text.clone(), underline.repeat(text.width().max(3))]
+            }
+            _ if text.is_empty() => vec![format!("{}{}", prefix, "#".repeat(level as usize))],
+            _ => vec![format!("{}{} {}", prefix, "#".repeat(level as usize), text)],
+        };
+        Some(Rewrite {
+            start,
+            end,
+            lines,
+            kind: Kind::Other,
+        })
+    }
+
+    /// A paragraph with normalized line ends, re-wrapped if `reflow`
+    fn paragraph(
+        &self,
+        lines: &[String],
+        start: usize,
+        end: usize,
+        prefix_len: usize,
+        reflow: bool,
+    ) -> Rewrite {
+        let first = &lines[start];
+        let prefix_len = prefix_len.min(first.len());
+        let prefix = &first[..prefix_len];
+        // Continuation lines line up under the text, inside any `>` quotes
+        let indent: String = prefix
+            .chars()
+            .map(|c| if c == '>' || c == '\t' { c } else { ' ' })
+            .collect();
+
+        let mut contents = vec![first[prefix_len..].to_string()];
+        for line in &lines[start + 1..=end] {
+            let content = match line.strip_prefix(indent.trim_end()) {
+                Some(rest) => rest.trim_start(),
+                None => line.trim_start_matches(|c: char| c.is_whitespace() || c == '>'),
+            };
+            contents.push(content.to_string());
+        }
+
+        // Each segment ends in a hard break, or the end of the paragraph
+        let mut segments: Vec<(String, &str)> = Vec::new();
+        let mut segment = Vec::new();
+        let last = contents.len() - 1;
+        for (idx, content) in contents.iter().enumerate() {
+            let trimmed = content.trim_end();
+            let hard_break = if idx == last {
+                ""
+            } else if trimmed.ends_with('\\') {
+                "\\"
+            } else if content.ends_with("  ") {
+                "  "
+            } else {
+                ""
+            };
+            segment.push(trimmed.trim_end_matches('\\').to_string());
+            if !hard_break.is_empty() || idx == last {
+                segments.push((segment.join("\n"), hard_break));
+                segment.clear();
+            }
+        }
+
+        let mut out: Vec<String> = Vec::new();
+        for (text, hard_break) in segments {
+            let segment_lines: Vec<String> = if reflow {
+                let width = self.width.saturating_sub(indent.width()).max(1);
+                wrap_words(&text, width)
+            } else {
+                text.split('\n').map(String::from).collect()
+            };
+            for line in segment_lines {
+                out.push(line);
+            }
+            if let Some(line) = out.last_mut() {
+                line.push_str(hard_break);
+            }
+        }
+        let lines = out
+            .into_iter()
+            .enumerate()
+            .map(|(idx, line)| match idx {
+                0 => format!("{}{}", prefix, line),
+                _ => format!("{}{}", indent, line),
+            })
+            .collect();
+        Rewrite {
+            start,
+            end,
+            lines,
+            kind: Kind::Paragraph,
+        }
+    }
+}
+
+impl Default for Formatter {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+fn marker_str(c: char) -> &'static str {
+    match c {
+        '*' => "*",
+        '+' => "+",
+        '_' => "_",
+        _ => "-",
+    }
+}
+
+/// Text of an ATX heading line without its `#` markers
+fn atx_text(content: &str) -> String {
+    let text = content.trim_start_matches('#').trim();
+    // A closing sequence needs a space before it
+    let without_closing = text.trim_end_matches('#');
+    if without_closing.is_empty() {
+        String::new()
+    } else if without_closing.ends_with([' ', '\t']) {
+        without_closing.trim_end().to_string()
+    } else {
+        text.to_string()
+    }
+}
+
+fn is_setext_underline(line: &str) -> bool {
+    let line = line.trim();
+    !line.is_empty() && (line.chars().all(|c| c == '=') || line.chars().all(|c| c == '-'))
+}
+
+/// A table laid out with aligned columns, keeping a container prefix
+fn table(lines: &[String], start: usize, end: usize, prefix_len: usize) -> Option<Rewrite> {
+    let prefix = lines[start].get(..prefix_len)?;
+    let rows = lines[start..=end]
+        .iter()
+        .map(|line| line.strip_prefix(prefix))
+        .collect::<Option<Vec<&str>>>()?;
+    let table = Table::parse(&rows)?;
+    Some(Rewrite {
+        start,
+        end,
+        lines: table
+            .format()
+            .into_iter()
+            .map(|line| format!("{}{}", prefix, line))
+            .collect(),
+        kind: Kind::Other,
+    })
+}
+
+/// Whether a line starts like a link reference definition, `[label]: `
+fn is_link_definition(line: &str) -> bool {
+    line.trim_start()
+        .strip_prefix('[')
+        .and_then(|rest| rest.split_once("]:"))
+        .is_some_and(|(label, _)| !label.trim().is_empty() && !label.contains(['[', ']']))
+}
+
+/// Whether a list directly follows or precedes another bullet list
+fn beside_bullet_list<'a>(list: &'a AstNode<'a>) -> bool {
+    [list.previous_sibling(), list.next_sibling()]
+        .into_iter()
+        .flatten()
+        .any(|sibling| {
+            matches!(
+                &sibling.data.borrow().value,
+                NodeValue::List(list) if list.list_type == ListType::Bullet
+            )
+        })
+}
+
+fn in_table<'a>(node: &'a AstNode<'a>) -> bool {
+    node.ancestors()
+        .any(|n| matches!(n.data.borrow().value, NodeValue::TableCell))
+}
+
+/// Whether a paragraph holds math or inline HTML, whose line breaks may matter
+fn has_raw_inlines<'a>(node: &'a AstNode<'a>) -> bool {
+    node.descendants().any(|n| {
+        matches!(
+            n.data.borrow().value,
+            NodeValue::Math(_) | NodeValue::HtmlInline(_)
+        )
+    })
+}
+
+/// Words of `text`, keeping code spans whole
+fn words(text: &str) -> Vec<String> {
+    let chars: Vec<char> = text.chars().collect();
+    let mut words = Vec::new();
+    let mut word = String::new();
+    let mut i = 0;
+    while i < chars.len() {
+        let c = chars[i];
+        if c == '`' {
+            let run = chars[i..].iter().take_while(|&&c| c == '`').count();
+            let close = (i + run..chars.len()).find(|&j| {
+                chars[j..].iter().take_while(|&&c| c == '`').count() == run
+                    && (j == 0 || chars[j - 1] != '`')
+            });
+            let stop = close.map_or(i + run, |j| j + run);
+            word.extend(&chars[i..stop]);
+            i = stop;
+        } else if c.is_whitespace() {
+            if !word.is_empty() {
+                words.push(std::mem::take(&mut word));
+            }
+            i += 1;
+        } else {
+            word.push(c);
+            i += 1;
+        }
+    }
+    if !word.is_empty() {
+        words.push(word);
+    }
+    words
+}
+
+/// Whether a word at the start of a line would begin a block instead of
+/// continuing the paragraph
+fn starts_block(word: &str) -> bool {
+    let digits = word.chars().take_while(char::is_ascii_digit).count();
+    matches!(word, "-" | "+" | "*" | "|")
+        || (1..=6).any(|n| word == "#".repeat(n))
+        || word.starts_with(['>', '|'])
+        || word.starts_with("```")
+        || word.starts_with("~~~")
+        || is_setext_underline(word)
+        || (digits > 0 && digits <= 9 && matches!(&word[digits..], "." | ")"))
+}
+
+/// Greedily fill lines of at most `width` columns
+fn wrap_words(text: &str, width: usize) -> Vec<String> {
+    let mut lines: Vec<String> = Vec::new();
+    let mut line = String::new();
+    for word in words(text) {
+        if line.is_empty() {
+            line = word;
+        } else if line.width() + 1 + word.width() <= width || starts_block(&word) {
+            line.push(' ');
+            line.push_str(&word);
+        } else {
+            lines.push(std::mem::take(&mut line));
+            line = word;
+        }
+    }
+    lines.push(line);
+    lines
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_markers_and_emphasis() {
+        let input = "* one with _emph_ and __strong__\n* two with ***both***\n\nsnake_case *x*y\n";
+        assert_eq!(
+            Formatter::new().format(input),
+            "- one with *emph* and **strong**\n- two with ***both***\n\nsnake_case *x*y\n"
+        );
+        let underscores = Formatter::new().emphasis('_').strong('_').list_marker('*');
+        assert_eq!(
+            underscores.format(input),
+            "* one with _emph_ and __strong__\n* two with ___both___\n\nsnake_case *x*y\n"
+        );
+    }
+
+    #[test]
+    fn test_adjacent_lists_keep_markers() {
+        let input = "* a\n* b\n+ c\n\n* d\n";
+        assert_eq!(Formatter::new().format(input), "* a\n* b\n+ c\n\n* d\n");
+        assert_eq!(
+            Formatter::new().format("- a\n- b\n* c\n"),
+            "- a\n- b\n* c\n"
+        );
+    }
+
+    #[test]
+    fn test_headings() {
+        let input = "Title\n=====\n\n##   Usage ##\n\n> ### Quoted #\n\nSub\n---\n";
+        assert_eq!(
+            Formatter::new().format(input),
+            "# Title\n\n## Usage\n\n> ### Quoted\n\n## Sub\n"
+        );
+        let setext = Formatter::new().heading_style(HeadingStyle::Setext);
+        assert_eq!(
+            setext.format("# Title\n\n## Usage\n\n### Deep\n"),
+            "Title\n=====\n\nUsage\n-----\n\n### Deep\n"
+        );
+    }
+
+    #[test]
+    fn test_tables_and_whitespace() {
+        let input = "\n\n| a | long |\n|-|:-:|\n| 日本 | x |\n\n\n\ntext \nbreak   \nend\n\n";
+        assert_eq!(
+            Formatter::new().format(input),
+            "| a    | long |\n| ---- | :--: |\n| 日本 |  x   |\n\ntext\nbreak  \nend\n"
+        );
+    }
+
+    #[test]
+    fn test_wrap() {
+        let input = "- a list item whose text `keeps  code` together and wraps\n\n\
+                     > quoted text that is long enough to wrap, with a\\\n> hard break\n\n\
+                     Numbers 1. must not\nstart a line: 2.\n";
+        assert_eq!(
+            Formatter::new().width(24).format(input),
+            "- a list item whose text\n  `keeps  code` together\n  and wraps\n\n\
+             > quoted text that is\n> long enough to wrap,\n> with a\\\n> hard break\n\n\
+             Numbers 1. must not\nstart a line: 2.\n"
+        );
+    }
+
+    #[test]
+    fn test_link_definitions_not_reflowed() {
+        let input =
+            "[a]: http://example.com\n[b]: http://b.example\nSome text\n\nA paragraph\nto join\n";
+        assert_eq!(
+            Formatter::new().width(40).format(input),
+            "[a]: http://example.com\n[b]: http://b.example\nSome text\n\nA paragraph to join\n"
+        );
+    }
+
+    #[test]
+    fn test_code_and_frontmatter_untouched() {
+        let input = "---\ntitle:   x  \n---\n* a\n\n```\n* not   a list  \n\n\n_x_\n```\n\n<div>\n  kept  \n</div>\n";
+        assert_eq!(
+            Formatter::new().width(10).format(input),
+            "---\ntitle:   x  \n---\n- a\n\n```\n* not   a list  \n\n\n_x_\n```\n\n<div>\n  kept  \n</div>\n"
+        );
+    }
+}
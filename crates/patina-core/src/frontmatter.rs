@@ -69,6 +69,52 @@ impl Frontmatter {
         (None, content)
     }
 
+    /// Why delimited frontmatter at the start of `content` doesn't parse, if
+    /// it doesn't; `extract` leaves such frontmatter in the body
+    pub fn error(content: &str) -> Option<String> {
+        let trimmed = content.trim_start();
+        ["---", "+++"].into_iter().find_map(|delimiter| {
+            let rest = trimmed.strip_prefix(delimiter)?;
+            let end = rest.find(&format!("\n{}", delimiter))?;
+            let raw = rest[..end].trim();
+            let parsed = if delimiter == "---" {
+                Self::parse_yaml(raw)
+            } else {
+                Self::parse_toml(raw)
+            };
+            parsed.err()
+        })
+    }
+
+    /// Line and column (1-indexed, in `content`) where the parser gave up on
+    /// delimited frontmatter, when it says
+    pub fn error_location(content: &str) -> Option<(usize, usize)> {
+        let trimmed = content.trim_start();
+        ["---", "+++"].into_iter().find_map(|delimiter| {
+            let rest = trimmed.strip_prefix(delimiter)?;
+            let end = rest.find(&format!("\n{}", delimiter))?;
+            let raw = rest[..end].trim();
+            let (line, column) = if delimiter == "---" {
+                let location = serde_yaml::from_str::<serde_yaml::Value>(raw)
+                    .err()?
+                    .location()?;
+                (location.line(), location.column())
+            } else {
+                let span = toml::from_str::<toml::Value>(raw).err()?.span()?;
+                let before = &raw[..span.start.min(raw.len())];
+                let line_start = before.rfind('\n').map_or(0, |i| i + 1);
+                (
+                    before.matches('\n').count() + 1,
+                    before[line_start..].chars().count() + 1,
+                )
+            };
+            // `raw` starts on the first non-blank line after the delimiter
+            let start = content.len() - rest[..end].trim_start().len() - (rest.len() - end);
+            let first_line = content[..start].matches('\n').count() + 1;
+            Some((first_line + line - 1, column))
+        })
+    }
+
     /// Parse YAML frontmatter
     fn parse_yaml(raw: &str) -> Result<HashMap<String, Value>, String> {
         let yaml_value: serde_yaml::Value = serde_yaml::from_str(raw).map_err(|e| e.to_string())?;
@@ -143,6 +189,28 @@ mod tests {
         assert!(body.starts_with("# Hello"));
     }
 
+    #[test]
+    fn test_frontmatter_error() {
+        assert!(Frontmatter::error("---\ntitle: [unclosed\n---\nBody").is_some());
+        assert!(Frontmatter::error("+++\ntitle = \n+++\nBody").is_some());
+        assert_eq!(
+            Frontmatter::error("---\n- a list\n---\n").as_deref(),
+            Some("Frontmatter must be an object")
+        );
+        assert_eq!(Frontmatter::error("---\ntitle: Fine\n---\nBody"), None);
+        assert_eq!(Frontmatter::error("# No frontmatter"), None);
+
+        assert_eq!(
+            Frontmatter::error_location("---\ntitle: Fine\nlist: [a, b\n---\n"),
+            Some((4, 1))
+        );
+        assert_eq!(
+            Frontmatter::error_location("\n+++\n\ntitle = \"x\"\nbad = \n+++\n"),
+            Some((5, 6))
+        );
+        assert_eq!(Frontmatter::error_location("---\n- a list\n---\n"), None);
+    }
+
     #[test]
     fn test_no_frontmatter() {
         let content = "# Just a heading\n\nSome content.";
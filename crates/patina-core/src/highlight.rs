@@ -0,0 +1,750 @@
+//! Incremental markdown source highlighting.
+//!
+//! Tokenizes the raw markdown source line by line for the editor pane.
+//! Block-level context that spans lines (fenced code, frontmatter) is carried
+//! in a [`LineState`], and the state at the start of every line is cached so
+//! only lines after an edit need to be rescanned. The syntect state of fenced
+//! code is cached per line the same way.
+
+use crate::syntax::CodeState;
+use crate::Buffer;
+use std::cell::RefCell;
+use std::collections::BTreeMap;
+
+/// Kind of a highlighted source token
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TokenKind {
+    /// `#` markers of an ATX heading
+    HeadingMarker,
+    /// Heading text
+    Heading,
+    /// `*emphasis*` / `_emphasis_` (including delimiters)
+    Emphasis,
+    /// `**strong**` / `__strong__` (including delimiters)
+    Strong,
+    /// `~~strikethrough~~` (including delimiters)
+    Strikethrough,
+    /// Inline `code` span (including backticks)
+    CodeSpan,
+    /// Opening or closing code fence line
+    CodeFence,
+    /// Line inside a fenced code block
+    CodeBlock,
+    /// `[text]` part of a link or image
+    LinkText,
+    /// `(url)` part of a link, or an autolink
+    LinkUrl,
+    /// List bullet or number
+    ListMarker,
+    /// Task list checkbox
+    TaskMarker,
+    /// `>` blockquote marker
+    BlockquoteMarker,
+    /// Thematic break (`---`, `***`)
+    HorizontalRule,
+    /// Frontmatter `---` / `+++` delimiter
+    FrontmatterDelimiter,
+    /// Frontmatter key
+    FrontmatterKey,
+    /// Frontmatter value
+    FrontmatterValue,
+}
+
+/// A highlighted range within a line (char offsets, end exclusive)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Token {
+    pub kind: TokenKind,
+    pub start: usize,
+    pub end: usize,
+}
+
+/// Block context at the start of a line
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub enum LineState {
+    /// Ordinary markdown
+    #[default]
+    Normal,
+    /// Inside a frontmatter block opened by the given delimiter char (`-` or `+`)
+    Frontmatter(char),
+    /// Inside a fenced code block
+    Fence {
+        /// Fence character (`` ` `` or `~`)
+        marker: char,
+        /// Length of the opening fence
+        len: usize,
+        /// Language from the fence info string (may be empty)
+        lang: String,
+        /// Line of the opening fence (0-indexed)
+        start_line: usize,
+    },
+}
+
+/// Tokens for one line plus the block context it was scanned in
+#[derive(Debug, Clone)]
+pub struct LineHighlight {
+    /// State at the start of the line
+    pub state: LineState,
+    /// Highlighted ranges, in order and non-overlapping
+    pub tokens: Vec<Token>,
+}
+
+/// Markdown source highlighter with a per-line state cache
+#[derive(Debug, Default)]
+pub struct SourceHighlighter {
+    /// `states[i]` is the state at the start of line `i`
+    states: RefCell<Vec<LineState>>,
+    /// Syntect state at the start of fenced code lines
+    code_states: RefCell<BTreeMap<usize, CodeState>>,
+}
+
+impl SourceHighlighter {
+    /// Create a highlighter with an empty cache
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Drop cached states from `line` onwards (call after editing that line)
+    pub fn invalidate_from(&self, line: usize) {
+        self.states.borrow_mut().truncate(line + 1);
+        self.code_states.borrow_mut().split_off(&(line + 1));
+    }
+
+    /// Drop the whole cache
+    pub fn clear(&self) {
+        self.states.borrow_mut().clear();
+        self.code_states.borrow_mut().clear();
+    }
+
+    /// Cached syntect state at the start of the nearest line in
+    /// `after + 1..=line`, with that line
+    pub fn code_state(&self, after: usize, line: usize) -> Option<(usize, CodeState)> {
+        self.code_states
+            .borrow()
+            .range(after + 1..=line)
+            .next_back()
+            .map(|(&line, state)| (line, state.clone()))
+    }
+
+    /// Remember the syntect state at the start of `line`
+    pub fn set_code_state(&self, line: usize, state: CodeState) {
+        self.code_states.borrow_mut().insert(line, state);
+    }
+
+    /// Number of lines with a cached start state
+    pub fn cached_lines(&self) -> usize {
+        self.states.borrow().len()
+    }
+
+    /// Block state at the start of `line`, scanning forward from the last cached line
+    pub fn state_at(&self, buffer: &Buffer, line: usize) -> LineState {
+        let mut states = self.states.borrow_mut();
+        if states.is_empty() {
+            states.push(LineState::Normal);
+        }
+
+        while states.len() <= line {
+            let idx = states.len() - 1;
+            let text = buffer.line(idx).unwrap_or_default();
+            let next = scan_line(&text, idx, &states[idx]).1;
+            states.push(next);
+        }
+
+        states[line].clone()
+    }
+
+    /// Highlight a single line of the buffer
+    pub fn highlight(&self, buffer: &Buffer, line: usize) -> LineHighlight {
+        let state = self.state_at(buffer, line);
+        let text = buffer.line(line).unwrap_or_default();
+        let (tokens, _) = scan_line(&text, line, &state);
+        LineHighlight { state, tokens }
+    }
+}
+
+/// Tokenize one line given its start state, returning tokens and the state for the next line
+pub fn scan_line(line: &str, line_idx: usize, state: &LineState) -> (Vec<Token>, LineState) {
+    let chars: Vec<char> = line.trim_end_matches(['\n', '\r']).chars().collect();
+    let len = chars.len();
+    let whole = |kind| {
+        vec![Token {
+            kind,
+            start: 0,
+            end: len,
+        }]
+    };
+
+    match state {
+        LineState::Frontmatter(delim) => {
+            if is_frontmatter_delimiter(&chars, *delim) {
+                return (whole(TokenKind::FrontmatterDelimiter), LineState::Normal);
+            }
+            (frontmatter_tokens(&chars, *delim), state.clone())
+        }
+
+        LineState::Fence {
+            marker,
+            len: fence_len,
+            ..
+        } => {
+            if let Some((m, n, info)) = fence_open(&chars) {
+                if m == *marker && n >= *fence_len && info.is_empty() {
+                    return (whole(TokenKind::CodeFence), LineState::Normal);
+                }
+            }
+            (whole(TokenKind::CodeBlock), state.clone())
+        }
+
+        LineState::Normal => {
+            if line_idx == 0 {
+                for delim in ['-', '+'] {
+                    if is_frontmatter_delimiter(&chars, delim) {
+                        return (
+                            whole(TokenKind::FrontmatterDelimiter),
+                            LineState::Frontmatter(delim),
+                        );
+                    }
+                }
+            }
+
+            if let Some((marker, n, info)) = fence_open(&chars) {
+                // Backtick fences may not have backticks in the info string
+                if marker == '~' || !info.contains('`') {
+                    let lang = info.split_whitespace().next().unwrap_or("").to_string();
+                    return (
+                        whole(TokenKind::CodeFence),
+                        LineState::Fence {
+                            marker,
+                            len: n,
+                            lang,
+                            start_line: line_idx,
+                        },
+                    );
+                }
+            }
+
+            (block_tokens(&chars), LineState::Normal)
+        }
+    }
+}
+
+fn is_frontmatter_delimiter(chars: &[char], delim: char) -> bool {
+    let trimmed = trim_end(chars);
+    trimmed.len() == 3 && trimmed.iter().all(|&c| c == delim)
+}
+
+fn trim_end(chars: &[char]) -> &[char] {
+    let end = chars
+        .iter()
+        .rposition(|c| !c.is_whitespace())
+        .map_or(0, |p| p + 1);
+    &chars[..end]
+}
+
+/// Parse an opening/closing fence: (marker, run length, info string)
+fn fence_open(chars: &[char]) -> Option<(char, usize, String)> {
+    let indent = chars.iter().take_while(|&&c| c == ' ').count();
+    if indent > 3 {
+        return None;
+    }
+    let marker = *chars.get(indent)?;
+    if marker != '`' && marker != '~' {
+        return None;
+    }
+    let run = chars[indent..].iter().take_while(|&&c| c == marker).count();
+    if run < 3 {
+        return None;
+    }
+    let info: String = chars[indent + run..].iter().collect();
+    Some((marker, run, info.trim().to_string()))
+}
+
+fn frontmatter_tokens(chars: &[char], delim: char) -> Vec<Token> {
+    let sep = if delim == '+' { '=' } else { ':' };
+    let indent = chars.iter().take_while(|c| c.is_whitespace()).count();
+    // YAML sequence items (`- a: b`) are values, not keys
+    let key_end = chars
+        .iter()
+        .position(|&c| c == sep)
+        .filter(|&pos| pos > indent && chars.get(indent) != Some(&'-'));
+
+    match key_end {
+        Some(pos) => {
+            let mut tokens = vec![Token {
+                kind: TokenKind::FrontmatterKey,
+                start: 0,
+                end: pos,
+            }];
+            if pos + 1 < chars.len() {
+                tokens.push(Token {
+                    kind: TokenKind::FrontmatterValue,
+                    start: pos + 1,
+                    end: chars.len(),
+                });
+            }
+            tokens
+        }
+        None if chars.is_empty() => Vec::new(),
+        None => vec![Token {
+            kind: TokenKind::FrontmatterValue,
+            start: 0,
+            end: chars.len(),
+        }],
+    }
+}
+
+/// Tokens for a line of ordinary markdown: block prefixes followed by inline markup
+fn block_tokens(chars: &[char]) -> Vec<Token> {
+    let mut tokens = Vec::new();
+    let mut i = 0;
+
+    // Blockquote markers (possibly nested)
+    loop {
+        let indent = chars[i..].iter().take_while(|&&c| c == ' ').count();
+        if indent <= 3 && chars.get(i + indent) == Some(&'>') {
+            tokens.push(Token {
+                kind: TokenKind::BlockquoteMarker,
+                start: i + indent,
+                end: i + indent + 1,
+            });
+            i += indent + 1;
+            if chars.get(i) == Some(&' ') {
+                i += 1;
+            }
+        } else {
+            break;
+        }
+    }
+
+    let rest = &chars[i..];
+    let indent = rest.iter().take_while(|&&c| c == ' ' || c == '\t').count();
+    let content = &rest[indent..];
+
+    // Thematic break
+    if is_thematic_break(content) {
+        tokens.push(Token {
+            kind: TokenKind::HorizontalRule,
+            start: i + indent,
+            end: chars.len(),
+        });
+        return tokens;
+    }
+
+    // ATX heading
+    if indent <= 3 {
+        let hashes = content.iter().take_while(|&&c| c == '#').count();
+        if (1..=6).contains(&hashes) && matches!(content.get(hashes), None | Some(' ' | '\t')) {
+            let start = i + indent;
+            tokens.push(Token {
+                kind: TokenKind::HeadingMarker,
+                start,
+                end: start + hashes,
+            });
+            if start + hashes < chars.len() {
+                tokens.push(Token {
+                    kind: TokenKind::Heading,
+                    start: start + hashes,
+                    end: chars.len(),
+                });
+            }
+            return tokens;
+        }
+    }
+
+    // List marker and task checkbox
+    let mut text_start = i + indent;
+    if let Some(marker_len) = list_marker_len(content) {
+        let start = i + indent;
+        tokens.push(Token {
+            kind: TokenKind::ListMarker,
+            start,
+            end: start + marker_len,
+        });
+        text_start = start + marker_len + 1;
+
+        let after = &chars[text_start.min(chars.len())..];
+        if after.len() >= 3
+            && after[0] == '['
+            && matches!(after[1], ' ' | 'x' | 'X')
+            && after[2] == ']'
+            && matches!(after.get(3), None | Some(' '))
+        {
+            tokens.push(Token {
+                kind: TokenKind::TaskMarker,
+                start: text_start,
+                end: text_start + 3,
+            });
+            text_start += 3;
+        }
+    }
+
+    inline_tokens(chars, text_start.min(chars.len()), &mut tokens);
+    tokens
+}
+
+fn is_thematic_break(content: &[char]) -> bool {
+    let content = trim_end(content);
+    let Some(&first) = content.first() else {
+        return false;
+    };
+    if !matches!(first, '-' | '*' | '_') {
+        return false;
+    }
+    let count = content.iter().filter(|&&c| c == first).count();
+    count >= 3 && content.iter().all(|&c| c == first || c == ' ' || c == '\t')
+}
+
+/// Length of a list marker (`-`, `*`, `+`, `1.`, `1)`) if followed by a space
+fn list_marker_len(content: &[char]) -> Option<usize> {
+    let len = match content.first()? {
+        '-' | '*' | '+' => 1,
+        c if c.is_ascii_digit() => {
+            let digits = content.iter().take_while(|c| c.is_ascii_digit()).count();
+            if digits > 9 || !matches!(content.get(digits), Some('.' | ')')) {
+                return None;
+            }
+            digits + 1
+        }
+        _ => return None,
+    };
+    match content.get(len) {
+        Some(' ' | '\t') => Some(len),
+        _ => None,
+    }
+}
+
+/// Scan inline markup from `start`, appending tokens
+fn inline_tokens(chars: &[char], start: usize, tokens: &mut Vec<Token>) {
+    let n = chars.len();
+    let mut i = start;
+
+    while i < n {
+        let c = chars[i];
+        match c {
+            '\\' => {
+                i += 2;
+            }
+
+            '`' => {
+                let run = chars[i..].iter().take_while(|&&c| c == '`').count();
+                match find_run(chars, i + run, '`', run) {
+                    Some(close) => {
+                        tokens.push(Token {
+                            kind: TokenKind::CodeSpan,
+                            start: i,
+                            end: close + run,
+                        });
+                        i = close + run;
+                    }
+                    None => i += run,
+                }
+            }
+
+            '!' | '[' => {
+                let open = if c == '!' { i + 1 } else { i };
+                if chars.get(open) != Some(&'[') {
+                    i += 1;
+                    continue;
+                }
+                match link_end(chars, open) {
+                    Some((text_end, url)) => {
+                        tokens.push(Token {
+                            kind: TokenKind::LinkText,
+                            start: i,
+                            end: text_end,
+                        });
+                        if let Some((url_start, url_end)) = url {
+                            tokens.push(Token {
+                                kind: TokenKind::LinkUrl,
+                                start: url_start,
+                                end: url_end,
+                            });
+                            i = url_end;
+                        } else {
+                            i = text_end;
+                        }
+                    }
+                    None => i += 1,
+                }
+            }
+
+            '<' => {
+                let rest: String = chars[i + 1..].iter().take(8).collect();
+                let is_autolink = ["http://", "https://", "mailto:"]
+                    .iter()
+                    .any(|scheme| rest.starts_with(scheme));
+                match chars[i..].iter().position(|&c| c == '>') {
+                    Some(offset) if is_autolink => {
+                        tokens.push(Token {
+                            kind: TokenKind::LinkUrl,
+                            start: i,
+                            end: i + offset + 1,
+                        });
+                        i += offset + 1;
+                    }
+                    _ => i += 1,
+                }
+            }
+
+            '~' if chars.get(i + 1) == Some(&'~') => match find_run(chars, i + 2, '~', 2) {
+                Some(close) if close > i + 2 => {
+                    tokens.push(Token {
+                        kind: TokenKind::Strikethrough,
+                        start: i,
+                        end: close + 2,
+                    });
+                    i = close + 2;
+                }
+                _ => i += 2,
+            },
+
+            '*' | '_' => {
+                let run = chars[i..].iter().take_while(|&&d| d == c).count();
+                let intraword = c == '_' && i > 0 && chars[i - 1].is_alphanumeric();
+                let opens = chars.get(i + run).is_some_and(|n| !n.is_whitespace());
+                let width = run.min(3);
+
+                if intraword || !opens {
+                    i += run;
+                    continue;
+                }
+
+                match find_closing_emphasis(chars, i + run, c, width) {
+                    Some(close) => {
+                        let kind = if width == 1 {
+                            TokenKind::Emphasis
+                        } else {
+                            TokenKind::Strong
+                        };
+                        tokens.push(Token {
+                            kind,
+                            start: i,
+                            end: close + width,
+                        });
+                        i = close + width;
+                    }
+                    None => i += run,
+                }
+            }
+
+            _ => i += 1,
+        }
+    }
+}
+
+/// Find a run of exactly `len` copies of `marker` starting at or after `from`
+fn find_run(chars: &[char], from: usize, marker: char, len: usize) -> Option<usize> {
+    let mut j = from;
+    while j < chars.len() {
+        if chars[j] == marker {
+            let run = chars[j..].iter().take_while(|&&c| c == marker).count();
+            if run == len {
+                return Some(j);
+            }
+            j += run;
+        } else {
+            j += 1;
+        }
+    }
+    None
+}
+
+/// Find a closing emphasis delimiter of at least `width` markers preceded by non-whitespace
+fn find_closing_emphasis(chars: &[char], from: usize, marker: char, width: usize) -> Option<usize> {
+    let mut j = from;
+    while j < chars.len() {
+        if chars[j] == '\\' {
+            j += 2;
+            continue;
+        }
+        if chars[j] == marker {
+            let run = chars[j..].iter().take_while(|&&c| c == marker).count();
+            let after_ok =
+                marker != '_' || !chars.get(j + run).is_some_and(|c| c.is_alphanumeric());
+            if run >= width && j > from && !chars[j - 1].is_whitespace() && after_ok {
+                return Some(j + run - width);
+            }
+            j += run;
+        } else {
+            j += 1;
+        }
+    }
+    None
+}
+
+/// Given the index of `[`, return the end of the `[...]` part and the `(...)` / `[...]` target
+#[allow(clippy::type_complexity)]
+fn link_end(chars: &[char], open: usize) -> Option<(usize, Option<(usize, usize)>)> {
+    let mut depth = 0;
+    let mut j = open;
+    let close = loop {
+        match chars.get(j)? {
+            '\\' => j += 1,
+            '[' => depth += 1,
+            ']' => {
+                depth -= 1;
+                if depth == 0 {
+                    break j;
+                }
+            }
+            _ => {}
+        }
+        j += 1;
+    };
+
+    let text_end = close + 1;
+    match chars.get(text_end) {
+        Some('(') => {
+            let mut depth = 0;
+            let mut k = text_end;
+            while k < chars.len() {
+                match chars[k] {
+                    '(' => depth += 1,
+                    ')' => {
+                        depth -= 1;
+                        if depth == 0 {
+                            return Some((text_end, Some((text_end, k + 1))));
+                        }
+                    }
+                    _ => {}
+                }
+                k += 1;
+            }
+            Some((text_end, None))
+        }
+        Some('[') => {
+            let end = chars[text_end..].iter().position(|&c| c == ']')?;
+            Some((text_end, Some((text_end, text_end + end + 1))))
+        }
+        // Reference-style, footnote or reference definition
+        _ => Some((text_end, None)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn kinds(line: &str) -> Vec<(TokenKind, String)> {
+        let (tokens, _) = scan_line(line, 1, &LineState::Normal);
+        let chars: Vec<char> = line.chars().collect();
+        tokens
+            .into_iter()
+            .map(|t| (t.kind, chars[t.start..t.end].iter().collect()))
+            .collect()
+    }
+
+    #[test]
+    fn test_heading() {
+        assert_eq!(
+            kinds("## Title"),
+            vec![
+                (TokenKind::HeadingMarker, "##".to_string()),
+                (TokenKind::Heading, " Title".to_string()),
+            ]
+        );
+        assert!(kinds("#hashtag").is_empty());
+    }
+
+    #[test]
+    fn test_emphasis_and_code() {
+        assert_eq!(
+            kinds("a *b* **c** `d*e*`"),
+            vec![
+                (TokenKind::Emphasis, "*b*".to_string()),
+                (TokenKind::Strong, "**c**".to_string()),
+                (TokenKind::CodeSpan, "`d*e*`".to_string()),
+            ]
+        );
+        assert!(kinds("snake_case_name").is_empty());
+        assert!(kinds("2 * 3 * 4").is_empty());
+    }
+
+    #[test]
+    fn test_links() {
+        assert_eq!(
+            kinds("see [docs](https://x.y) and ![img](a.png)"),
+            vec![
+                (TokenKind::LinkText, "[docs]".to_string()),
+                (TokenKind::LinkUrl, "(https://x.y)".to_string()),
+                (TokenKind::LinkText, "![img]".to_string()),
+                (TokenKind::LinkUrl, "(a.png)".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_list_and_quote() {
+        assert_eq!(
+            kinds("> - [x] done"),
+            vec![
+                (TokenKind::BlockquoteMarker, ">".to_string()),
+                (TokenKind::ListMarker, "-".to_string()),
+                (TokenKind::TaskMarker, "[x]".to_string()),
+            ]
+        );
+        assert_eq!(
+            kinds("---"),
+            vec![(TokenKind::HorizontalRule, "---".to_string())]
+        );
+    }
+
+    #[test]
+    fn test_fence_state() {
+        let (_, state) = scan_line("```rust", 3, &LineState::Normal);
+        assert_eq!(
+            state,
+            LineState::Fence {
+                marker: '`',
+                len: 3,
+                lang: "rust".to_string(),
+                start_line: 3,
+            }
+        );
+
+        let (tokens, next) = scan_line("let x = *y*;", 4, &state);
+        assert_eq!(tokens[0].kind, TokenKind::CodeBlock);
+        assert_eq!(next, state);
+
+        let (_, next) = scan_line("```", 5, &state);
+        assert_eq!(next, LineState::Normal);
+    }
+
+    #[test]
+    fn test_frontmatter() {
+        let (tokens, state) = scan_line("---", 0, &LineState::Normal);
+        assert_eq!(tokens[0].kind, TokenKind::FrontmatterDelimiter);
+        assert_eq!(state, LineState::Frontmatter('-'));
+
+        let (tokens, _) = scan_line("title: Hello", 1, &state);
+        assert_eq!(tokens[0].kind, TokenKind::FrontmatterKey);
+        assert_eq!(tokens[1].kind, TokenKind::FrontmatterValue);
+
+        // A thematic break later in the document is not frontmatter
+        let (tokens, state) = scan_line("---", 5, &LineState::Normal);
+        assert_eq!(tokens[0].kind, TokenKind::HorizontalRule);
+        assert_eq!(state, LineState::Normal);
+    }
+
+    #[test]
+    fn test_state_cache_invalidation() {
+        let mut buffer = Buffer::from_text("text\n```\ncode\n```\nafter");
+        let highlighter = SourceHighlighter::new();
+
+        assert!(matches!(
+            highlighter.highlight(&buffer, 2).state,
+            LineState::Fence { .. }
+        ));
+        assert_eq!(highlighter.state_at(&buffer, 4), LineState::Normal);
+        assert_eq!(highlighter.cached_lines(), 5);
+
+        // Removing the opening fence changes everything below it
+        buffer.replace(5, 8, "");
+        highlighter.invalidate_from(1);
+        assert_eq!(highlighter.cached_lines(), 2);
+        assert_eq!(highlighter.state_at(&buffer, 2), LineState::Normal);
+        assert!(matches!(
+            highlighter.state_at(&buffer, 4),
+            LineState::Fence { .. }
+        ));
+    }
+}
@@ -4,21 +4,33 @@
 //! Contains the text buffer, markdown parser, document model, and editing operations.
 
 pub mod buffer;
+pub mod check;
 pub mod document;
+pub mod format;
 pub mod frontmatter;
+pub mod highlight;
 pub mod history;
+pub mod lint;
+pub mod navigation;
 pub mod parser;
 pub mod selection;
 pub mod syntax;
+pub mod table;
 
 // Re-exports for convenience
 pub use buffer::Buffer;
+pub use check::{Diagnostic, Severity};
 pub use document::Document;
+pub use format::{Formatter, HeadingStyle};
 pub use frontmatter::Frontmatter;
+pub use highlight::SourceHighlighter;
 pub use history::{Edit, History};
+pub use lint::Linter;
+pub use navigation::GoToTarget;
 pub use parser::MarkdownParser;
 pub use selection::Selection;
-pub use syntax::Highlighter;
+pub use syntax::{Highlighter, SyntaxAssets};
+pub use table::Table;
 
 /// Core result type
 pub type Result<T> = std::result::Result<T, Error>;
@@ -35,6 +47,9 @@ pub enum Error {
     #[error("Frontmatter error: {0}")]
     Frontmatter(String),
 
+    #[error("Syntax error: {0}")]
+    Syntax(String),
+
     #[error("IO error: {0}")]
     Io(#[from] std::io::Error),
 }
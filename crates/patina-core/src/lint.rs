@@ -0,0 +1,440 @@
+//! Style checks on the markdown AST, in the spirit of markdownlint.
+
+use crate::check::{char_column, Diagnostic, Severity};
+use crate::Document;
+use comrak::nodes::{AstNode, ListType, NodeValue};
+use comrak::Arena;
+use std::collections::HashMap;
+use unicode_width::UnicodeWidthChar;
+
+/// Rule names, with what each one checks
+pub const RULES: [(&str, &str); 7] = [
+    ("heading-increment", "heading levels go up one at a time"),
+    ("duplicate-heading", "no two headings have the same text"),
+    (
+        "trailing-spaces",
+        "no whitespace at line ends, except a two-space break",
+    ),
+    ("list-marker", "bullet lists all use the same marker"),
+    ("bare-url", "URLs are written as links or <autolinks>"),
+    ("image-alt", "images have alt text"),
+    ("line-length", "lines fit within the maximum width"),
+];
+
+/// Maximum line width unless configured
+pub const DEFAULT_LINE_LENGTH: usize = 80;
+
+/// Runs the lint rules over a document
+#[derive(Debug, Clone)]
+pub struct Linter {
+    line_length: usize,
+    /// Severity of each configured rule, `None` when it is off
+    levels: HashMap<String, Option<Severity>>,
+}
+
+impl Linter {
+    /// Create a linter reporting every rule as a warning
+    pub fn new() -> Self {
+        Self {
+            line_length: DEFAULT_LINE_LENGTH,
+            levels: HashMap::new(),
+        }
+    }
+
+    /// Maximum display width of a line
+    pub fn line_length(mut self, width: usize) -> Self {
+        self.line_length = width;
+        self
+    }
+
+    /// Report `rule` with `severity`, or turn it off with `None`
+    pub fn rule(mut self, rule: &str, severity: Option<Severity>) -> Self {
+        self.levels.insert(rule.to_string(), severity);
+        self
+    }
+
+    /// Severity `rule` is reported with, `None` if it is off
+    pub fn severity(&self, rule: &str) -> Option<Severity> {
+        self.levels
+            .get(rule)
+            .copied()
+            .unwrap_or(Some(Severity::Warning))
+    }
+
+    /// Problems in the document's body, sorted by position
+    pub fn lint(&self, doc: &Document) -> Vec<Diagnostic> {
+        let text = doc.buffer.text();
+        let lines: Vec<&str> = text.lines().collect();
+        let arena = Arena::new();
+        let root = doc.parse(&arena);
+
+        let enabled = |rule| self.severity(rule).is_some();
+        let mut diagnostics = Vec::new();
+        if enabled("heading-increment") || enabled("duplicate-heading") {
+            diagnostics.extend(check_headings(doc, &lines));
+        }
+        if enabled("trailing-spaces") {
+            diagnostics.extend(check_trailing_spaces(&lines));
+        }
+        if enabled("list-marker") {
+            diagnostics.extend(check_list_markers(root, &lines));
+        }
+        if enabled("bare-url") {
+            diagnostics.extend(check_bare_urls(root, &lines));
+        }
+        if enabled("image-alt") {
+            diagnostics.extend(check_image_alt(root, &lines));
+        }
+        if enabled("line-length") {
+            diagnostics.extend(check_line_length(root, &lines, self.line_length));
+        }
+
+        let mut diagnostics: Vec<Diagnostic> = diagnostics
+            .into_iter()
+            .filter_map(|mut diagnostic| {
+                diagnostic.severity = self.severity(diagnostic.rule)?;
+                Some(diagnostic)
+            })
+            .collect();
+        diagnostics.sort_by_key(|d| (d.line, d.column));
+        diagnostics
+    }
+}
+
+impl Default for Linter {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Width of a line in chars, for diagnostics that run to its end
+fn line_end(lines: &[&str], line: usize) -> usize {
+    lines
+        .get(line.saturating_sub(1))
+        .map_or(1, |text| text.chars().count())
+}
+
+/// Headings that skip a level, and headings repeating an earlier one's text
+fn check_headings(doc: &Document, lines: &[&str]) -> Vec<Diagnostic> {
+    let mut diagnostics = Vec::new();
+    let mut previous: Option<u8> = None;
+    let mut seen: HashMap<&str, usize> = HashMap::new();
+    let headings = doc.headings();
+    for heading in &headings {
+        let end = line_end(lines, heading.line);
+        if let Some(level) = previous.filter(|&level| heading.level > level + 1) {
+            diagnostics.push(
+                Diagnostic::warning(
+                    heading.line,
+                    1,
+                    "heading-increment",
+                    format!("heading level jumps from h{} to h{}", level, heading.level),
+                )
+                .until(end),
+            );
+        }
+        previous = Some(heading.level);
+
+        let text = heading.text.trim();
+        match seen.get(text) {
+            Some(first) => diagnostics.push(
+                Diagnostic::warning(
+                    heading.line,
+                    1,
+                    "duplicate-heading",
+                    format!("duplicate heading \"{}\" (first on line {})", text, first),
+                )
+                .until(end),
+            ),
+            None => {
+                seen.insert(text, heading.line);
+            }
+        }
+    }
+    diagnostics
+}
+
+/// Spaces or tabs at the end of a line; exactly two spaces are a line break
+fn check_trailing_spaces(lines: &[&str]) -> Vec<Diagnostic> {
+    let mut diagnostics = Vec::new();
+    for (idx, text) in lines.iter().enumerate() {
+        let trimmed = text.trim_end_matches([' ', '\t']);
+        let trailing = &text[trimmed.len()..];
+        if trailing.is_empty() || (trailing == "  " && !trimmed.trim().is_empty()) {
+            continue;
+        }
+        let column = trimmed.chars().count() + 1;
+        diagnostics.push(
+            Diagnostic::warning(
+                idx + 1,
+                column,
+                "trailing-spaces",
+                "trailing whitespace".to_string(),
+            )
+            .until(column + trailing.chars().count() - 1),
+        );
+    }
+    diagnostics
+}
+
+/// Bullet items whose marker differs from the first bullet in the document
+fn check_list_markers<'a>(root: &'a AstNode<'a>, lines: &[&str]) -> Vec<Diagnostic> {
+    let mut diagnostics = Vec::new();
+    let mut expected: Option<char> = None;
+    for node in root.descendants() {
+        let ast = node.data.borrow();
+        let NodeValue::Item(item) = &ast.value else {
+            continue;
+        };
+        if item.list_type != ListType::Bullet {
+            continue;
+        }
+        let marker = item.bullet_char as char;
+        let expected = *expected.get_or_insert(marker);
+        if marker != expected {
+            let start = ast.sourcepos.start;
+            let text = lines.get(start.line - 1).copied().unwrap_or_default();
+            let column = char_column(text, start.column);
+            diagnostics.push(Diagnostic::warning(
+                start.line,
+                column,
+                "list-marker",
+                format!(
+                    "list marker `{}` differs from `{}` used first",
+                    marker, expected
+                ),
+            ));
+        }
+    }
+    diagnostics
+}
+
+/// URLs the autolink extension turned into links, rather than written as
+/// `<url>` or `[text](url)`
+fn check_bare_urls<'a>(root: &'a AstNode<'a>, lines: &[&str]) -> Vec<Diagnostic> {
+    let mut diagnostics = Vec::new();
+    // Occurrences already reported in each block, so repeats find the next one
+    let mut found: HashMap<(usize, String), usize> = HashMap::new();
+    for node in root.descendants() {
+        if !matches!(node.data.borrow().value, NodeValue::Link(_)) {
+            continue;
+        }
+        // The extension leaves its links without a position
+        if node.data.borrow().sourcepos.start.line != 0 {
+            continue;
+        }
+        let url = inline_text(node);
+        let Some(block) = node
+            .ancestors()
+            .find(|ancestor| ancestor.data.borrow().sourcepos.start.line != 0)
+        else {
+            continue;
+        };
+        let sourcepos = block.data.borrow().sourcepos;
+        let first_line = sourcepos.start.line;
+        let skip = found.entry((first_line, url.clone())).or_default();
+
+        let occurrence = (first_line..=sourcepos.end.line)
+            .flat_map(|line| {
+                let text = lines.get(line - 1).copied().unwrap_or_default();
+                text.match_indices(url.as_str())
+                    .filter(move |(byte, _)| !text[..*byte].ends_with(['<', '(', '[']))
+                    .map(move |(byte, _)| (line, text[..byte].chars().count() + 1))
+            })
+            .nth(*skip);
+        *skip += 1;
+        if let Some((line, column)) = occurrence {
+            diagnostics.push(
+                Diagnostic::warning(
+                    line,
+                    column,
+                    "bare-url",
+                    format!("bare URL {}; write it as <{}>", url, url),
+                )
+                .until(column + url.chars().count() - 1),
+            );
+        }
+    }
+    diagnostics
+}
+
+/// Images without alt text
+fn check_image_alt<'a>(root: &'a AstNode<'a>, lines: &[&str]) -> Vec<Diagnostic> {
+    let mut diagnostics = Vec::new();
+    for node in root.descendants() {
+        if !matches!(node.data.borrow().value, NodeValue::Image(_)) {
+            continue;
+        }
+        if !inline_text(node).trim().is_empty() {
+            continue;
+        }
+        let sourcepos = node.data.borrow().sourcepos;
+        let text = lines
+            .get(sourcepos.start.line.saturating_sub(1))
+            .copied()
+            .unwrap_or_default();
+        let column = char_column(text, sourcepos.start.column);
+        let mut diagnostic = Diagnostic::warning(
+            sourcepos.start.line,
+            column,
+            "image-alt",
+            "image has no alt text".to_string(),
+        );
+        if sourcepos.end.line == sourcepos.start.line {
+            diagnostic = diagnostic.until(char_column(text, sourcepos.end.column));
+        }
+        diagnostics.push(diagnostic);
+    }
+    diagnostics
+}
+
+/// Lines wider than `max`, outside code blocks, tables and HTML. Lines that
+/// only overflow with a single word, like a long URL, are allowed.
+fn check_line_length<'a>(root: &'a AstNode<'a>, lines: &[&str], max: usize) -> Vec<Diagnostic> {
+    let mut exempt = vec![false; lines.len() + 1];
+    for node in root.descendants() {
+        let ast = node.data.borrow();
+        if matches!(
+            ast.value,
+            NodeValue::CodeBlock(_) | NodeValue::Table(_) | NodeValue::HtmlBlock(_)
+        ) {
+            let end = ast.sourcepos.end.line.min(lines.len());
+            for slot in exempt
+                .iter_mut()
+                .take(end + 1)
+                .skip(ast.sourcepos.start.line)
+            {
+                *slot = true;
+            }
+        }
+    }
+
+    let mut diagnostics = Vec::new();
+    for (idx, text) in lines.iter().enumerate() {
+        if exempt[idx + 1] {
+            continue;
+        }
+        let mut width = 0;
+        let overflow = text.chars().position(|c| {
+            width += c.width().unwrap_or(0);
+            width > max
+        });
+        let Some(overflow) = overflow else {
+            continue;
+        };
+        if !text.chars().skip(overflow).any(char::is_whitespace) {
+            continue;
+        }
+        let length: usize = text.chars().map(|c| c.width().unwrap_or(0)).sum();
+        diagnostics.push(
+            Diagnostic::warning(
+                idx + 1,
+                overflow + 1,
+                "line-length",
+                format!("line is {} columns, more than {}", length, max),
+            )
+            .until(text.chars().count()),
+        );
+    }
+    diagnostics
+}
+
+/// Text of an inline node's children
+fn inline_text<'a>(node: &'a AstNode<'a>) -> String {
+    let mut text = String::new();
+    for child in node.descendants().skip(1) {
+        match &child.data.borrow().value {
+            NodeValue::Text(t) => text.push_str(t),
+            NodeValue::Code(c) => text.push_str(&c.literal),
+            _ => {}
+        }
+    }
+    text
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn lint(content: &str) -> Vec<(usize, usize, &'static str)> {
+        Linter::new()
+            .lint(&Document::from_content(content))
+            .iter()
+            .map(|d| (d.line, d.column, d.rule))
+            .collect()
+    }
+
+    #[test]
+    fn test_headings() {
+        let found = lint("# Title\n\n### Deep\n\n## Usage\n\n## Usage\n");
+        assert_eq!(
+            found,
+            vec![(3, 1, "heading-increment"), (7, 1, "duplicate-heading")]
+        );
+    }
+
+    #[test]
+    fn test_trailing_spaces() {
+        let found = lint("break  \nnext\ntabs\t\nthree   \n  \n");
+        assert_eq!(
+            found,
+            vec![
+                (3, 5, "trailing-spaces"),
+                (4, 6, "trailing-spaces"),
+                (5, 1, "trailing-spaces")
+            ]
+        );
+    }
+
+    #[test]
+    fn test_list_markers() {
+        let found = lint("- a\n- b\n\n * c\n   - d\n\n   + e\n");
+        assert_eq!(found, vec![(4, 2, "list-marker"), (7, 4, "list-marker")]);
+    }
+
+    #[test]
+    fn test_bare_urls() {
+        let content = "Café https://a.io and <https://b.io>, [c](https://c.io)\n\
+                       then https://a.io again and www.d.com.\n";
+        let diagnostics = Linter::new().lint(&Document::from_content(content));
+        let found: Vec<_> = diagnostics
+            .iter()
+            .map(|d| (d.line, d.column, d.end_column))
+            .collect();
+        assert_eq!(found, vec![(1, 6, 17), (2, 6, 17), (2, 29, 37)]);
+        assert!(diagnostics.iter().all(|d| d.rule == "bare-url"));
+    }
+
+    #[test]
+    fn test_image_alt() {
+        let found = lint("![](a.png) and ![ok](b.png) and ![ ](c.png)\n");
+        assert_eq!(found, vec![(1, 1, "image-alt"), (1, 33, "image-alt")]);
+    }
+
+    #[test]
+    fn test_line_length() {
+        let long = "word ".repeat(20).trim_end().to_string();
+        let url = format!("see <https://example.com/{}>", "x".repeat(90));
+        let content = format!(
+            "{}\n\n{}\n\n```\n{}\n```\n\n| a |\n|---|\n| {} |\n",
+            long, url, long, long
+        );
+        assert_eq!(lint(&content), vec![(1, 81, "line-length")]);
+        assert!(Linter::new()
+            .line_length(120)
+            .lint(&Document::from_content(&content))
+            .is_empty());
+    }
+
+    #[test]
+    fn test_rule_levels() {
+        let doc = Document::from_content("# A\n\n# A\n\n![](x.png)\n");
+        let diagnostics = Linter::new()
+            .rule("duplicate-heading", Some(Severity::Error))
+            .rule("image-alt", None)
+            .lint(&doc);
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(diagnostics[0].rule, "duplicate-heading");
+        assert_eq!(diagnostics[0].severity, Severity::Error);
+    }
+}
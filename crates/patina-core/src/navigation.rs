@@ -0,0 +1,208 @@
+//! Go-to targets and heading anchors.
+
+use crate::parser::Heading;
+use crate::Document;
+
+/// Where a go-to prompt should move the cursor
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum GoToTarget {
+    /// `line` or `line:col` (1-indexed)
+    Line { line: usize, column: Option<usize> },
+    /// `#slug` — a heading anchor
+    Heading(String),
+    /// `@name` — a heading, link reference or footnote by name
+    Symbol(String),
+}
+
+impl GoToTarget {
+    /// Parse prompt input, returning `None` if it matches no form
+    pub fn parse(input: &str) -> Option<Self> {
+        let input = input.trim();
+        if let Some(slug) = input.strip_prefix('#') {
+            return Some(Self::Heading(slug.trim().to_lowercase()));
+        }
+        if let Some(symbol) = input.strip_prefix('@') {
+            return Some(Self::Symbol(symbol.trim().to_string()));
+        }
+
+        let (line, column) = match input.split_once(':') {
+            Some((line, column)) => (line, Some(column.trim().parse().ok()?)),
+            None => (input, None),
+        };
+        Some(Self::Line {
+            line: line.trim().parse().ok()?,
+            column,
+        })
+    }
+
+    /// Resolve to a 0-indexed cursor position in `doc`, clamped to its contents
+    pub fn resolve(&self, doc: &Document) -> Option<(usize, usize)> {
+        let last_line = doc.buffer.len_lines().saturating_sub(1);
+        let line_len = |line: usize| {
+            doc.buffer
+                .line(line)
+                .map(|l| l.trim_end_matches('\n').chars().count())
+                .unwrap_or(0)
+        };
+
+        match self {
+            Self::Line { line, column } => {
+                let line = line.saturating_sub(1).min(last_line);
+                let col = column.unwrap_or(1).saturating_sub(1).min(line_len(line));
+                Some((line, col))
+            }
+            Self::Heading(slug) => {
+                let headings = doc.headings();
+                let slugs = heading_slugs(&headings);
+                let idx = slugs.iter().position(|s| s == slug)?;
+                Some((headings[idx].line.saturating_sub(1), 0))
+            }
+            Self::Symbol(name) => {
+                let symbols = symbols(doc);
+                let name = name.to_lowercase();
+                symbols
+                    .iter()
+                    .find(|(label, _)| label.to_lowercase() == name)
+                    .or_else(|| {
+                        symbols
+                            .iter()
+                            .find(|(label, _)| label.to_lowercase().contains(&name))
+                    })
+                    .map(|&(_, line)| (line, 0))
+            }
+        }
+    }
+}
+
+/// GitHub-style anchor slug for heading text
+pub fn slugify(text: &str) -> String {
+    text.trim()
+        .to_lowercase()
+        .chars()
+        .filter_map(|c| match c {
+            ' ' => Some('-'),
+            c if c.is_alphanumeric() || c == '-' || c == '_' => Some(c),
+            _ => None,
+        })
+        .collect()
+}
+
+/// Anchor slugs for each heading, suffixing repeats with `-1`, `-2`, ...
+pub fn heading_slugs(headings: &[Heading]) -> Vec<String> {
+    let mut seen: Vec<String> = Vec::new();
+    headings
+        .iter()
+        .map(|heading| {
+            let base = slugify(&heading.text);
+            let mut slug = base.clone();
+            let mut n = 0;
+            while seen.contains(&slug) {
+                n += 1;
+                slug = format!("{}-{}", base, n);
+            }
+            seen.push(slug.clone());
+            slug
+        })
+        .collect()
+}
+
+/// Named places in a document: headings, link reference definitions and footnotes.
+///
+/// Returns `(label, line)` pairs with 0-indexed lines.
+pub fn symbols(doc: &Document) -> Vec<(String, usize)> {
+    let mut symbols: Vec<(String, usize)> = doc
+        .headings()
+        .into_iter()
+        .map(|h| (h.text, h.line.saturating_sub(1)))
+        .collect();
+
+    for (idx, line) in doc.buffer.text().lines().enumerate() {
+        let trimmed = line.trim_start();
+        let Some(rest) = trimmed.strip_prefix('[') else {
+            continue;
+        };
+        if let Some((label, _)) = rest.split_once("]:") {
+            let label = label.strip_prefix('^').unwrap_or(label);
+            if !label.is_empty() {
+                symbols.push((label.to_string(), idx));
+            }
+        }
+    }
+
+    symbols.sort_by_key(|&(_, line)| line);
+    symbols
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_forms() {
+        assert_eq!(
+            GoToTarget::parse("42"),
+            Some(GoToTarget::Line {
+                line: 42,
+                column: None
+            })
+        );
+        assert_eq!(
+            GoToTarget::parse(" 3:7 "),
+            Some(GoToTarget::Line {
+                line: 3,
+                column: Some(7)
+            })
+        );
+        assert_eq!(
+            GoToTarget::parse("#Getting-Started"),
+            Some(GoToTarget::Heading("getting-started".to_string()))
+        );
+        assert_eq!(
+            GoToTarget::parse("@intro"),
+            Some(GoToTarget::Symbol("intro".to_string()))
+        );
+        assert_eq!(GoToTarget::parse("abc"), None);
+        assert_eq!(GoToTarget::parse("3:x"), None);
+    }
+
+    #[test]
+    fn test_slugify() {
+        assert_eq!(slugify("Hello, World!"), "hello-world");
+        assert_eq!(slugify("API v2.0 (beta)"), "api-v20-beta");
+        assert_eq!(slugify("snake_case-name"), "snake_case-name");
+    }
+
+    #[test]
+    fn test_duplicate_slugs() {
+        let doc = Document::from_content("# Notes\n\n## Notes\n\n## Notes\n");
+        let slugs = heading_slugs(&doc.headings());
+        assert_eq!(slugs, ["notes", "notes-1", "notes-2"]);
+    }
+
+    #[test]
+    fn test_resolve_line_clamps() {
+        let doc = Document::from_content("one\ntwo\nthree");
+        let target = GoToTarget::parse("2:99").unwrap();
+        assert_eq!(target.resolve(&doc), Some((1, 3)));
+        let target = GoToTarget::parse("100").unwrap();
+        assert_eq!(target.resolve(&doc), Some((2, 0)));
+    }
+
+    #[test]
+    fn test_resolve_heading_and_symbol() {
+        let doc = Document::from_content(
+            "# Intro\n\ntext[^note]\n\n## Set Up\n\n[^note]: A note\n[docs]: https://example.com\n",
+        );
+        let heading = GoToTarget::parse("#set-up").unwrap();
+        assert_eq!(heading.resolve(&doc), Some((4, 0)));
+
+        let symbol = GoToTarget::parse("@note").unwrap();
+        assert_eq!(symbol.resolve(&doc), Some((6, 0)));
+        let symbol = GoToTarget::parse("@DOCS").unwrap();
+        assert_eq!(symbol.resolve(&doc), Some((7, 0)));
+        let partial = GoToTarget::parse("@set").unwrap();
+        assert_eq!(partial.resolve(&doc), Some((4, 0)));
+
+        assert_eq!(GoToTarget::parse("#missing").unwrap().resolve(&doc), None);
+    }
+}
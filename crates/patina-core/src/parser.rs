@@ -21,6 +21,10 @@ impl MarkdownParser {
         options.extension.footnotes = true;
         options.extension.description_lists = true;
 
+        // `$...$`, `$$...$$`, `` $`...`$ `` and ```` ```math ````
+        options.extension.math_dollars = true;
+        options.extension.math_code = true;
+
         // Parsing options
         options.parse.smart = true;
 
@@ -42,6 +46,11 @@ impl MarkdownParser {
         String::from_utf8(html).unwrap_or_default()
     }
 
+    /// comrak options the parser uses, for rendering its AST
+    pub fn options(&self) -> &Options {
+        &self.options
+    }
+
     /// Parse markdown and return the AST
     pub fn parse<'a>(&self, arena: &'a Arena<AstNode<'a>>, markdown: &str) -> &'a AstNode<'a> {
         parse_document(arena, markdown, &self.options)
@@ -386,6 +395,17 @@ fn main() {}
         assert!(html.contains("<table>"));
     }
 
+    #[test]
+    fn test_math() {
+        let parser = MarkdownParser::new();
+        let html = parser.to_html("$a^2$ and $$b$$\n\n```math\nc\n```\n\nIt costs $5 and $10.");
+
+        assert!(html.contains("<span data-math-style=\"inline\">a^2</span>"));
+        assert!(html.contains("<span data-math-style=\"display\">b</span>"));
+        assert!(html.contains("<pre lang=\"math\" data-math-style=\"display\"><code>c"));
+        assert!(html.contains("It costs $5 and $10."));
+    }
+
     #[test]
     fn test_html_escaping() {
         let parser = MarkdownParser::new();
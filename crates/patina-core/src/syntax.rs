@@ -1,47 +1,166 @@
 //! Syntax highlighting using syntect.
 
+use crate::Error;
 use once_cell::sync::Lazy;
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
 use syntect::easy::HighlightLines;
-use syntect::highlighting::{Style, Theme, ThemeSet};
-use syntect::parsing::{SyntaxReference, SyntaxSet};
+use syntect::highlighting::{HighlightState, Style, Theme, ThemeSet};
+use syntect::parsing::{ParseState, SyntaxDefinition, SyntaxReference, SyntaxSet};
+
+/// Theme used when a requested theme does not exist
+const FALLBACK_THEME: &str = "base16-ocean.dark";
 
 /// Global syntax set (loaded once)
-static SYNTAX_SET: Lazy<SyntaxSet> = Lazy::new(SyntaxSet::load_defaults_newlines);
+static SYNTAX_SET: Lazy<Arc<SyntaxSet>> =
+    Lazy::new(|| Arc::new(SyntaxSet::load_defaults_newlines()));
 
 /// Global theme set
 static THEME_SET: Lazy<ThemeSet> = Lazy::new(ThemeSet::load_defaults);
 
-/// A syntax highlighter
+/// syntect's default syntaxes and themes
+static DEFAULT_ASSETS: Lazy<SyntaxAssets> = Lazy::new(|| SyntaxAssets {
+    syntaxes: SYNTAX_SET.clone(),
+    themes: THEME_SET
+        .themes
+        .iter()
+        .map(|(name, theme)| (name.clone(), Arc::new(theme.clone())))
+        .collect(),
+});
+
+/// Syntax definitions and syntect themes available for highlighting
+#[derive(Debug, Clone)]
+pub struct SyntaxAssets {
+    syntaxes: Arc<SyntaxSet>,
+    themes: BTreeMap<String, Arc<Theme>>,
+}
+
+impl SyntaxAssets {
+    /// The defaults plus `*.tmTheme` files in `themes_dir` and
+    /// `*.sublime-syntax` files in `syntaxes_dir`.
+    ///
+    /// Files that fail to load are skipped and returned as errors.
+    pub fn load(themes_dir: &Path, syntaxes_dir: &Path) -> (Self, Vec<Error>) {
+        let mut assets = Self::default();
+        let mut errors = Vec::new();
+        let error = |path: &Path, e: &dyn std::fmt::Display| {
+            Error::Syntax(format!("{}: {}", path.display(), e))
+        };
+
+        for path in files_with_extension(themes_dir, "tmTheme") {
+            match ThemeSet::get_theme(&path) {
+                Ok(theme) => {
+                    assets.themes.insert(file_stem(&path), Arc::new(theme));
+                }
+                Err(e) => errors.push(error(&path, &e)),
+            }
+        }
+
+        let mut definitions = Vec::new();
+        for path in files_with_extension(syntaxes_dir, "sublime-syntax") {
+            let loaded = std::fs::read_to_string(&path)
+                .map_err(|e| error(&path, &e))
+                .and_then(|source| {
+                    SyntaxDefinition::load_from_str(&source, true, Some(&file_stem(&path)))
+                        .map_err(|e| error(&path, &e))
+                });
+            match loaded {
+                Ok(definition) => definitions.push(definition),
+                Err(e) => errors.push(e),
+            }
+        }
+        if !definitions.is_empty() {
+            let mut builder = SyntaxSet::clone(&assets.syntaxes).into_builder();
+            for definition in definitions {
+                builder.add(definition);
+            }
+            assets.syntaxes = Arc::new(builder.build());
+        }
+
+        (assets, errors)
+    }
+
+    /// Look up a syntect theme by name (case-insensitive)
+    pub fn theme(&self, name: &str) -> Option<Arc<Theme>> {
+        self.themes
+            .iter()
+            .find(|(key, _)| key.eq_ignore_ascii_case(name))
+            .map(|(_, theme)| theme.clone())
+    }
+
+    /// Names of all available syntect themes
+    pub fn theme_names(&self) -> Vec<&str> {
+        self.themes.keys().map(String::as_str).collect()
+    }
+
+    /// Create a highlighter for these syntaxes using `theme`
+    pub fn highlighter(&self, theme: Arc<Theme>) -> Highlighter {
+        Highlighter {
+            syntaxes: self.syntaxes.clone(),
+            theme,
+        }
+    }
+}
+
+impl Default for SyntaxAssets {
+    fn default() -> Self {
+        DEFAULT_ASSETS.clone()
+    }
+}
+
+/// Files in `dir` with the given extension, sorted (none if `dir` is missing)
+fn files_with_extension(dir: &Path, extension: &str) -> Vec<PathBuf> {
+    let mut paths: Vec<PathBuf> = match std::fs::read_dir(dir) {
+        Ok(entries) => entries
+            .filter_map(|entry| entry.ok().map(|e| e.path()))
+            .filter(|path| path.extension().is_some_and(|ext| ext == extension))
+            .collect(),
+        Err(_) => Vec::new(),
+    };
+    paths.sort();
+    paths
+}
+
+/// File name without its extension
+fn file_stem(path: &Path) -> String {
+    path.file_stem()
+        .map(|stem| stem.to_string_lossy().into_owned())
+        .unwrap_or_default()
+}
+
+/// A syntax highlighter; cheap to clone
+#[derive(Debug, Clone)]
 pub struct Highlighter {
-    theme_name: String,
+    syntaxes: Arc<SyntaxSet>,
+    theme: Arc<Theme>,
 }
 
 impl Highlighter {
-    /// Create a highlighter with a theme
+    /// Create a highlighter with one of syntect's default themes
     pub fn new(theme_name: &str) -> Self {
-        Self {
-            theme_name: theme_name.to_string(),
-        }
+        let theme = DEFAULT_ASSETS
+            .theme(theme_name)
+            .or_else(|| DEFAULT_ASSETS.theme(FALLBACK_THEME))
+            .expect("fallback theme is bundled");
+        DEFAULT_ASSETS.highlighter(theme)
     }
 
     /// Get the current theme
     pub fn theme(&self) -> &Theme {
-        THEME_SET
-            .themes
-            .get(&self.theme_name)
-            .unwrap_or_else(|| THEME_SET.themes.get("base16-ocean.dark").unwrap())
+        &self.theme
     }
 
     /// Get syntax for a language
     pub fn syntax_for_language(&self, lang: &str) -> Option<&SyntaxReference> {
-        SYNTAX_SET
+        self.syntaxes
             .find_syntax_by_token(lang)
-            .or_else(|| SYNTAX_SET.find_syntax_by_extension(lang))
+            .or_else(|| self.syntaxes.find_syntax_by_extension(lang))
     }
 
     /// Get syntax for markdown
     pub fn markdown_syntax(&self) -> &SyntaxReference {
-        SYNTAX_SET.find_syntax_by_extension("md").unwrap()
+        self.syntaxes.find_syntax_by_extension("md").unwrap()
     }
 
     /// Highlight a line of code
@@ -52,7 +171,7 @@ impl Highlighter {
     ) -> Vec<(Style, &'a str)> {
         let mut highlighter = HighlightLines::new(syntax, self.theme());
         highlighter
-            .highlight_line(line, &SYNTAX_SET)
+            .highlight_line(line, &self.syntaxes)
             .unwrap_or_else(|_| vec![(Style::default(), line)])
     }
 
@@ -67,12 +186,35 @@ impl Highlighter {
             .iter()
             .map(|line| {
                 highlighter
-                    .highlight_line(line, &SYNTAX_SET)
+                    .highlight_line(line, &self.syntaxes)
                     .unwrap_or_else(|_| vec![(Style::default(), *line)])
             })
             .collect()
     }
 
+    /// Start an incremental highlighting session where state carries across lines
+    pub fn start<'h>(&'h self, syntax: &SyntaxReference) -> LineHighlighter<'h> {
+        LineHighlighter {
+            inner: Some(HighlightLines::new(syntax, self.theme())),
+            highlighter: self,
+        }
+    }
+
+    /// Continue a session from a saved state, unless the state was made with
+    /// another theme or syntax set
+    pub fn resume<'h>(&'h self, state: &CodeState) -> Option<LineHighlighter<'h>> {
+        let same =
+            Arc::ptr_eq(&state.theme, &self.theme) && Arc::ptr_eq(&state.syntaxes, &self.syntaxes);
+        same.then(|| LineHighlighter {
+            inner: Some(HighlightLines::from_state(
+                self.theme(),
+                state.highlight.clone(),
+                state.parse.clone(),
+            )),
+            highlighter: self,
+        })
+    }
+
     /// List available themes
     pub fn available_themes() -> Vec<&'static str> {
         THEME_SET
@@ -91,17 +233,59 @@ impl Highlighter {
             .collect()
     }
 
-    /// Set theme
+    /// Switch to one of syntect's default themes (ignored if unknown)
     pub fn set_theme(&mut self, theme_name: &str) {
-        if THEME_SET.themes.contains_key(theme_name) {
-            self.theme_name = theme_name.to_string();
+        if let Some(theme) = DEFAULT_ASSETS.theme(theme_name) {
+            self.theme = theme;
+        }
+    }
+}
+
+/// Stateful highlighter for consecutive lines of one code block
+pub struct LineHighlighter<'h> {
+    /// Only `None` while [`LineHighlighter::state`] takes the state out
+    inner: Option<HighlightLines<'h>>,
+    highlighter: &'h Highlighter,
+}
+
+impl<'h> LineHighlighter<'h> {
+    /// Highlight the next line (lines must be fed in order)
+    pub fn highlight<'l>(&mut self, line: &'l str) -> Vec<(Style, &'l str)> {
+        let inner = self.inner.as_mut().expect("highlighter state");
+        inner
+            .highlight_line(line, &self.highlighter.syntaxes)
+            .unwrap_or_else(|_| vec![(Style::default(), line)])
+    }
+
+    /// State before the next line, to resume from with [`Highlighter::resume`]
+    pub fn state(&mut self) -> CodeState {
+        let (highlight, parse) = self.inner.take().expect("highlighter state").state();
+        self.inner = Some(HighlightLines::from_state(
+            self.highlighter.theme(),
+            highlight.clone(),
+            parse.clone(),
+        ));
+        CodeState {
+            theme: self.highlighter.theme.clone(),
+            syntaxes: self.highlighter.syntaxes.clone(),
+            highlight,
+            parse,
         }
     }
 }
 
+/// Syntect state between two lines of a code block
+#[derive(Debug, Clone)]
+pub struct CodeState {
+    theme: Arc<Theme>,
+    syntaxes: Arc<SyntaxSet>,
+    highlight: HighlightState,
+    parse: ParseState,
+}
+
 impl Default for Highlighter {
     fn default() -> Self {
-        Self::new("base16-ocean.dark")
+        Self::new(FALLBACK_THEME)
     }
 }
 
@@ -130,6 +314,25 @@ mod tests {
         assert!(!themes.is_empty());
     }
 
+    #[test]
+    fn test_resume_from_state() {
+        let highlighter = Highlighter::default();
+        let syntax = highlighter.syntax_for_language("rust").unwrap();
+        let lines = ["/* a", "b */ let x = 1;"];
+
+        let mut session = highlighter.start(syntax);
+        session.highlight(lines[0]);
+        let state = session.state();
+        let expected = session.highlight(lines[1]);
+
+        let mut resumed = highlighter.resume(&state).unwrap();
+        assert_eq!(resumed.highlight(lines[1]), expected);
+        assert_ne!(highlighter.start(syntax).highlight(lines[1]), expected);
+
+        let other = Highlighter::new("InspiredGitHub");
+        assert!(other.resume(&state).is_none());
+    }
+
     #[test]
     fn test_markdown_highlighting() {
         let highlighter = Highlighter::default();
@@ -146,4 +349,38 @@ mod tests {
             assert!(!result.is_empty());
         }
     }
+
+    #[test]
+    fn test_load_user_assets() {
+        let dir = std::env::temp_dir().join(format!("patina-syntax-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(
+            dir.join("Mono.tmTheme"),
+            r#"<?xml version="1.0" encoding="UTF-8"?>
+<plist version="1.0"><dict>
+  <key>name</key><string>Mono</string>
+  <key>settings</key><array>
+    <dict><key>settings</key><dict><key>foreground</key><string>#123456</string></dict></dict>
+  </array>
+</dict></plist>"#,
+        )
+        .unwrap();
+        std::fs::write(
+            dir.join("Ini.sublime-syntax"),
+            "%YAML 1.2\n---\nname: Ini\nfile_extensions: [ini]\nscope: source.ini\n\
+             contexts:\n  main:\n    - match: '^\\[.*\\]'\n      scope: entity.name.section\n",
+        )
+        .unwrap();
+        std::fs::write(dir.join("Broken.tmTheme"), "not a plist").unwrap();
+
+        let (assets, errors) = SyntaxAssets::load(&dir, &dir);
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        assert_eq!(errors.len(), 1);
+        let theme = assets.theme("mono").unwrap();
+        assert_eq!(theme.settings.foreground.unwrap().b, 0x56);
+        let highlighter = assets.highlighter(theme);
+        assert!(highlighter.syntax_for_language("ini").is_some());
+        assert!(highlighter.syntax_for_language("rust").is_some());
+    }
 }
@@ -0,0 +1,711 @@
+//! GFM tables in source text: splitting rows into cells, laying them out
+//! with aligned columns, and editing them cell by cell.
+
+use crate::Document;
+use comrak::nodes::{NodeValue, TableAlignment};
+use comrak::Arena;
+use unicode_width::UnicodeWidthStr;
+
+/// Alignment of a table column, from its delimiter row
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Alignment {
+    #[default]
+    None,
+    Left,
+    Center,
+    Right,
+}
+
+impl Alignment {
+    /// Parse a delimiter cell like `:---:`
+    fn parse(cell: &str) -> Option<Self> {
+        let cell = cell.trim();
+        let dashes = cell.trim_start_matches(':').trim_end_matches(':');
+        if dashes.is_empty() || !dashes.chars().all(|c| c == '-') {
+            return None;
+        }
+        Some(match (cell.starts_with(':'), cell.ends_with(':')) {
+            (true, true) => Self::Center,
+            (true, false) => Self::Left,
+            (false, true) => Self::Right,
+            (false, false) => Self::None,
+        })
+    }
+
+    /// The next alignment in the order none, left, center, right
+    pub fn next(self) -> Self {
+        match self {
+            Self::None => Self::Left,
+            Self::Left => Self::Center,
+            Self::Center => Self::Right,
+            Self::Right => Self::None,
+        }
+    }
+
+    /// Pad `cell` to `width` display columns
+    pub fn pad(self, cell: &str, width: usize) -> String {
+        let space = width.saturating_sub(cell.width());
+        let (left, right) = match self {
+            Self::Right => (space, 0),
+            Self::Center => (space / 2, space - space / 2),
+            Self::None | Self::Left => (0, space),
+        };
+        format!("{}{}{}", " ".repeat(left), cell, " ".repeat(right))
+    }
+
+    /// Delimiter cell of `width` columns
+    fn delimiter(self, width: usize) -> String {
+        let width = width.max(3);
+        match self {
+            Self::None => "-".repeat(width),
+            Self::Left => format!(":{}", "-".repeat(width - 1)),
+            Self::Center => format!(":{}:", "-".repeat(width - 2)),
+            Self::Right => format!("{}:", "-".repeat(width - 1)),
+        }
+    }
+}
+
+impl From<TableAlignment> for Alignment {
+    fn from(alignment: TableAlignment) -> Self {
+        match alignment {
+            TableAlignment::None => Self::None,
+            TableAlignment::Left => Self::Left,
+            TableAlignment::Center => Self::Center,
+            TableAlignment::Right => Self::Right,
+        }
+    }
+}
+
+/// A table's cells, trimmed of surrounding spaces
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Table {
+    pub alignments: Vec<Alignment>,
+    pub header: Vec<String>,
+    pub rows: Vec<Vec<String>>,
+}
+
+impl Table {
+    /// Parse the lines of a table: header, delimiter row, then body rows
+    pub fn parse(lines: &[&str]) -> Option<Self> {
+        let (header, rest) = lines.split_first()?;
+        let (delimiter, rows) = rest.split_first()?;
+        let alignments = split_row(delimiter)
+            .iter()
+            .map(|cell| Alignment::parse(cell))
+            .collect::<Option<Vec<_>>>()?;
+        let header = split_row(header);
+        if header.len() != alignments.len() {
+            return None;
+        }
+        Some(Self {
+            alignments,
+            header,
+            rows: rows.iter().map(|row| split_row(row)).collect(),
+        })
+    }
+
+    /// Number of columns, as given by the header
+    pub fn columns(&self) -> usize {
+        self.header.len()
+    }
+
+    /// Source lines with each column padded to its widest cell. Short rows
+    /// get empty cells; cells past the header's columns are kept unpadded.
+    pub fn format(&self) -> Vec<String> {
+        let widths: Vec<usize> = (0..self.columns())
+            .map(|col| {
+                std::iter::once(&self.header)
+                    .chain(&self.rows)
+                    .filter_map(|row| row.get(col))
+                    .map(|cell| cell.width())
+                    .max()
+                    .unwrap_or(0)
+                    .max(3)
+            })
+            .collect();
+
+        let delimiter: Vec<String> = self
+            .alignments
+            .iter()
+            .zip(&widths)
+            .map(|(alignment, &width)| alignment.delimiter(width))
+            .collect();
+        let mut lines = vec![self.format_row(&self.header, &widths)];
+        lines.push(format!("| {} |", delimiter.join(" | ")));
+        lines.extend(self.rows.iter().map(|row| self.format_row(row, &widths)));
+        lines
+    }
+
+    /// Insert an empty column before `column`
+    pub fn insert_column(&mut self, column: usize) {
+        let column = column.min(self.columns());
+        self.header.insert(column, String::new());
+        self.alignments.insert(column, Alignment::None);
+        for row in &mut self.rows {
+            if row.len() >= column {
+                row.insert(column, String::new());
+            }
+        }
+    }
+
+    /// Remove `column` from the header and every row
+    pub fn remove_column(&mut self, column: usize) {
+        if column >= self.columns() {
+            return;
+        }
+        self.header.remove(column);
+        self.alignments.remove(column);
+        for row in &mut self.rows {
+            if column < row.len() {
+                row.remove(column);
+            }
+        }
+    }
+
+    /// Move `column` to index `to`, shifting the columns in between
+    pub fn move_column(&mut self, column: usize, to: usize) {
+        let columns = self.columns();
+        if column >= columns || to >= columns {
+            return;
+        }
+        let cell = self.header.remove(column);
+        self.header.insert(to, cell);
+        let alignment = self.alignments.remove(column);
+        self.alignments.insert(to, alignment);
+        for row in &mut self.rows {
+            row.resize(row.len().max(columns), String::new());
+            let cell = row.remove(column);
+            row.insert(to, cell);
+        }
+    }
+
+    fn format_row(&self, row: &[String], widths: &[usize]) -> String {
+        let cells: Vec<String> = (0..widths.len().max(row.len()))
+            .map(|col| {
+                let cell = row.get(col).map_or("", String::as_str);
+                match widths.get(col) {
+                    Some(&width) => self.alignments[col].pad(cell, width),
+                    None => cell.to_string(),
+                }
+            })
+            .collect();
+        format!("| {} |", cells.join(" | "))
+    }
+}
+
+/// Cells of a table row, split at unescaped pipes and trimmed. The optional
+/// leading and trailing pipes don't start cells.
+pub fn split_row(line: &str) -> Vec<String> {
+    let line = line.trim();
+    let line = line.strip_prefix('|').unwrap_or(line);
+    let mut cells = Vec::new();
+    let mut cell = String::new();
+    let mut chars = line.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' if chars.peek() == Some(&'|') => {
+                cell.push_str("\\|");
+                chars.next();
+            }
+            '|' => cells.push(std::mem::take(&mut cell)),
+            c => cell.push(c),
+        }
+    }
+    // A trailing pipe closes the last cell rather than opening an empty one
+    if !cell.trim().is_empty() || !line.ends_with('|') || line.ends_with("\\|") {
+        cells.push(cell);
+    }
+    cells.iter().map(|cell| cell.trim().to_string()).collect()
+}
+
+/// Character ranges of each cell in a row: the text between its pipes, and
+/// the cell's text within that without surrounding spaces
+fn cell_spans(line: &str) -> Vec<(usize, usize, usize, usize)> {
+    let chars: Vec<char> = line.chars().collect();
+    let mut pipes = Vec::new();
+    let mut i = 0;
+    while i < chars.len() {
+        match chars[i] {
+            '\\' if chars.get(i + 1) == Some(&'|') => i += 1,
+            '|' => pipes.push(i),
+            _ => {}
+        }
+        i += 1;
+    }
+    let first = chars.iter().position(|c| !c.is_whitespace());
+    let last = chars.iter().rposition(|c| !c.is_whitespace());
+    let leading = first.is_some() && pipes.first().copied() == first;
+    let trailing = pipes.len() > usize::from(leading) && pipes.last().copied() == last;
+
+    let mut segments = Vec::new();
+    let mut start = if leading { pipes[0] + 1 } else { 0 };
+    for &pipe in &pipes[usize::from(leading)..] {
+        segments.push((start, pipe));
+        start = pipe + 1;
+    }
+    if !trailing {
+        segments.push((start, chars.len()));
+    }
+
+    segments
+        .into_iter()
+        .map(|(start, end)| {
+            let mut text = (start..end).filter(|&i| !chars[i].is_whitespace());
+            let (text_start, text_end) = match (text.next(), text.next_back()) {
+                (Some(first), Some(last)) => (first, last + 1),
+                (Some(only), None) => (only, only + 1),
+                _ => {
+                    let empty = (start + 1).min(end);
+                    (empty, empty)
+                }
+            };
+            (start, end, text_start, text_end)
+        })
+        .collect()
+}
+
+/// The cell of a row at character `column`, and how many characters into
+/// the cell's text it is. Positions in the padding after the text count on
+/// from its end.
+pub fn cell_at(line: &str, column: usize) -> (usize, usize) {
+    let spans = cell_spans(line);
+    let Some(&(_, _, last_start, last_text_end)) = spans.last() else {
+        return (0, 0);
+    };
+    match spans.iter().position(|&(_, end, _, _)| column <= end) {
+        Some(cell) => {
+            let (_, end, text_start, text_end) = spans[cell];
+            let max = end.saturating_sub(1).max(text_end);
+            (cell, column.min(max).saturating_sub(text_start))
+        }
+        None => (spans.len() - 1, last_text_end - last_start),
+    }
+}
+
+/// Character column of `offset` characters into the text of `cell`
+pub fn cell_column(line: &str, cell: usize, offset: usize) -> usize {
+    let spans = cell_spans(line);
+    match spans.get(cell).or(spans.last()) {
+        Some(&(_, end, text_start, _)) => {
+            (text_start + offset).min(end.saturating_sub(1).max(text_start))
+        }
+        None => 0,
+    }
+}
+
+/// A table in a document with the cursor in one of its cells. Every edit
+/// goes through the table's cells, so [`TableEditor::source`] is always
+/// laid out with aligned columns.
+#[derive(Debug, Clone)]
+pub struct TableEditor {
+    /// First line of the table (0-indexed)
+    pub start: usize,
+    /// Number of lines the table spans
+    pub len: usize,
+    /// Text before the table on each line, like `> ` in a block quote
+    prefix: String,
+    pub table: Table,
+    /// Row of the cursor, 0 for the header and 1 for the first body row
+    pub row: usize,
+    /// Column of the cursor
+    pub column: usize,
+    /// Characters into the cell's text
+    pub offset: usize,
+}
+
+impl TableEditor {
+    /// The table around `cursor` (line, column), unless the cursor is on the
+    /// delimiter row or in a cell past the header's columns
+    pub fn at(doc: &Document, cursor: (usize, usize)) -> Option<Self> {
+        let arena = Arena::new();
+        let root = doc.parse(&arena);
+        let (start, end, prefix_len) = root.descendants().find_map(|node| {
+            let ast = node.data.borrow();
+            let pos = ast.sourcepos;
+            let lines = pos.start.line.checked_sub(1)?..pos.end.line;
+            (matches!(ast.value, NodeValue::Table(_)) && lines.contains(&cursor.0))
+                .then(|| (lines.start, lines.end - 1, pos.start.column - 1))
+        })?;
+        if cursor.0 == start + 1 {
+            return None;
+        }
+
+        let lines: Vec<String> = (start..=end)
+            .map(|idx| {
+                let line = doc.buffer.line(idx).unwrap_or_default();
+                line.trim_end_matches(['\n', '\r']).to_string()
+            })
+            .collect();
+        let prefix = lines[0].get(..prefix_len)?.to_string();
+        let rows = lines
+            .iter()
+            .map(|line| line.strip_prefix(prefix.as_str()))
+            .collect::<Option<Vec<&str>>>()?;
+        let mut table = Table::parse(&rows)?;
+        for row in &mut table.rows {
+            row.resize(row.len().max(table.header.len()), String::new());
+        }
+
+        let line = rows[cursor.0 - start];
+        let column = cursor.1.saturating_sub(prefix.chars().count());
+        let (column, offset) = cell_at(line, column);
+        if column >= table.columns() {
+            return None;
+        }
+        let mut editor = Self {
+            start,
+            len: lines.len(),
+            prefix,
+            row: (cursor.0 - start).saturating_sub(1),
+            column,
+            offset,
+            table,
+        };
+        editor.snap();
+        Some(editor)
+    }
+
+    /// Lines of the table with aligned columns
+    pub fn source(&self) -> Vec<String> {
+        self.table
+            .format()
+            .into_iter()
+            .map(|line| format!("{}{}", self.prefix, line))
+            .collect()
+    }
+
+    /// Cursor (line, column) in [`TableEditor::source`]
+    pub fn cursor(&self) -> (usize, usize) {
+        let line = if self.row == 0 { 0 } else { self.row + 1 };
+        let source = self.table.format().swap_remove(line);
+        let column = cell_column(&source, self.column, self.offset);
+        (self.start + line, self.prefix.chars().count() + column)
+    }
+
+    fn cell(&mut self) -> &mut String {
+        let row = match self.row {
+            0 => &mut self.table.header,
+            row => &mut self.table.rows[row - 1],
+        };
+        if row.len() <= self.column {
+            row.resize(self.column + 1, String::new());
+        }
+        &mut row[self.column]
+    }
+
+    /// Put the cursor at the end of a cell's text
+    fn select(&mut self, row: usize, column: usize) {
+        self.row = row;
+        self.column = column;
+        self.offset = self.cell().chars().count();
+    }
+
+    /// Move the cursor past the pipe if it sits inside an escaped `\\|`, so
+    /// edits treat the escape as one character
+    fn snap(&mut self) {
+        let offset = self.offset;
+        let chars: Vec<char> = self.cell().chars().collect();
+        if offset > 0 && chars.get(offset - 1..=offset) == Some(&['\\', '|'][..]) {
+            self.offset += 1;
+        }
+    }
+
+    /// Insert text at the cursor, escaping pipes so they stay in the cell
+    pub fn insert(&mut self, text: &str) {
+        self.snap();
+        let text = text.replace('|', "\\|");
+        let offset = self.offset;
+        let cell = self.cell();
+        let len = cell.chars().count();
+        // Typing in the padding after the text keeps the spaces before it
+        cell.push_str(&" ".repeat(offset.saturating_sub(len)));
+        let byte = cell
+            .char_indices()
+            .nth(offset)
+            .map_or(cell.len(), |(i, _)| i);
+        cell.insert_str(byte, &text);
+        self.offset += text.chars().count();
+    }
+
+    /// Delete the character before the cursor within the cell, returning
+    /// whether anything changed
+    pub fn delete_backward(&mut self) -> bool {
+        self.snap();
+        let offset = self.offset;
+        if offset == 0 {
+            return false;
+        }
+        let cell = self.cell();
+        let mut chars: Vec<char> = cell.chars().collect();
+        if offset <= chars.len() {
+            let escaped = offset >= 2 && chars[offset - 2..offset] == ['\\', '|'];
+            let count = if escaped { 2 } else { 1 };
+            chars.drain(offset - count..offset);
+            *cell = chars.into_iter().collect();
+            self.offset -= count;
+        } else {
+            self.offset -= 1;
+        }
+        true
+    }
+
+    /// Delete the character after the cursor within the cell, returning
+    /// whether anything changed
+    pub fn delete_forward(&mut self) -> bool {
+        self.snap();
+        let offset = self.offset;
+        let cell = self.cell();
+        let mut chars: Vec<char> = cell.chars().collect();
+        if offset >= chars.len() {
+            return false;
+        }
+        let escaped = chars[offset..].starts_with(&['\\', '|']);
+        chars.drain(offset..offset + if escaped { 2 } else { 1 });
+        *cell = chars.into_iter().collect();
+        true
+    }
+
+    /// Move to the next cell, adding a row after the last one
+    pub fn next_cell(&mut self) {
+        if self.column + 1 < self.table.columns() {
+            self.select(self.row, self.column + 1);
+        } else {
+            if self.row == self.table.rows.len() {
+                self.table
+                    .rows
+                    .push(vec![String::new(); self.table.columns()]);
+            }
+            self.select(self.row + 1, 0);
+        }
+    }
+
+    /// Move to the previous cell, stopping at the first header cell
+    pub fn prev_cell(&mut self) {
+        if self.column > 0 {
+            self.select(self.row, self.column - 1);
+        } else if self.row > 0 {
+            self.select(self.row - 1, self.table.columns() - 1);
+        }
+    }
+
+    /// Add an empty row below or above the cursor's row and move into it.
+    /// Rows can't go above the header, so from there it becomes the first
+    /// body row either way.
+    pub fn insert_row(&mut self, below: bool) {
+        let index = if below || self.row == 0 {
+            self.row
+        } else {
+            self.row - 1
+        };
+        let row = vec![String::new(); self.table.columns()];
+        self.table.rows.insert(index, row);
+        self.select(index + 1, 0);
+    }
+
+    /// Remove the cursor's row; the header stays
+    pub fn remove_row(&mut self) -> bool {
+        if self.row == 0 {
+            return false;
+        }
+        self.table.rows.remove(self.row - 1);
+        self.select(self.row.min(self.table.rows.len()), self.column);
+        true
+    }
+
+    /// Move the cursor's body row up or down by `delta`
+    pub fn move_row(&mut self, delta: isize) -> bool {
+        let rows = self.table.rows.len();
+        if self.row == 0 {
+            return false;
+        }
+        let to = self.row.saturating_add_signed(delta).clamp(1, rows);
+        if to == self.row {
+            return false;
+        }
+        let row = self.table.rows.remove(self.row - 1);
+        self.table.rows.insert(to - 1, row);
+        self.row = to;
+        true
+    }
+
+    /// Add an empty column after the cursor's and move into it
+    pub fn insert_column(&mut self) {
+        self.table.insert_column(self.column + 1);
+        self.select(self.row, self.column + 1);
+    }
+
+    /// Remove the cursor's column, unless it is the only one
+    pub fn remove_column(&mut self) -> bool {
+        let columns = self.table.columns();
+        if columns == 1 {
+            return false;
+        }
+        self.table.remove_column(self.column);
+        self.select(self.row, self.column.min(columns - 2));
+        true
+    }
+
+    /// Move the cursor's column left or right by `delta`
+    pub fn move_column(&mut self, delta: isize) -> bool {
+        let last = self.table.columns() - 1;
+        let to = self.column.saturating_add_signed(delta).min(last);
+        if to == self.column {
+            return false;
+        }
+        self.table.move_column(self.column, to);
+        self.column = to;
+        true
+    }
+
+    /// Switch the cursor's column to the next alignment, returning it
+    pub fn cycle_alignment(&mut self) -> Alignment {
+        let alignment = &mut self.table.alignments[self.column];
+        *alignment = alignment.next();
+        *alignment
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_split_row() {
+        assert_eq!(split_row("| a | b |"), ["a", "b"]);
+        assert_eq!(split_row("a | b"), ["a", "b"]);
+        assert_eq!(split_row("| a \\| b | |"), ["a \\| b", ""]);
+        assert_eq!(split_row("|a|"), ["a"]);
+    }
+
+    #[test]
+    fn test_format_aligns_by_display_width() {
+        let table = Table::parse(&[
+            "| Name | Qty | Note |",
+            "|:-|-:|:-:|",
+            "| 日本 | 12 | x |",
+            "| a |",
+        ])
+        .unwrap();
+        assert_eq!(
+            table.format(),
+            [
+                "| Name | Qty | Note |",
+                "| :--- | --: | :--: |",
+                "| 日本 |  12 |  x   |",
+                "| a    |     |      |",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_not_a_table() {
+        assert!(Table::parse(&["| a | b |", "| -- |"]).is_none());
+        assert!(Table::parse(&["| a |", "| text |"]).is_none());
+        assert!(Table::parse(&["| a |"]).is_none());
+    }
+
+    fn editor(content: &str, cursor: (usize, usize)) -> TableEditor {
+        TableEditor::at(&Document::from_content(content), cursor).unwrap()
+    }
+
+    #[test]
+    fn test_cell_at() {
+        let line = "| ab |   x | |";
+        assert_eq!(cell_at(line, 0), (0, 0));
+        assert_eq!(cell_at(line, 3), (0, 1));
+        assert_eq!(cell_at(line, 5), (0, 2));
+        assert_eq!(cell_at(line, 6), (1, 0));
+        assert_eq!(cell_at(line, 8), (1, 0));
+        assert_eq!(cell_at(line, 11), (1, 1));
+        assert_eq!(cell_at(line, 13), (2, 0));
+        assert_eq!(cell_at(line, 20), (2, 0));
+        assert_eq!(cell_column(line, 1, 1), 10);
+        assert_eq!(cell_column(line, 2, 0), 13);
+        assert_eq!(cell_at("a \\| b | c", 4), (0, 4));
+    }
+
+    #[test]
+    fn test_typing_realigns() {
+        let content = "Intro\n\n> | A | B |\n> |---|--:|\n> | x | 1 |\n";
+        assert!(TableEditor::at(&Document::from_content(content), (3, 4)).is_none());
+
+        let mut table = editor(content, (4, 5));
+        assert_eq!((table.row, table.column, table.offset), (1, 0, 1));
+        table.insert(" 日本|");
+        assert_eq!(
+            table.source(),
+            [
+                "> | A        |   B |",
+                "> | -------- | --: |",
+                "> | x 日本\\| |   1 |",
+            ]
+        );
+        assert_eq!(table.cursor(), (4, 10));
+        assert!(table.delete_backward());
+        assert_eq!(table.table.rows[0][0], "x 日本");
+
+        // The cursor in the padding after a cell's text keeps its spaces
+        let mut table = editor("| A   |\n| --- |\n| x   |\n", (2, 4));
+        table.insert("y");
+        assert_eq!(table.table.rows[0][0], "x y");
+    }
+
+    #[test]
+    fn test_escaped_pipe_is_one_character() {
+        let content = "| A      |\n| ------ |\n| a \\| b |\n";
+        // Between the backslash and the pipe the cursor moves past the pipe
+        let mut table = editor(content, (2, 5));
+        assert_eq!(table.offset, 4);
+        table.insert("x");
+        assert_eq!(table.table.rows[0][0], "a \\|x b");
+        assert_eq!(table.table.columns(), 1);
+
+        let mut table = editor(content, (2, 5));
+        assert!(table.delete_backward());
+        assert_eq!((table.table.rows[0][0].as_str(), table.offset), ("a  b", 2));
+
+        let mut table = editor(content, (2, 5));
+        table.offset = 3;
+        assert!(table.delete_forward());
+        assert_eq!(table.table.rows[0][0], "a \\|b");
+    }
+
+    #[test]
+    fn test_navigation() {
+        let mut table = editor("| A | B |\n|---|---|\n| x | y |\n", (0, 2));
+        table.next_cell();
+        assert_eq!((table.row, table.column, table.offset), (0, 1, 1));
+        table.next_cell();
+        assert_eq!((table.row, table.column), (1, 0));
+        table.next_cell();
+        table.next_cell();
+        assert_eq!((table.row, table.column, table.offset), (2, 0, 0));
+        assert_eq!(table.table.rows.len(), 2);
+        table.prev_cell();
+        assert_eq!((table.row, table.column, table.offset), (1, 1, 1));
+        table.insert_row(false);
+        assert_eq!(table.row, 1);
+        assert_eq!(table.table.rows, [["", ""], ["x", "y"], ["", ""]]);
+    }
+
+    #[test]
+    fn test_rows_and_columns() {
+        let mut table = editor("| A | B |\n|:--|---|\n| 1 | 2 |\n| 3 | 4 |\n", (3, 2));
+        assert!(table.move_row(-1));
+        assert!(!table.move_row(-1));
+        assert_eq!(table.table.rows, [["3", "4"], ["1", "2"]]);
+        assert!(table.move_column(1));
+        assert_eq!(table.table.header, ["B", "A"]);
+        assert_eq!(table.table.alignments, [Alignment::None, Alignment::Left]);
+        assert_eq!(table.cycle_alignment(), Alignment::Center);
+        table.insert_column();
+        assert_eq!((table.column, table.table.rows[0].len()), (2, 3));
+        assert!(table.remove_column());
+        assert!(table.remove_row());
+        assert_eq!(
+            table.source(),
+            ["| B   |  A  |", "| --- | :-: |", "| 2   |  1  |"]
+        );
+        assert_eq!((table.row, table.column), (1, 1));
+    }
+}
@@ -0,0 +1,152 @@
+//! Generates lookup tables from the files in `data/`.
+
+use std::env;
+use std::fs;
+use std::path::Path;
+
+fn main() {
+    println!("cargo:rerun-if-changed=build.rs");
+    println!("cargo:rerun-if-changed=data/unicode-math-table.tex");
+    println!("cargo:rerun-if-changed=data/emoji.tsv");
+
+    let out_dir = env::var("OUT_DIR").expect("OUT_DIR is set by cargo");
+    let table = fs::read_to_string("data/unicode-math-table.tex")
+        .expect("failed to read data/unicode-math-table.tex");
+    fs::write(
+        Path::new(&out_dir).join("unicode_math.rs"),
+        unicode_math(&table),
+    )
+    .expect("failed to write unicode_math.rs");
+
+    let emoji = fs::read_to_string("data/emoji.tsv").expect("failed to read data/emoji.tsv");
+    fs::write(Path::new(&out_dir).join("emoji.rs"), emoji_table(&emoji))
+        .expect("failed to write emoji.rs");
+}
+
+/// `UNICODE_MATH`, sorted by command name, from `\UnicodeMathSymbol` lines
+fn unicode_math(table: &str) -> String {
+    let mut entries: Vec<(String, char, &str)> = Vec::new();
+    for (number, line) in table.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('%') {
+            continue;
+        }
+        let fields = line
+            .strip_prefix("\\UnicodeMathSymbol")
+            .map(braced_fields)
+            .unwrap_or_default();
+        let [code, name, class, ..] = fields.as_slice() else {
+            panic!("unicode-math-table.tex:{}: malformed entry", number + 1);
+        };
+        let symbol = u32::from_str_radix(code.trim_start_matches('"'), 16)
+            .ok()
+            .and_then(char::from_u32)
+            .unwrap_or_else(|| panic!("unicode-math-table.tex:{}: bad code point", number + 1));
+        let class = match class.trim_start_matches('\\') {
+            "mathalpha" => "Alpha",
+            "mathord" => "Ord",
+            "mathbin" => "Bin",
+            "mathrel" => "Rel",
+            "mathop" => "Op",
+            "mathopen" => "Open",
+            "mathclose" => "Close",
+            "mathfence" => "Fence",
+            "mathpunct" => "Punct",
+            "mathinner" => "Inner",
+            // Stretchy brackets over or under an argument, shown as symbols
+            "mathover" | "mathunder" => "Ord",
+            "mathaccent" | "mathaccentoverlay" => "Accent",
+            "mathaccentwide" => "WideAccent",
+            "mathbotaccent" | "mathbotaccentwide" => "BottomAccent",
+            other => panic!(
+                "unicode-math-table.tex:{}: unknown class {}",
+                number + 1,
+                other
+            ),
+        };
+        let name = name.trim_start_matches('\\').to_string();
+        if !entries.iter().any(|(existing, ..)| *existing == name) {
+            entries.push((name, symbol, class));
+        }
+    }
+    entries.sort_by(|a, b| a.0.cmp(&b.0));
+
+    let mut out = String::from("static UNICODE_MATH: &[(&str, char, MathClass)] = &[\n");
+    for (name, symbol, class) in entries {
+        out.push_str(&format!(
+            "    ({:?}, {:?}, MathClass::{}),\n",
+            name, symbol, class
+        ));
+    }
+    out.push_str("];\n");
+    out
+}
+
+/// `EMOJI` in file order and `SHORTCODES`, every shortcode sorted with the
+/// index of its emoji, from the lines of `emoji.tsv`
+fn emoji_table(table: &str) -> String {
+    let mut emoji = String::from("static EMOJI: &[Emoji] = &[\n");
+    let mut shortcodes: Vec<(&str, usize)> = Vec::new();
+    let lines = table
+        .lines()
+        .filter(|line| !line.is_empty() && !line.starts_with('#'));
+    for (index, line) in lines.enumerate() {
+        // Trailing empty columns may have been trimmed by an editor
+        let mut fields: Vec<&str> = line.split('\t').collect();
+        fields.resize(fields.len().max(6), "");
+        let [glyph, aliases, category, description, keywords, skin_tones] = fields.as_slice()
+        else {
+            panic!("emoji.tsv: malformed entry {:?}", line);
+        };
+        let category = match *category {
+            "Smileys & Emotion" => "SmileysAndEmotion",
+            "People & Body" => "PeopleAndBody",
+            "Animals & Nature" => "AnimalsAndNature",
+            "Food & Drink" => "FoodAndDrink",
+            "Travel & Places" => "TravelAndPlaces",
+            "Activities" => "Activities",
+            "Objects" => "Objects",
+            "Symbols" => "Symbols",
+            "Flags" => "Flags",
+            other => panic!("emoji.tsv: unknown category {:?}", other),
+        };
+        let aliases: Vec<&str> = aliases.split_whitespace().collect();
+        let keywords: Vec<&str> = keywords.split_whitespace().collect();
+        emoji.push_str(&format!(
+            "    Emoji {{ emoji: {:?}, aliases: &{:?}, category: Category::{}, description: {:?}, keywords: &{:?}, skin_tones: {} }},\n",
+            glyph,
+            aliases,
+            category,
+            description,
+            keywords,
+            *skin_tones == "1"
+        ));
+        shortcodes.extend(aliases.iter().map(|alias| (*alias, index)));
+    }
+    emoji.push_str("];\n\n");
+
+    shortcodes.sort();
+    if let Some(pair) = shortcodes.windows(2).find(|pair| pair[0].0 == pair[1].0) {
+        panic!("emoji.tsv: duplicate shortcode {}", pair[0].0);
+    }
+    emoji.push_str("static SHORTCODES: &[(&str, u16)] = &[\n");
+    for (shortcode, index) in shortcodes {
+        emoji.push_str(&format!("    ({:?}, {}),\n", shortcode, index));
+    }
+    emoji.push_str("];\n");
+    emoji
+}
+
+/// Contents of the `{...}` groups at the start of `text`, trimmed
+fn braced_fields(text: &str) -> Vec<String> {
+    let mut fields = Vec::new();
+    let mut rest = text;
+    while let Some(inner) = rest.strip_prefix('{') {
+        let Some(end) = inner.find('}') else {
+            break;
+        };
+        fields.push(inner[..end].trim().to_string());
+        rest = &inner[end + 1..];
+    }
+    fields
+}
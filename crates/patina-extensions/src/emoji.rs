@@ -1,90 +1,132 @@
 //! Emoji shortcode expansion.
+//!
+//! The shortcodes are GitHub's gemoji set, generated by `build.rs` from
+//! `data/emoji.tsv`.
 
-use once_cell::sync::Lazy;
-use std::collections::HashMap;
-
-/// Common emoji shortcodes (subset - full list would be ~1800)
-static EMOJI: Lazy<HashMap<&'static str, &'static str>> = Lazy::new(|| {
-    let mut m = HashMap::new();
-
-    // Smileys
-    m.insert("smile", "😊");
-    m.insert("grin", "😁");
-    m.insert("joy", "😂");
-    m.insert("rofl", "🤣");
-    m.insert("wink", "😉");
-    m.insert("heart_eyes", "😍");
-    m.insert("thinking", "🤔");
-    m.insert("sunglasses", "😎");
-
-    // Gestures
-    m.insert("+1", "👍");
-    m.insert("thumbsup", "👍");
-    m.insert("-1", "👎");
-    m.insert("thumbsdown", "👎");
-    m.insert("wave", "👋");
-    m.insert("clap", "👏");
-    m.insert("pray", "🙏");
-    m.insert("muscle", "💪");
-
-    // Hearts
-    m.insert("heart", "❤️");
-    m.insert("sparkling_heart", "💖");
-    m.insert("broken_heart", "💔");
-
-    // Objects
-    m.insert("rocket", "🚀");
-    m.insert("star", "⭐");
-    m.insert("fire", "🔥");
-    m.insert("100", "💯");
-    m.insert("bulb", "💡");
-    m.insert("books", "📚");
-    m.insert("memo", "📝");
-    m.insert("computer", "💻");
-    m.insert("phone", "📱");
-
-    // Nature
-    m.insert("sun", "☀️");
-    m.insert("moon", "🌙");
-    m.insert("cloud", "☁️");
-    m.insert("rainbow", "🌈");
-    m.insert("tree", "🌳");
-    m.insert("flower", "🌸");
-
-    // Symbols
-    m.insert("check", "✅");
-    m.insert("x", "❌");
-    m.insert("warning", "⚠️");
-    m.insert("question", "❓");
-    m.insert("exclamation", "❗");
-    m.insert("heavy_check_mark", "✔️");
-    m.insert("heavy_multiplication_x", "✖️");
-    m.insert("arrow_right", "➡️");
-    m.insert("arrow_left", "⬅️");
-    m.insert("arrow_up", "⬆️");
-    m.insert("arrow_down", "⬇️");
-
-    // Programming related
-    m.insert("bug", "🐛");
-    m.insert("gear", "⚙️");
-    m.insert("wrench", "🔧");
-    m.insert("hammer", "🔨");
-    m.insert("package", "📦");
-    m.insert("link", "🔗");
-    m.insert("lock", "🔒");
-    m.insert("key", "🔑");
-    m.insert("sparkles", "✨");
-    m.insert("zap", "⚡");
-
-    // Additional common emojis
-    m.insert("tada", "🎉");
-    m.insert("construction", "🚧");
-    m.insert("white_check_mark", "✅");
-    m.insert("round_pushpin", "📍");
-    m.insert("pushpin", "📌");
-
-    m
-});
+/// Group an emoji belongs to, as in Unicode's emoji ordering
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Category {
+    SmileysAndEmotion,
+    PeopleAndBody,
+    AnimalsAndNature,
+    FoodAndDrink,
+    TravelAndPlaces,
+    Activities,
+    Objects,
+    Symbols,
+    Flags,
+}
+
+impl Category {
+    /// All categories, in palette order
+    pub const ALL: [Category; 9] = [
+        Category::SmileysAndEmotion,
+        Category::PeopleAndBody,
+        Category::AnimalsAndNature,
+        Category::FoodAndDrink,
+        Category::TravelAndPlaces,
+        Category::Activities,
+        Category::Objects,
+        Category::Symbols,
+        Category::Flags,
+    ];
+
+    /// Display name, as used by Unicode and gemoji
+    pub fn name(&self) -> &'static str {
+        match self {
+            Category::SmileysAndEmotion => "Smileys & Emotion",
+            Category::PeopleAndBody => "People & Body",
+            Category::AnimalsAndNature => "Animals & Nature",
+            Category::FoodAndDrink => "Food & Drink",
+            Category::TravelAndPlaces => "Travel & Places",
+            Category::Activities => "Activities",
+            Category::Objects => "Objects",
+            Category::Symbols => "Symbols",
+            Category::Flags => "Flags",
+        }
+    }
+}
+
+/// Skin tone modifier, written `:skin-tone-2:` to `:skin-tone-6:` after a
+/// shortcode
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SkinTone {
+    Light,
+    MediumLight,
+    Medium,
+    MediumDark,
+    Dark,
+}
+
+impl SkinTone {
+    /// Tone for a `skin-tone-N` shortcode (without colons)
+    pub fn from_shortcode(shortcode: &str) -> Option<Self> {
+        let tone = match shortcode {
+            "skin-tone-2" => SkinTone::Light,
+            "skin-tone-3" => SkinTone::MediumLight,
+            "skin-tone-4" => SkinTone::Medium,
+            "skin-tone-5" => SkinTone::MediumDark,
+            "skin-tone-6" => SkinTone::Dark,
+            _ => return None,
+        };
+        Some(tone)
+    }
+
+    /// Emoji modifier character for the tone
+    pub fn modifier(&self) -> char {
+        match self {
+            SkinTone::Light => '\u{1F3FB}',
+            SkinTone::MediumLight => '\u{1F3FC}',
+            SkinTone::Medium => '\u{1F3FD}',
+            SkinTone::MediumDark => '\u{1F3FE}',
+            SkinTone::Dark => '\u{1F3FF}',
+        }
+    }
+}
+
+/// An emoji with its shortcodes
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Emoji {
+    pub emoji: &'static str,
+    /// Shortcodes without colons, the canonical one first
+    pub aliases: &'static [&'static str],
+    pub category: Category,
+    /// Unicode name, like "thumbs up"
+    pub description: &'static str,
+    /// Extra search terms
+    pub keywords: &'static [&'static str],
+    /// Whether the emoji takes a skin tone modifier
+    pub skin_tones: bool,
+}
+
+impl Emoji {
+    /// Canonical shortcode
+    pub fn shortcode(&self) -> &'static str {
+        self.aliases[0]
+    }
+
+    /// The emoji with a skin tone, if it takes one.
+    ///
+    /// The modifier goes after the first character, replacing a variation
+    /// selector, so `👩‍💻` becomes `👩🏽‍💻`.
+    pub fn with_skin_tone(&self, tone: SkinTone) -> Option<String> {
+        if !self.skin_tones {
+            return None;
+        }
+        let mut chars = self.emoji.chars();
+        let first = chars.next()?;
+        let rest = chars.as_str();
+        let rest = rest.strip_prefix('\u{FE0F}').unwrap_or(rest);
+        Some(format!("{}{}{}", first, tone.modifier(), rest))
+    }
+}
+
+include!(concat!(env!("OUT_DIR"), "/emoji.rs"));
+
+/// Characters allowed in a shortcode
+fn is_shortcode_char(c: char) -> bool {
+    c.is_ascii_lowercase() || c.is_ascii_digit() || matches!(c, '_' | '+' | '-')
+}
 
 /// Emoji shortcode expander
 pub struct EmojiExpander;
@@ -95,72 +137,93 @@ impl EmojiExpander {
         Self
     }
 
+    /// Look up a shortcode (without colons)
+    pub fn lookup(&self, shortcode: &str) -> Option<&'static Emoji> {
+        SHORTCODES
+            .binary_search_by_key(&shortcode, |&(code, _)| code)
+            .ok()
+            .map(|i| &EMOJI[SHORTCODES[i].1 as usize])
+    }
+
     /// Expand a single shortcode (without colons)
     pub fn expand(&self, shortcode: &str) -> Option<&'static str> {
-        EMOJI.get(shortcode).copied()
+        self.lookup(shortcode).map(|emoji| emoji.emoji)
     }
 
-    /// Expand all shortcodes in text (:shortcode: -> emoji)
+    /// Expand all shortcodes in text (:shortcode: -> emoji).
+    ///
+    /// A `:skin-tone-N:` right after a shortcode applies to it. A colon that
+    /// doesn't start a known shortcode is kept, and the next colon is tried,
+    /// so `:a::b:` and `10::rocket:` both expand.
     pub fn expand_all(&self, text: &str) -> String {
         let mut result = String::with_capacity(text.len());
-        let mut chars = text.char_indices();
-
-        while let Some((i, c)) = chars.next() {
-            if c == ':' {
-                // Collect characters until we hit ':', ' ', '\n', or end of string
-                let mut shortcode = String::new();
-                let mut found_closing = false;
-
-                // Peek ahead and collect shortcode characters
-                let remaining = &text[i + 1..];
-                for ch in remaining.chars() {
-                    if ch == ':' {
-                        found_closing = true;
-                        break;
-                    } else if ch == ' ' || ch == '\n' {
-                        break;
-                    } else {
-                        shortcode.push(ch);
-                    }
-                }
+        let mut rest = text;
 
-                // Try to expand if we found a valid shortcode
-                if found_closing && !shortcode.is_empty() {
-                    if let Some(emoji) = EMOJI.get(shortcode.as_str()) {
-                        result.push_str(emoji);
-                        // Skip the shortcode and closing colon
-                        for _ in 0..shortcode.len() {
-                            chars.next();
+        while let Some(start) = rest.find(':') {
+            result.push_str(&rest[..start]);
+            let after = &rest[start + 1..];
+            match self.shortcode_at(after) {
+                Some((emoji, len)) => {
+                    let mut after = &after[len + 1..];
+                    let toned = Self::skin_tone_at(after)
+                        .and_then(|(tone, len)| Some((emoji.with_skin_tone(tone)?, len)));
+                    match toned {
+                        Some((toned, len)) => {
+                            result.push_str(&toned);
+                            after = &after[len..];
                         }
-                        chars.next(); // Skip closing colon
-                        continue;
+                        None => result.push_str(emoji.emoji),
                     }
+                    rest = after;
+                }
+                None => {
+                    result.push(':');
+                    rest = after;
                 }
-
-                // Not a valid shortcode, output the opening colon
-                result.push(':');
-            } else {
-                result.push(c);
             }
         }
 
+        result.push_str(rest);
         result
     }
 
+    /// Emoji for a shortcode and its closing colon at the start of `text`,
+    /// with the length of the shortcode
+    fn shortcode_at(&self, text: &str) -> Option<(&'static Emoji, usize)> {
+        let len = text
+            .find(|c: char| !is_shortcode_char(c))
+            .unwrap_or(text.len());
+        if len == 0 || !text[len..].starts_with(':') {
+            return None;
+        }
+        self.lookup(&text[..len]).map(|emoji| (emoji, len))
+    }
+
+    /// `:skin-tone-N:` at the start of `text`, with its length
+    fn skin_tone_at(text: &str) -> Option<(SkinTone, usize)> {
+        let inner = text.strip_prefix(':')?;
+        let end = inner.find(':')?;
+        SkinTone::from_shortcode(&inner[..end]).map(|tone| (tone, end + 2))
+    }
+
+    /// Every emoji, in Unicode's palette order
+    pub fn all() -> &'static [Emoji] {
+        EMOJI
+    }
+
     /// Get all available shortcodes
     pub fn available_shortcodes() -> Vec<&'static str> {
-        let mut codes: Vec<_> = EMOJI.keys().copied().collect();
-        codes.sort();
-        codes
+        SHORTCODES.iter().map(|&(code, _)| code).collect()
     }
 
     /// Search shortcodes by prefix
     pub fn search(&self, prefix: &str) -> Vec<(&'static str, &'static str)> {
         let prefix_lower = prefix.to_lowercase();
-        EMOJI
+        let start = SHORTCODES.partition_point(|&(code, _)| code < prefix_lower.as_str());
+        SHORTCODES[start..]
             .iter()
-            .filter(|(k, _)| k.starts_with(&prefix_lower))
-            .map(|(&k, &v)| (k, v))
+            .take_while(|(code, _)| code.starts_with(&prefix_lower))
+            .map(|&(code, i)| (code, EMOJI[i as usize].emoji))
             .collect()
     }
 }
@@ -196,4 +259,42 @@ mod tests {
         assert!(!codes.is_empty());
         assert!(codes.contains(&"rocket"));
     }
+
+    #[test]
+    fn test_full_set_with_aliases() {
+        let expander = EmojiExpander::new();
+        assert!(EmojiExpander::all().len() > 1800);
+        assert_eq!(expander.expand("+1"), Some("👍"));
+        assert_eq!(expander.expand("thumbsup"), Some("👍"));
+        assert_eq!(expander.expand("melting_face"), Some("🫠"));
+        assert_eq!(expander.expand("de"), Some("🇩🇪"));
+
+        let poop = expander.lookup("shit").unwrap();
+        assert_eq!(poop.shortcode(), "hankey");
+        assert_eq!(poop.category, Category::SmileysAndEmotion);
+        assert!(poop.keywords.contains(&"crap"));
+    }
+
+    #[test]
+    fn test_expand_all() {
+        let expander = EmojiExpander::new();
+        assert_eq!(expander.expand_all(":rocket::tada:"), "🚀🎉");
+        assert_eq!(expander.expand_all("a :nope::rocket: b"), "a :nope:🚀 b");
+        assert_eq!(expander.expand_all("at 10:30:00 ok"), "at 10:30:00 ok");
+        assert_eq!(expander.expand_all("café :fire:"), "café 🔥");
+        assert_eq!(expander.expand_all("a::zz: :"), "a::zz: :");
+    }
+
+    #[test]
+    fn test_skin_tones() {
+        let expander = EmojiExpander::new();
+        assert_eq!(expander.expand_all(":+1::skin-tone-3:"), "👍🏼");
+        assert_eq!(expander.expand_all(":point_up::skin-tone-6:"), "☝🏿");
+        assert_eq!(expander.expand_all(":technologist::skin-tone-4:"), "🧑🏽‍💻");
+        // Tones only apply to emoji that take them
+        assert_eq!(
+            expander.expand_all(":rocket::skin-tone-2:"),
+            "🚀:skin-tone-2:"
+        );
+    }
 }
@@ -0,0 +1,175 @@
+//! Math font commands, drawn with Unicode mathematical alphanumeric symbols.
+
+/// Alphabet selected by `\mathbb`, `\mathbf` and friends
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(super) enum Font {
+    /// Upright letters as typed
+    Roman,
+    Bold,
+    Italic,
+    BoldItalic,
+    Script,
+    BoldScript,
+    Fraktur,
+    BoldFraktur,
+    DoubleStruck,
+    SansSerif,
+    SansSerifBold,
+    SansSerifItalic,
+    SansSerifBoldItalic,
+    Monospace,
+}
+
+/// First code point of each alphabet: capitals, small letters, digits and
+/// Greek capitals, where Unicode has them
+struct Alphabet {
+    capital: u32,
+    small: u32,
+    digit: Option<u32>,
+    greek: Option<u32>,
+}
+
+impl Font {
+    /// Font selected by a command, for math (`\mathbf`) and text (`\textbf`)
+    pub(super) fn from_command(name: &str) -> Option<Self> {
+        let font = match name {
+            "mathrm" | "mathup" | "textrm" | "textup" | "textnormal" => Font::Roman,
+            "mathbf" | "mathbfup" | "textbf" => Font::Bold,
+            "mathit" | "textit" | "textsl" => Font::Italic,
+            "mathbfit" | "boldsymbol" | "bm" => Font::BoldItalic,
+            "mathcal" | "mathscr" => Font::Script,
+            "mathbfcal" | "mathbfscr" => Font::BoldScript,
+            "mathfrak" => Font::Fraktur,
+            "mathbffrak" => Font::BoldFraktur,
+            "mathbb" | "Bbb" | "mathbbm" => Font::DoubleStruck,
+            "mathsf" | "mathsfup" | "textsf" => Font::SansSerif,
+            "mathbfsf" | "mathsfbf" | "mathbfsfup" => Font::SansSerifBold,
+            "mathsfit" => Font::SansSerifItalic,
+            "mathbfsfit" | "mathsfbfit" => Font::SansSerifBoldItalic,
+            "mathtt" | "texttt" => Font::Monospace,
+            _ => return None,
+        };
+        Some(font)
+    }
+
+    fn alphabet(self) -> Option<Alphabet> {
+        let (capital, small, digit, greek) = match self {
+            Font::Roman => return None,
+            Font::Bold => (0x1D400, 0x1D41A, Some(0x1D7CE), Some(0x1D6A8)),
+            Font::Italic => (0x1D434, 0x1D44E, None, Some(0x1D6E2)),
+            Font::BoldItalic => (0x1D468, 0x1D482, None, Some(0x1D71C)),
+            Font::Script => (0x1D49C, 0x1D4B6, None, None),
+            Font::BoldScript => (0x1D4D0, 0x1D4EA, None, None),
+            Font::Fraktur => (0x1D504, 0x1D51E, None, None),
+            Font::BoldFraktur => (0x1D56C, 0x1D586, None, None),
+            Font::DoubleStruck => (0x1D538, 0x1D552, Some(0x1D7D8), None),
+            Font::SansSerif => (0x1D5A0, 0x1D5BA, Some(0x1D7E2), None),
+            Font::SansSerifBold => (0x1D5D4, 0x1D5EE, Some(0x1D7EC), Some(0x1D756)),
+            Font::SansSerifItalic => (0x1D608, 0x1D622, None, None),
+            Font::SansSerifBoldItalic => (0x1D63C, 0x1D656, None, Some(0x1D790)),
+            Font::Monospace => (0x1D670, 0x1D68A, Some(0x1D7F6), None),
+        };
+        Some(Alphabet {
+            capital,
+            small,
+            digit,
+            greek,
+        })
+    }
+
+    /// Letters that were in Unicode's Letterlike Symbols block before the
+    /// mathematical alphabets, and are left out of them
+    fn letterlike(self, c: char) -> Option<char> {
+        let letter = match (self, c) {
+            (Font::Italic, 'h') => 'ℎ',
+            (Font::Script, 'B') => 'ℬ',
+            (Font::Script, 'E') => 'ℰ',
+            (Font::Script, 'F') => 'ℱ',
+            (Font::Script, 'H') => 'ℋ',
+            (Font::Script, 'I') => 'ℐ',
+            (Font::Script, 'L') => 'ℒ',
+            (Font::Script, 'M') => 'ℳ',
+            (Font::Script, 'R') => 'ℛ',
+            (Font::Script, 'e') => 'ℯ',
+            (Font::Script, 'g') => 'ℊ',
+            (Font::Script, 'o') => 'ℴ',
+            (Font::Fraktur, 'C') => 'ℭ',
+            (Font::Fraktur, 'H') => 'ℌ',
+            (Font::Fraktur, 'I') => 'ℑ',
+            (Font::Fraktur, 'R') => 'ℜ',
+            (Font::Fraktur, 'Z') => 'ℨ',
+            (Font::DoubleStruck, 'C') => 'ℂ',
+            (Font::DoubleStruck, 'H') => 'ℍ',
+            (Font::DoubleStruck, 'N') => 'ℕ',
+            (Font::DoubleStruck, 'P') => 'ℙ',
+            (Font::DoubleStruck, 'Q') => 'ℚ',
+            (Font::DoubleStruck, 'R') => 'ℝ',
+            (Font::DoubleStruck, 'Z') => 'ℤ',
+            _ => return None,
+        };
+        Some(letter)
+    }
+
+    /// `c` in this font, or unchanged if the font has no such character
+    pub(super) fn apply(self, c: char) -> char {
+        if let Some(letter) = self.letterlike(c) {
+            return letter;
+        }
+        let Some(alphabet) = self.alphabet() else {
+            return c;
+        };
+        let code = match c {
+            'A'..='Z' => alphabet.capital + (c as u32 - 'A' as u32),
+            'a'..='z' => alphabet.small + (c as u32 - 'a' as u32),
+            '0'..='9' => match alphabet.digit {
+                Some(digit) => digit + (c as u32 - '0' as u32),
+                None => return c,
+            },
+            _ => match (alphabet.greek, greek_offset(c)) {
+                (Some(greek), Some(offset)) => greek + offset,
+                _ => return c,
+            },
+        };
+        char::from_u32(code).unwrap_or(c)
+    }
+}
+
+/// Position of a Greek letter in the mathematical Greek alphabets
+fn greek_offset(c: char) -> Option<u32> {
+    let offset = match c {
+        'Α'..='Ω' => c as u32 - 'Α' as u32,
+        'ϴ' => 17,
+        '∇' => 25,
+        'α'..='ω' => 26 + (c as u32 - 'α' as u32),
+        '∂' => 51,
+        'ϵ' => 52,
+        'ϑ' => 53,
+        'ϰ' => 54,
+        'ϕ' => 55,
+        'ϱ' => 56,
+        'ϖ' => 57,
+        _ => return None,
+    };
+    Some(offset)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn styled(text: &str, font: Font) -> String {
+        text.chars().map(|c| font.apply(c)).collect()
+    }
+
+    #[test]
+    fn test_alphabets() {
+        assert_eq!(styled("RNZx1", Font::DoubleStruck), "ℝℕℤ𝕩𝟙");
+        assert_eq!(styled("ALb", Font::Script), "𝒜ℒ𝒷");
+        assert_eq!(styled("vh2", Font::Bold), "𝐯𝐡𝟐");
+        assert_eq!(styled("h", Font::Italic), "ℎ");
+        assert_eq!(styled("gR", Font::Fraktur), "𝔤ℜ");
+        assert_eq!(styled("αΩ∇", Font::Bold), "𝛂𝛀𝛁");
+        assert_eq!(styled("x+1", Font::Monospace), "𝚡+𝟷");
+        assert_eq!(styled("dx", Font::Roman), "dx");
+    }
+}
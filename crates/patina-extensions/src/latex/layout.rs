@@ -0,0 +1,659 @@
+//! Layout of parsed formulas, on one line or as a block of text rows.
+
+use super::parse::{Align, Class, Node, Placement};
+use super::{SUBSCRIPTS, SUPERSCRIPTS};
+use std::collections::HashMap;
+use unicode_width::UnicodeWidthStr;
+
+/// Lines of text sharing a baseline, all padded to the same width
+#[derive(Debug, Clone, PartialEq)]
+struct TextBox {
+    lines: Vec<String>,
+    width: usize,
+    /// Row the surrounding text lines up with
+    baseline: usize,
+}
+
+impl TextBox {
+    fn text(text: &str) -> Self {
+        Self {
+            lines: vec![text.to_string()],
+            width: text.width(),
+            baseline: 0,
+        }
+    }
+
+    fn height(&self) -> usize {
+        self.lines.len()
+    }
+
+    /// Rows below the baseline
+    fn depth(&self) -> usize {
+        self.height() - self.baseline - 1
+    }
+
+    /// Boxes side by side, lined up on their baselines
+    fn row(boxes: Vec<TextBox>) -> Self {
+        let ascent = boxes.iter().map(|b| b.baseline).max().unwrap_or(0);
+        let depth = boxes.iter().map(TextBox::depth).max().unwrap_or(0);
+        let mut lines = vec![String::new(); ascent + depth + 1];
+        for b in &boxes {
+            let top = ascent - b.baseline;
+            for (r, line) in lines.iter_mut().enumerate() {
+                match r.checked_sub(top).and_then(|i| b.lines.get(i)) {
+                    Some(text) => line.push_str(text),
+                    None => line.push_str(&" ".repeat(b.width)),
+                }
+            }
+        }
+        Self {
+            lines,
+            width: boxes.iter().map(|b| b.width).sum(),
+            baseline: ascent,
+        }
+    }
+
+    /// Boxes stacked and centered, with the baseline on row `baseline`
+    fn column(boxes: Vec<TextBox>, baseline: usize) -> Self {
+        let width = boxes.iter().map(|b| b.width).max().unwrap_or(0);
+        let lines = boxes
+            .into_iter()
+            .flat_map(|b| {
+                let left = (width - b.width) / 2;
+                b.lines
+                    .into_iter()
+                    .map(move |line| pad(&line, left, width - b.width - left))
+            })
+            .collect();
+        Self {
+            lines,
+            width,
+            baseline,
+        }
+    }
+
+    /// The box widened to `width` columns
+    fn aligned(self, width: usize, align: Align) -> Self {
+        let extra = width.saturating_sub(self.width);
+        let left = match align {
+            Align::Center => extra / 2,
+            Align::Left => 0,
+            Align::Pairs => extra,
+        };
+        Self {
+            lines: self
+                .lines
+                .iter()
+                .map(|line| pad(line, left, extra - left))
+                .collect(),
+            width: self.width.max(width),
+            baseline: self.baseline,
+        }
+    }
+}
+
+fn pad(line: &str, left: usize, right: usize) -> String {
+    format!("{}{}{}", " ".repeat(left), line, " ".repeat(right))
+}
+
+/// Whether a space follows each atom, following TeX's spacing rules
+fn gaps(nodes: &[Node], script: bool) -> Vec<bool> {
+    let mut classes: Vec<Class> = Vec::with_capacity(nodes.len());
+    for node in nodes {
+        let class = match node.class() {
+            // A sign rather than an operator
+            Class::Bin
+                if classes.last().is_none_or(|prev| {
+                    matches!(
+                        prev,
+                        Class::Bin | Class::Rel | Class::Open | Class::Punct | Class::Op
+                    )
+                }) =>
+            {
+                Class::Ord
+            }
+            class => class,
+        };
+        classes.push(class);
+    }
+    if script {
+        return vec![false; nodes.len()];
+    }
+
+    (0..nodes.len())
+        .map(|i| {
+            let Some(&next) = classes.get(i + 1) else {
+                return false;
+            };
+            match (classes[i], next) {
+                (Class::Bin | Class::Rel, _) | (_, Class::Bin | Class::Rel) => true,
+                (Class::Punct, _) => true,
+                (Class::Op, next) => !matches!(next, Class::Open | Class::Close | Class::Punct),
+                _ => false,
+            }
+        })
+        .collect()
+}
+
+/// Formula on a single line, with scripts as Unicode sub- and superscripts
+/// where possible
+pub(super) fn linear(nodes: &[Node], script: bool) -> String {
+    let gaps = gaps(nodes, script);
+    let mut out = String::new();
+    for (node, gap) in nodes.iter().zip(gaps) {
+        out.push_str(&linear_node(node, script));
+        if gap {
+            out.push(' ');
+        }
+    }
+    out
+}
+
+fn linear_node(node: &Node, script: bool) -> String {
+    match node {
+        Node::Symbol(text, _) => text.clone(),
+        Node::Space(n) => " ".repeat(*n),
+        Node::Group(nodes) => linear(nodes, script),
+        Node::Op { name, .. } => name.clone(),
+        Node::Scripts { base, sub, sup } => {
+            let mut out = linear_node(base, script);
+            if let Some(sub) = sub {
+                out.push_str(&script_text(sub, &SUBSCRIPTS, '_'));
+            }
+            if let Some(sup) = sup {
+                out.push_str(&script_text(sup, &SUPERSCRIPTS, '^'));
+            }
+            out
+        }
+        Node::Frac { num, den, bar } => {
+            let separator = if *bar { "/" } else { " " };
+            format!(
+                "{}{}{}",
+                wrapped(num, script),
+                separator,
+                wrapped(den, script)
+            )
+        }
+        Node::Sqrt { index, body } => {
+            let radical = match index.as_deref().map(|index| linear(index, true)) {
+                None => "√".to_string(),
+                Some(index) if index == "3" => "∛".to_string(),
+                Some(index) if index == "4" => "∜".to_string(),
+                Some(index) => convert(&index, &SUPERSCRIPTS).unwrap_or(index) + "√",
+            };
+            radical + &wrapped(body, script)
+        }
+        Node::Delimited { open, close, body } => {
+            format!("{}{}{}", open, linear(body, script), close)
+        }
+        Node::Matrix {
+            rows,
+            open,
+            close,
+            align,
+        } => {
+            let separator = if *align == Align::Center { ", " } else { " " };
+            let rows: Vec<String> = rows
+                .iter()
+                .map(|row| {
+                    row.iter()
+                        .map(|cell| linear(cell, script))
+                        .collect::<Vec<_>>()
+                        .join(separator)
+                })
+                .collect();
+            format!("{}{}{}", open, rows.join("; "), close)
+        }
+        Node::Accent {
+            mark,
+            placement,
+            body,
+        } => accented(&linear(body, script), *mark, *placement),
+    }
+}
+
+/// `text` with a combining mark over its middle character, or over or under
+/// every character
+fn accented(text: &str, mark: char, placement: Placement) -> String {
+    let marked: Vec<usize> = text
+        .char_indices()
+        .filter(|(_, c)| !c.is_whitespace())
+        .map(|(i, c)| i + c.len_utf8())
+        .collect();
+    let ends = match placement {
+        Placement::Over => marked
+            .get(marked.len().saturating_sub(1) / 2)
+            .copied()
+            .into_iter()
+            .collect(),
+        Placement::Wide | Placement::Under => marked,
+    };
+    let mut out = String::new();
+    let mut start = 0;
+    for end in ends {
+        out.push_str(&text[start..end]);
+        out.push(mark);
+        start = end;
+    }
+    out.push_str(&text[start..]);
+    out
+}
+
+/// Spacing form of a combining accent, for a row above or below a tall box
+pub(super) fn spacing_accent(mark: char) -> char {
+    match mark {
+        '\u{300}' => '`',
+        '\u{301}' => '´',
+        '\u{302}' => '^',
+        '\u{303}' | '\u{330}' => '~',
+        '\u{306}' => '˘',
+        '\u{307}' => '˙',
+        '\u{308}' => '¨',
+        '\u{30A}' => '˚',
+        '\u{30C}' => 'ˇ',
+        '\u{331}' | '\u{332}' => '_',
+        '\u{20D6}' | '\u{20EE}' => '←',
+        '\u{20D7}' | '\u{20EF}' => '→',
+        '\u{20E1}' => '↔',
+        _ => '‾',
+    }
+}
+
+/// A fraction part or radicand, in parentheses when it's more than one atom
+fn wrapped(nodes: &[Node], script: bool) -> String {
+    let text = linear(nodes, script);
+    if nodes.len() > 1 {
+        format!("({})", text)
+    } else {
+        text
+    }
+}
+
+/// Every character of `text` mapped through `map`, if they all have a mapping
+fn convert(text: &str, map: &HashMap<char, char>) -> Option<String> {
+    text.chars().map(|c| map.get(&c).copied()).collect()
+}
+
+/// A script as Unicode sub- or superscript characters, or marked with `_`/`^`
+fn script_text(nodes: &[Node], map: &HashMap<char, char>, mark: char) -> String {
+    let text = linear(nodes, true);
+    if let Some(converted) = convert(&text, map) {
+        converted
+    } else if text.chars().count() == 1 {
+        format!("{}{}", mark, text)
+    } else {
+        format!("{}({})", mark, text)
+    }
+}
+
+/// Formula laid out over several rows, for display math
+pub(super) fn display(nodes: &[Node]) -> Vec<String> {
+    list_box(nodes, false)
+        .lines
+        .into_iter()
+        .map(|line| line.trim_end().to_string())
+        .collect()
+}
+
+fn list_box(nodes: &[Node], script: bool) -> TextBox {
+    let gaps = gaps(nodes, script);
+    let mut boxes = Vec::new();
+    for (node, gap) in nodes.iter().zip(gaps) {
+        boxes.push(node_box(node, script));
+        if gap {
+            boxes.push(TextBox::text(" "));
+        }
+    }
+    TextBox::row(boxes)
+}
+
+fn node_box(node: &Node, script: bool) -> TextBox {
+    match node {
+        Node::Symbol(..) | Node::Space(_) | Node::Op { .. } => {
+            TextBox::text(&linear_node(node, script))
+        }
+        Node::Group(nodes) => list_box(nodes, script),
+        Node::Scripts { base, sub, sup } => {
+            scripts_box(base, sub.as_deref(), sup.as_deref(), script)
+        }
+        Node::Frac { num, den, bar } => {
+            let num = list_box(num, script);
+            let den = list_box(den, script);
+            let baseline = num.height();
+            if *bar {
+                let width = num.width.max(den.width) + 2;
+                TextBox::column(vec![num, TextBox::text(&"─".repeat(width)), den], baseline)
+            } else {
+                TextBox::column(vec![num, den], baseline - 1)
+            }
+        }
+        Node::Sqrt { index, body } => sqrt_box(index.as_deref(), &list_box(body, script)),
+        Node::Delimited { open, close, body } => {
+            let body = list_box(body, script);
+            let (height, baseline) = (body.height(), body.baseline);
+            TextBox::row(vec![
+                delimiter(open, height, baseline),
+                body,
+                delimiter(close, height, baseline),
+            ])
+        }
+        Node::Matrix {
+            rows,
+            open,
+            close,
+            align,
+        } => {
+            let body = matrix_box(rows, *align, script);
+            let (height, baseline) = (body.height(), body.baseline);
+            let mut parts = vec![delimiter(open, height, baseline)];
+            if *align != Align::Center && !open.is_empty() {
+                parts.push(TextBox::text(" "));
+            }
+            parts.push(body);
+            parts.push(delimiter(close, height, baseline));
+            TextBox::row(parts)
+        }
+        Node::Accent {
+            mark,
+            placement,
+            body,
+        } => {
+            let body = list_box(body, script);
+            if body.height() == 1 {
+                return TextBox {
+                    lines: vec![accented(&body.lines[0], *mark, *placement)],
+                    ..body
+                };
+            }
+            let glyph = spacing_accent(*mark).to_string();
+            match placement {
+                Placement::Over => {
+                    let baseline = body.baseline + 1;
+                    TextBox::column(vec![TextBox::text(&glyph), body], baseline)
+                }
+                Placement::Wide => {
+                    let baseline = body.baseline + 1;
+                    let bar = TextBox::text(&glyph.repeat(body.width));
+                    TextBox::column(vec![bar, body], baseline)
+                }
+                Placement::Under => {
+                    let baseline = body.baseline;
+                    let bar = TextBox::text(&glyph.repeat(body.width));
+                    TextBox::column(vec![body, bar], baseline)
+                }
+            }
+        }
+    }
+}
+
+fn scripts_box(base: &Node, sub: Option<&[Node]>, sup: Option<&[Node]>, script: bool) -> TextBox {
+    let base_box = node_box(base, script);
+
+    // Limits above and below a big operator
+    if let (Node::Op { limits: true, .. }, false) = (base, script) {
+        let mut parts = Vec::new();
+        let mut baseline = base_box.baseline;
+        if let Some(sup) = sup {
+            let sup = list_box(sup, true);
+            baseline += sup.height();
+            parts.push(sup);
+        }
+        parts.push(base_box);
+        parts.extend(sub.map(|sub| list_box(sub, true)));
+        return TextBox::column(parts, baseline);
+    }
+
+    // Unicode sub- and superscripts on a single line
+    if base_box.height() == 1 {
+        let sub_text = sub.map(|sub| convert(&linear(sub, true), &SUBSCRIPTS));
+        let sup_text = sup.map(|sup| convert(&linear(sup, true), &SUPERSCRIPTS));
+        if let (Some(sub_text), Some(sup_text)) = (
+            sub_text.unwrap_or(Some(String::new())),
+            sup_text.unwrap_or(Some(String::new())),
+        ) {
+            return TextBox::text(&format!("{}{}{}", base_box.lines[0], sub_text, sup_text));
+        }
+    }
+
+    // Scripts raised above and lowered below the base
+    let sup = sup.map(|sup| list_box(sup, true));
+    let sub = sub.map(|sub| list_box(sub, true));
+    let width = sup.iter().chain(&sub).map(|b| b.width).max().unwrap_or(0);
+    let sup_height = sup.as_ref().map_or(0, TextBox::height);
+    let mut lines = Vec::new();
+    for line in sup.iter().flat_map(|b| &b.lines) {
+        lines.push(format!(
+            "{}{}",
+            " ".repeat(base_box.width),
+            pad(line, 0, width - line.width())
+        ));
+    }
+    for line in &base_box.lines {
+        lines.push(format!("{}{}", line, " ".repeat(width)));
+    }
+    for line in sub.iter().flat_map(|b| &b.lines) {
+        lines.push(format!(
+            "{}{}",
+            " ".repeat(base_box.width),
+            pad(line, 0, width - line.width())
+        ));
+    }
+    TextBox {
+        lines,
+        width: base_box.width + width,
+        baseline: sup_height + base_box.baseline,
+    }
+}
+
+/// Radical with an overbar, its diagonal as tall as the radicand
+fn sqrt_box(index: Option<&[Node]>, body: &TextBox) -> TextBox {
+    let height = body.height();
+    let radical_width = height + 1;
+    let index = index.map(|index| {
+        let text = linear(index, true);
+        convert(&text, &SUPERSCRIPTS).unwrap_or(text)
+    });
+    let index_width = index.as_deref().map_or(0, UnicodeWidthStr::width);
+    let indent = index_width.saturating_sub(radical_width);
+
+    let mut lines = vec![format!(
+        "{}{}{}",
+        index.as_deref().unwrap_or(""),
+        " ".repeat(radical_width + indent - index_width),
+        "_".repeat(body.width + 1)
+    )];
+    for (r, line) in body.lines.iter().enumerate() {
+        let slash = height - r;
+        let mut prefix = " ".repeat(indent);
+        if r + 1 == height {
+            prefix.push('╲');
+            prefix.push_str(&" ".repeat(slash - 1));
+        } else {
+            prefix.push_str(&" ".repeat(slash));
+        }
+        prefix.push('╱');
+        prefix.push_str(&" ".repeat(radical_width - slash - 1));
+        lines.push(format!("{} {}", prefix, line));
+    }
+    TextBox {
+        lines,
+        width: indent + radical_width + body.width + 1,
+        baseline: body.baseline + 1,
+    }
+}
+
+/// A delimiter stretched to `height` rows
+fn delimiter(delimiter: &str, height: usize, baseline: usize) -> TextBox {
+    if delimiter.is_empty() {
+        return TextBox {
+            lines: vec![String::new(); height],
+            width: 0,
+            baseline,
+        };
+    }
+    if height == 1 {
+        return TextBox::text(delimiter);
+    }
+    let middle = (height - 1) / 2;
+    let pieces = match delimiter {
+        "(" => ["⎛", "⎜", "⎝", "⎜"],
+        ")" => ["⎞", "⎟", "⎠", "⎟"],
+        "[" => ["⎡", "⎢", "⎣", "⎢"],
+        "]" => ["⎤", "⎥", "⎦", "⎥"],
+        "{" => ["⎧", "⎪", "⎩", "⎨"],
+        "}" => ["⎫", "⎪", "⎭", "⎬"],
+        "⌈" => ["⎡", "⎢", "⎢", "⎢"],
+        "⌉" => ["⎤", "⎥", "⎥", "⎥"],
+        "⌊" => ["⎢", "⎢", "⎣", "⎢"],
+        "⌋" => ["⎥", "⎥", "⎦", "⎥"],
+        "⟨" => ["╱", "╱", "╲", "╲"],
+        "⟩" => ["╲", "╲", "╱", "╱"],
+        "|" => ["│"; 4],
+        _ => [delimiter; 4],
+    };
+    let lines = (0..height)
+        .map(|r| {
+            let piece = match r {
+                0 => pieces[0],
+                _ if r + 1 == height => pieces[2],
+                _ if matches!(delimiter, "⟨" | "⟩") => pieces[if r <= middle { 1 } else { 3 }],
+                _ if r == middle && height > 2 => pieces[3],
+                _ => pieces[1],
+            };
+            piece.to_string()
+        })
+        .collect();
+    TextBox {
+        lines,
+        width: 1,
+        baseline,
+    }
+}
+
+/// Cells of a matrix in aligned columns
+fn matrix_box(rows: &[Vec<Vec<Node>>], align: Align, script: bool) -> TextBox {
+    let cells: Vec<Vec<TextBox>> = rows
+        .iter()
+        .map(|row| row.iter().map(|cell| list_box(cell, script)).collect())
+        .collect();
+    let columns = cells.iter().map(Vec::len).max().unwrap_or(0);
+    let widths: Vec<usize> = (0..columns)
+        .map(|c| {
+            cells
+                .iter()
+                .filter_map(|row| row.get(c).map(|cell| cell.width))
+                .max()
+                .unwrap_or(0)
+        })
+        .collect();
+    let tall = cells.iter().flatten().any(|cell| cell.height() > 1);
+
+    let mut lines = Vec::new();
+    for (r, row) in cells.into_iter().enumerate() {
+        if r > 0 && tall {
+            lines.push(String::new());
+        }
+        let mut parts = Vec::new();
+        let mut row = row.into_iter();
+        for (c, &width) in widths.iter().enumerate() {
+            let cell_align = match align {
+                Align::Pairs if c % 2 == 1 => Align::Left,
+                align => align,
+            };
+            let cell = row.next().unwrap_or_else(|| TextBox::text(""));
+            parts.push(cell.aligned(width, cell_align));
+            if c + 1 < columns {
+                let gap = if align == Align::Pairs && c % 2 == 0 {
+                    1
+                } else {
+                    2
+                };
+                parts.push(TextBox::text(&" ".repeat(gap)));
+            }
+        }
+        lines.extend(TextBox::row(parts).lines);
+    }
+
+    let width = lines.iter().map(|line| line.width()).max().unwrap_or(0);
+    let lines: Vec<String> = lines
+        .iter()
+        .map(|line| pad(line, 0, width - line.width()))
+        .collect();
+    let baseline = lines.len().saturating_sub(1) / 2;
+    TextBox {
+        lines,
+        width,
+        baseline,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::parse::parse;
+    use super::*;
+
+    fn show(latex: &str) -> Vec<String> {
+        display(&parse(latex))
+    }
+
+    #[test]
+    fn test_linear() {
+        assert_eq!(linear(&parse("a+b=-c"), false), "a + b = −c");
+        assert_eq!(linear(&parse("\\frac{x+1}{2}"), false), "(x + 1)/2");
+        assert_eq!(linear(&parse("\\sqrt[3]{x}"), false), "∛x");
+        assert_eq!(linear(&parse("e^{i\\pi}"), false), "e^(iπ)");
+        assert_eq!(linear(&parse("\\sin x"), false), "sin x");
+    }
+
+    #[test]
+    fn test_fraction_is_stacked() {
+        assert_eq!(
+            show("\\frac{a+b}{c} = 1"),
+            [" a + b", "─────── = 1", "   c"]
+        );
+    }
+
+    #[test]
+    fn test_sum_with_limits() {
+        assert_eq!(
+            show("\\sum_{i=0}^{n} \\frac{1}{i}"),
+            [" n   1", " Σ  ───", "i=0  i"]
+        );
+    }
+
+    #[test]
+    fn test_square_root() {
+        assert_eq!(show("\\sqrt{x+1}"), ["  ______", "╲╱ x + 1"]);
+        assert_eq!(
+            show("\\sqrt{\\frac{a}{b}}"),
+            ["    ____", "   ╱  a", "  ╱  ───", "╲╱    b"]
+        );
+    }
+
+    #[test]
+    fn test_matrix_with_brackets() {
+        assert_eq!(
+            show("A = \\begin{pmatrix} 1 & 20 \\\\ 300 & 4 \\end{pmatrix}"),
+            ["A = ⎛ 1   20⎞", "    ⎝300  4 ⎠"]
+        );
+        assert_eq!(
+            show("\\left[ \\frac{1}{2} \\right]"),
+            ["⎡ 1 ⎤", "⎢───⎥", "⎣ 2 ⎦"]
+        );
+    }
+
+    #[test]
+    fn test_cases() {
+        let lines = show("|x| = \\begin{cases} x & x \\geq 0 \\\\ -x & x < 0 \\end{cases}");
+        assert_eq!(lines, ["|x| = ⎧ x   x ≥ 0", "      ⎩ −x  x < 0"]);
+    }
+
+    #[test]
+    fn test_accents() {
+        assert_eq!(
+            show("\\vec{v} + \\overline{ab}"),
+            ["v\u{20D7} + a\u{305}b\u{305}"]
+        );
+        assert_eq!(show("\\overline{\\frac{1}{2}}"), ["‾‾‾", " 1", "───", " 2"]);
+    }
+}
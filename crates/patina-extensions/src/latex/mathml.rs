@@ -0,0 +1,246 @@
+//! MathML output, for HTML export.
+
+use super::layout::spacing_accent;
+use super::parse::{Align, Class, Node, Placement};
+
+/// A `<math>` element for `nodes`, with the TeX source as an annotation
+pub(super) fn math(source: &str, nodes: &[Node], display: bool) -> String {
+    format!(
+        "<math xmlns=\"http://www.w3.org/1998/Math/MathML\" display=\"{}\">\
+         <semantics>{}<annotation encoding=\"application/x-tex\">{}</annotation></semantics></math>",
+        if display { "block" } else { "inline" },
+        row(nodes),
+        escape(source.trim())
+    )
+}
+
+/// Escape text for XML content and attribute values
+fn escape(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => out.push_str("&amp;"),
+            '<' => out.push_str("&lt;"),
+            '>' => out.push_str("&gt;"),
+            '"' => out.push_str("&quot;"),
+            _ => out.push(c),
+        }
+    }
+    out
+}
+
+/// Nodes as one `<mrow>`, with runs of digits merged into a single number
+fn row(nodes: &[Node]) -> String {
+    let mut out = String::from("<mrow>");
+    let mut number = String::new();
+    for node in nodes {
+        match node {
+            Node::Symbol(text, Class::Ord) if is_number(text) => number.push_str(text),
+            _ => {
+                flush_number(&mut out, &mut number);
+                out.push_str(&element(node));
+            }
+        }
+    }
+    flush_number(&mut out, &mut number);
+    out.push_str("</mrow>");
+    out
+}
+
+fn is_number(text: &str) -> bool {
+    !text.is_empty() && text.chars().all(|c| c.is_ascii_digit() || c == '.')
+}
+
+fn flush_number(out: &mut String, number: &mut String) {
+    if !number.is_empty() {
+        out.push_str(&format!("<mn>{}</mn>", escape(number)));
+        number.clear();
+    }
+}
+
+/// Big operator glyph for MathML, where the terminal form uses a Greek letter
+fn operator_glyph(name: &str) -> &str {
+    match name {
+        "Σ" => "∑",
+        "Π" => "∏",
+        _ => name,
+    }
+}
+
+/// Whether an operator is a word like `lim` rather than a symbol
+fn is_word(name: &str) -> bool {
+    name.chars().count() > 1 && name.chars().all(char::is_alphabetic)
+}
+
+fn element(node: &Node) -> String {
+    match node {
+        Node::Symbol(text, class) => symbol(text, *class),
+        Node::Space(columns) => format!("<mspace width=\"{}em\"/>", *columns as f32 / 2.0),
+        Node::Group(nodes) => row(nodes),
+        Node::Op { name, .. } => operator(name),
+        Node::Scripts { base, sub, sup } => {
+            let limits = matches!(**base, Node::Op { limits: true, .. });
+            let (under, over, both) = if limits {
+                ("munder", "mover", "munderover")
+            } else {
+                ("msub", "msup", "msubsup")
+            };
+            let base = element(base);
+            match (sub, sup) {
+                (Some(sub), Some(sup)) => {
+                    format!("<{0}>{1}{2}{3}</{0}>", both, base, row(sub), row(sup))
+                }
+                (Some(sub), None) => format!("<{0}>{1}{2}</{0}>", under, base, row(sub)),
+                (None, Some(sup)) => format!("<{0}>{1}{2}</{0}>", over, base, row(sup)),
+                (None, None) => base,
+            }
+        }
+        Node::Frac { num, den, bar } => format!(
+            "<mfrac{}>{}{}</mfrac>",
+            if *bar { "" } else { " linethickness=\"0\"" },
+            row(num),
+            row(den)
+        ),
+        Node::Sqrt { index: None, body } => format!("<msqrt>{}</msqrt>", row(body)),
+        Node::Sqrt {
+            index: Some(index),
+            body,
+        } => format!("<mroot>{}{}</mroot>", row(body), row(index)),
+        Node::Delimited { open, close, body } => {
+            format!("<mrow>{}{}{}</mrow>", fence(open), row(body), fence(close))
+        }
+        Node::Matrix {
+            rows,
+            open,
+            close,
+            align,
+        } => {
+            let columns = rows.iter().map(Vec::len).max().unwrap_or(0);
+            let alignment = match align {
+                Align::Center => String::new(),
+                Align::Left => " columnalign=\"left\"".to_string(),
+                Align::Pairs => {
+                    let sides: Vec<&str> = (0..columns)
+                        .map(|i| if i % 2 == 0 { "right" } else { "left" })
+                        .collect();
+                    format!(" columnalign=\"{}\"", sides.join(" "))
+                }
+            };
+            let mut table = format!("<mtable{}>", alignment);
+            for cells in rows {
+                table.push_str("<mtr>");
+                for cell in cells {
+                    table.push_str(&format!("<mtd>{}</mtd>", row(cell)));
+                }
+                table.push_str("</mtr>");
+            }
+            table.push_str("</mtable>");
+            format!("<mrow>{}{}{}</mrow>", fence(open), table, fence(close))
+        }
+        Node::Accent {
+            mark,
+            placement,
+            body,
+        } => {
+            let mark = escape(&spacing_accent(*mark).to_string());
+            match placement {
+                Placement::Over | Placement::Wide => format!(
+                    "<mover accent=\"true\">{}<mo stretchy=\"{}\">{}</mo></mover>",
+                    row(body),
+                    *placement == Placement::Wide,
+                    mark
+                ),
+                Placement::Under => format!(
+                    "<munder accentunder=\"true\">{}<mo stretchy=\"true\">{}</mo></munder>",
+                    row(body),
+                    mark
+                ),
+            }
+        }
+    }
+}
+
+fn symbol(text: &str, class: Class) -> String {
+    let text_escaped = escape(text);
+    match class {
+        // Function names such as `\sin` and `\operatorname`
+        Class::Op => format!("<mi>{}</mi>", text_escaped),
+        Class::Bin | Class::Rel | Class::Punct => format!("<mo>{}</mo>", text_escaped),
+        Class::Open | Class::Close => format!("<mo stretchy=\"false\">{}</mo>", text_escaped),
+        Class::Ord => {
+            let mut chars = text.chars();
+            match (chars.next(), chars.next()) {
+                (Some(c), None) if c.is_alphabetic() || is_ord_symbol(c) => {
+                    format!("<mi>{}</mi>", text_escaped)
+                }
+                (Some(_), None) => format!("<mo>{}</mo>", text_escaped),
+                // `\text{...}`, or an unknown command kept as written
+                _ => format!("<mtext>{}</mtext>", text_escaped),
+            }
+        }
+    }
+}
+
+/// Non-letter symbols that are identifiers rather than operators
+fn is_ord_symbol(c: char) -> bool {
+    matches!(c, '∞' | '∅' | '∂' | '∇' | 'ℏ' | 'ℓ' | '℘' | '′' | '″' | '‴')
+}
+
+fn operator(name: &str) -> String {
+    if is_word(name) {
+        format!("<mi>{}</mi>", escape(name))
+    } else {
+        format!(
+            "<mo largeop=\"true\" movablelimits=\"true\">{}</mo>",
+            escape(operator_glyph(name))
+        )
+    }
+}
+
+/// A stretchy delimiter, or nothing for an empty one
+fn fence(delimiter: &str) -> String {
+    if delimiter.is_empty() {
+        String::new()
+    } else {
+        format!(
+            "<mo fence=\"true\" stretchy=\"true\">{}</mo>",
+            escape(delimiter)
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::parse::parse;
+    use super::*;
+
+    fn mathml(latex: &str) -> String {
+        row(&parse(latex))
+    }
+
+    #[test]
+    fn test_elements() {
+        assert_eq!(
+            mathml("x^2 + 10"),
+            "<mrow><msup><mi>x</mi><mrow><mn>2</mn></mrow></msup><mo>+</mo><mn>10</mn></mrow>"
+        );
+        assert_eq!(
+            mathml("\\frac{a}{b}"),
+            "<mrow><mfrac><mrow><mi>a</mi></mrow><mrow><mi>b</mi></mrow></mfrac></mrow>"
+        );
+        assert!(mathml("\\sum_{i=1}^n i").starts_with("<mrow><munderover><mo largeop"));
+        assert!(mathml("\\sum_{i=1}^n i").contains(">∑</mo>"));
+        assert!(mathml("\\int_0^1").starts_with("<mrow><msubsup>"));
+        assert!(mathml("\\sqrt[3]{x}").starts_with("<mrow><mroot>"));
+        assert!(mathml("\\text{if } x < y").contains("<mtext>if </mtext>"));
+        assert!(mathml("x < y").contains("<mo>&lt;</mo>"));
+    }
+
+    #[test]
+    fn test_matrix_and_accent() {
+        let matrix = mathml("\\begin{pmatrix} a & b \\\\ c & d \\end{pmatrix}");
+        assert!(matrix.contains("<mo fence=\"true\" stretchy=\"true\">(</mo><mtable><mtr>"));
+        assert_eq!(matrix.matches("<mtd>").count(), 4);
+        assert!(mathml("\\vec{v}").contains("<mover accent=\"true\">"));
+    }
+}
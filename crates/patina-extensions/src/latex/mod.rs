@@ -1,4 +1,10 @@
-//! LaTeX math rendering to Unicode.
+//! LaTeX math rendering to Unicode and MathML.
+
+mod fonts;
+mod layout;
+mod mathml;
+mod parse;
+mod symbols;
 
 use once_cell::sync::Lazy;
 use std::collections::HashMap;
@@ -103,8 +109,13 @@ static SUPERSCRIPTS: Lazy<HashMap<char, char>> = Lazy::new(|| {
     m.insert('=', '⁼');
     m.insert('(', '⁽');
     m.insert(')', '⁾');
-    m.insert('n', 'ⁿ');
-    m.insert('i', 'ⁱ');
+    m.insert('−', '⁻');
+    for (letter, sup) in "abcdefghijklmnoprstuvwxyz"
+        .chars()
+        .zip("ᵃᵇᶜᵈᵉᶠᵍʰⁱʲᵏˡᵐⁿᵒᵖʳˢᵗᵘᵛʷˣʸᶻ".chars())
+    {
+        m.insert(letter, sup);
+    }
     m
 });
 
@@ -126,12 +137,11 @@ static SUBSCRIPTS: Lazy<HashMap<char, char>> = Lazy::new(|| {
     m.insert('=', '₌');
     m.insert('(', '₍');
     m.insert(')', '₎');
-    m.insert('a', 'ₐ');
-    m.insert('e', 'ₑ');
-    m.insert('i', 'ᵢ');
-    m.insert('n', 'ₙ');
-    m.insert('o', 'ₒ');
-    m.insert('x', 'ₓ');
+    m.insert('−', '₋');
+    for (letter, sub) in "aehijklmnoprstuvx".chars().zip("ₐₑₕᵢⱼₖₗₘₙₒₚᵣₛₜᵤᵥₓ".chars())
+    {
+        m.insert(letter, sub);
+    }
     m
 });
 
@@ -144,79 +154,22 @@ impl LatexRenderer {
         Self
     }
 
-    /// Render LaTeX to Unicode string
+    /// Render LaTeX to a single line of Unicode, for inline math
     pub fn render(&self, latex: &str) -> String {
-        let mut result = String::new();
-        let mut chars = latex.chars().peekable();
-
-        while let Some(c) = chars.next() {
-            match c {
-                '\\' => {
-                    // Parse command
-                    let cmd: String = chars.by_ref().take_while(|c| c.is_alphabetic()).collect();
+        layout::linear(&parse::parse(latex), false)
+    }
 
-                    if let Some(symbol) = SYMBOLS.get(cmd.as_str()) {
-                        result.push_str(symbol);
-                    } else {
-                        result.push('\\');
-                        result.push_str(&cmd);
-                    }
-                }
-                '^' => {
-                    // Superscript
-                    if let Some(next) = chars.next() {
-                        if next == '{' {
-                            // Grouped superscript
-                            let group: String = chars.by_ref().take_while(|c| *c != '}').collect();
-                            for gc in group.chars() {
-                                if let Some(&sup) = SUPERSCRIPTS.get(&gc) {
-                                    result.push(sup);
-                                } else {
-                                    result.push(gc);
-                                }
-                            }
-                        } else if let Some(&sup) = SUPERSCRIPTS.get(&next) {
-                            result.push(sup);
-                        } else {
-                            result.push('^');
-                            result.push(next);
-                        }
-                    }
-                }
-                '_' => {
-                    // Subscript
-                    if let Some(next) = chars.next() {
-                        if next == '{' {
-                            let group: String = chars.by_ref().take_while(|c| *c != '}').collect();
-                            for gc in group.chars() {
-                                if let Some(&sub) = SUBSCRIPTS.get(&gc) {
-                                    result.push(sub);
-                                } else {
-                                    result.push(gc);
-                                }
-                            }
-                        } else if let Some(&sub) = SUBSCRIPTS.get(&next) {
-                            result.push(sub);
-                        } else {
-                            result.push('_');
-                            result.push(next);
-                        }
-                    }
-                }
-                '{' | '}' => {
-                    // Skip grouping braces
-                }
-                ' ' => {
-                    // Preserve spaces
-                    result.push(' ');
-                }
-                _ => {
-                    result.push(c);
-                }
-            }
-        }
+    /// Render LaTeX as several lines of Unicode, for display math.
+    ///
+    /// Fractions are stacked, radicals get an overbar, big operators carry
+    /// their limits above and below, and matrices get full-height brackets.
+    pub fn render_display(&self, latex: &str) -> Vec<String> {
+        layout::display(&parse::parse(latex))
+    }
 
-        result
+    /// Render LaTeX as a MathML `<math>` element, for HTML export
+    pub fn render_mathml(&self, latex: &str, display: bool) -> String {
+        mathml::math(latex, &parse::parse(latex), display)
     }
 }
 
@@ -236,14 +189,38 @@ mod tests {
         assert_eq!(renderer.render("\\alpha"), "α");
         assert_eq!(renderer.render("\\sum"), "Σ");
         assert_eq!(renderer.render("\\infty"), "∞");
+        assert_eq!(renderer.render("A \\to B"), "A → B");
+        assert_eq!(renderer.render("x \\mapsto x^2"), "x ↦ x²");
+        assert_eq!(renderer.render("A \\subseteq B"), "A ⊆ B");
+        assert_eq!(renderer.render("\\bigoplus_i V_i"), "⨁ᵢ Vᵢ");
+    }
+
+    #[test]
+    fn test_fonts_and_text() {
+        let renderer = LatexRenderer::new();
+        assert_eq!(renderer.render("\\mathbb{R}^n"), "ℝⁿ");
+        assert_eq!(renderer.render("\\mathbf{v} \\in \\mathbb{Z}"), "𝐯 ∈ ℤ");
+        assert_eq!(renderer.render("\\mathfrak{g}"), "𝔤");
+        assert_eq!(renderer.render("x \\text{ if } y"), "x if y");
+        assert_eq!(renderer.render("\\hat{x}"), "x\u{302}");
+    }
+
+    #[test]
+    fn test_mathml() {
+        let renderer = LatexRenderer::new();
+        let math = renderer.render_mathml("a < b", true);
+        assert!(math
+            .starts_with("<math xmlns=\"http://www.w3.org/1998/Math/MathML\" display=\"block\">"));
+        assert!(math.ends_with(
+            "<annotation encoding=\"application/x-tex\">a &lt; b</annotation></semantics></math>"
+        ));
     }
 
     #[test]
     fn test_superscripts() {
         let renderer = LatexRenderer::new();
         assert_eq!(renderer.render("x^2"), "x²");
-        // TODO: Support braced superscripts better (e^{ix} -> eⁱˣ)
-        assert_eq!(renderer.render("e^{ix}"), "eⁱx");
+        assert_eq!(renderer.render("e^{ix}"), "eⁱˣ");
     }
 
     #[test]
@@ -0,0 +1,715 @@
+//! TeX math parser.
+//!
+//! Parsing never fails: unknown commands are kept as written and unbalanced
+//! groups are closed at the end of the input, so a half-typed formula still
+//! shows up in the preview.
+
+use super::fonts::Font;
+use super::symbols::{self, MathClass};
+use super::SYMBOLS;
+
+/// Spacing class of an atom, as in TeX
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(super) enum Class {
+    Ord,
+    /// Binary operator, spaced on both sides unless used as a sign
+    Bin,
+    /// Relation, spaced on both sides
+    Rel,
+    /// Punctuation, spaced after
+    Punct,
+    Open,
+    Close,
+    /// Function name or big operator, spaced before its argument
+    Op,
+}
+
+/// How the columns of a matrix environment are aligned
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(super) enum Align {
+    Center,
+    Left,
+    /// `aligned`: right, left, right, left...
+    Pairs,
+}
+
+/// Where an accent goes
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(super) enum Placement {
+    /// Over the middle of the argument, like `\hat`
+    Over,
+    /// Over every character, like `\overline`
+    Wide,
+    /// Under every character, like `\underline`
+    Under,
+}
+
+/// A parsed formula
+#[derive(Debug, Clone, PartialEq)]
+pub(super) enum Node {
+    Symbol(String, Class),
+    /// Explicit space, in columns
+    Space(usize),
+    Group(Vec<Node>),
+    /// Big operator or function like `\sum` and `\lim`
+    Op {
+        name: String,
+        limits: bool,
+    },
+    Scripts {
+        base: Box<Node>,
+        sub: Option<Vec<Node>>,
+        sup: Option<Vec<Node>>,
+    },
+    /// `\frac`, or `\binom` without the bar
+    Frac {
+        num: Vec<Node>,
+        den: Vec<Node>,
+        bar: bool,
+    },
+    Sqrt {
+        index: Option<Vec<Node>>,
+        body: Vec<Node>,
+    },
+    /// `\left( ... \right)`; an empty delimiter is `.`
+    Delimited {
+        open: String,
+        close: String,
+        body: Vec<Node>,
+    },
+    Matrix {
+        rows: Vec<Vec<Vec<Node>>>,
+        open: String,
+        close: String,
+        align: Align,
+    },
+    /// Combining mark such as `\vec` or `\overline`
+    Accent {
+        mark: char,
+        placement: Placement,
+        body: Vec<Node>,
+    },
+}
+
+impl Node {
+    /// Spacing class of the node
+    pub(super) fn class(&self) -> Class {
+        match self {
+            Node::Symbol(_, class) => *class,
+            Node::Op { .. } => Class::Op,
+            Node::Scripts { base, .. } => base.class(),
+            _ => Class::Ord,
+        }
+    }
+}
+
+/// Operator names with limits
+const LIMIT_NAMES: &[&str] = &[
+    "lim", "limsup", "liminf", "max", "min", "sup", "inf", "det", "gcd", "Pr", "argmax", "argmin",
+];
+
+/// Function names, set upright and followed by a space
+const FUNCTIONS: &[&str] = &[
+    "sin", "cos", "tan", "cot", "sec", "csc", "arcsin", "arccos", "arctan", "sinh", "cosh", "tanh",
+    "coth", "log", "ln", "lg", "exp", "arg", "deg", "dim", "hom", "ker",
+];
+
+/// Where a list of atoms ends
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Until {
+    End,
+    /// `}`
+    Brace,
+    /// `]`, for the index of `\sqrt`
+    Bracket,
+    /// `\right`
+    Right,
+    /// `&`, `\\` or `\end`, in a matrix
+    Cell,
+}
+
+/// Parse TeX math into a list of atoms
+pub(super) fn parse(latex: &str) -> Vec<Node> {
+    Parser {
+        chars: latex.chars().collect(),
+        pos: 0,
+    }
+    .list(Until::End)
+}
+
+struct Parser {
+    chars: Vec<char>,
+    pos: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<char> {
+        self.chars.get(self.pos).copied()
+    }
+
+    fn skip_whitespace(&mut self) {
+        while self.peek().is_some_and(char::is_whitespace) {
+            self.pos += 1;
+        }
+    }
+
+    /// Check for `\name` (not followed by more letters) at the current position
+    fn at_command(&self, name: &str) -> bool {
+        let mut pos = self.pos;
+        if self.chars.get(pos) != Some(&'\\') {
+            return false;
+        }
+        pos += 1;
+        for c in name.chars() {
+            if self.chars.get(pos) != Some(&c) {
+                return false;
+            }
+            pos += 1;
+        }
+        !(name.chars().all(char::is_alphabetic)
+            && self.chars.get(pos).is_some_and(|c| c.is_alphabetic()))
+    }
+
+    /// Read a command name after `\`: a run of letters or a single other character
+    fn command_name(&mut self) -> String {
+        let start = self.pos;
+        while self.peek().is_some_and(char::is_alphabetic) {
+            self.pos += 1;
+        }
+        if self.pos == start && self.pos < self.chars.len() {
+            self.pos += 1;
+        }
+        self.chars[start..self.pos].iter().collect()
+    }
+
+    /// Raw text of a `{...}` group, such as an environment name
+    fn raw_group(&mut self) -> String {
+        self.skip_whitespace();
+        if self.peek() != Some('{') {
+            return String::new();
+        }
+        self.pos += 1;
+        let start = self.pos;
+        let mut depth = 0;
+        while let Some(c) = self.peek() {
+            match c {
+                '{' => depth += 1,
+                '}' if depth == 0 => break,
+                '}' => depth -= 1,
+                _ => {}
+            }
+            self.pos += 1;
+        }
+        let text = self.chars[start..self.pos].iter().collect();
+        self.pos = (self.pos + 1).min(self.chars.len());
+        text
+    }
+
+    fn list(&mut self, until: Until) -> Vec<Node> {
+        let mut nodes = Vec::new();
+        loop {
+            self.skip_whitespace();
+            let Some(c) = self.peek() else {
+                break;
+            };
+            match c {
+                '}' if until == Until::Brace => break,
+                ']' if until == Until::Bracket => break,
+                '&' if until == Until::Cell => break,
+                _ if until == Until::Right && self.at_command("right") => break,
+                _ if until == Until::Cell && (self.at_command("\\") || self.at_command("end")) => {
+                    break
+                }
+                '}' | '&' => self.pos += 1,
+                '^' | '_' => {
+                    self.pos += 1;
+                    let script = self.argument();
+                    attach_script(&mut nodes, c == '^', script);
+                }
+                _ if self.at_command("limits") || self.at_command("nolimits") => {
+                    let limits = self.at_command("limits");
+                    self.pos += 1;
+                    self.command_name();
+                    if let Some(Node::Op { limits: l, .. }) = nodes.last_mut() {
+                        *l = limits;
+                    }
+                }
+                _ => nodes.extend(self.atom()),
+            }
+        }
+        nodes
+    }
+
+    /// A script or command argument: a group, a command or a single character
+    fn argument(&mut self) -> Vec<Node> {
+        self.skip_whitespace();
+        match self.peek() {
+            Some('{') => {
+                self.pos += 1;
+                let nodes = self.list(Until::Brace);
+                self.pos = (self.pos + 1).min(self.chars.len());
+                nodes
+            }
+            Some(_) => self.atom().into_iter().collect(),
+            None => Vec::new(),
+        }
+    }
+
+    fn atom(&mut self) -> Option<Node> {
+        let c = self.peek()?;
+        self.pos += 1;
+        match c {
+            '{' => {
+                let nodes = self.list(Until::Brace);
+                self.pos = (self.pos + 1).min(self.chars.len());
+                Some(Node::Group(nodes))
+            }
+            '\\' => {
+                let name = self.command_name();
+                self.command(&name)
+            }
+            _ => Some(char_symbol(c)),
+        }
+    }
+
+    fn command(&mut self, name: &str) -> Option<Node> {
+        let node = match name {
+            "frac" | "dfrac" | "tfrac" | "cfrac" => Node::Frac {
+                num: self.argument(),
+                den: self.argument(),
+                bar: true,
+            },
+            "binom" | "dbinom" | "tbinom" => Node::Delimited {
+                open: "(".to_string(),
+                close: ")".to_string(),
+                body: vec![Node::Frac {
+                    num: self.argument(),
+                    den: self.argument(),
+                    bar: false,
+                }],
+            },
+            "sqrt" => {
+                self.skip_whitespace();
+                let index = (self.peek() == Some('[')).then(|| {
+                    self.pos += 1;
+                    let index = self.list(Until::Bracket);
+                    self.pos = (self.pos + 1).min(self.chars.len());
+                    index
+                });
+                Node::Sqrt {
+                    index,
+                    body: self.argument(),
+                }
+            }
+            "left" => {
+                let open = self.delimiter();
+                let body = self.list(Until::Right);
+                let close = if self.at_command("right") {
+                    self.pos += 1;
+                    self.command_name();
+                    self.delimiter()
+                } else {
+                    String::new()
+                };
+                Node::Delimited { open, close, body }
+            }
+            "right" => {
+                self.delimiter();
+                return None;
+            }
+            "begin" => {
+                let env = self.raw_group();
+                self.environment(&env)
+            }
+            "end" => {
+                self.raw_group();
+                return None;
+            }
+            "," | ":" | ";" | ">" | " " => Node::Space(1),
+            "quad" => Node::Space(2),
+            "qquad" => Node::Space(4),
+            "!" | "\\" => return None,
+            "{" => Node::Symbol("{".to_string(), Class::Open),
+            "}" => Node::Symbol("}".to_string(), Class::Close),
+            "|" => Node::Symbol("‖".to_string(), Class::Ord),
+            "not" => negated(self.argument()),
+            "text" | "mbox" | "hbox" | "textnormal" => {
+                Node::Symbol(text_mode(&self.raw_group()), Class::Ord)
+            }
+            "operatorname" => {
+                let limits = self.peek() == Some('*');
+                if limits {
+                    self.pos += 1;
+                }
+                let name = text_mode(&self.raw_group());
+                if limits {
+                    Node::Op { name, limits }
+                } else {
+                    Node::Symbol(name, Class::Op)
+                }
+            }
+            _ if name.len() == 1 && !name.chars().all(char::is_alphabetic) => {
+                Node::Symbol(name.to_string(), Class::Ord)
+            }
+            _ if name.starts_with("text") && Font::from_command(name).is_some() => {
+                let font = Font::from_command(name).unwrap_or(Font::Roman);
+                let text = text_mode(&self.raw_group());
+                Node::Symbol(text.chars().map(|c| font.apply(c)).collect(), Class::Ord)
+            }
+            _ if Font::from_command(name).is_some() => {
+                let font = Font::from_command(name).unwrap_or(Font::Roman);
+                let mut nodes = self.argument();
+                nodes.iter_mut().for_each(|node| restyle(node, font));
+                Node::Group(nodes)
+            }
+            _ if LIMIT_NAMES.contains(&name) => Node::Op {
+                name: name.to_string(),
+                limits: true,
+            },
+            _ if FUNCTIONS.contains(&name) => Node::Symbol(name.to_string(), Class::Op),
+            _ => match symbols::lookup(name) {
+                Some((
+                    mark,
+                    class @ (MathClass::Accent | MathClass::WideAccent | MathClass::BottomAccent),
+                )) => {
+                    let placement = match class {
+                        MathClass::Accent => Placement::Over,
+                        MathClass::WideAccent => Placement::Wide,
+                        _ => Placement::Under,
+                    };
+                    Node::Accent {
+                        mark,
+                        placement,
+                        body: self.argument(),
+                    }
+                }
+                Some((symbol, class)) => {
+                    // Glyphs that read better in a terminal, such as Σ for `\sum`
+                    let text = SYMBOLS
+                        .get(name)
+                        .map_or_else(|| symbol.to_string(), |s| s.to_string());
+                    match class {
+                        MathClass::Op => Node::Op {
+                            limits: !name.contains("int"),
+                            name: text,
+                        },
+                        MathClass::Bin => Node::Symbol(text, Class::Bin),
+                        MathClass::Rel => Node::Symbol(text, Class::Rel),
+                        MathClass::Open => Node::Symbol(text, Class::Open),
+                        MathClass::Close => Node::Symbol(text, Class::Close),
+                        MathClass::Punct => Node::Symbol(text, Class::Punct),
+                        _ => Node::Symbol(text, Class::Ord),
+                    }
+                }
+                None => match SYMBOLS.get(name) {
+                    Some(symbol) => Node::Symbol(symbol.to_string(), Class::Ord),
+                    None => Node::Symbol(format!("\\{}", name), Class::Ord),
+                },
+            },
+        };
+        Some(node)
+    }
+
+    /// Delimiter after `\left` or `\right`
+    fn delimiter(&mut self) -> String {
+        self.skip_whitespace();
+        let Some(c) = self.peek() else {
+            return String::new();
+        };
+        self.pos += 1;
+        let delimiter = match c {
+            '.' => "",
+            '\\' => match self.command_name().as_str() {
+                "{" => "{",
+                "}" => "}",
+                "|" => "‖",
+                name => {
+                    return match symbols::lookup(name) {
+                        Some((symbol, MathClass::Open | MathClass::Close | MathClass::Fence)) => {
+                            symbol.to_string()
+                        }
+                        _ => String::new(),
+                    }
+                }
+            },
+            _ => return c.to_string(),
+        };
+        delimiter.to_string()
+    }
+
+    /// Rows of a `\begin{env}` ... `\end{env}` block
+    fn environment(&mut self, env: &str) -> Node {
+        let (open, close, align) = match env.trim_end_matches('*') {
+            "pmatrix" => ("(", ")", Align::Center),
+            "bmatrix" => ("[", "]", Align::Center),
+            "Bmatrix" => ("{", "}", Align::Center),
+            "vmatrix" => ("|", "|", Align::Center),
+            "Vmatrix" => ("‖", "‖", Align::Center),
+            "cases" => ("{", "", Align::Left),
+            "aligned" | "align" | "split" | "alignat" | "eqnarray" => ("", "", Align::Pairs),
+            "array" => {
+                // Column specification
+                self.raw_group();
+                ("", "", Align::Center)
+            }
+            _ => ("", "", Align::Center),
+        };
+
+        let mut rows = Vec::new();
+        let mut row = Vec::new();
+        loop {
+            row.push(self.list(Until::Cell));
+            if self.peek() == Some('&') {
+                self.pos += 1;
+            } else if self.at_command("\\") {
+                self.pos += 2;
+                rows.push(std::mem::take(&mut row));
+            } else {
+                if self.at_command("end") {
+                    self.pos += 1;
+                    self.command_name();
+                    self.raw_group();
+                }
+                // A trailing `\\` leaves an empty last row
+                if !(row.len() == 1 && row[0].is_empty()) {
+                    rows.push(row);
+                }
+                break;
+            }
+        }
+
+        Node::Matrix {
+            rows,
+            open: open.to_string(),
+            close: close.to_string(),
+            align,
+        }
+    }
+}
+
+/// Attach a sub- or superscript to the last atom
+fn attach_script(nodes: &mut Vec<Node>, sup: bool, script: Vec<Node>) {
+    let base = nodes.pop().unwrap_or_else(|| Node::Group(Vec::new()));
+    let node = match base {
+        Node::Scripts {
+            base,
+            sub,
+            sup: None,
+        } if sup => Node::Scripts {
+            base,
+            sub,
+            sup: Some(script),
+        },
+        Node::Scripts {
+            base,
+            sub: None,
+            sup: upper,
+        } if !sup => Node::Scripts {
+            base,
+            sub: Some(script),
+            sup: upper,
+        },
+        base if sup => Node::Scripts {
+            base: Box::new(base),
+            sub: None,
+            sup: Some(script),
+        },
+        base => Node::Scripts {
+            base: Box::new(base),
+            sub: Some(script),
+            sup: None,
+        },
+    };
+    nodes.push(node);
+}
+
+/// Letters of `node` and everything in it set in `font`
+fn restyle(node: &mut Node, font: Font) {
+    let children: Vec<&mut Vec<Node>> = match node {
+        Node::Symbol(text, _) => {
+            if !text.starts_with('\\') {
+                *text = text.chars().map(|c| font.apply(c)).collect();
+            }
+            return;
+        }
+        Node::Space(_) | Node::Op { .. } => return,
+        Node::Scripts { base, sub, sup } => {
+            restyle(base, font);
+            sub.iter_mut().chain(sup.iter_mut()).collect()
+        }
+        Node::Group(nodes)
+        | Node::Delimited { body: nodes, .. }
+        | Node::Accent { body: nodes, .. } => vec![nodes],
+        Node::Frac { num, den, .. } => vec![num, den],
+        Node::Sqrt { index, body } => index.iter_mut().chain([body]).collect(),
+        Node::Matrix { rows, .. } => rows.iter_mut().flatten().collect(),
+    };
+    for node in children.into_iter().flatten() {
+        restyle(node, font);
+    }
+}
+
+/// `\not` applied to the next atom, precomposed where Unicode has the
+/// negated symbol
+fn negated(nodes: Vec<Node>) -> Node {
+    let Some(Node::Symbol(text, class)) = nodes.first() else {
+        return Node::Symbol("\u{338}".to_string(), Class::Rel);
+    };
+    let precomposed = match text.as_str() {
+        "=" => "≠",
+        "<" => "≮",
+        ">" => "≯",
+        "∈" => "∉",
+        "∋" => "∌",
+        "≡" => "≢",
+        "∼" => "≁",
+        "≈" => "≉",
+        "≅" => "≇",
+        "≤" => "≰",
+        "≥" => "≱",
+        "⊂" => "⊄",
+        "⊃" => "⊅",
+        "⊆" => "⊈",
+        "⊇" => "⊉",
+        "∣" => "∤",
+        "∥" => "∦",
+        _ => return Node::Symbol(format!("{}\u{338}", text), *class),
+    };
+    Node::Symbol(precomposed.to_string(), *class)
+}
+
+/// Contents of a `\text` group: spaces kept, escapes and braces removed
+fn text_mode(raw: &str) -> String {
+    let mut out = String::new();
+    let mut chars = raw.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '{' | '}' => {}
+            '\\' if chars.peek().is_some_and(|c| !c.is_alphabetic()) => {
+                out.extend(chars.next().filter(|c| *c != '\\'));
+            }
+            '~' => out.push(' '),
+            _ => out.push(c),
+        }
+    }
+    out
+}
+
+/// Atom for a plain character
+fn char_symbol(c: char) -> Node {
+    let (text, class) = match c {
+        '+' => ("+", Class::Bin),
+        '-' => ("−", Class::Bin),
+        '*' => ("∗", Class::Bin),
+        '/' => ("/", Class::Ord),
+        '=' => ("=", Class::Rel),
+        '<' => ("<", Class::Rel),
+        '>' => (">", Class::Rel),
+        ':' => (":", Class::Rel),
+        ',' | ';' => return Node::Symbol(c.to_string(), Class::Punct),
+        '(' | '[' => return Node::Symbol(c.to_string(), Class::Open),
+        ')' | ']' => return Node::Symbol(c.to_string(), Class::Close),
+        '\'' => ("′", Class::Ord),
+        '~' => return Node::Space(1),
+        _ => return Node::Symbol(c.to_string(), Class::Ord),
+    };
+    Node::Symbol(text.to_string(), class)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sym(text: &str) -> Node {
+        Node::Symbol(text.to_string(), Class::Ord)
+    }
+
+    #[test]
+    fn test_nested_groups_and_scripts() {
+        let nodes = parse("x_{i}^{2} + \\frac{a}{b_{1}}");
+        assert_eq!(
+            nodes[0],
+            Node::Scripts {
+                base: Box::new(sym("x")),
+                sub: Some(vec![sym("i")]),
+                sup: Some(vec![sym("2")]),
+            }
+        );
+        assert_eq!(nodes[1].class(), Class::Bin);
+        let Node::Frac { num, den, bar } = &nodes[2] else {
+            panic!("{:?}", nodes[2]);
+        };
+        assert_eq!(num, &[sym("a")]);
+        assert!(matches!(den[0], Node::Scripts { .. }));
+        assert!(bar);
+    }
+
+    #[test]
+    fn test_environments_and_delimiters() {
+        let nodes = parse("\\begin{pmatrix} 1 & 2 \\\\ 3 & 4 \\\\ \\end{pmatrix}");
+        let Node::Matrix { rows, open, .. } = &nodes[0] else {
+            panic!("{:?}", nodes);
+        };
+        assert_eq!(open, "(");
+        assert_eq!(rows.len(), 2);
+        assert_eq!(rows[1], [vec![sym("3")], vec![sym("4")]]);
+
+        let nodes = parse("\\left\\{ x \\right.");
+        assert_eq!(
+            nodes,
+            [Node::Delimited {
+                open: "{".to_string(),
+                close: String::new(),
+                body: vec![sym("x")],
+            }]
+        );
+    }
+
+    #[test]
+    fn test_unbalanced_input_is_kept() {
+        assert_eq!(
+            parse("\\frac{a"),
+            [Node::Frac {
+                num: vec![sym("a")],
+                den: Vec::new(),
+                bar: true,
+            }]
+        );
+        assert_eq!(parse("a}b"), [sym("a"), sym("b")]);
+        assert_eq!(parse("\\foo"), [sym("\\foo")]);
+    }
+
+    #[test]
+    fn test_fonts_text_and_accents() {
+        assert_eq!(
+            parse("\\mathbb{R}^n"),
+            [Node::Scripts {
+                base: Box::new(Node::Group(vec![sym("ℝ")])),
+                sub: None,
+                sup: Some(vec![sym("n")]),
+            }]
+        );
+        assert_eq!(parse("\\mathcal L"), [Node::Group(vec![sym("ℒ")])]);
+        assert_eq!(parse("\\text{ if } x"), [sym(" if "), sym("x")]);
+        assert_eq!(
+            parse("\\operatorname{rank} A")[0],
+            Node::Symbol("rank".to_string(), Class::Op)
+        );
+        assert_eq!(
+            parse("\\vec{v}"),
+            [Node::Accent {
+                mark: '\u{20D7}',
+                placement: Placement::Over,
+                body: vec![sym("v")],
+            }]
+        );
+        assert_eq!(
+            parse("x \\mapsto y \\not\\in A")[1],
+            Node::Symbol("↦".to_string(), Class::Rel)
+        );
+        assert_eq!(
+            parse("x \\not\\in A")[1],
+            Node::Symbol("∉".to_string(), Class::Rel)
+        );
+    }
+}
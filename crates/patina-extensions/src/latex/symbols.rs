@@ -0,0 +1,64 @@
+//! Symbol commands from `data/unicode-math-table.tex`.
+//!
+//! The table is turned into a sorted array by `build.rs`, so adding a symbol
+//! only means adding a line to the data file.
+
+/// Class of a symbol, as given in the unicode-math table
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(super) enum MathClass {
+    /// Letter, set like an ordinary symbol
+    Alpha,
+    Ord,
+    Bin,
+    Rel,
+    /// Big operator such as `\sum` or `\int`
+    Op,
+    Open,
+    Close,
+    /// Delimiter that can open or close, such as `\Vert`
+    Fence,
+    Punct,
+    /// Ellipsis
+    Inner,
+    /// Combining mark placed over one character
+    Accent,
+    /// Combining mark stretched over the whole argument
+    WideAccent,
+    /// Combining mark placed under the whole argument
+    BottomAccent,
+}
+
+include!(concat!(env!("OUT_DIR"), "/unicode_math.rs"));
+
+/// Character and class for a symbol command, without the backslash
+pub(super) fn lookup(name: &str) -> Option<(char, MathClass)> {
+    UNICODE_MATH
+        .binary_search_by_key(&name, |&(n, ..)| n)
+        .ok()
+        .map(|i| (UNICODE_MATH[i].1, UNICODE_MATH[i].2))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_lookup() {
+        assert!(UNICODE_MATH.windows(2).all(|w| w[0].0 < w[1].0));
+        assert_eq!(lookup("mapsto"), Some(('↦', MathClass::Rel)));
+        assert_eq!(lookup("subseteq"), Some(('⊆', MathClass::Rel)));
+        assert_eq!(lookup("bigoplus"), Some(('⨁', MathClass::Op)));
+        assert_eq!(lookup("multimap"), Some(('⊸', MathClass::Rel)));
+        assert_eq!(lookup("Bbbk"), Some(('𝕜', MathClass::Ord)));
+        assert_eq!(lookup("vec"), Some(('\u{20D7}', MathClass::Accent)));
+
+        // Math alphanumerics, including letters outside their block
+        assert_eq!(lookup("mbfA"), Some(('𝐀', MathClass::Alpha)));
+        assert_eq!(lookup("mitalpha"), Some(('𝛼', MathClass::Alpha)));
+        assert_eq!(lookup("mbfvarTheta"), Some(('𝚹', MathClass::Alpha)));
+        assert_eq!(lookup("mscrB"), Some(('ℬ', MathClass::Alpha)));
+        assert_eq!(lookup("mttzero"), Some(('𝟶', MathClass::Alpha)));
+        assert!(UNICODE_MATH.len() > 1400);
+        assert_eq!(lookup("nosuchsymbol"), None);
+    }
+}
@@ -9,6 +9,6 @@ pub mod emoji;
 pub mod latex;
 pub mod mermaid;
 
-pub use emoji::EmojiExpander;
+pub use emoji::{Category, Emoji, EmojiExpander, SkinTone};
 pub use latex::LatexRenderer;
 pub use mermaid::MermaidRenderer;
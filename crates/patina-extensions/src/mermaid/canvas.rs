@@ -0,0 +1,292 @@
+//! Character grid that diagrams are drawn on.
+
+use super::text_width;
+use unicode_width::UnicodeWidthChar;
+
+/// Line directions leaving a cell
+pub(crate) const UP: u8 = 1;
+pub(crate) const DOWN: u8 = 2;
+pub(crate) const LEFT: u8 = 4;
+pub(crate) const RIGHT: u8 = 8;
+
+/// How a line is drawn
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Stroke {
+    Solid,
+    Dotted,
+    Thick,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Cell {
+    Empty,
+    Char(char),
+    /// Second column of a wide character
+    Continuation,
+    /// Line segments leaving the cell, joined with box-drawing characters
+    Line(u8, Stroke),
+}
+
+/// A grid of characters with line drawing that joins crossing lines
+pub(crate) struct Canvas {
+    width: usize,
+    height: usize,
+    cells: Vec<Cell>,
+    unicode: bool,
+}
+
+impl Canvas {
+    /// Create an empty canvas; `unicode` selects box-drawing over ASCII characters
+    pub(crate) fn new(width: usize, height: usize, unicode: bool) -> Self {
+        Self {
+            width,
+            height,
+            cells: vec![Cell::Empty; width * height],
+            unicode,
+        }
+    }
+
+    pub(crate) fn width(&self) -> usize {
+        self.width
+    }
+
+    pub(crate) fn unicode(&self) -> bool {
+        self.unicode
+    }
+
+    fn cell_mut(&mut self, x: usize, y: usize) -> Option<&mut Cell> {
+        if x < self.width && y < self.height {
+            self.cells.get_mut(y * self.width + x)
+        } else {
+            None
+        }
+    }
+
+    /// Put a character, using the ASCII fallback in ASCII mode
+    pub(crate) fn put(&mut self, x: usize, y: usize, unicode: char, ascii: char) {
+        let ch = if self.unicode { unicode } else { ascii };
+        self.text(x, y, &ch.to_string());
+    }
+
+    /// Write text starting at `(x, y)`, clipped to the canvas
+    pub(crate) fn text(&mut self, x: usize, y: usize, text: &str) {
+        let mut x = x;
+        for ch in text.chars() {
+            let width = ch.width().unwrap_or(0);
+            if width == 0 {
+                continue;
+            }
+            if x + width > self.width {
+                break;
+            }
+            if let Some(cell) = self.cell_mut(x, y) {
+                *cell = Cell::Char(ch);
+            }
+            if width == 2 {
+                if let Some(cell) = self.cell_mut(x + 1, y) {
+                    *cell = Cell::Continuation;
+                }
+            }
+            x += width;
+        }
+    }
+
+    /// Add line directions to a cell, joining any line already there
+    fn join(&mut self, x: usize, y: usize, dirs: u8, stroke: Stroke) {
+        if let Some(cell) = self.cell_mut(x, y) {
+            *cell = match *cell {
+                Cell::Line(existing, _) => Cell::Line(existing | dirs, stroke),
+                _ => Cell::Line(dirs, stroke),
+            };
+        }
+    }
+
+    /// Draw an axis-aligned polyline through `points`
+    pub(crate) fn line(&mut self, points: &[(usize, usize)], stroke: Stroke) {
+        for pair in points.windows(2) {
+            let ((x0, y0), (x1, y1)) = (pair[0], pair[1]);
+            if x0 == x1 {
+                let (top, bottom) = (y0.min(y1), y0.max(y1));
+                for y in top..bottom {
+                    self.join(x0, y, DOWN, stroke);
+                    self.join(x0, y + 1, UP, stroke);
+                }
+            } else if y0 == y1 {
+                let (left, right) = (x0.min(x1), x0.max(x1));
+                for x in left..right {
+                    self.join(x, y0, RIGHT, stroke);
+                    self.join(x + 1, y0, LEFT, stroke);
+                }
+            }
+        }
+        // A single point is a short stub in no particular direction
+        if let [(x, y)] = points {
+            self.join(*x, *y, UP | DOWN, stroke);
+        }
+    }
+
+    /// Draw the outline of a rectangle with lines
+    pub(crate) fn frame(&mut self, x: usize, y: usize, width: usize, height: usize) {
+        if width < 2 || height < 2 {
+            return;
+        }
+        let (right, bottom) = (x + width - 1, y + height - 1);
+        self.line(
+            &[(x, y), (right, y), (right, bottom), (x, bottom), (x, y)],
+            Stroke::Solid,
+        );
+    }
+
+    /// Draw a box with the given corners (top-left, top-right, bottom-left,
+    /// bottom-right) and sides, clearing its inside and centering `lines` in it
+    #[allow(clippy::too_many_arguments)]
+    pub(crate) fn boxed(
+        &mut self,
+        x: usize,
+        y: usize,
+        width: usize,
+        height: usize,
+        corners: [char; 4],
+        sides: [char; 2],
+        lines: &[String],
+    ) {
+        if width < 2 || height < 2 {
+            return;
+        }
+        let [tl, tr, bl, br] = corners;
+        let horizontal = if self.unicode { "─" } else { "-" };
+        let edge = horizontal.repeat(width - 2);
+        self.text(x, y, &format!("{}{}{}", tl, edge, tr));
+        self.text(x, y + height - 1, &format!("{}{}{}", bl, edge, br));
+        for row in 1..height - 1 {
+            self.text(x, y + row, &sides[0].to_string());
+            self.text(x + width - 1, y + row, &sides[1].to_string());
+            self.text(x + 1, y + row, &" ".repeat(width - 2));
+        }
+
+        let top = 1 + (height - 2).saturating_sub(lines.len()) / 2;
+        for (row, line) in lines.iter().enumerate() {
+            let indent = (width - 2).saturating_sub(text_width(line)) / 2;
+            self.text(x + 1 + indent, y + top + row, line);
+        }
+    }
+
+    /// Render the canvas, trimming trailing spaces and blank rows and
+    /// clipping rows to `max_width` columns. Clipped rows end in a marker
+    /// so the cut is visible.
+    pub(crate) fn render(&self, max_width: usize) -> String {
+        let marker = if self.unicode { '…' } else { '>' };
+        let mut rows: Vec<String> = self
+            .cells
+            .chunks(self.width.max(1))
+            .map(|row| {
+                let mut text = String::new();
+                let mut width = 0;
+                for (idx, cell) in row.iter().enumerate() {
+                    let (ch, w) = match *cell {
+                        Cell::Empty => (' ', 1),
+                        Cell::Char(ch) => (ch, ch.width().unwrap_or(1)),
+                        Cell::Continuation => continue,
+                        Cell::Line(dirs, stroke) => (line_glyph(dirs, stroke, self.unicode), 1),
+                    };
+                    if width + w > max_width {
+                        if row[idx..].iter().any(|c| *c != Cell::Empty) && max_width > 0 {
+                            while width + 1 > max_width {
+                                let cut = text.pop().map_or(0, |c| c.width().unwrap_or(1));
+                                width -= cut;
+                            }
+                            text.push_str(&" ".repeat(max_width - 1 - width));
+                            text.push(marker);
+                        }
+                        break;
+                    }
+                    text.push(ch);
+                    width += w;
+                }
+                text.trim_end().to_string()
+            })
+            .collect();
+        while rows.last().is_some_and(|row| row.is_empty()) {
+            rows.pop();
+        }
+        rows.join("\n")
+    }
+}
+
+/// Character for a line cell with the given directions
+fn line_glyph(dirs: u8, stroke: Stroke, unicode: bool) -> char {
+    let vertical = dirs & (UP | DOWN) != 0;
+    let horizontal = dirs & (LEFT | RIGHT) != 0;
+
+    if !unicode {
+        return match (vertical, horizontal, stroke) {
+            (true, true, _) => '+',
+            (_, false, Stroke::Dotted) => ':',
+            (_, false, _) => '|',
+            (false, true, Stroke::Dotted) => '.',
+            (false, true, Stroke::Thick) => '=',
+            (false, true, Stroke::Solid) => '-',
+        };
+    }
+
+    let heavy = stroke == Stroke::Thick;
+    if !horizontal {
+        return match stroke {
+            Stroke::Solid => '│',
+            Stroke::Dotted => '┆',
+            Stroke::Thick => '┃',
+        };
+    }
+    if !vertical {
+        return match stroke {
+            Stroke::Solid => '─',
+            Stroke::Dotted => '┄',
+            Stroke::Thick => '━',
+        };
+    }
+
+    let (light, thick) = match dirs {
+        d if d == DOWN | RIGHT => ('┌', '┏'),
+        d if d == DOWN | LEFT => ('┐', '┓'),
+        d if d == UP | RIGHT => ('└', '┗'),
+        d if d == UP | LEFT => ('┘', '┛'),
+        d if d == UP | DOWN | RIGHT => ('├', '┣'),
+        d if d == UP | DOWN | LEFT => ('┤', '┫'),
+        d if d == DOWN | LEFT | RIGHT => ('┬', '┳'),
+        d if d == UP | LEFT | RIGHT => ('┴', '┻'),
+        _ => ('┼', '╋'),
+    };
+    if heavy {
+        thick
+    } else {
+        light
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_lines_join() {
+        let mut canvas = Canvas::new(5, 3, true);
+        canvas.line(&[(2, 0), (2, 2)], Stroke::Solid);
+        canvas.line(&[(0, 1), (4, 1)], Stroke::Solid);
+        canvas.text(0, 2, "ab");
+        assert_eq!(canvas.render(80), "  │\n──┼──\nab│");
+    }
+
+    #[test]
+    fn test_ascii_and_clipping() {
+        let mut canvas = Canvas::new(6, 2, false);
+        canvas.frame(0, 0, 6, 2);
+        assert_eq!(canvas.render(80), "+----+\n+----+");
+        assert_eq!(canvas.render(3), "+->\n+->");
+        assert_eq!(canvas.render(6), "+----+\n+----+");
+
+        let mut canvas = Canvas::new(8, 2, true);
+        canvas.text(0, 0, "ab");
+        canvas.text(0, 1, "abc 好d");
+        assert_eq!(canvas.render(6), "ab\nabc  …");
+    }
+}
@@ -0,0 +1,394 @@
+//! Class diagrams (`classDiagram`).
+//!
+//! Classes are boxes with compartments for attributes and methods, laid out
+//! with the flowchart layout; relationships end in UML markers.
+
+use super::canvas::Stroke;
+use super::flowchart::{Direction, Flowchart, Head, Link};
+use super::{clean_label, content_lines, MermaidError, SourceLine};
+use std::collections::HashMap;
+
+/// Relationship ends written before the line, longest first
+const LEFT_ENDS: &[(&str, Head)] = &[
+    ("<|", Head::Triangle),
+    ("*", Head::Diamond),
+    ("o", Head::OpenDiamond),
+    ("<", Head::Arrow),
+];
+
+/// Relationship ends written after the line, longest first
+const RIGHT_ENDS: &[(&str, Head)] = &[
+    ("|>", Head::Triangle),
+    ("*", Head::Diamond),
+    ("o", Head::OpenDiamond),
+    (">", Head::Arrow),
+];
+
+/// Render class diagram source as text no wider than `max_width`
+pub(crate) fn render(
+    source: &str,
+    max_width: usize,
+    unicode: bool,
+) -> Result<String, MermaidError> {
+    Ok(parse(source)?.draw(max_width, unicode))
+}
+
+/// Members of a class, split into compartments as they're added
+#[derive(Debug, Default)]
+struct Class {
+    annotation: Option<String>,
+    label: Option<String>,
+    attributes: Vec<String>,
+    methods: Vec<String>,
+}
+
+/// Parse class diagram source into a graph
+fn parse(source: &str) -> Result<Flowchart, MermaidError> {
+    let mut lines = content_lines(source).into_iter();
+    if lines.next().is_none() {
+        return Err(MermaidError::parse(1, 1, "empty diagram"));
+    }
+
+    let mut chart = Flowchart::new(Direction::TopDown);
+    // Classes by node index; notes are nodes too
+    let mut classes: HashMap<usize, Class> = HashMap::new();
+    // Class whose `{ ... }` body is open, and the line it was opened on
+    let mut body: Option<(usize, usize)> = None;
+    // Open namespace and the line it was opened on
+    let mut namespace: Option<(usize, usize)> = None;
+    let mut notes = 0;
+
+    for line in lines {
+        let text = line.text;
+        if let Some((class, _)) = body {
+            if text == "}" {
+                body = None;
+            } else if let Some(annotation) = annotation(text) {
+                class_mut(&mut classes, class).annotation = Some(annotation);
+            } else {
+                add_member(class_mut(&mut classes, class), text);
+            }
+            continue;
+        }
+
+        let keyword = text.split_whitespace().next().unwrap_or("");
+        let scope = namespace.map(|(subgraph, _)| subgraph);
+        match keyword {
+            "direction" => {
+                let name = text["direction".len()..].trim();
+                chart.direction = Direction::from_name(name)
+                    .ok_or_else(|| line.error(10, format!("unknown direction '{}'", name)))?;
+            }
+            "namespace" => {
+                let name = text["namespace".len()..]
+                    .trim()
+                    .trim_end_matches('{')
+                    .trim();
+                namespace = Some((chart.subgraph(name.to_string(), None), line.number));
+            }
+            "}" if namespace.is_some() => namespace = None,
+            "class" => {
+                let rest = text["class".len()..].trim();
+                let (rest, opens) = match rest.strip_suffix('{') {
+                    Some(rest) => (rest.trim(), true),
+                    None => (rest, false),
+                };
+                let (name, label) = match rest.split_once('[') {
+                    Some((name, label)) => {
+                        (name.trim(), Some(clean_label(label.trim_end_matches(']'))))
+                    }
+                    None => (rest, None),
+                };
+                let name = name.split(":::").next().unwrap_or(name);
+                if name.is_empty() {
+                    return Err(line.error(0, "expected a class name"));
+                }
+                let class = class_node(&mut chart, &mut classes, name, scope);
+                if label.is_some() {
+                    class_mut(&mut classes, class).label = label;
+                }
+                if opens {
+                    body = Some((class, line.number));
+                }
+            }
+            "note" => {
+                let rest = text["note".len()..].trim();
+                let (target, text) = match rest.strip_prefix("for ") {
+                    Some(rest) => match rest.trim().split_once(char::is_whitespace) {
+                        Some((target, text)) => (Some(target), text),
+                        None => (Some(rest.trim()), ""),
+                    },
+                    None => (None, rest),
+                };
+                notes += 1;
+                let note = chart.node(&format!("\0note{}", notes), scope);
+                chart.nodes[note].label = clean_label(text);
+                if let Some(target) = target {
+                    let class = class_node(&mut chart, &mut classes, target, scope);
+                    chart.edge(
+                        class,
+                        note,
+                        link(Stroke::Dotted, Head::None, Head::None, None),
+                    );
+                }
+            }
+            // Interaction and styling don't affect text output
+            "click" | "link" | "callback" | "cssClass" | "style" | "classDef" | "title" => {}
+            _ if keyword.starts_with("accTitle") || keyword.starts_with("accDescr") => {}
+            _ if text.starts_with("<<") => {
+                let Some(annotation) = annotation(text) else {
+                    return Err(line.error(0, "annotation is never closed with '>>'"));
+                };
+                let name = text[text.find(">>").unwrap_or(0) + 2..].trim();
+                let class = class_node(&mut chart, &mut classes, name, scope);
+                class_mut(&mut classes, class).annotation = Some(annotation);
+            }
+            _ => statement(&mut chart, &mut classes, &line, scope)?,
+        }
+    }
+
+    if let Some((_, line)) = body {
+        return Err(MermaidError::parse(
+            line,
+            1,
+            "class body is never closed with '}'",
+        ));
+    }
+    if let Some((_, line)) = namespace {
+        return Err(MermaidError::parse(
+            line,
+            1,
+            "namespace is never closed with '}'",
+        ));
+    }
+
+    for (idx, class) in classes {
+        let node = &mut chart.nodes[idx];
+        let name = class.label.unwrap_or_else(|| generic(&node.label));
+        node.label = match class.annotation {
+            Some(annotation) => format!("<<{}>>\n{}", annotation, name),
+            None => name,
+        };
+        node.members = vec![class.attributes, class.methods];
+    }
+    Ok(chart)
+}
+
+/// Node of a class, adding it if new
+fn class_node(
+    chart: &mut Flowchart,
+    classes: &mut HashMap<usize, Class>,
+    name: &str,
+    scope: Option<usize>,
+) -> usize {
+    let idx = chart.node(name.trim(), scope);
+    classes.entry(idx).or_default();
+    idx
+}
+
+/// Members of the class at node `idx`
+fn class_mut(classes: &mut HashMap<usize, Class>, idx: usize) -> &mut Class {
+    classes.entry(idx).or_default()
+}
+
+/// `~T~` generics as `<T>`
+fn generic(text: &str) -> String {
+    let mut out = String::new();
+    let mut open = false;
+    for c in text.chars() {
+        if c == '~' {
+            out.push(if open { '>' } else { '<' });
+            open = !open;
+        } else {
+            out.push(c);
+        }
+    }
+    out
+}
+
+/// `<<interface>>` as `interface`
+fn annotation(text: &str) -> Option<String> {
+    let rest = text.trim().strip_prefix("<<")?;
+    let (annotation, _) = rest.split_once(">>")?;
+    Some(annotation.trim().to_string())
+}
+
+/// Add an attribute or, if it has parentheses, a method
+fn add_member(class: &mut Class, member: &str) {
+    let member = generic(member.trim());
+    if member.is_empty() {
+        return;
+    }
+    if member.contains('(') {
+        class.methods.push(member);
+    } else {
+        class.attributes.push(member);
+    }
+}
+
+/// A relationship (`A <|-- B : label`) or a member (`A : +int age`)
+fn statement(
+    chart: &mut Flowchart,
+    classes: &mut HashMap<usize, Class>,
+    line: &SourceLine,
+    scope: Option<usize>,
+) -> Result<(), MermaidError> {
+    let text = line.text;
+    let (body, label) = match split_label(text) {
+        Some((body, label)) => (body.trim(), Some(label.trim())),
+        None => (text, None),
+    };
+
+    let Some((line_pos, stroke)) = ["--", ".."]
+        .iter()
+        .filter_map(|token| body.find(token).map(|pos| (pos, *token)))
+        .min()
+        .map(|(pos, token)| {
+            (
+                pos,
+                if token == "--" {
+                    Stroke::Solid
+                } else {
+                    Stroke::Dotted
+                },
+            )
+        })
+    else {
+        // `Class : member`
+        let name = body;
+        if name.is_empty() || name.contains(char::is_whitespace) || label.is_none() {
+            return Err(line.error(0, format!("unrecognized statement '{}'", text)));
+        }
+        let class = class_node(chart, classes, name, scope);
+        add_member(class_mut(classes, class), label.unwrap_or_default());
+        return Ok(());
+    };
+
+    let (left, right) = (
+        &body[..line_pos],
+        body[line_pos + 2..].trim_start_matches(['-', '.']),
+    );
+    let (left, tail) = strip_end(left.trim_end(), LEFT_ENDS, true);
+    let (right, head) = strip_end(right.trim_start(), RIGHT_ENDS, false);
+    let (from, from_card) = cardinality(left, true);
+    let (to, to_card) = cardinality(right, false);
+    if from.is_empty() || to.is_empty() {
+        return Err(line.error(0, "relationship needs a class on both sides"));
+    }
+
+    let from = class_node(chart, classes, from, scope);
+    let to = class_node(chart, classes, to, scope);
+    let label: Vec<String> = [from_card, label.map(clean_label), to_card]
+        .into_iter()
+        .flatten()
+        .filter(|part| !part.is_empty())
+        .collect();
+    let label = (!label.is_empty()).then(|| label.join(" "));
+    chart.edge(from, to, link(stroke, head, tail, label));
+    Ok(())
+}
+
+/// Split `body : label` at a colon outside quotes
+fn split_label(text: &str) -> Option<(&str, &str)> {
+    let mut quoted = false;
+    for (i, c) in text.char_indices() {
+        match c {
+            '"' => quoted = !quoted,
+            ':' if !quoted => return Some((&text[..i], &text[i + 1..])),
+            _ => {}
+        }
+    }
+    None
+}
+
+/// Strip a relationship end marker next to the line
+fn strip_end<'a>(text: &'a str, ends: &[(&str, Head)], left: bool) -> (&'a str, Head) {
+    for &(marker, head) in ends {
+        let stripped = if left {
+            text.strip_suffix(marker)
+        } else {
+            text.strip_prefix(marker)
+        };
+        let Some(stripped) = stripped else {
+            continue;
+        };
+        // `o` only counts as a marker when separated from the class name
+        let separated = if left {
+            stripped.is_empty() || stripped.ends_with([' ', '"'])
+        } else {
+            stripped.is_empty() || stripped.starts_with([' ', '"'])
+        };
+        if marker != "o" || separated {
+            return (stripped, head);
+        }
+    }
+    (text, Head::None)
+}
+
+/// Split a quoted cardinality (`"1..*"`) from the class name next to it
+fn cardinality(text: &str, left: bool) -> (&str, Option<String>) {
+    let text = text.trim();
+    let quoted = if left {
+        text.strip_suffix('"')
+            .and_then(|t| t.rfind('"').map(|i| (&t[..i], &t[i + 1..])))
+    } else {
+        text.strip_prefix('"')
+            .and_then(|t| t.find('"').map(|i| (&t[i + 1..], &t[..i])))
+    };
+    match quoted {
+        Some((name, card)) => (name.trim(), Some(card.to_string())),
+        None => (text, None),
+    }
+}
+
+fn link(stroke: Stroke, head: Head, tail: Head, label: Option<String>) -> Link {
+    Link {
+        stroke,
+        head,
+        tail,
+        label,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_classes_and_members() {
+        let chart = parse(
+            "classDiagram\n  class Animal {\n    <<abstract>>\n    +String name\n    +eat(food) bool\n  }\n  Animal : +int age\n  class List~T~",
+        )
+        .unwrap();
+        assert_eq!(chart.nodes[0].label, "<<abstract>>\nAnimal");
+        assert_eq!(
+            chart.nodes[0].members,
+            [
+                vec!["+String name".to_string(), "+int age".to_string()],
+                vec!["+eat(food) bool".to_string()]
+            ]
+        );
+        assert_eq!(chart.nodes[1].label, "List<T>");
+    }
+
+    #[test]
+    fn test_relationships() {
+        let out = render(
+            "classDiagram\n  Animal <|-- Duck\n  Duck \"1\" *-- \"many\" Egg : lays\n  Duck : +swim()",
+            80,
+            true,
+        )
+        .unwrap();
+        assert!(out.contains('△'), "{}", out);
+        assert!(out.contains('◆'), "{}", out);
+        assert!(out.contains("1 lays many"), "{}", out);
+        assert!(out.contains("├─────────┤"), "{}", out);
+        assert!(out.contains("│ +swim() │"), "{}", out);
+    }
+
+    #[test]
+    fn test_unclosed_body() {
+        let err = parse("classDiagram\n  class A {\n    +x").unwrap_err();
+        assert_eq!(err.position(), Some((2, 1)));
+    }
+}
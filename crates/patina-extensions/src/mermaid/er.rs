@@ -0,0 +1,201 @@
+//! Entity relationship diagrams (`erDiagram`).
+//!
+//! Entities are boxes listing their attributes, laid out with the flowchart
+//! layout. Relationship cardinalities are written out next to the label.
+
+use super::canvas::Stroke;
+use super::flowchart::{Direction, Flowchart, Head, Link};
+use super::{clean_label, content_lines, MermaidError, SourceLine};
+
+/// Render ER diagram source as text no wider than `max_width`
+pub(crate) fn render(
+    source: &str,
+    max_width: usize,
+    unicode: bool,
+) -> Result<String, MermaidError> {
+    Ok(parse(source)?.draw(max_width, unicode))
+}
+
+/// Cardinality of one end of a relationship, from its crow's foot marker
+fn cardinality(marker: &str) -> Option<&'static str> {
+    match marker {
+        "||" => Some("1"),
+        "|o" | "o|" => Some("0..1"),
+        "}|" | "|{" => Some("1..*"),
+        "}o" | "o{" => Some("0..*"),
+        _ => None,
+    }
+}
+
+/// Parse ER diagram source into a graph
+fn parse(source: &str) -> Result<Flowchart, MermaidError> {
+    let mut lines = content_lines(source).into_iter();
+    if lines.next().is_none() {
+        return Err(MermaidError::parse(1, 1, "empty diagram"));
+    }
+
+    let mut chart = Flowchart::new(Direction::TopDown);
+    // Entity whose `{ ... }` block is open, and the line it was opened on
+    let mut block: Option<(usize, usize)> = None;
+
+    for line in lines {
+        let text = line.text;
+        if let Some((entity, _)) = block {
+            if text == "}" {
+                block = None;
+            } else {
+                let attributes = &mut chart.nodes[entity].members;
+                if attributes.is_empty() {
+                    attributes.push(Vec::new());
+                }
+                attributes[0].push(attribute(text));
+            }
+            continue;
+        }
+
+        let keyword = text.split_whitespace().next().unwrap_or("");
+        match keyword {
+            "direction" => {
+                let name = text["direction".len()..].trim();
+                chart.direction = Direction::from_name(name)
+                    .ok_or_else(|| line.error(10, format!("unknown direction '{}'", name)))?;
+            }
+            "title" | "style" | "classDef" | "class" => {}
+            _ if keyword.starts_with("accTitle") || keyword.starts_with("accDescr") => {}
+            _ if text.ends_with('{') => {
+                let name = text.trim_end_matches('{').trim();
+                if name.is_empty() {
+                    return Err(line.error(0, "expected an entity name"));
+                }
+                block = Some((entity(&mut chart, name), line.number));
+            }
+            _ => relationship(&mut chart, &line)?,
+        }
+    }
+
+    if let Some((_, line)) = block {
+        return Err(MermaidError::parse(
+            line,
+            1,
+            "entity is never closed with '}'",
+        ));
+    }
+    Ok(chart)
+}
+
+/// Node of an entity, `NAME` or `NAME["Alias"]`
+fn entity(chart: &mut Flowchart, name: &str) -> usize {
+    let (id, alias) = match name.split_once('[') {
+        Some((id, alias)) => (id.trim(), Some(clean_label(alias.trim_end_matches(']')))),
+        None => (name.trim(), None),
+    };
+    let idx = chart.node(id, None);
+    if let Some(alias) = alias {
+        chart.nodes[idx].label = alias;
+    }
+    idx
+}
+
+/// An attribute line, `type name PK "comment"`, as displayed
+fn attribute(text: &str) -> String {
+    let (definition, comment) = match text.split_once('"') {
+        Some((definition, comment)) => (definition, Some(comment.trim_end_matches('"'))),
+        None => (text, None),
+    };
+    let mut words: Vec<&str> = definition.split_whitespace().collect();
+    words.extend(comment.filter(|c| !c.is_empty()));
+    words.join(" ")
+}
+
+/// `A ||--o{ B : label`
+fn relationship(chart: &mut Flowchart, line: &SourceLine) -> Result<(), MermaidError> {
+    let text = line.text;
+    let (body, label) = match text.split_once(':') {
+        Some((body, label)) => (body, clean_label(label)),
+        None => (text, String::new()),
+    };
+    let words: Vec<&str> = body.split_whitespace().collect();
+    let [from, link, to] = words[..] else {
+        if words.len() == 1 {
+            // A lone entity name declares it
+            entity(chart, words[0]);
+            return Ok(());
+        }
+        return Err(line.error(0, format!("unrecognized statement '{}'", text)));
+    };
+
+    let link_pos = text.find(link).unwrap_or(0);
+    let link_column = text[..link_pos].chars().count();
+    let invalid = || line.error(link_column, format!("invalid relationship '{}'", link));
+    if !link.is_char_boundary(2) || link.len() < 6 || !link.is_char_boundary(link.len() - 2) {
+        return Err(invalid());
+    }
+    let (left, line_part, right) = (
+        &link[..2],
+        &link[2..link.len() - 2],
+        &link[link.len() - 2..],
+    );
+    let stroke = match line_part {
+        "--" => Stroke::Solid,
+        ".." => Stroke::Dotted,
+        _ => return Err(invalid()),
+    };
+    let (Some(left), Some(right)) = (cardinality(left), cardinality(right)) else {
+        return Err(invalid());
+    };
+
+    let from = entity(chart, from);
+    let to = entity(chart, to);
+    let label = [left, label.as_str(), right]
+        .into_iter()
+        .filter(|part| !part.is_empty())
+        .collect::<Vec<_>>()
+        .join(" ");
+    chart.edge(
+        from,
+        to,
+        Link {
+            stroke,
+            head: Head::None,
+            tail: Head::None,
+            label: Some(label),
+        },
+    );
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_entities_and_relationships() {
+        let chart = parse(
+            "erDiagram\n  CUSTOMER ||--o{ ORDER : places\n  CUSTOMER {\n    string name PK \"full name\"\n    int age\n  }\n  ORDER }|..|{ LINE-ITEM : contains",
+        )
+        .unwrap();
+        assert_eq!(
+            chart.nodes[0].members,
+            [vec![
+                "string name PK full name".to_string(),
+                "int age".to_string()
+            ]]
+        );
+
+        let out = chart.draw(80, true);
+        assert!(out.contains("1 places 0..*"), "{}", out);
+        assert!(out.contains("1..* contains 1..*"), "{}", out);
+        assert!(
+            out.contains("│ CUSTOMER │") || out.contains("CUSTOMER"),
+            "{}",
+            out
+        );
+        assert!(out.contains('┆'), "{}", out);
+    }
+
+    #[test]
+    fn test_invalid_relationship() {
+        let err = parse("erDiagram\n  A ||-->o{ B : x").unwrap_err();
+        assert_eq!(err.position(), Some((2, 5)));
+    }
+}
@@ -0,0 +1,2054 @@
+//! Flowcharts (`graph` / `flowchart`): parsing and layered layout.
+//!
+//! Nodes are assigned to layers by longest path (cycles are broken by
+//! reversing back edges), ordered within layers by barycenter sweeps, and
+//! edges are routed orthogonally through the gaps between layers. Edges that
+//! span several layers pass through one-cell dummy nodes, and edge labels get
+//! a layer slot of their own so they never overlap boxes. Subgraphs are
+//! framed, and edges at a subgraph's id stop at its frame.
+
+use super::canvas::{Canvas, Stroke};
+use super::{
+    clean_label, content_lines, text_width, wrap_text, MermaidError, SourceLine, WRAP_WIDTHS,
+};
+use std::collections::HashMap;
+
+/// Flow direction
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(super) enum Direction {
+    TopDown,
+    BottomUp,
+    LeftRight,
+    RightLeft,
+}
+
+impl Direction {
+    pub(super) fn from_name(name: &str) -> Option<Self> {
+        match name.to_uppercase().as_str() {
+            "TD" | "TB" => Some(Self::TopDown),
+            "BT" => Some(Self::BottomUp),
+            "LR" => Some(Self::LeftRight),
+            "RL" => Some(Self::RightLeft),
+            _ => None,
+        }
+    }
+
+    /// Whether layers stack vertically
+    fn is_vertical(self) -> bool {
+        matches!(self, Self::TopDown | Self::BottomUp)
+    }
+}
+
+/// Node shapes, named after their Mermaid syntax
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(super) enum Shape {
+    Rect,
+    Round,
+    Stadium,
+    Subroutine,
+    Cylinder,
+    Circle,
+    DoubleCircle,
+    Asymmetric,
+    Rhombus,
+    Hexagon,
+    Parallelogram,
+    ParallelogramAlt,
+    Trapezoid,
+    TrapezoidAlt,
+    /// Filled dot for the start of a state diagram
+    Start,
+    /// Ringed dot for the end of a state diagram
+    End,
+}
+
+impl Shape {
+    /// Corners (top-left, top-right, bottom-left, bottom-right) and sides
+    fn outline(self, unicode: bool) -> ([char; 4], [char; 2]) {
+        use Shape::*;
+        if !unicode {
+            let (corners, sides) = match self {
+                Round | Stadium | Cylinder | Circle | DoubleCircle => {
+                    (['.', '.', '\'', '\''], ['(', ')'])
+                }
+                Rhombus | Hexagon => (['/', '\\', '\\', '/'], ['<', '>']),
+                Parallelogram => (['/', '/', '/', '/'], ['|', '|']),
+                ParallelogramAlt => (['\\', '\\', '\\', '\\'], ['|', '|']),
+                Trapezoid => (['/', '\\', '+', '+'], ['|', '|']),
+                TrapezoidAlt => (['+', '+', '\\', '/'], ['|', '|']),
+                Asymmetric => (['+', '+', '+', '+'], ['>', '|']),
+                Subroutine => (['+', '+', '+', '+'], ['#', '#']),
+                Rect | Start | End => (['+', '+', '+', '+'], ['|', '|']),
+            };
+            return (corners, sides);
+        }
+        match self {
+            Round | Cylinder => (['╭', '╮', '╰', '╯'], ['│', '│']),
+            Stadium | Circle | DoubleCircle => (['╭', '╮', '╰', '╯'], ['(', ')']),
+            Rhombus => (['╱', '╲', '╲', '╱'], ['<', '>']),
+            Hexagon => (['╱', '╲', '╲', '╱'], ['│', '│']),
+            Parallelogram => (['╱', '╱', '╱', '╱'], ['│', '│']),
+            ParallelogramAlt => (['╲', '╲', '╲', '╲'], ['│', '│']),
+            Trapezoid => (['╱', '╲', '└', '┘'], ['│', '│']),
+            TrapezoidAlt => (['┌', '┐', '╲', '╱'], ['│', '│']),
+            Asymmetric => (['┌', '┐', '└', '┘'], ['>', '│']),
+            Subroutine => (['┌', '┐', '└', '┘'], ['║', '║']),
+            Rect | Start | End => (['┌', '┐', '└', '┘'], ['│', '│']),
+        }
+    }
+}
+
+/// Node shape delimiters, longest openings first (`[/` and `[\` are handled separately)
+const SHAPES: &[(&str, &str, Shape)] = &[
+    ("(((", ")))", Shape::DoubleCircle),
+    ("((", "))", Shape::Circle),
+    ("([", "])", Shape::Stadium),
+    ("[[", "]]", Shape::Subroutine),
+    ("[(", ")]", Shape::Cylinder),
+    ("{{", "}}", Shape::Hexagon),
+    ("(", ")", Shape::Round),
+    ("[", "]", Shape::Rect),
+    ("{", "}", Shape::Rhombus),
+    (">", "]", Shape::Asymmetric),
+];
+
+/// Marker at either end of an edge
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(super) enum Head {
+    None,
+    Arrow,
+    Circle,
+    Cross,
+    /// Hollow triangle (UML inheritance and realization)
+    Triangle,
+    /// Filled diamond (UML composition)
+    Diamond,
+    /// Hollow diamond (UML aggregation)
+    OpenDiamond,
+}
+
+#[derive(Debug, Clone)]
+pub(super) struct Node {
+    pub(super) label: String,
+    pub(super) shape: Shape,
+    /// Innermost subgraph containing the node
+    pub(super) subgraph: Option<usize>,
+    /// Compartments listed below the label, left-aligned and divided by lines
+    pub(super) members: Vec<Vec<String>>,
+}
+
+/// Edge style and label, as written between two nodes
+#[derive(Debug, Clone)]
+pub(super) struct Link {
+    pub(super) stroke: Stroke,
+    pub(super) head: Head,
+    pub(super) tail: Head,
+    pub(super) label: Option<String>,
+}
+
+/// One end of an edge: a node, or the frame of a subgraph
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum End {
+    Node(usize),
+    Frame(usize),
+}
+
+#[derive(Debug, Clone)]
+struct Edge {
+    from: End,
+    to: End,
+    link: Link,
+}
+
+#[derive(Debug, Clone)]
+struct Subgraph {
+    title: String,
+    parent: Option<usize>,
+}
+
+/// A parsed flowchart, or another diagram built as a graph of boxes
+#[derive(Debug)]
+pub(super) struct Flowchart {
+    pub(super) direction: Direction,
+    pub(super) nodes: Vec<Node>,
+    edges: Vec<Edge>,
+    subgraphs: Vec<Subgraph>,
+    ids: HashMap<String, usize>,
+}
+
+impl Flowchart {
+    pub(super) fn new(direction: Direction) -> Self {
+        Self {
+            direction,
+            nodes: Vec::new(),
+            edges: Vec::new(),
+            subgraphs: Vec::new(),
+            ids: HashMap::new(),
+        }
+    }
+
+    /// Index of node `id`, if it exists
+    #[cfg(test)]
+    pub(super) fn find(&self, id: &str) -> Option<usize> {
+        self.ids.get(id).copied()
+    }
+
+    /// Index of node `id`, creating it as a plain box if new
+    pub(super) fn node(&mut self, id: &str, subgraph: Option<usize>) -> usize {
+        let idx = *self.ids.entry(id.to_string()).or_insert_with(|| {
+            self.nodes.push(Node {
+                label: id.to_string(),
+                shape: Shape::Rect,
+                subgraph: None,
+                members: Vec::new(),
+            });
+            self.nodes.len() - 1
+        });
+        let node = &mut self.nodes[idx];
+        if node.subgraph.is_none() {
+            node.subgraph = subgraph;
+        }
+        idx
+    }
+
+    /// Add a subgraph, returning its index
+    pub(super) fn subgraph(&mut self, title: String, parent: Option<usize>) -> usize {
+        self.subgraphs.push(Subgraph { title, parent });
+        self.subgraphs.len() - 1
+    }
+
+    /// Connect two nodes
+    pub(super) fn edge(&mut self, from: usize, to: usize, link: Link) {
+        self.edges.push(Edge {
+            from: End::Node(from),
+            to: End::Node(to),
+            link,
+        });
+    }
+
+    /// Attach edges at subgraph ids to the subgraph frames, dropping the
+    /// placeholder nodes those ids created
+    fn link_frames(&mut self, subgraph_ids: &HashMap<String, usize>) {
+        let frames: HashMap<usize, usize> = subgraph_ids
+            .iter()
+            .filter_map(|(id, &subgraph)| {
+                let idx = *self.ids.get(id)?;
+                let node = &self.nodes[idx];
+                let placeholder =
+                    node.label == *id && node.shape == Shape::Rect && node.members.is_empty();
+                placeholder.then_some((idx, subgraph))
+            })
+            .collect();
+        if frames.is_empty() {
+            return;
+        }
+
+        let mut renumbered = Vec::with_capacity(self.nodes.len());
+        let mut next = 0;
+        for idx in 0..self.nodes.len() {
+            renumbered.push(next);
+            if !frames.contains_key(&idx) {
+                next += 1;
+            }
+        }
+        let end = |end: End| match end {
+            End::Node(idx) => frames
+                .get(&idx)
+                .map_or(End::Node(renumbered[idx]), |&subgraph| End::Frame(subgraph)),
+            frame => frame,
+        };
+        for edge in &mut self.edges {
+            edge.from = end(edge.from);
+            edge.to = end(edge.to);
+        }
+        let mut idx = 0;
+        self.nodes.retain(|_| {
+            idx += 1;
+            !frames.contains_key(&(idx - 1))
+        });
+        self.ids.retain(|_, idx| !frames.contains_key(idx));
+        for idx in self.ids.values_mut() {
+            *idx = renumbered[*idx];
+        }
+    }
+
+    /// Nodes inside `subgraph`, including those in nested subgraphs
+    fn members(&self, subgraph: usize) -> Vec<usize> {
+        (0..self.nodes.len())
+            .filter(|&idx| {
+                self.subgraph_path(self.nodes[idx].subgraph)
+                    .contains(&subgraph)
+            })
+            .collect()
+    }
+
+    /// Nodes an edge end stands for: a node, or every node in a frame
+    fn end_nodes(&self, end: End) -> Vec<usize> {
+        match end {
+            End::Node(idx) => vec![idx],
+            End::Frame(subgraph) => self.members(subgraph),
+        }
+    }
+
+    /// Lay out and draw the graph, narrowing labels until it fits `max_width`.
+    ///
+    /// Left-right graphs are also tried top-down, and wrapping that breaks
+    /// words is the last resort. When nothing fits, the narrowest drawing
+    /// with whole words is clipped.
+    pub(super) fn draw(&self, max_width: usize, unicode: bool) -> String {
+        if self.nodes.is_empty() {
+            return String::new();
+        }
+        let mut directions = vec![self.direction];
+        if !self.direction.is_vertical() {
+            directions.push(Direction::TopDown);
+        }
+        let mut attempts = Vec::new();
+        for whole_words in [true, false] {
+            for &direction in &directions {
+                let wraps = WRAP_WIDTHS
+                    .into_iter()
+                    .filter(|&wrap| self.breaks_words(wrap) != whole_words);
+                attempts.extend(wraps.map(|wrap| (direction, wrap)));
+            }
+        }
+
+        let mut fallback: Option<Canvas> = None;
+        for (direction, wrap) in attempts {
+            let drawn = Layout::new(self, direction, wrap).draw(unicode);
+            if drawn.width() <= max_width {
+                return drawn.render(max_width);
+            }
+            if !self.breaks_words(wrap)
+                && fallback.as_ref().is_none_or(|f| drawn.width() < f.width())
+            {
+                fallback = Some(drawn);
+            }
+        }
+        fallback.map(|c| c.render(max_width)).unwrap_or_default()
+    }
+
+    /// Whether wrapping labels to `wrap` columns splits a word across lines
+    fn breaks_words(&self, wrap: Option<usize>) -> bool {
+        let Some(wrap) = wrap else {
+            return false;
+        };
+        let node_text = self
+            .nodes
+            .iter()
+            .flat_map(|node| std::iter::once(&node.label).chain(node.members.iter().flatten()));
+        let edge_labels = self
+            .edges
+            .iter()
+            .filter_map(|edge| edge.link.label.as_ref());
+        node_text
+            .chain(edge_labels)
+            .flat_map(|text| text.split_whitespace())
+            .any(|word| text_width(word) > wrap)
+    }
+
+    /// Subgraphs containing `subgraph`, outermost first, ending with itself
+    fn subgraph_path(&self, subgraph: Option<usize>) -> Vec<usize> {
+        let mut path = Vec::new();
+        let mut current = subgraph;
+        while let Some(idx) = current {
+            path.push(idx);
+            current = self.subgraphs[idx].parent;
+        }
+        path.reverse();
+        path
+    }
+
+    /// Number of subgraph levels nested inside `subgraph`
+    fn subgraph_height(&self, subgraph: usize) -> usize {
+        self.subgraphs
+            .iter()
+            .enumerate()
+            .filter(|(_, s)| s.parent == Some(subgraph))
+            .map(|(idx, _)| 1 + self.subgraph_height(idx))
+            .max()
+            .unwrap_or(0)
+    }
+}
+
+/// Render flowchart source as text no wider than `max_width`
+pub(crate) fn render(
+    source: &str,
+    max_width: usize,
+    unicode: bool,
+) -> Result<String, MermaidError> {
+    Ok(parse(source)?.draw(max_width, unicode))
+}
+
+/// Parse flowchart source
+fn parse(source: &str) -> Result<Flowchart, MermaidError> {
+    let lines = content_lines(source);
+    let mut statements = lines.iter().flat_map(|line| line.statements());
+    let header = statements
+        .next()
+        .ok_or_else(|| MermaidError::parse(1, 1, "empty diagram"))?;
+
+    let mut words = header.text.split_whitespace();
+    words.next(); // graph / flowchart
+    let direction = match words.next() {
+        None => Direction::TopDown,
+        Some(word) => Direction::from_name(word.trim_end_matches(';')).ok_or_else(|| {
+            let offset = header.text.find(word).unwrap_or(0);
+            MermaidError::parse(
+                header.number,
+                header.column + header.text[..offset].chars().count(),
+                format!(
+                    "unknown direction '{}' (expected TD, TB, BT, LR or RL)",
+                    word
+                ),
+            )
+        })?,
+    };
+
+    let mut chart = Flowchart::new(direction);
+    // Open subgraphs and the line each was opened on
+    let mut open: Vec<(usize, usize)> = Vec::new();
+    let mut subgraph_ids = HashMap::new();
+
+    for statement in statements {
+        let subgraph = open.last().map(|(idx, _)| *idx);
+        let keyword = statement.text.split_whitespace().next().unwrap_or("");
+        match keyword {
+            "subgraph" => {
+                let rest = statement.text["subgraph".len()..].trim();
+                let (id, title) = match (rest.find('['), rest.rfind(']')) {
+                    (Some(start), Some(end)) if start < end => {
+                        (rest[..start].trim(), clean_label(&rest[start + 1..end]))
+                    }
+                    _ => (rest, clean_label(rest)),
+                };
+                let idx = chart.subgraph(title, subgraph);
+                if !id.is_empty() && id.chars().all(is_id_char) {
+                    subgraph_ids.insert(id.to_string(), idx);
+                }
+                open.push((idx, statement.number));
+            }
+            "end" => {
+                if open.pop().is_none() {
+                    return Err(statement.error(0, "'end' without a matching 'subgraph'"));
+                }
+            }
+            // Styling and interaction don't affect text output
+            "direction" | "classDef" | "class" | "style" | "linkStyle" | "click" => {}
+            _ => Scanner::new(&statement).chain(&mut chart, subgraph)?,
+        }
+    }
+
+    if let Some((_, line)) = open.last() {
+        return Err(MermaidError::parse(
+            *line,
+            1,
+            "'subgraph' is never closed with 'end'",
+        ));
+    }
+    chart.link_frames(&subgraph_ids);
+    Ok(chart)
+}
+
+/// Whether `c` can appear in a node id
+fn is_id_char(c: char) -> bool {
+    c.is_alphanumeric() || c == '_'
+}
+
+/// Whether `c` can appear in the body of a link
+fn is_link_char(c: char) -> bool {
+    matches!(c, '-' | '=' | '.')
+}
+
+/// Stroke and head of a link, and the text closing its inline label if it opens one
+type LinkBody = (Stroke, Head, Option<&'static str>);
+
+/// Cursor over one statement
+struct Scanner<'s> {
+    statement: &'s SourceLine<'s>,
+    chars: Vec<char>,
+    pos: usize,
+}
+
+impl<'s> Scanner<'s> {
+    fn new(statement: &'s SourceLine<'s>) -> Self {
+        Self {
+            statement,
+            chars: statement.text.chars().collect(),
+            pos: 0,
+        }
+    }
+
+    fn error(&self, pos: usize, message: impl Into<String>) -> MermaidError {
+        self.statement.error(pos, message)
+    }
+
+    fn peek(&self, offset: usize) -> Option<char> {
+        self.chars.get(self.pos + offset).copied()
+    }
+
+    fn starts_with(&self, text: &str) -> bool {
+        text.chars()
+            .enumerate()
+            .all(|(i, c)| self.peek(i) == Some(c))
+    }
+
+    fn skip_whitespace(&mut self) {
+        while self.peek(0).is_some_and(char::is_whitespace) {
+            self.pos += 1;
+        }
+    }
+
+    fn at_end(&self) -> bool {
+        self.pos >= self.chars.len()
+    }
+
+    /// Text up to (not including) the next `close` at or after the cursor
+    fn until(&mut self, close: &str, open_pos: usize, open: &str) -> Result<String, MermaidError> {
+        let mut text = String::new();
+        let mut quoted = false;
+        while !self.at_end() {
+            if !quoted && self.starts_with(close) {
+                self.pos += close.chars().count();
+                return Ok(text);
+            }
+            let c = self.chars[self.pos];
+            if c == '"' {
+                quoted = !quoted;
+            }
+            text.push(c);
+            self.pos += 1;
+        }
+        Err(self.error(
+            open_pos,
+            format!("'{}' is never closed with '{}'", open, close),
+        ))
+    }
+
+    /// Parse `nodes (link nodes)*`, adding nodes and edges to the chart
+    fn chain(
+        &mut self,
+        chart: &mut Flowchart,
+        subgraph: Option<usize>,
+    ) -> Result<(), MermaidError> {
+        let mut left = self.node_group(chart, subgraph)?;
+        loop {
+            self.skip_whitespace();
+            if self.at_end() {
+                return Ok(());
+            }
+            let pos = self.pos;
+            let Some(link) = self.link()? else {
+                return Err(self.error(pos, "expected a link such as '-->'"));
+            };
+            let right = self.node_group(chart, subgraph)?;
+            for &from in &left {
+                for &to in &right {
+                    chart.edge(from, to, link.clone());
+                }
+            }
+            left = right;
+        }
+    }
+
+    /// Parse `node (& node)*`
+    fn node_group(
+        &mut self,
+        chart: &mut Flowchart,
+        subgraph: Option<usize>,
+    ) -> Result<Vec<usize>, MermaidError> {
+        let mut nodes = vec![self.node(chart, subgraph)?];
+        loop {
+            self.skip_whitespace();
+            if self.peek(0) != Some('&') {
+                return Ok(nodes);
+            }
+            self.pos += 1;
+            nodes.push(self.node(chart, subgraph)?);
+        }
+    }
+
+    /// Parse a node id with an optional shape and label
+    fn node(
+        &mut self,
+        chart: &mut Flowchart,
+        subgraph: Option<usize>,
+    ) -> Result<usize, MermaidError> {
+        self.skip_whitespace();
+        let start = self.pos;
+        while self.peek(0).is_some_and(is_id_char) {
+            self.pos += 1;
+        }
+        if self.pos == start {
+            return Err(match self.peek(0) {
+                Some(c) => self.error(start, format!("expected a node id, found '{}'", c)),
+                None => self.error(start, "expected a node id"),
+            });
+        }
+        let id: String = self.chars[start..self.pos].iter().collect();
+        let idx = chart.node(&id, subgraph);
+
+        if let Some((shape, label)) = self.shape()? {
+            chart.nodes[idx].shape = shape;
+            chart.nodes[idx].label = clean_label(&label);
+        }
+
+        // Class shorthand (`A:::important`) only styles the node
+        if self.starts_with(":::") {
+            self.pos += 3;
+            while self.peek(0).is_some_and(|c| is_id_char(c) || c == '-') {
+                self.pos += 1;
+            }
+        }
+        Ok(idx)
+    }
+
+    /// Parse a shape right after a node id
+    fn shape(&mut self) -> Result<Option<(Shape, String)>, MermaidError> {
+        let open_pos = self.pos;
+        for slant in ["[/", "[\\"] {
+            if self.starts_with(slant) {
+                self.pos += 2;
+                let text = self.until("]", open_pos, slant)?;
+                let (text, shape) = match (slant, text.chars().last()) {
+                    ("[/", Some('/')) => (&text[..text.len() - 1], Shape::Parallelogram),
+                    ("[/", Some('\\')) => (&text[..text.len() - 1], Shape::Trapezoid),
+                    ("[\\", Some('\\')) => (&text[..text.len() - 1], Shape::ParallelogramAlt),
+                    ("[\\", Some('/')) => (&text[..text.len() - 1], Shape::TrapezoidAlt),
+                    _ => (text.as_str(), Shape::Rect),
+                };
+                return Ok(Some((shape, text.to_string())));
+            }
+        }
+        for (open, close, shape) in SHAPES {
+            if self.starts_with(open) {
+                self.pos += open.chars().count();
+                let text = self.until(close, open_pos, open)?;
+                return Ok(Some((*shape, text)));
+            }
+        }
+        Ok(None)
+    }
+
+    /// Parse a link such as `-->`, `-. label .->`, `==>|label|` or `<-->`
+    fn link(&mut self) -> Result<Option<Link>, MermaidError> {
+        let start = self.pos;
+        let tail = match (self.peek(0), self.peek(1), self.peek(2)) {
+            (Some('<'), Some(c), _) if is_link_char(c) => Head::Arrow,
+            (Some('o'), Some(a), Some(b)) if is_link_char(a) && is_link_char(b) => Head::Circle,
+            (Some('x'), Some(a), Some(b)) if is_link_char(a) && is_link_char(b) => Head::Cross,
+            _ => Head::None,
+        };
+        if tail != Head::None {
+            self.pos += 1;
+        }
+
+        let Some((stroke, mut head, open)) = self.link_body(start)? else {
+            self.pos = start;
+            return Ok(None);
+        };
+
+        let mut label = None;
+        if let Some(close) = open {
+            // Label written inside the link: `-- text -->`
+            let text_start = self.pos;
+            let mut found = false;
+            while !self.at_end() {
+                if self.starts_with(close) {
+                    found = true;
+                    break;
+                }
+                self.pos += 1;
+            }
+            if !found {
+                return Err(self.error(start, "link label is never closed"));
+            }
+            let text: String = self.chars[text_start..self.pos].iter().collect();
+            label = Some(clean_label(&text));
+            match self.link_body(start)? {
+                Some((_, end_head, None)) => head = end_head,
+                _ => return Err(self.error(self.pos, "link label is never closed")),
+            }
+        }
+
+        // Label after the link: `-->|text|`
+        self.skip_whitespace();
+        if self.peek(0) == Some('|') {
+            let open_pos = self.pos;
+            self.pos += 1;
+            label = Some(clean_label(&self.until("|", open_pos, "|")?));
+        }
+
+        Ok(Some(Link {
+            stroke,
+            head,
+            tail,
+            label,
+        }))
+    }
+
+    /// Parse the dashes of a link and its head.
+    ///
+    /// Returns the stroke, the head, and for a link that opens an inline label
+    /// (`--`, `-.` or `==` followed by a space) the text that closes it.
+    fn link_body(&mut self, start: usize) -> Result<Option<LinkBody>, MermaidError> {
+        let body_start = self.pos;
+        while self.peek(0).is_some_and(is_link_char) {
+            self.pos += 1;
+        }
+        let body: String = self.chars[body_start..self.pos].iter().collect();
+
+        let (stroke, can_open) = if body.len() >= 2 && body.chars().all(|c| c == '-') {
+            (Stroke::Solid, body.len() == 2)
+        } else if body.len() >= 2 && body.chars().all(|c| c == '=') {
+            (Stroke::Thick, body.len() == 2)
+        } else if body.contains('.') && body.chars().all(|c| c == '-' || c == '.') {
+            (Stroke::Dotted, body == "-.")
+        } else if body.is_empty() {
+            return Ok(None);
+        } else {
+            return Err(self.error(start, format!("invalid link '{}'", body)));
+        };
+
+        let head = match self.peek(0) {
+            Some('>') => Head::Arrow,
+            Some('o') if !self.peek(1).is_some_and(is_id_char) => Head::Circle,
+            Some('x') if !self.peek(1).is_some_and(is_id_char) => Head::Cross,
+            _ => Head::None,
+        };
+        if head != Head::None {
+            self.pos += 1;
+            return Ok(Some((stroke, head, None)));
+        }
+
+        if can_open && self.peek(0).is_some_and(char::is_whitespace) {
+            let close = match stroke {
+                Stroke::Solid => "--",
+                Stroke::Thick => "==",
+                Stroke::Dotted => ".-",
+            };
+            return Ok(Some((stroke, head, Some(close))));
+        }
+        if can_open {
+            return Err(self.error(start, format!("incomplete link '{}'", body)));
+        }
+        Ok(Some((stroke, head, None)))
+    }
+}
+
+/// What a layout vertex stands for
+#[derive(Debug, Clone)]
+enum Kind {
+    Node(usize),
+    /// Edge label, occupying its own layer slot
+    Label(Vec<String>),
+    /// Point where a long edge crosses a layer
+    Dummy,
+}
+
+#[derive(Debug, Clone)]
+struct Vertex {
+    kind: Kind,
+    layer: usize,
+    /// Size across layers (width in TD) and along them (height in TD)
+    across: isize,
+    along: isize,
+    /// Start of the vertex across layers
+    pos: isize,
+    /// Start of the vertex along layers
+    start: isize,
+    /// Enclosing subgraphs, outermost first
+    path: Vec<usize>,
+}
+
+impl Vertex {
+    fn center(&self) -> isize {
+        self.pos + self.across / 2
+    }
+}
+
+/// Vertices an edge passes through, from its layout source to its layout target
+#[derive(Debug)]
+struct Chain {
+    edge: usize,
+    vertices: Vec<usize>,
+    /// Laid out against its direction to break a cycle
+    reversed: bool,
+    /// Frames whose border the chain starts or ends at, instead of its vertices
+    frames: [Option<usize>; 2],
+}
+
+/// Something to draw, in canvas coordinates (possibly negative until placed)
+enum Item {
+    Frame {
+        rect: Rect,
+    },
+    /// Frame title on its top border, `offset` columns in
+    Title {
+        rect: Rect,
+        offset: isize,
+        title: String,
+    },
+    Line {
+        points: Vec<(isize, isize)>,
+        stroke: Stroke,
+    },
+    Box {
+        rect: Rect,
+        shape: Shape,
+        text: NodeText,
+    },
+    Text {
+        rect: Rect,
+        lines: Vec<String>,
+    },
+    Mark {
+        at: (isize, isize),
+        unicode: char,
+        ascii: char,
+    },
+}
+
+/// Lines inside a node box
+#[derive(Debug, Clone)]
+struct NodeText {
+    lines: Vec<String>,
+    /// Lines of the label, centered; the rest are members, left-aligned
+    label_lines: usize,
+    /// Lines drawn as dividers between compartments
+    dividers: Vec<usize>,
+}
+
+impl NodeText {
+    fn new(node: &Node, wrap: Option<usize>) -> Self {
+        let mut lines = wrap_text(&node.label, wrap);
+        let label_lines = lines.len();
+        let mut dividers = Vec::new();
+        for compartment in node.members.iter().filter(|c| !c.is_empty()) {
+            dividers.push(lines.len());
+            lines.push(String::new());
+            for member in compartment {
+                lines.extend(wrap_text(member, wrap));
+            }
+        }
+        Self {
+            lines,
+            label_lines,
+            dividers,
+        }
+    }
+
+    fn width(&self) -> usize {
+        self.lines.iter().map(|l| text_width(l)).max().unwrap_or(0)
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Rect {
+    x: isize,
+    y: isize,
+    width: isize,
+    height: isize,
+}
+
+/// Edge segments routed along one track between two layers
+struct TrackGroup {
+    /// Shared source vertex, or shared target vertex when `true`
+    key: (usize, bool),
+    lo: isize,
+    hi: isize,
+    /// (chain, segment) pairs
+    segments: Vec<(usize, usize)>,
+}
+
+struct Layout<'c> {
+    chart: &'c Flowchart,
+    /// Flow direction, which may differ from the chart's to save width
+    direction: Direction,
+    /// Column limit for labels
+    wrap: Option<usize>,
+    vertices: Vec<Vertex>,
+    layers: Vec<Vec<usize>>,
+    chains: Vec<Chain>,
+    /// Start of each layer along the flow, and its extent
+    layer_start: Vec<isize>,
+    layer_extent: Vec<isize>,
+    /// Rows between layers before the first edge track
+    gap_before: isize,
+    /// Edge track assigned to each (chain, segment)
+    tracks: HashMap<(usize, usize), isize>,
+    /// Room added before and after each frame's members for its title
+    margins: Vec<(isize, isize)>,
+}
+
+impl<'c> Layout<'c> {
+    /// Lay out `chart` flowing in `direction`, wrapping labels to `wrap` columns
+    fn new(chart: &'c Flowchart, direction: Direction, wrap: Option<usize>) -> Self {
+        let vertical = direction.is_vertical();
+        let mut vertices: Vec<Vertex> = chart
+            .nodes
+            .iter()
+            .enumerate()
+            .map(|(idx, node)| {
+                let text = NodeText::new(node, wrap);
+                let (width, height) = match node.shape {
+                    Shape::Start | Shape::End => (1, 1),
+                    _ => (text.width() as isize + 4, text.lines.len() as isize + 2),
+                };
+                let (across, along) = if vertical {
+                    (width, height)
+                } else {
+                    (height, width)
+                };
+                Vertex {
+                    kind: Kind::Node(idx),
+                    layer: 0,
+                    across,
+                    along,
+                    pos: 0,
+                    start: 0,
+                    path: chart.subgraph_path(node.subgraph),
+                }
+            })
+            .collect();
+
+        let layer_of = assign_layers(chart);
+        for (vertex, layer) in vertices.iter_mut().zip(&layer_of) {
+            vertex.layer = *layer;
+        }
+
+        // Chains through labels and dummies for every edge but self-loops.
+        // Edges at a frame run to its first node in the flow, or from its
+        // last, and stop at the frame's border.
+        let anchor = |end: End, last: bool| {
+            let nodes = chart.end_nodes(end).into_iter();
+            if last {
+                nodes.max_by_key(|&idx| layer_of[idx])
+            } else {
+                nodes.min_by_key(|&idx| layer_of[idx])
+            }
+        };
+        let mut chains = Vec::new();
+        for (idx, edge) in chart.edges.iter().enumerate() {
+            let (Some(from), Some(to)) = (anchor(edge.from, true), anchor(edge.to, false)) else {
+                continue;
+            };
+            if layer_of[from] == layer_of[to] {
+                continue;
+            }
+            let reversed = layer_of[from] > layer_of[to];
+            let (source, target) = if reversed { (to, from) } else { (from, to) };
+            // A frame's border is only crossed by edges from outside it
+            let border = |end: End, other: usize| match end {
+                End::Frame(subgraph) if !reversed => Some(subgraph).filter(|&s| {
+                    !chart
+                        .subgraph_path(chart.nodes[other].subgraph)
+                        .contains(&s)
+                }),
+                _ => None,
+            };
+            let frames = [border(edge.from, to), border(edge.to, from)];
+            let mut chain = vec![source];
+            for layer in layer_of[source] + 1..layer_of[target] {
+                let label = edge
+                    .link
+                    .label
+                    .as_ref()
+                    .filter(|_| layer == layer_of[source] + 1);
+                let vertex = match label {
+                    Some(label) => {
+                        let lines = wrap_text(label, wrap);
+                        let width = lines.iter().map(|l| text_width(l)).max().unwrap_or(0) as isize;
+                        let (across, along) = if vertical {
+                            (width, lines.len() as isize)
+                        } else {
+                            (lines.len() as isize, width + 2)
+                        };
+                        Vertex {
+                            kind: Kind::Label(lines),
+                            layer,
+                            across,
+                            along,
+                            pos: 0,
+                            start: 0,
+                            path: Vec::new(),
+                        }
+                    }
+                    None => Vertex {
+                        kind: Kind::Dummy,
+                        layer,
+                        across: 1,
+                        along: 0,
+                        pos: 0,
+                        start: 0,
+                        path: Vec::new(),
+                    },
+                };
+                vertices.push(vertex);
+                chain.push(vertices.len() - 1);
+            }
+            chain.push(target);
+            chains.push(Chain {
+                edge: idx,
+                vertices: chain,
+                reversed,
+                frames,
+            });
+        }
+
+        let layer_count = vertices.iter().map(|v| v.layer + 1).max().unwrap_or(0);
+        let mut layers = vec![Vec::new(); layer_count];
+        for (idx, vertex) in vertices.iter().enumerate() {
+            layers[vertex.layer].push(idx);
+        }
+
+        let mut layout = Self {
+            chart,
+            direction,
+            wrap,
+            vertices,
+            layers,
+            chains,
+            layer_start: Vec::new(),
+            layer_extent: Vec::new(),
+            gap_before: 0,
+            tracks: HashMap::new(),
+            margins: vec![(0, 0); chart.subgraphs.len()],
+        };
+        layout.order();
+        layout.place_across();
+        layout.clear_frames();
+        layout.fit_titles();
+        layout.place_along();
+        layout
+    }
+
+    fn vertical(&self) -> bool {
+        self.direction.is_vertical()
+    }
+
+    /// Neighbors of each vertex in the previous and next layers
+    fn neighbors(&self) -> (Vec<Vec<usize>>, Vec<Vec<usize>>) {
+        let mut preds = vec![Vec::new(); self.vertices.len()];
+        let mut succs = vec![Vec::new(); self.vertices.len()];
+        for chain in &self.chains {
+            for pair in chain.vertices.windows(2) {
+                succs[pair[0]].push(pair[1]);
+                preds[pair[1]].push(pair[0]);
+            }
+        }
+        (preds, succs)
+    }
+
+    /// Order vertices within layers to reduce crossings
+    fn order(&mut self) {
+        let (preds, succs) = self.neighbors();
+        for layer in 0..self.layers.len() {
+            self.group_layer(layer);
+        }
+        for _ in 0..4 {
+            for layer in 1..self.layers.len() {
+                self.sort_by_barycenter(layer, layer - 1, &preds);
+            }
+            for layer in (0..self.layers.len().saturating_sub(1)).rev() {
+                self.sort_by_barycenter(layer, layer + 1, &succs);
+            }
+        }
+    }
+
+    /// Sort a layer by the mean position of each vertex's neighbors in `other`
+    fn sort_by_barycenter(&mut self, layer: usize, other: usize, neighbors: &[Vec<usize>]) {
+        let index: HashMap<usize, usize> = self.layers[other]
+            .iter()
+            .enumerate()
+            .map(|(i, &v)| (v, i))
+            .collect();
+        let mut keyed: Vec<(f64, usize)> = self.layers[layer]
+            .iter()
+            .enumerate()
+            .map(|(i, &v)| {
+                let positions: Vec<usize> = neighbors[v]
+                    .iter()
+                    .filter_map(|n| index.get(n).copied())
+                    .collect();
+                let key = if positions.is_empty() {
+                    i as f64
+                } else {
+                    positions.iter().sum::<usize>() as f64 / positions.len() as f64
+                };
+                (key, v)
+            })
+            .collect();
+        keyed.sort_by(|a, b| a.0.total_cmp(&b.0));
+        self.layers[layer] = keyed.into_iter().map(|(_, v)| v).collect();
+        self.group_layer(layer);
+    }
+
+    /// Keep members of each subgraph next to each other within a layer
+    fn group_layer(&mut self, layer: usize) {
+        let order = &self.layers[layer];
+        let paths: Vec<&Vec<usize>> = order.iter().map(|&v| &self.vertices[v].path).collect();
+        let mut keys: Vec<(Vec<usize>, usize)> = (0..order.len())
+            .map(|i| {
+                let mut key: Vec<usize> = (1..=paths[i].len())
+                    .map(|depth| {
+                        paths
+                            .iter()
+                            .position(|p| p.starts_with(&paths[i][..depth]))
+                            .unwrap_or(i)
+                    })
+                    .collect();
+                key.push(i);
+                (key, order[i])
+            })
+            .collect();
+        keys.sort();
+        self.layers[layer] = keys.into_iter().map(|(_, v)| v).collect();
+    }
+
+    /// Padding between a subgraph's members and its frame, across layers
+    fn frame_padding(&self, subgraph: usize) -> isize {
+        let base = if self.vertical() { 2 } else { 1 };
+        base + self.chart.subgraph_height(subgraph) as isize
+    }
+
+    /// Space needed between neighbors `a` and `b` in a layer
+    fn separation(&self, a: usize, b: usize) -> isize {
+        let base = if self.vertical() { 3 } else { 1 };
+        let (pa, pb) = (&self.vertices[a].path, &self.vertices[b].path);
+        let common = pa.iter().zip(pb).take_while(|(x, y)| x == y).count();
+        // Frames closing after `a` and opening before `b`
+        let closing = pa.get(common).map_or(0, |&s| self.frame_padding(s));
+        let opening = pb.get(common).map_or(0, |&s| self.frame_padding(s));
+        if closing + opening == 0 {
+            base
+        } else {
+            base.max(closing + opening + 1)
+        }
+    }
+
+    /// Position vertices across layers, centering them on their neighbors
+    fn place_across(&mut self) {
+        let (preds, succs) = self.neighbors();
+        for layer in 0..self.layers.len() {
+            let mut next = 0;
+            for i in 0..self.layers[layer].len() {
+                let v = self.layers[layer][i];
+                if i > 0 {
+                    next += self.separation(self.layers[layer][i - 1], v);
+                }
+                self.vertices[v].pos = next;
+                next += self.vertices[v].across;
+            }
+        }
+
+        // Alternate sweeps, ending upward so edge labels sit over their targets
+        for pass in 0..8 {
+            let downward = pass % 2 == 0;
+            let layers: Vec<usize> = if downward {
+                (1..self.layers.len()).collect()
+            } else {
+                (0..self.layers.len().saturating_sub(1)).rev().collect()
+            };
+            let neighbors = if downward { &preds } else { &succs };
+            for layer in layers {
+                self.align_layer(layer, neighbors);
+            }
+        }
+    }
+
+    /// Move each vertex of a layer toward the mean center of its neighbors
+    fn align_layer(&mut self, layer: usize, neighbors: &[Vec<usize>]) {
+        let order = self.layers[layer].clone();
+        let mut min_pos = isize::MIN;
+        for (i, &v) in order.iter().enumerate() {
+            let centers: Vec<isize> = neighbors[v]
+                .iter()
+                .map(|&n| self.vertices[n].center())
+                .collect();
+            let desired = if centers.is_empty() {
+                self.vertices[v].pos
+            } else {
+                let mean = centers.iter().sum::<isize>() as f64 / centers.len() as f64;
+                mean.round() as isize - self.vertices[v].across / 2
+            };
+            if i > 0 {
+                let prev = &self.vertices[order[i - 1]];
+                min_pos = prev.pos + prev.across + self.separation(order[i - 1], v);
+            }
+            self.vertices[v].pos = desired.max(min_pos);
+        }
+    }
+
+    /// Span of a subgraph's frame across layers, end exclusive
+    fn frame_across(&self, subgraph: usize) -> Option<(isize, isize)> {
+        let pad = self.frame_padding(subgraph);
+        let members = self
+            .vertices
+            .iter()
+            .filter(|v| v.path.contains(&subgraph))
+            .map(|v| (v.pos - pad, v.pos + v.across + pad));
+        // Nested frames may have been widened past their members
+        let nested = (0..self.chart.subgraphs.len())
+            .filter(|&s| self.chart.subgraphs[s].parent == Some(subgraph))
+            .filter_map(|s| self.frame_across(s))
+            .map(|(lo, hi)| (lo - 1, hi + 1));
+        let (lo, hi) = members
+            .chain(nested)
+            .reduce(|(lo, hi), (a, b)| (lo.min(a), hi.max(b)))?;
+        let (before, after) = self.margins[subgraph];
+        Some((lo - before, hi + after))
+    }
+
+    /// First and last row of a subgraph's frame along the flow
+    fn frame_along(&self, subgraph: usize) -> (isize, isize) {
+        let height = self.chart.subgraph_height(subgraph) as isize;
+        let members = || self.vertices.iter().filter(|v| v.path.contains(&subgraph));
+        let first = members().map(|v| v.start).min().unwrap_or(0) - 2 - height;
+        let last = members().map(|v| v.start + v.along).max().unwrap_or(0) + height;
+        (first, last)
+    }
+
+    /// Centers of the edges crossing the border a subgraph's title sits on
+    fn title_crossings(&self, subgraph: usize) -> Vec<isize> {
+        let (preds, succs) = self.neighbors();
+        // The title border faces the flow's start, except when flowing upward
+        let (neighbors, upward) = match self.direction {
+            Direction::TopDown => (&preds, false),
+            Direction::BottomUp => (&succs, true),
+            Direction::LeftRight | Direction::RightLeft => return Vec::new(),
+        };
+        let inside = |v: usize| self.vertices[v].path.contains(&subgraph);
+        let members: Vec<usize> = (0..self.vertices.len()).filter(|&v| inside(v)).collect();
+        let layers = members.iter().map(|&v| self.vertices[v].layer);
+        let Some(edge_layer) = (if upward { layers.max() } else { layers.min() }) else {
+            return Vec::new();
+        };
+        let mut crossings: Vec<isize> = members
+            .into_iter()
+            .filter(|&v| self.vertices[v].layer == edge_layer)
+            .filter(|&v| neighbors[v].iter().any(|&n| !inside(n)))
+            .map(|v| self.vertices[v].center())
+            .collect();
+        crossings.sort_unstable();
+        crossings
+    }
+
+    /// Move vertices out of frames they don't belong to.
+    ///
+    /// A frame spans its widest layer, so a vertex placed beside the members
+    /// of a narrower layer can still end up inside it.
+    fn clear_frames(&mut self) {
+        for _ in 0..=self.chart.subgraphs.len() {
+            let mut moved = false;
+            for subgraph in 0..self.chart.subgraphs.len() {
+                let Some((lo, hi)) = self.frame_across(subgraph) else {
+                    continue;
+                };
+                let inside = |v: &Vertex| v.path.contains(&subgraph);
+                let layers = self.vertices.iter().filter(|v| inside(v)).map(|v| v.layer);
+                let (Some(first), Some(last)) = (layers.clone().min(), layers.max()) else {
+                    continue;
+                };
+                for layer in first..=last {
+                    let order = self.layers[layer].clone();
+                    // Vertices before the members, or before the middle of the frame
+                    let split = match order.iter().position(|&v| inside(&self.vertices[v])) {
+                        Some(split) => split,
+                        None => order
+                            .iter()
+                            .position(|&v| self.vertices[v].center() >= (lo + hi) / 2)
+                            .unwrap_or(order.len()),
+                    };
+                    let after = order[split..]
+                        .iter()
+                        .position(|&v| !inside(&self.vertices[v]))
+                        .map_or(order.len(), |i| split + i);
+                    if let Some(&v) = order.get(after) {
+                        let shift = hi + 1 - self.vertices[v].pos;
+                        if shift > 0 {
+                            order[after..]
+                                .iter()
+                                .for_each(|&v| self.vertices[v].pos += shift);
+                            moved = true;
+                        }
+                    }
+                    if let Some(&v) = split.checked_sub(1).map(|i| &order[i]) {
+                        let shift = self.vertices[v].pos + self.vertices[v].across - (lo - 1);
+                        if shift > 0 {
+                            order[..split]
+                                .iter()
+                                .for_each(|&v| self.vertices[v].pos -= shift);
+                            moved = true;
+                        }
+                    }
+                }
+            }
+            if !moved {
+                break;
+            }
+        }
+    }
+
+    /// Widen frames whose title doesn't fit between the edges crossing its
+    /// border, moving their members and everything beyond them over
+    fn fit_titles(&mut self) {
+        if !self.vertical() {
+            return;
+        }
+        let mut subgraphs: Vec<usize> = (0..self.chart.subgraphs.len()).collect();
+        subgraphs.sort_by_key(|&s| self.chart.subgraph_height(s));
+        for subgraph in subgraphs {
+            let title = text_width(&self.chart.subgraphs[subgraph].title) as isize;
+            let Some((lo, hi)) = self.frame_across(subgraph) else {
+                continue;
+            };
+            let crossings = self.title_crossings(subgraph);
+            if title == 0 || title_offset(lo, hi, &crossings, title).is_some() {
+                continue;
+            }
+            // Center the members under a title that has the border to itself,
+            // otherwise make room for it before the first edge
+            let (left, right) = match crossings.first() {
+                None => {
+                    let short = title + 6 - (hi - lo);
+                    (short / 2, short - short / 2)
+                }
+                Some(&first) => (lo + title + 5 - first, 0),
+            };
+            for vertex in &mut self.vertices {
+                let center = vertex.center();
+                if center >= hi {
+                    vertex.pos += left + right;
+                } else if center >= lo {
+                    vertex.pos += left;
+                }
+            }
+            self.margins[subgraph] = (left, right);
+        }
+    }
+
+    /// Number of subgraph nesting levels
+    fn frame_levels(&self) -> isize {
+        (0..self.chart.subgraphs.len())
+            .filter(|&s| self.chart.subgraphs[s].parent.is_none())
+            .map(|s| self.chart.subgraph_height(s) as isize + 1)
+            .max()
+            .unwrap_or(0)
+    }
+
+    /// Position layers along the flow, leaving room for edge tracks between them
+    fn place_along(&mut self) {
+        let levels = self.frame_levels();
+        self.gap_before = levels.max(1);
+        let gap_after = levels + 1;
+
+        let mut start = 0;
+        for layer in 0..self.layers.len() {
+            let extent = self.layers[layer]
+                .iter()
+                .map(|&v| self.vertices[v].along)
+                .max()
+                .unwrap_or(0);
+            for &v in &self.layers[layer] {
+                let vertex = &mut self.vertices[v];
+                vertex.start = start + (extent - vertex.along) / 2;
+            }
+            self.layer_start.push(start);
+            self.layer_extent.push(extent);
+
+            let tracks = self.assign_tracks(layer);
+            start += extent + self.gap_before + tracks + gap_after;
+        }
+    }
+
+    /// Give each bent edge segment leaving `layer` a track in the gap below it.
+    ///
+    /// Segments fanning out of one vertex share a track, as do segments
+    /// merging into a vertex whose sources have no other bent segments; the
+    /// rest get separate tracks where their runs would overlap. Returns the
+    /// number of tracks.
+    fn assign_tracks(&mut self, layer: usize) -> isize {
+        let mut segments = Vec::new();
+        for (chain_idx, chain) in self.chains.iter().enumerate() {
+            for (seg, pair) in chain.vertices.windows(2).enumerate() {
+                let (a, b) = (&self.vertices[pair[0]], &self.vertices[pair[1]]);
+                if a.layer == layer && a.center() != b.center() {
+                    segments.push(((chain_idx, seg), pair[0], pair[1]));
+                }
+            }
+        }
+        let fan_out = |source: usize| segments.iter().filter(|s| s.1 == source).count();
+        let merges: Vec<bool> = segments
+            .iter()
+            .map(|&(_, _, target)| {
+                let sources: Vec<usize> = segments
+                    .iter()
+                    .filter(|s| s.2 == target)
+                    .map(|s| s.1)
+                    .collect();
+                sources.len() > 1 && sources.iter().all(|&source| fan_out(source) == 1)
+            })
+            .collect();
+
+        // Horizontal span of each group of segments sharing a track
+        let mut groups: Vec<TrackGroup> = Vec::new();
+        for (&(segment, source, target), merge) in segments.iter().zip(merges) {
+            let group = if merge {
+                (target, true)
+            } else {
+                (source, false)
+            };
+            let (a, b) = (
+                self.vertices[source].center(),
+                self.vertices[target].center(),
+            );
+            let (lo, hi) = (a.min(b), a.max(b));
+            match groups.iter_mut().find(|g| g.key == group) {
+                Some(g) => {
+                    g.lo = g.lo.min(lo);
+                    g.hi = g.hi.max(hi);
+                    g.segments.push(segment);
+                }
+                None => groups.push(TrackGroup {
+                    key: group,
+                    lo,
+                    hi,
+                    segments: vec![segment],
+                }),
+            }
+        }
+        groups.sort_by_key(|g| (g.lo, g.hi));
+
+        let mut track_ends: Vec<isize> = Vec::new();
+        for TrackGroup {
+            lo, hi, segments, ..
+        } in groups
+        {
+            let track = match track_ends.iter().position(|&end| end + 1 < lo) {
+                Some(track) => {
+                    track_ends[track] = hi;
+                    track
+                }
+                None => {
+                    track_ends.push(hi);
+                    track_ends.len() - 1
+                }
+            };
+            for segment in segments {
+                self.tracks.insert(segment, track as isize);
+            }
+        }
+        track_ends.len() as isize
+    }
+
+    /// Along-flow coordinate where an edge leaves a vertex
+    fn exit(&self, v: usize) -> isize {
+        let vertex = &self.vertices[v];
+        match vertex.kind {
+            Kind::Dummy => self.layer_start[vertex.layer] + self.layer_extent[vertex.layer],
+            Kind::Label(_) if !self.vertical() => vertex.start + vertex.along - 1,
+            _ => vertex.start + vertex.along,
+        }
+    }
+
+    /// Along-flow coordinate where an edge enters a vertex
+    fn entry(&self, v: usize) -> isize {
+        let vertex = &self.vertices[v];
+        match vertex.kind {
+            Kind::Dummy => self.layer_start[vertex.layer] + self.layer_extent[vertex.layer] - 1,
+            Kind::Label(_) if !self.vertical() => vertex.start,
+            _ => vertex.start - 1,
+        }
+    }
+
+    /// Map a point (across, along) to canvas coordinates
+    fn point(&self, across: isize, along: isize) -> (isize, isize) {
+        match self.direction {
+            Direction::TopDown => (across, along),
+            Direction::BottomUp => (across, -along),
+            Direction::LeftRight => (along, across),
+            Direction::RightLeft => (-along, across),
+        }
+    }
+
+    /// Map a rectangle (across, along, size across, size along) to canvas coordinates
+    fn rect(&self, across: isize, along: isize, size_across: isize, size_along: isize) -> Rect {
+        let (x, y) = self.point(across, along);
+        let (x, y) = match self.direction {
+            Direction::TopDown | Direction::LeftRight => (x, y),
+            Direction::BottomUp => (x, y - size_along + 1),
+            Direction::RightLeft => (x - size_along + 1, y),
+        };
+        let (width, height) = if self.vertical() {
+            (size_across, size_along)
+        } else {
+            (size_along, size_across)
+        };
+        Rect {
+            x,
+            y,
+            width,
+            height,
+        }
+    }
+
+    /// Everything to draw, in canvas coordinates
+    fn items(&self) -> Vec<Item> {
+        let mut items = Vec::new();
+
+        // Subgraph frames, outermost first, with titles clear of crossing edges
+        for (idx, subgraph) in self.chart.subgraphs.iter().enumerate() {
+            let Some((lo, hi)) = self.frame_across(idx) else {
+                continue;
+            };
+            let (first, last) = self.frame_along(idx);
+            let rect = self.rect(lo, first, hi - lo, last - first + 1);
+            let offset = if self.vertical() {
+                let title = text_width(&subgraph.title) as isize;
+                title_offset(lo, hi, &self.title_crossings(idx), title).map_or(2, |at| at - lo)
+            } else {
+                2
+            };
+            items.push(Item::Frame { rect });
+            items.push(Item::Title {
+                rect,
+                offset,
+                title: subgraph.title.clone(),
+            });
+        }
+
+        for (chain_idx, chain) in self.chains.iter().enumerate() {
+            let link = &self.chart.edges[chain.edge].link;
+            let mut polyline: Vec<(isize, isize)> = Vec::new();
+            let mut polylines = Vec::new();
+            for (seg, pair) in chain.vertices.windows(2).enumerate() {
+                let (a, b) = (pair[0], pair[1]);
+                let (from, to) = (self.vertices[a].center(), self.vertices[b].center());
+                let (exit, entry) = (self.exit(a), self.entry(b));
+                polyline.push(self.point(from, exit));
+                if from != to {
+                    let layer = self.vertices[a].layer;
+                    let track = self.layer_start[layer]
+                        + self.layer_extent[layer]
+                        + self.gap_before
+                        + self.tracks.get(&(chain_idx, seg)).copied().unwrap_or(0);
+                    polyline.push(self.point(from, track));
+                    polyline.push(self.point(to, track));
+                }
+                polyline.push(self.point(to, entry));
+                // Labels interrupt the line; dummies continue it
+                if !matches!(self.vertices[b].kind, Kind::Dummy) {
+                    polylines.push(std::mem::take(&mut polyline));
+                }
+            }
+            // Edges at a frame stop at its border
+            if let (Some(subgraph), Some(first)) = (chain.frames[0], polylines.first_mut()) {
+                let across = self.vertices[chain.vertices[0]].center();
+                first[0] = self.point(across, self.frame_along(subgraph).1);
+            }
+            if let (Some(subgraph), Some(last)) = (chain.frames[1], polylines.last_mut()) {
+                let across = self.vertices[chain.vertices[chain.vertices.len() - 1]].center();
+                if let Some(point) = last.last_mut() {
+                    *point = self.point(across, self.frame_along(subgraph).0);
+                }
+            }
+
+            let (start_head, end_head) = if chain.reversed {
+                (link.head, link.tail)
+            } else {
+                (link.tail, link.head)
+            };
+            if let Some(first) = polylines.first() {
+                items.extend(head_mark(first, start_head, true));
+            }
+            if let Some(last) = polylines.last() {
+                items.extend(head_mark(last, end_head, false));
+            }
+            for points in polylines.into_iter().rev() {
+                items.insert(
+                    0,
+                    Item::Line {
+                        points,
+                        stroke: link.stroke,
+                    },
+                );
+            }
+        }
+
+        for vertex in &self.vertices {
+            let rect = self.rect(vertex.pos, vertex.start, vertex.across, vertex.along);
+            match &vertex.kind {
+                Kind::Node(idx) => {
+                    let node = &self.chart.nodes[*idx];
+                    items.push(Item::Box {
+                        rect,
+                        shape: node.shape,
+                        text: NodeText::new(node, self.wrap),
+                    });
+                }
+                Kind::Label(lines) => items.push(Item::Text {
+                    rect,
+                    lines: lines.clone(),
+                }),
+                Kind::Dummy => {}
+            }
+        }
+
+        // Frames go underneath everything else, their titles over edge lines
+        items.sort_by_key(|item| match item {
+            Item::Frame { .. } => 0,
+            Item::Line { .. } => 1,
+            Item::Title { .. } => 2,
+            _ => 3,
+        });
+        items
+    }
+
+    /// Draw the layout onto a canvas sized to fit it
+    fn draw(&self, unicode: bool) -> Canvas {
+        let items = self.items();
+        let (mut min_x, mut min_y) = (isize::MAX, isize::MAX);
+        let (mut max_x, mut max_y) = (isize::MIN, isize::MIN);
+        let mut extend = |x: isize, y: isize| {
+            min_x = min_x.min(x);
+            min_y = min_y.min(y);
+            max_x = max_x.max(x);
+            max_y = max_y.max(y);
+        };
+        for item in &items {
+            match item {
+                Item::Frame { rect }
+                | Item::Title { rect, .. }
+                | Item::Box { rect, .. }
+                | Item::Text { rect, .. } => {
+                    extend(rect.x, rect.y);
+                    extend(rect.x + rect.width - 1, rect.y + rect.height - 1);
+                }
+                Item::Line { points, .. } => points.iter().for_each(|&(x, y)| extend(x, y)),
+                Item::Mark { at, .. } => extend(at.0, at.1),
+            }
+        }
+
+        let place = |x: isize, y: isize| ((x - min_x) as usize, (y - min_y) as usize);
+        let mut canvas = Canvas::new(
+            (max_x - min_x + 1) as usize,
+            (max_y - min_y + 1) as usize,
+            unicode,
+        );
+        for item in items {
+            match item {
+                Item::Frame { rect } => {
+                    let (x, y) = place(rect.x, rect.y);
+                    canvas.frame(x, y, rect.width as usize, rect.height as usize);
+                }
+                Item::Title {
+                    rect,
+                    offset,
+                    title,
+                } => {
+                    let (x, y) = place(rect.x, rect.y);
+                    let room = (rect.width - offset - 2).max(0) as usize;
+                    let title = wrap_text(&title, Some(room.max(1))).into_iter().next();
+                    if let Some(title) = title.filter(|t| !t.is_empty() && room > 2) {
+                        canvas.text(x + offset as usize, y, &format!(" {} ", title));
+                    }
+                }
+                Item::Line { points, stroke } => {
+                    let points: Vec<(usize, usize)> =
+                        points.into_iter().map(|(x, y)| place(x, y)).collect();
+                    canvas.line(&points, stroke);
+                }
+                Item::Box { rect, shape, text } => {
+                    let (x, y) = place(rect.x, rect.y);
+                    draw_box(&mut canvas, x, y, rect, shape, &text);
+                }
+                Item::Text { rect, lines } => {
+                    let (x, y) = place(rect.x, rect.y);
+                    for (row, line) in lines.iter().enumerate() {
+                        let indent = (rect.width as usize).saturating_sub(text_width(line)) / 2;
+                        canvas.text(x + indent, y + row, line);
+                    }
+                }
+                Item::Mark { at, unicode, ascii } => {
+                    let (x, y) = place(at.0, at.1);
+                    canvas.put(x, y, unicode, ascii);
+                }
+            }
+        }
+        canvas
+    }
+}
+
+/// Assign layers by longest path, reversing back edges to break cycles.
+///
+/// Labeled edges span two layers so the label gets its own slot, and edges
+/// at a frame keep every node inside it on the far side.
+fn assign_layers(chart: &Flowchart) -> Vec<usize> {
+    let n = chart.nodes.len();
+    // (from, to, span) between nodes
+    let mut arcs = Vec::new();
+    for edge in &chart.edges {
+        let span = if edge.link.label.is_some() { 2 } else { 1 };
+        for from in chart.end_nodes(edge.from) {
+            for to in chart.end_nodes(edge.to) {
+                if from != to {
+                    arcs.push((from, to, span));
+                }
+            }
+        }
+    }
+    let mut outgoing = vec![Vec::new(); n];
+    for (idx, &(from, _, _)) in arcs.iter().enumerate() {
+        outgoing[from].push(idx);
+    }
+
+    // Depth-first search; arcs into a node still on the stack close a cycle
+    let mut reversed = vec![false; arcs.len()];
+    let mut state = vec![0u8; n]; // 0 new, 1 on stack, 2 done
+    for root in 0..n {
+        if state[root] != 0 {
+            continue;
+        }
+        let mut stack = vec![(root, 0)];
+        state[root] = 1;
+        while let Some(&mut (node, ref mut next)) = stack.last_mut() {
+            if let Some(&arc) = outgoing[node].get(*next) {
+                *next += 1;
+                let target = arcs[arc].1;
+                match state[target] {
+                    0 => {
+                        state[target] = 1;
+                        stack.push((target, 0));
+                    }
+                    1 => reversed[arc] = true,
+                    _ => {}
+                }
+            } else {
+                state[node] = 2;
+                stack.pop();
+            }
+        }
+    }
+
+    // Longest path over the now acyclic graph (Kahn's algorithm)
+    let mut successors = vec![Vec::new(); n];
+    let mut indegree = vec![0; n];
+    for (&(from, to, span), reversed) in arcs.iter().zip(reversed) {
+        let (from, to) = if reversed { (to, from) } else { (from, to) };
+        successors[from].push((to, span));
+        indegree[to] += 1;
+    }
+    let mut layer = vec![0; n];
+    let mut ready: Vec<usize> = (0..n).filter(|&v| indegree[v] == 0).rev().collect();
+    while let Some(v) = ready.pop() {
+        for &(to, span) in &successors[v] {
+            layer[to] = layer[to].max(layer[v] + span);
+            indegree[to] -= 1;
+            if indegree[to] == 0 {
+                ready.push(to);
+            }
+        }
+    }
+    layer
+}
+
+/// Start of a title `width` columns wide on the border of a frame spanning
+/// `lo..hi`, clear of the corners and of edges crossing at `crossings`
+fn title_offset(lo: isize, hi: isize, crossings: &[isize], width: isize) -> Option<isize> {
+    // The title is padded with a space either side and kept a border cell
+    // away from each corner and crossing
+    let width = width + 2;
+    std::iter::once(lo + 2)
+        .chain(crossings.iter().map(|c| c + 2))
+        .find(|&at| at + width <= hi - 2 && crossings.iter().all(|&c| c < at - 1 || c > at + width))
+}
+
+/// Arrowhead (or circle/cross) at one end of a polyline
+fn head_mark(points: &[(isize, isize)], head: Head, at_start: bool) -> Option<Item> {
+    if head == Head::None || points.is_empty() {
+        return None;
+    }
+    let (at, toward) = if at_start {
+        (points[0], points.get(1).copied())
+    } else {
+        (
+            points[points.len() - 1],
+            points.len().checked_sub(2).map(|i| points[i]),
+        )
+    };
+    let (unicode, ascii) = match head {
+        Head::Circle => ('o', 'o'),
+        Head::Cross => ('x', 'x'),
+        Head::Diamond => ('◆', '*'),
+        Head::OpenDiamond => ('◇', 'o'),
+        _ => {
+            // Point away from the rest of the line
+            let (dx, dy) = toward.map_or((0, -1), |(x, y)| (at.0 - x, at.1 - y));
+            let (filled, hollow, ascii) = match (dx.signum(), dy.signum()) {
+                (0, 1) => ('▼', '▽', 'v'),
+                (0, -1) => ('▲', '△', '^'),
+                (1, _) => ('▶', '▷', '>'),
+                _ => ('◀', '◁', '<'),
+            };
+            let unicode = if head == Head::Triangle {
+                hollow
+            } else {
+                filled
+            };
+            (unicode, ascii)
+        }
+    };
+    Some(Item::Mark { at, unicode, ascii })
+}
+
+/// Draw a node box with its label centered inside and members below it
+fn draw_box(canvas: &mut Canvas, x: usize, y: usize, rect: Rect, shape: Shape, text: &NodeText) {
+    match shape {
+        Shape::Start => return canvas.put(x, y, '●', '*'),
+        Shape::End => return canvas.put(x, y, '◉', '@'),
+        _ => {}
+    }
+    let (corners, sides) = shape.outline(canvas.unicode());
+    let (width, height) = (rect.width as usize, rect.height as usize);
+    let label = &text.lines[..text.label_lines.min(text.lines.len())];
+    if text.lines.len() == label.len() {
+        return canvas.boxed(x, y, width, height, corners, sides, label);
+    }
+
+    canvas.boxed(x, y, width, height, corners, sides, &[]);
+    let (left, line, right) = if canvas.unicode() {
+        ('├', '─', '┤')
+    } else {
+        ('+', '-', '+')
+    };
+    let divider: String = std::iter::once(left)
+        .chain(std::iter::repeat_n(line, width.saturating_sub(2)))
+        .chain(std::iter::once(right))
+        .collect();
+    for (row, content) in text.lines.iter().enumerate() {
+        let y = y + 1 + row;
+        if text.dividers.contains(&row) {
+            canvas.text(x, y, &divider);
+        } else if row < text.label_lines {
+            let indent = width.saturating_sub(2 + text_width(content)) / 2;
+            canvas.text(x + 1 + indent, y, content);
+        } else {
+            canvas.text(x + 2, y, content);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn draw(source: &str) -> String {
+        render(source, 200, true).unwrap()
+    }
+
+    #[test]
+    fn test_parse_shapes_and_links() {
+        let chart = parse(
+            "flowchart LR\n  A[Start] -->|go| B{Ready?}\n  B -- yes --> C((Done))\n  B -.-> D[(Store)] & E>Flag]\n  C ==> A; D --- E",
+        )
+        .unwrap();
+        assert_eq!(chart.direction, Direction::LeftRight);
+        let shapes: Vec<Shape> = chart.nodes.iter().map(|n| n.shape).collect();
+        assert_eq!(
+            shapes,
+            [
+                Shape::Rect,
+                Shape::Rhombus,
+                Shape::Circle,
+                Shape::Cylinder,
+                Shape::Asymmetric
+            ]
+        );
+        assert_eq!(chart.nodes[1].label, "Ready?");
+        assert_eq!(chart.edges.len(), 6);
+        assert_eq!(chart.edges[0].link.label.as_deref(), Some("go"));
+        assert_eq!(chart.edges[1].link.label.as_deref(), Some("yes"));
+        assert_eq!(chart.edges[1].link.head, Head::Arrow);
+        assert_eq!(chart.edges[2].link.stroke, Stroke::Dotted);
+        assert_eq!(chart.edges[3].to, End::Node(4));
+        assert_eq!(chart.edges[4].link.stroke, Stroke::Thick);
+        assert_eq!(chart.edges[5].link.head, Head::None);
+
+        let chart = parse("graph TD\n  A -. maybe .-> B\n  A o--o C").unwrap();
+        assert_eq!(chart.edges[0].link.stroke, Stroke::Dotted);
+        assert_eq!(chart.edges[0].link.label.as_deref(), Some("maybe"));
+        assert_eq!(chart.edges[1].link.tail, Head::Circle);
+        assert_eq!(chart.edges[1].link.head, Head::Circle);
+    }
+
+    #[test]
+    fn test_parse_subgraphs() {
+        let chart = parse(
+            "graph TD\n  subgraph one [First]\n    a1 --> a2\n  end\n  subgraph two\n    b1\n  end\n  a2 --> b1",
+        )
+        .unwrap();
+        assert_eq!(chart.subgraphs.len(), 2);
+        assert_eq!(chart.subgraphs[0].title, "First");
+        assert_eq!(chart.nodes[0].subgraph, Some(0));
+        assert_eq!(chart.nodes[2].subgraph, Some(1));
+    }
+
+    #[test]
+    fn test_parse_errors_have_positions() {
+        let err = parse("graph TD\n  A --> B[oops").unwrap_err();
+        assert_eq!(err.position(), Some((2, 10)));
+
+        let err = parse("graph XY\n  A --> B").unwrap_err();
+        assert_eq!(err.position(), Some((1, 7)));
+
+        let err = parse("graph TD\n  A --> \n").unwrap_err();
+        assert_eq!(err.position(), Some((2, 8)));
+
+        let err = parse("graph TD\n  A ~~ B").unwrap_err();
+        assert_eq!(err.position(), Some((2, 5)));
+
+        let err = parse("graph TD\n  subgraph S\n  A").unwrap_err();
+        assert_eq!(err.position(), Some((2, 1)));
+    }
+
+    #[test]
+    fn test_top_down() {
+        assert_eq!(
+            draw("graph TD\n  A[Start] --> B[End]"),
+            "┌───────┐\n\
+             │ Start │\n\
+             └───────┘\n    \
+             │\n    \
+             ▼\n \
+             ┌─────┐\n \
+             │ End │\n \
+             └─────┘"
+        );
+    }
+
+    #[test]
+    fn test_left_right_with_label() {
+        assert_eq!(
+            draw("graph LR\n  A -->|yes| B"),
+            "┌───┐         ┌───┐\n\
+             │ A │───yes──▶│ B │\n\
+             └───┘         └───┘"
+        );
+    }
+
+    #[test]
+    fn test_bottom_up_points_up() {
+        let out = draw("graph BT\n  A --> B");
+        let lines: Vec<&str> = out.lines().collect();
+        assert!(lines[1].contains('B'));
+        assert!(lines[3].contains('▲'));
+        assert!(lines[6].contains('A'));
+    }
+
+    #[test]
+    fn test_branches_and_cycles() {
+        let out = draw("graph TD\n  A --> B\n  A --> C\n  B --> D\n  C --> D\n  D --> A");
+        for name in ["A", "B", "C", "D"] {
+            assert!(out.contains(&format!("│ {} │", name)), "{}", out);
+        }
+        // Fan-out from A joins on one track
+        assert!(out.contains('┬'), "{}", out);
+        assert!(out.matches('▼').count() >= 3, "{}", out);
+        assert!(out.contains('▲'), "{}", out);
+    }
+
+    #[test]
+    fn test_subgraph_frame_has_title() {
+        let out = draw("graph TD\n  subgraph S [Stage]\n    A --> B\n  end\n  B --> C");
+        assert!(out.contains("┌─ Stage ─"), "{}", out);
+        assert_eq!(out.lines().filter(|l| l.contains("│ C │")).count(), 1);
+    }
+
+    #[test]
+    fn test_edges_at_subgraph_ids() {
+        let source =
+            "graph TD\n  A --> one\n  subgraph one [Group One]\n    a1 --> a2\n  end\n  one --> B";
+        let chart = parse(source).unwrap();
+        assert!(chart.find("one").is_none());
+        assert_eq!(chart.nodes.len(), 4);
+        assert_eq!(chart.edges[0].to, End::Frame(0));
+        assert_eq!(chart.edges[2].from, End::Frame(0));
+
+        let out = draw(source);
+        assert!(!out.contains("one │"), "{}", out);
+        let lines: Vec<&str> = out.lines().collect();
+        // The edges stop at the frame's borders, above a1 and below a2
+        let top = lines.iter().position(|l| l.contains("Group One")).unwrap();
+        assert!(lines[top].contains('▼'), "{}", out);
+        let bottom = lines.iter().rposition(|l| l.starts_with('└')).unwrap();
+        assert!(lines[bottom].contains('┬'), "{}", out);
+        assert!(lines[bottom + 4].contains("│ B │"), "{}", out);
+    }
+
+    #[test]
+    fn test_subgraph_title_clear_of_edges() {
+        assert_eq!(
+            draw("graph TD; A --> X; subgraph one [Group One]; X --> Y; end"),
+            "            ┌───┐\n            \
+             │ A │\n            \
+             └───┘\n              \
+             │\n\
+             ┌─ Group One ─┼───┐\n\
+             │             ▼   │\n\
+             │           ┌───┐ │\n\
+             │           │ X │ │\n\
+             │           └───┘ │\n\
+             │             │   │\n\
+             │             │   │\n\
+             │             ▼   │\n\
+             │           ┌───┐ │\n\
+             │           │ Y │ │\n\
+             │           └───┘ │\n\
+             └─────────────────┘"
+        );
+    }
+
+    #[test]
+    fn test_ascii_and_max_width() {
+        let out = render(
+            "graph LR\n  A[A long first step] --> B[Another long step]",
+            30,
+            false,
+        )
+        .unwrap();
+        assert!(out.lines().all(|l| text_width(l) <= 30), "{}", out);
+        assert!(out.is_ascii(), "{}", out);
+        assert!(out.contains('>'), "{}", out);
+
+        // Rows cut short end in a marker
+        let source = "graph TD\n  A --> B & C & D & E & F & G & H";
+        let out = draw(source);
+        assert!(out.lines().all(|l| !l.ends_with('…')), "{}", out);
+        let out = render(source, 30, true).unwrap();
+        assert!(out.lines().all(|l| text_width(l) <= 30), "{}", out);
+        assert!(out.lines().any(|l| l.ends_with('…')), "{}", out);
+    }
+
+    #[test]
+    fn test_wide_left_right_graph_turns_top_down() {
+        let source = "graph LR\n  A[web client] --> B[api gateway] --> C[auth service] \
+                      --> D[user service] --> E[order service] --> F[billing service]";
+        let out = draw(source);
+        assert_eq!(out.lines().count(), 3, "{}", out);
+
+        let out = render(source, 50, true).unwrap();
+        assert!(out.lines().all(|l| text_width(l) <= 50), "{}", out);
+        assert!(!out.contains('…'), "{}", out);
+        for label in ["web client", "api gateway", "billing service"] {
+            assert!(out.contains(label), "{}", out);
+        }
+        // Words are only broken to fit; when nothing fits they stay whole
+        let source = "graph LR\n  A[unbreakable_identifier] --> B";
+        let out = render(source, 12, true).unwrap();
+        assert!(out.contains("│ kable_ │"), "{}", out);
+        let out = render(source, 8, true).unwrap();
+        assert!(out.contains("│ unbre…"), "{}", out);
+        assert!(!out.contains("kable"), "{}", out);
+    }
+}
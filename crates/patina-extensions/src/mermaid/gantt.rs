@@ -0,0 +1,373 @@
+//! Gantt charts (`gantt`), drawn as one bar per task on a shared time axis.
+
+use super::pie::truncate;
+use super::{clean_label, content_lines, text_width, MermaidError, SourceLine};
+
+/// Narrowest time axis, in columns
+const MIN_CHART: usize = 10;
+
+/// How a task is drawn
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Status {
+    Normal,
+    Active,
+    Done,
+    Critical,
+}
+
+#[derive(Debug)]
+struct Task {
+    name: String,
+    id: Option<String>,
+    status: Status,
+    milestone: bool,
+    /// Days since 1970-01-01
+    start: f64,
+    end: f64,
+}
+
+#[derive(Debug)]
+enum Row {
+    Section(String),
+    Task(Task),
+}
+
+/// A parsed Gantt chart
+#[derive(Debug, Default)]
+struct Gantt {
+    title: Option<String>,
+    rows: Vec<Row>,
+}
+
+impl Gantt {
+    fn tasks(&self) -> impl Iterator<Item = &Task> {
+        self.rows.iter().filter_map(|row| match row {
+            Row::Task(task) => Some(task),
+            Row::Section(_) => None,
+        })
+    }
+
+    fn task(&self, id: &str) -> Option<&Task> {
+        self.tasks().find(|task| task.id.as_deref() == Some(id))
+    }
+}
+
+/// Render Gantt chart source as text no wider than `max_width`
+pub(crate) fn render(
+    source: &str,
+    max_width: usize,
+    unicode: bool,
+) -> Result<String, MermaidError> {
+    let gantt = parse(source)?;
+    let mut lines = Vec::new();
+    if let Some(title) = &gantt.title {
+        lines.push(title.clone());
+    }
+    let (Some(first), Some(last)) = (
+        gantt.tasks().map(|t| t.start).min_by(f64::total_cmp),
+        gantt.tasks().map(|t| t.end).max_by(f64::total_cmp),
+    ) else {
+        return Ok(lines.join("\n"));
+    };
+
+    let sections = gantt.rows.iter().any(|row| matches!(row, Row::Section(_)));
+    let indent = if sections { 2 } else { 0 };
+    let name_width = (gantt
+        .tasks()
+        .map(|t| text_width(&t.name) + indent)
+        .max()
+        .unwrap_or(0)
+        + 2)
+    .min(max_width / 3);
+    let chart_width = max_width.saturating_sub(name_width).max(MIN_CHART);
+    let span = (last - first).max(1.0);
+    let column = |day: f64| ((day - first) / span * chart_width as f64).round() as usize;
+
+    // Dates at both ends of the axis
+    let (start_date, end_date) = (format_date(first), format_date(last));
+    let mut axis = " ".repeat(name_width) + &start_date;
+    if chart_width > start_date.len() + end_date.len() {
+        axis.push_str(&" ".repeat(chart_width - start_date.len() - end_date.len()));
+        axis.push_str(&end_date);
+    }
+    lines.push(axis);
+
+    for row in &gantt.rows {
+        let task = match row {
+            Row::Section(name) => {
+                lines.push(truncate(name, max_width, unicode));
+                continue;
+            }
+            Row::Task(task) => task,
+        };
+        let name = truncate(&task.name, name_width.saturating_sub(indent + 1), unicode);
+        let mut line = " ".repeat(indent) + &name;
+        line.push_str(&" ".repeat(name_width.saturating_sub(indent + text_width(&name))));
+
+        let start = column(task.start).min(chart_width - 1);
+        line.push_str(&" ".repeat(start));
+        if task.milestone {
+            line.push(if unicode { '◆' } else { '*' });
+        } else {
+            let length = column(task.end).saturating_sub(start).max(1);
+            let (full, ascii) = match task.status {
+                Status::Normal => ('█', '#'),
+                Status::Active => ('▒', '+'),
+                Status::Done => ('░', '='),
+                Status::Critical => ('▓', '!'),
+            };
+            let glyph = if unicode { full } else { ascii };
+            line.extend(std::iter::repeat_n(glyph, length.min(chart_width - start)));
+        }
+        lines.push(line);
+    }
+    Ok(lines.join("\n"))
+}
+
+/// Parse Gantt chart source
+fn parse(source: &str) -> Result<Gantt, MermaidError> {
+    let mut lines = content_lines(source).into_iter();
+    if lines.next().is_none() {
+        return Err(MermaidError::parse(1, 1, "empty diagram"));
+    }
+
+    let mut gantt = Gantt::default();
+    for line in lines {
+        let text = line.text;
+        let (keyword, rest) = match text.split_once(char::is_whitespace) {
+            Some((keyword, rest)) => (keyword, rest.trim()),
+            None => (text, ""),
+        };
+        match keyword {
+            "title" => gantt.title = Some(clean_label(rest)),
+            "section" => gantt.rows.push(Row::Section(clean_label(rest))),
+            // Formatting, calendars and interaction don't change the text layout
+            "dateFormat" | "axisFormat" | "tickInterval" | "todayMarker" | "excludes"
+            | "includes" | "weekday" | "weekend" | "inclusiveEndDates" | "topAxis"
+            | "displayMode" | "click" => {}
+            _ if keyword.starts_with("accTitle") || keyword.starts_with("accDescr") => {}
+            _ => {
+                let task = task(&gantt, &line)?;
+                gantt.rows.push(Row::Task(task));
+            }
+        }
+    }
+    Ok(gantt)
+}
+
+/// `Name :tags, id, start, end`
+fn task(gantt: &Gantt, line: &SourceLine) -> Result<Task, MermaidError> {
+    let text = line.text;
+    let Some((name, spec)) = text.split_once(':') else {
+        return Err(line.error(0, "expected 'Task name : start, end'"));
+    };
+    let spec_column = name.chars().count() + 1;
+
+    // Fields with their columns, for error positions
+    let mut fields = Vec::new();
+    let mut offset = spec_column;
+    for field in spec.split(',') {
+        let lead = field.chars().count() - field.trim_start().chars().count();
+        fields.push((field.trim(), offset + lead));
+        offset += field.chars().count() + 1;
+    }
+
+    let mut status = Status::Normal;
+    let mut milestone = false;
+    while let Some(&(tag, _)) = fields.first() {
+        match tag {
+            "done" if status == Status::Normal => status = Status::Done,
+            "active" if status != Status::Critical => status = Status::Active,
+            "crit" => status = Status::Critical,
+            "done" | "active" => {}
+            "milestone" => milestone = true,
+            _ => break,
+        }
+        fields.remove(0);
+    }
+
+    let previous_end = gantt.tasks().last().map(|t| t.end);
+    let (id, start, end) = match fields[..] {
+        [end] => {
+            let Some(start) = previous_end else {
+                return Err(line.error(end.1, "the first task needs a start date"));
+            };
+            (None, start, end)
+        }
+        [start, end] => (None, start_time(gantt, line, start)?, end),
+        [id, start, end] => (Some(id.0.to_string()), start_time(gantt, line, start)?, end),
+        _ => return Err(line.error(spec_column, "expected 'start, end' or a duration")),
+    };
+    let end = end_time(gantt, line, start, end)?;
+
+    Ok(Task {
+        name: clean_label(name),
+        id,
+        status,
+        milestone,
+        start,
+        end: end.max(start),
+    })
+}
+
+/// A start date, or `after id...` for the latest end of those tasks
+fn start_time(
+    gantt: &Gantt,
+    line: &SourceLine,
+    (text, column): (&str, usize),
+) -> Result<f64, MermaidError> {
+    if let Some(ids) = text.strip_prefix("after ") {
+        let mut latest: Option<f64> = None;
+        for id in ids.split_whitespace() {
+            let task = gantt
+                .task(id)
+                .ok_or_else(|| line.error(column, format!("unknown task '{}'", id)))?;
+            latest = Some(latest.map_or(task.end, |l| l.max(task.end)));
+        }
+        return latest.ok_or_else(|| line.error(column, "expected a task id after 'after'"));
+    }
+    parse_date(text)
+        .ok_or_else(|| line.error(column, format!("'{}' is not a date like 2024-01-31", text)))
+}
+
+/// An end date, a duration from `start`, or `until id` for the start of that task
+fn end_time(
+    gantt: &Gantt,
+    line: &SourceLine,
+    start: f64,
+    (text, column): (&str, usize),
+) -> Result<f64, MermaidError> {
+    if let Some(id) = text.strip_prefix("until ") {
+        let task = gantt
+            .task(id.trim())
+            .ok_or_else(|| line.error(column, format!("unknown task '{}'", id.trim())))?;
+        return Ok(task.start);
+    }
+    if let Some(days) = parse_duration(text) {
+        return Ok(start + days);
+    }
+    parse_date(text).ok_or_else(|| {
+        line.error(
+            column,
+            format!("'{}' is not a date or a duration like 3d", text),
+        )
+    })
+}
+
+/// Duration such as `3d`, `1w` or `12h`, in days
+fn parse_duration(text: &str) -> Option<f64> {
+    let split = text.find(|c: char| !c.is_ascii_digit() && c != '.')?;
+    let (number, unit) = text.split_at(split);
+    let number: f64 = number.parse().ok()?;
+    let days = match unit {
+        "ms" => 1.0 / 86_400_000.0,
+        "s" => 1.0 / 86_400.0,
+        "m" => 1.0 / 1_440.0,
+        "h" => 1.0 / 24.0,
+        "d" => 1.0,
+        "w" => 7.0,
+        _ => return None,
+    };
+    Some(number * days)
+}
+
+/// `YYYY-MM-DD` with an optional ` HH:mm`, in days since 1970-01-01
+fn parse_date(text: &str) -> Option<f64> {
+    let (date, time) = match text.split_once([' ', 'T']) {
+        Some((date, time)) => (date, Some(time)),
+        None => (text, None),
+    };
+    let mut parts = date.split('-').map(|p| p.parse::<i64>().ok());
+    let (year, month, day) = (parts.next()??, parts.next()??, parts.next()??);
+    if parts.next().is_some() || !(1..=12).contains(&month) || !(1..=31).contains(&day) {
+        return None;
+    }
+    let mut days = days_from_civil(year, month, day) as f64;
+    if let Some(time) = time {
+        let mut parts = time.split(':').map(|p| p.parse::<f64>().ok());
+        let (hours, minutes) = (parts.next()??, parts.next().flatten().unwrap_or(0.0));
+        days += (hours + minutes / 60.0) / 24.0;
+    }
+    Some(days)
+}
+
+/// Days since 1970-01-01 of a proleptic Gregorian date
+fn days_from_civil(year: i64, month: i64, day: i64) -> i64 {
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let year_of_era = year - era * 400;
+    let day_of_year = (153 * ((month + 9) % 12) + 2) / 5 + day - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    era * 146_097 + day_of_era - 719_468
+}
+
+/// `YYYY-MM-DD` for days since 1970-01-01
+fn format_date(days: f64) -> String {
+    let days = days.floor() as i64 + 719_468;
+    let era = days.div_euclid(146_097);
+    let day_of_era = days - era * 146_097;
+    let year_of_era =
+        (day_of_era - day_of_era / 1_460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let month_index = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * month_index + 2) / 5 + 1;
+    let month = if month_index < 10 {
+        month_index + 3
+    } else {
+        month_index - 9
+    };
+    let year = year_of_era + era * 400 + i64::from(month <= 2);
+    format!("{:04}-{:02}-{:02}", year, month, day)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_dates() {
+        assert_eq!(days_from_civil(1970, 1, 1), 0);
+        assert_eq!(
+            days_from_civil(2024, 3, 1) - days_from_civil(2024, 2, 28),
+            2
+        );
+        assert_eq!(format_date(parse_date("2024-02-29").unwrap()), "2024-02-29");
+        assert_eq!(parse_date("2024-01-01 12:00").unwrap().fract(), 0.5);
+        assert_eq!(parse_duration("2w"), Some(14.0));
+        assert_eq!(parse_date("01/02/2024"), None);
+    }
+
+    #[test]
+    fn test_tasks_and_sections() {
+        let out = render(
+            "gantt\n  title Plan\n  dateFormat YYYY-MM-DD\n  section Build\n    Design :done, des, 2024-01-01, 10d\n    Code   :active, code, after des, 10d\n  section Ship\n    Release :milestone, after code, 0d",
+            50,
+            true,
+        )
+        .unwrap();
+        let lines: Vec<&str> = out.lines().collect();
+        assert_eq!(lines[0], "Plan");
+        assert!(lines[1].trim_start().starts_with("2024-01-01"));
+        assert!(lines[1].ends_with("2024-01-21"));
+        assert_eq!(lines[2], "Build");
+        assert!(lines[3].starts_with("  Design"));
+        // Code starts where Design ends
+        let design_end = lines[3].rfind('░').unwrap() + '░'.len_utf8();
+        let code_start = lines[4].find('▒').unwrap();
+        assert_eq!(
+            lines[3][..design_end].chars().count(),
+            lines[4][..code_start].chars().count()
+        );
+        assert!(lines[6].ends_with('◆'));
+        assert!(out.lines().all(|l| text_width(l) <= 50), "{}", out);
+    }
+
+    #[test]
+    fn test_errors() {
+        let err = parse("gantt\n  A :a1, 2024-13-01, 3d").unwrap_err();
+        assert_eq!(err.position(), Some((2, 10)));
+
+        let err = parse("gantt\n  A :after zz, 3d").unwrap_err();
+        assert_eq!(err.position(), Some((2, 6)));
+    }
+}
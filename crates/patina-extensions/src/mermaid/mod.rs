@@ -0,0 +1,338 @@
+//! Mermaid diagram rendering to ASCII art.
+//!
+//! Supports flowcharts, sequence, state, class, ER and Gantt diagrams and pie
+//! charts, rendered as Unicode box-drawing characters.
+
+mod canvas;
+mod class;
+mod er;
+mod flowchart;
+mod gantt;
+mod pie;
+mod sequence;
+mod state;
+
+use unicode_width::UnicodeWidthStr;
+
+/// Label widths tried, narrowest last, until a diagram fits `max_width`
+const WRAP_WIDTHS: [Option<usize>; 5] = [None, Some(24), Some(16), Some(10), Some(6)];
+
+/// Mermaid diagram renderer
+pub struct MermaidRenderer {
+    /// Maximum width for output
+    max_width: usize,
+    /// Use Unicode box-drawing characters
+    use_unicode: bool,
+}
+
+impl MermaidRenderer {
+    /// Create a new renderer
+    pub fn new() -> Self {
+        Self {
+            max_width: 80,
+            use_unicode: true,
+        }
+    }
+
+    /// Set maximum output width
+    pub fn max_width(mut self, width: usize) -> Self {
+        self.max_width = width;
+        self
+    }
+
+    /// Use ASCII instead of Unicode
+    pub fn ascii_mode(mut self) -> Self {
+        self.use_unicode = false;
+        self
+    }
+
+    /// Render a Mermaid diagram to text
+    pub fn render(&self, mermaid: &str) -> Result<String, MermaidError> {
+        let diagram_type = self.detect_type(mermaid)?;
+
+        match diagram_type {
+            DiagramType::Flowchart => self.render_flowchart(mermaid),
+            DiagramType::Sequence => self.render_sequence(mermaid),
+            DiagramType::State => self.render_state(mermaid),
+            DiagramType::Class => self.render_class(mermaid),
+            DiagramType::Er => self.render_er(mermaid),
+            DiagramType::Gantt => self.render_gantt(mermaid),
+            DiagramType::Pie => self.render_pie(mermaid),
+            DiagramType::Unknown => Err(MermaidError::UnsupportedDiagram),
+        }
+    }
+
+    fn detect_type(&self, mermaid: &str) -> Result<DiagramType, MermaidError> {
+        let first_line = content_lines(mermaid)
+            .first()
+            .map(|line| line.text.to_lowercase())
+            .unwrap_or_default();
+
+        Ok(
+            if first_line.starts_with("graph") || first_line.starts_with("flowchart") {
+                DiagramType::Flowchart
+            } else if first_line.starts_with("sequencediagram") {
+                DiagramType::Sequence
+            } else if first_line.starts_with("statediagram") {
+                DiagramType::State
+            } else if first_line.starts_with("classdiagram") {
+                DiagramType::Class
+            } else if first_line.starts_with("erdiagram") {
+                DiagramType::Er
+            } else if first_line.starts_with("gantt") {
+                DiagramType::Gantt
+            } else if first_line.starts_with("pie") {
+                DiagramType::Pie
+            } else {
+                DiagramType::Unknown
+            },
+        )
+    }
+
+    fn render_flowchart(&self, mermaid: &str) -> Result<String, MermaidError> {
+        flowchart::render(mermaid, self.max_width, self.use_unicode)
+    }
+
+    fn render_sequence(&self, mermaid: &str) -> Result<String, MermaidError> {
+        sequence::render(mermaid, self.max_width, self.use_unicode)
+    }
+
+    fn render_state(&self, mermaid: &str) -> Result<String, MermaidError> {
+        state::render(mermaid, self.max_width, self.use_unicode)
+    }
+
+    fn render_class(&self, mermaid: &str) -> Result<String, MermaidError> {
+        class::render(mermaid, self.max_width, self.use_unicode)
+    }
+
+    fn render_er(&self, mermaid: &str) -> Result<String, MermaidError> {
+        er::render(mermaid, self.max_width, self.use_unicode)
+    }
+
+    fn render_gantt(&self, mermaid: &str) -> Result<String, MermaidError> {
+        gantt::render(mermaid, self.max_width, self.use_unicode)
+    }
+
+    fn render_pie(&self, mermaid: &str) -> Result<String, MermaidError> {
+        pie::render(mermaid, self.max_width, self.use_unicode)
+    }
+}
+
+impl Default for MermaidRenderer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Diagram types
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum DiagramType {
+    Flowchart,
+    Sequence,
+    State,
+    Class,
+    Er,
+    Gantt,
+    Pie,
+    Unknown,
+}
+
+/// Mermaid rendering errors
+#[derive(Debug, thiserror::Error)]
+pub enum MermaidError {
+    #[error("Line {line}, column {column}: {message}")]
+    ParseError {
+        /// 1-based line within the diagram source
+        line: usize,
+        /// 1-based column, in characters
+        column: usize,
+        message: String,
+    },
+
+    #[error("Unsupported diagram type")]
+    UnsupportedDiagram,
+}
+
+impl MermaidError {
+    pub(crate) fn parse(line: usize, column: usize, message: impl Into<String>) -> Self {
+        Self::ParseError {
+            line,
+            column,
+            message: message.into(),
+        }
+    }
+
+    /// Line and column of a parse error
+    pub fn position(&self) -> Option<(usize, usize)> {
+        match self {
+            Self::ParseError { line, column, .. } => Some((*line, *column)),
+            Self::UnsupportedDiagram => None,
+        }
+    }
+}
+
+/// A trimmed line (or `;`-separated statement) of diagram source
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct SourceLine<'a> {
+    /// 1-based line number
+    pub number: usize,
+    /// 1-based column of the first character of `text`
+    pub column: usize,
+    pub text: &'a str,
+}
+
+impl<'a> SourceLine<'a> {
+    /// Parse error at character offset `pos` within the text
+    pub fn error(&self, pos: usize, message: impl Into<String>) -> MermaidError {
+        MermaidError::parse(self.number, self.column + pos, message)
+    }
+
+    /// Statements separated by `;` outside of quotes, trimmed and non-empty
+    pub fn statements(&self) -> Vec<SourceLine<'a>> {
+        let mut statements = Vec::new();
+        let mut quoted = false;
+        let mut start = 0;
+        let text = self.text;
+        for (i, c) in text
+            .char_indices()
+            .chain(std::iter::once((text.len(), ';')))
+        {
+            match c {
+                '"' => quoted = !quoted,
+                ';' if !quoted => {
+                    let part = &text[start..i];
+                    let trimmed = part.trim();
+                    if !trimmed.is_empty() {
+                        let lead = part.len() - part.trim_start().len();
+                        statements.push(SourceLine {
+                            number: self.number,
+                            column: self.column + text[..start + lead].chars().count(),
+                            text: trimmed,
+                        });
+                    }
+                    start = i + 1;
+                }
+                _ => {}
+            }
+        }
+        statements
+    }
+}
+
+/// Non-blank lines of diagram source, skipping `%%` comments
+pub(crate) fn content_lines(source: &str) -> Vec<SourceLine<'_>> {
+    source
+        .lines()
+        .enumerate()
+        .filter_map(|(idx, line)| {
+            let text = line.trim();
+            if text.is_empty() || text.starts_with("%%") {
+                return None;
+            }
+            let lead = line.len() - line.trim_start().len();
+            Some(SourceLine {
+                number: idx + 1,
+                column: line[..lead].chars().count() + 1,
+                text,
+            })
+        })
+        .collect()
+}
+
+/// Label text without quotes or backticks, with `<br>` as line breaks
+pub(crate) fn clean_label(text: &str) -> String {
+    let text = text.trim();
+    let text = text
+        .strip_prefix('"')
+        .and_then(|t| t.strip_suffix('"'))
+        .unwrap_or(text);
+    let text = text
+        .strip_prefix('`')
+        .and_then(|t| t.strip_suffix('`'))
+        .unwrap_or(text);
+    ["<br />", "<br/>", "<br>"]
+        .iter()
+        .fold(text.to_string(), |acc, br| acc.replace(br, "\n"))
+        .lines()
+        .map(str::trim)
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// Display width of text in terminal columns
+pub(crate) fn text_width(text: &str) -> usize {
+    UnicodeWidthStr::width(text)
+}
+
+/// Split text into lines at most `width` columns wide, breaking at spaces
+/// where possible; explicit newlines are kept
+pub(crate) fn wrap_text(text: &str, width: Option<usize>) -> Vec<String> {
+    let mut lines = Vec::new();
+    for part in text.split('\n') {
+        let width = match width {
+            Some(width) if text_width(part) > width => width.max(1),
+            _ => {
+                lines.push(part.to_string());
+                continue;
+            }
+        };
+        let mut line = String::new();
+        for word in part.split_whitespace() {
+            if !line.is_empty() && text_width(&line) + 1 + text_width(word) <= width {
+                line.push(' ');
+                line.push_str(word);
+                continue;
+            }
+            if !line.is_empty() {
+                lines.push(std::mem::take(&mut line));
+            }
+            // Break words that don't fit on a line of their own
+            for c in word.chars() {
+                if text_width(&line) + c.to_string().width() > width && !line.is_empty() {
+                    lines.push(std::mem::take(&mut line));
+                }
+                line.push(c);
+            }
+        }
+        lines.push(line);
+    }
+    lines
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_detect_flowchart() {
+        let renderer = MermaidRenderer::new();
+        let mermaid = "graph TD\n    A --> B";
+        let result = renderer.render(mermaid);
+        assert!(result.is_ok());
+    }
+
+    #[test]
+    fn test_detect_pie() {
+        let renderer = MermaidRenderer::new();
+        let mermaid = "pie\n    title Test\n    \"A\": 50\n    \"B\": 50";
+        let result = renderer.render(mermaid);
+        assert!(result.is_ok());
+    }
+
+    #[test]
+    fn test_wrap_text() {
+        assert_eq!(wrap_text("a b c", None), ["a b c"]);
+        assert_eq!(wrap_text("one two three", Some(7)), ["one two", "three"]);
+        assert_eq!(wrap_text("abcdefgh", Some(3)), ["abc", "def", "gh"]);
+        assert_eq!(clean_label("\"x<br>y\""), "x\ny");
+    }
+
+    #[test]
+    fn test_parse_error_display() {
+        let err = MermaidRenderer::new()
+            .render("%% comment\ngraph TD\n  A --> B[")
+            .unwrap_err();
+        assert_eq!(err.position(), Some((3, 10)));
+        assert!(err.to_string().starts_with("Line 3, column 10: "));
+    }
+}
@@ -0,0 +1,202 @@
+//! Pie charts (`pie`), drawn as proportional horizontal bars.
+
+use super::{clean_label, content_lines, text_width, MermaidError};
+
+/// Bar length limits, in columns
+const MIN_BAR: usize = 10;
+const MAX_BAR: usize = 40;
+/// Narrowest the label column gets before the bar gives up its minimum
+const MIN_LABEL: usize = 4;
+
+/// A parsed pie chart
+#[derive(Debug, Default)]
+struct Pie {
+    title: Option<String>,
+    show_data: bool,
+    slices: Vec<(String, f64)>,
+}
+
+/// Render pie chart source as text no wider than `max_width`
+pub(crate) fn render(
+    source: &str,
+    max_width: usize,
+    unicode: bool,
+) -> Result<String, MermaidError> {
+    let pie = parse(source)?;
+    let total: f64 = pie.slices.iter().map(|(_, value)| value).sum();
+
+    let values: Vec<String> = pie
+        .slices
+        .iter()
+        .map(|(_, value)| format!("  ({})", value))
+        .collect();
+    let mut value_width = if pie.show_data {
+        values.iter().map(|v| v.len()).max().unwrap_or(0)
+    } else {
+        0
+    };
+    // "  100.0%" after the bar
+    let percent_width = 8;
+    let mut label_width = pie
+        .slices
+        .iter()
+        .map(|(label, _)| text_width(label))
+        .max()
+        .unwrap_or(0)
+        .min(max_width / 3);
+    // When short of room, narrow the labels, then drop the values, and only
+    // then shorten the bar below its minimum
+    let short = |label_width: usize, value_width: usize| {
+        (label_width + 1 + MIN_BAR + percent_width + value_width).saturating_sub(max_width)
+    };
+    let min_label = label_width.min(MIN_LABEL);
+    label_width -= short(label_width, value_width).min(label_width - min_label);
+    if short(label_width, value_width) > 0 {
+        value_width = 0;
+    }
+    let bar_width = max_width
+        .saturating_sub(label_width + 1 + percent_width + value_width)
+        .min(MAX_BAR);
+    let (full, empty) = if unicode { ('█', '░') } else { ('#', '.') };
+
+    let mut lines = Vec::new();
+    if let Some(title) = &pie.title {
+        lines.push(truncate(title, max_width, unicode));
+    }
+    for ((label, value), shown) in pie.slices.iter().zip(&values) {
+        let share = if total > 0.0 { value / total } else { 0.0 };
+        let filled = (share * bar_width as f64).round() as usize;
+        let mut line = truncate(label, label_width, unicode);
+        line.push_str(&" ".repeat(label_width - text_width(&line) + 1));
+        line.extend(std::iter::repeat_n(full, filled));
+        line.extend(std::iter::repeat_n(empty, bar_width - filled));
+        line.push_str(&format!("  {:>5.1}%", share * 100.0));
+        if value_width > 0 {
+            line.push_str(shown);
+        }
+        // Even a bare percentage may not fit the narrowest widths
+        lines.push(truncate(&line, max_width, unicode));
+    }
+    Ok(lines.join("\n"))
+}
+
+/// Cut text to `width` columns, marking the cut with an ellipsis
+pub(crate) fn truncate(text: &str, width: usize, unicode: bool) -> String {
+    if text_width(text) <= width {
+        return text.to_string();
+    }
+    let ellipsis = if unicode { "…" } else { "." };
+    let mut out = String::new();
+    for c in text.chars() {
+        if text_width(&out) + text_width(&c.to_string()) + 1 > width {
+            break;
+        }
+        out.push(c);
+    }
+    if width > 0 {
+        out.push_str(ellipsis);
+    }
+    out
+}
+
+/// Parse pie chart source
+fn parse(source: &str) -> Result<Pie, MermaidError> {
+    let mut lines = content_lines(source).into_iter();
+    let header = lines
+        .next()
+        .ok_or_else(|| MermaidError::parse(1, 1, "empty diagram"))?;
+
+    let mut pie = Pie::default();
+    let mut rest = header.text["pie".len().min(header.text.len())..].trim();
+    if let Some(after) = rest.strip_prefix("showData") {
+        pie.show_data = true;
+        rest = after.trim();
+    }
+    if let Some(title) = rest.strip_prefix("title") {
+        pie.title = Some(clean_label(title));
+    }
+
+    for line in lines {
+        let text = line.text;
+        if let Some(title) = text.strip_prefix("title") {
+            pie.title = Some(clean_label(title));
+            continue;
+        }
+        if text == "showData" || text.starts_with("acc") {
+            pie.show_data |= text == "showData";
+            continue;
+        }
+        let Some(colon) = text.rfind(':') else {
+            return Err(line.error(0, "expected '\"label\" : value'"));
+        };
+        let value_text = text[colon + 1..].trim();
+        let value_pos = text.chars().count() - text[colon + 1..].trim_start().chars().count();
+        let value: f64 = value_text
+            .parse()
+            .map_err(|_| line.error(value_pos, format!("'{}' is not a number", value_text)))?;
+        if value < 0.0 || !value.is_finite() {
+            return Err(line.error(value_pos, "slice values must be positive"));
+        }
+        pie.slices.push((clean_label(&text[..colon]), value));
+    }
+    Ok(pie)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_bars_are_proportional() {
+        let out = render(
+            "pie title Pets\n  \"Dogs\" : 75\n  \"Cats\" : 25\n",
+            60,
+            true,
+        )
+        .unwrap();
+        let lines: Vec<&str> = out.lines().collect();
+        assert_eq!(lines[0], "Pets");
+        assert!(lines[1].starts_with("Dogs "));
+        assert_eq!(
+            lines[1].matches('█').count(),
+            3 * lines[2].matches('█').count()
+        );
+        assert!(lines[1].ends_with(" 75.0%"));
+        assert!(lines[2].ends_with(" 25.0%"));
+    }
+
+    #[test]
+    fn test_show_data_ascii_and_errors() {
+        let out = render("pie showData\n  \"A\" : 1.5\n  \"B\" : 0.5", 60, false).unwrap();
+        assert!(out.lines().next().unwrap().ends_with("75.0%  (1.5)"));
+        assert!(out.is_ascii());
+
+        let err = render("pie\n  \"A\" : lots", 60, true).unwrap_err();
+        assert_eq!(err.position(), Some((2, 9)));
+    }
+
+    #[test]
+    fn test_narrow_widths_fit() {
+        let source =
+            "pie showData title Household pets\n  \"Dogs and puppies\" : 386\n  \"Cats\" : 85.5";
+        for max_width in [6, 12, 15, 20, 25, 30, 40] {
+            for source in [source, &source.replacen(" showData", "", 1)] {
+                let out = render(source, max_width, true).unwrap();
+                assert!(
+                    out.lines().all(|l| text_width(l) <= max_width),
+                    "{}:\n{}",
+                    max_width,
+                    out
+                );
+            }
+        }
+
+        // Labels narrow and values go before the bar drops below its minimum
+        let out = render(source, 30, true).unwrap();
+        let dogs = out.lines().nth(1).unwrap();
+        assert!(dogs.starts_with("Dog… "), "{}", out);
+        assert!(dogs.ends_with("81.9%"), "{}", out);
+        let bar = dogs.chars().filter(|&c| c == '█' || c == '░').count();
+        assert!(bar >= MIN_BAR, "{}", out);
+    }
+}
@@ -0,0 +1,917 @@
+//! Sequence diagrams (`sequenceDiagram`).
+//!
+//! Participants are laid out left to right with vertical lifelines, and every
+//! message, note and block border takes its own rows below them. Gaps between
+//! lifelines grow until every label fits between the lifelines it spans.
+
+use super::canvas::{Canvas, Stroke};
+use super::{clean_label, content_lines, text_width, wrap_text, MermaidError, WRAP_WIDTHS};
+
+/// Arrow tokens, longest first at any given position
+const ARROWS: &[(&str, Stroke, Head, bool)] = &[
+    ("<<-->>", Stroke::Dotted, Head::Arrow, true),
+    ("<<->>", Stroke::Solid, Head::Arrow, true),
+    ("-->>", Stroke::Dotted, Head::Arrow, false),
+    ("->>", Stroke::Solid, Head::Arrow, false),
+    ("--x", Stroke::Dotted, Head::Cross, false),
+    ("-x", Stroke::Solid, Head::Cross, false),
+    ("--)", Stroke::Dotted, Head::Open, false),
+    ("-)", Stroke::Solid, Head::Open, false),
+    ("-->", Stroke::Dotted, Head::None, false),
+    ("->", Stroke::Solid, Head::None, false),
+];
+
+/// Blocks and the keyword that divides each into sections
+const BLOCKS: &[(&str, Option<&str>)] = &[
+    ("loop", None),
+    ("alt", Some("else")),
+    ("opt", None),
+    ("par", Some("and")),
+    ("critical", Some("option")),
+    ("break", None),
+];
+
+/// Marker at the end of a message
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Head {
+    None,
+    Arrow,
+    Cross,
+    /// Async message, drawn as an open arrow
+    Open,
+}
+
+#[derive(Debug, Clone)]
+struct Participant {
+    id: String,
+    label: String,
+    actor: bool,
+}
+
+/// Where a note is attached
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Placement {
+    LeftOf(usize),
+    RightOf(usize),
+    /// Over one participant or spanning from the first to the second
+    Over(usize, usize),
+}
+
+#[derive(Debug, Clone)]
+enum Event {
+    Message {
+        from: usize,
+        to: usize,
+        stroke: Stroke,
+        head: Head,
+        /// Arrowhead at the sender too (`<<->>`)
+        both: bool,
+        label: String,
+        /// Activate the receiver (`+`) or deactivate the sender (`-`)
+        activate: bool,
+        deactivate: bool,
+    },
+    Note {
+        placement: Placement,
+        label: String,
+    },
+    Activate(usize),
+    Deactivate(usize),
+    /// Start of a `loop`, `alt`, ... block
+    Start {
+        kind: String,
+        label: String,
+    },
+    /// `else`, `and` or `option` within a block
+    Section {
+        label: String,
+    },
+    End,
+}
+
+/// A parsed sequence diagram
+#[derive(Debug, Default)]
+struct Sequence {
+    participants: Vec<Participant>,
+    events: Vec<Event>,
+    autonumber: bool,
+}
+
+impl Sequence {
+    /// Index of participant `id`, adding it if new
+    fn participant(&mut self, id: &str) -> usize {
+        match self.participants.iter().position(|p| p.id == id) {
+            Some(idx) => idx,
+            None => {
+                self.participants.push(Participant {
+                    id: id.to_string(),
+                    label: id.to_string(),
+                    actor: false,
+                });
+                self.participants.len() - 1
+            }
+        }
+    }
+}
+
+/// Render sequence diagram source as text no wider than `max_width`
+pub(crate) fn render(
+    source: &str,
+    max_width: usize,
+    unicode: bool,
+) -> Result<String, MermaidError> {
+    let sequence = parse(source)?;
+    if sequence.participants.is_empty() {
+        return Ok(String::new());
+    }
+
+    let mut canvas = None;
+    for wrap in WRAP_WIDTHS {
+        let drawn = draw(&sequence, wrap, unicode);
+        let fits = drawn.width() <= max_width;
+        canvas = Some(drawn);
+        if fits {
+            break;
+        }
+    }
+    Ok(canvas.map(|c| c.render(max_width)).unwrap_or_default())
+}
+
+/// Open block while parsing
+struct OpenBlock {
+    line: usize,
+    /// Keyword allowed to start a new section, if any
+    section: Option<&'static str>,
+    /// Drawn blocks, as opposed to `box` and `rect` which only group
+    drawn: bool,
+}
+
+/// Parse sequence diagram source
+fn parse(source: &str) -> Result<Sequence, MermaidError> {
+    let mut lines = content_lines(source).into_iter();
+    if lines.next().is_none() {
+        return Err(MermaidError::parse(1, 1, "empty diagram"));
+    }
+
+    let mut sequence = Sequence::default();
+    let mut open: Vec<OpenBlock> = Vec::new();
+    let mut active: Vec<usize> = Vec::new();
+
+    for line in lines {
+        let text = line.text;
+        let (keyword, rest) = match text.split_once(char::is_whitespace) {
+            Some((keyword, rest)) => (keyword, rest.trim()),
+            None => (text, ""),
+        };
+        let rest_pos = text.chars().count() - rest.chars().count();
+
+        match keyword {
+            "participant" | "actor" => {
+                declare(&mut sequence, rest, keyword == "actor");
+            }
+            "create" => {
+                let (kind, name) = rest.split_once(char::is_whitespace).unwrap_or((rest, ""));
+                if !matches!(kind, "participant" | "actor") {
+                    return Err(line.error(rest_pos, "expected 'participant' or 'actor'"));
+                }
+                declare(&mut sequence, name.trim(), kind == "actor");
+            }
+            "autonumber" => sequence.autonumber = rest != "off",
+            "activate" | "deactivate" => {
+                if rest.is_empty() {
+                    return Err(line.error(rest_pos, "expected a participant"));
+                }
+                let idx = sequence.participant(rest);
+                if keyword == "activate" {
+                    active.push(idx);
+                    sequence.events.push(Event::Activate(idx));
+                } else {
+                    deactivate(&mut active, idx)
+                        .ok_or_else(|| line.error(rest_pos, format!("'{}' is not active", rest)))?;
+                    sequence.events.push(Event::Deactivate(idx));
+                }
+            }
+            "note" | "Note" => {
+                let event = note(&mut sequence, rest).ok_or_else(|| {
+                    line.error(rest_pos, "expected 'left of', 'right of' or 'over'")
+                })?;
+                sequence.events.push(event);
+            }
+            "box" | "rect" => open.push(OpenBlock {
+                line: line.number,
+                section: None,
+                drawn: false,
+            }),
+            "end" => match open.pop() {
+                Some(block) if block.drawn => sequence.events.push(Event::End),
+                Some(_) => {}
+                None => return Err(line.error(0, "'end' without a matching block")),
+            },
+            // Titles, links and accessibility text don't appear in text output
+            "title" | "accTitle" | "accDescr" | "link" | "links" | "properties" | "details"
+            | "destroy" => {}
+            _ if keyword.starts_with("title:") || keyword.starts_with("acc") => {}
+            _ => {
+                if let Some(&(kind, section)) = BLOCKS.iter().find(|(kind, _)| *kind == keyword) {
+                    open.push(OpenBlock {
+                        line: line.number,
+                        section,
+                        drawn: true,
+                    });
+                    sequence.events.push(Event::Start {
+                        kind: kind.to_string(),
+                        label: clean_label(rest),
+                    });
+                } else if BLOCKS.iter().any(|(_, section)| *section == Some(keyword)) {
+                    if open.last().and_then(|b| b.section) != Some(keyword) {
+                        return Err(line.error(0, format!("'{}' outside of its block", keyword)));
+                    }
+                    sequence.events.push(Event::Section {
+                        label: clean_label(rest),
+                    });
+                } else {
+                    let event = message(&mut sequence, text, &mut active)
+                        .map_err(|(pos, message)| line.error(pos, message))?;
+                    sequence.events.push(event);
+                }
+            }
+        }
+    }
+
+    if let Some(block) = open.last() {
+        return Err(MermaidError::parse(
+            block.line,
+            1,
+            "block is never closed with 'end'",
+        ));
+    }
+    Ok(sequence)
+}
+
+/// Declare a participant: `Alice`, or `A as Alice`
+fn declare(sequence: &mut Sequence, declaration: &str, actor: bool) {
+    let (id, label) = match declaration.split_once(" as ") {
+        Some((id, label)) => (id.trim(), clean_label(label)),
+        None => (declaration, clean_label(declaration)),
+    };
+    let idx = sequence.participant(id);
+    let participant = &mut sequence.participants[idx];
+    participant.label = label;
+    participant.actor = actor;
+}
+
+/// Remove one activation of `idx`, or `None` if it isn't active
+fn deactivate(active: &mut Vec<usize>, idx: usize) -> Option<()> {
+    let pos = active.iter().rposition(|&a| a == idx)?;
+    active.remove(pos);
+    Some(())
+}
+
+/// Parse the rest of a `Note` statement
+fn note(sequence: &mut Sequence, rest: &str) -> Option<Event> {
+    let (target, label) = rest.split_once(':').unwrap_or((rest, ""));
+    let mut words = target.split_whitespace();
+    let placement = match (words.next()?, words.clone().next()) {
+        ("left", Some("of")) | ("right", Some("of")) => {
+            let side = target.split_whitespace().next()?;
+            let name = target.trim().splitn(3, char::is_whitespace).nth(2)?.trim();
+            let idx = sequence.participant(name);
+            if side == "left" {
+                Placement::LeftOf(idx)
+            } else {
+                Placement::RightOf(idx)
+            }
+        }
+        ("over", _) => {
+            let names = target.trim()["over".len()..].trim();
+            let (first, last) = names.split_once(',').unwrap_or((names, names));
+            let (first, last) = (
+                sequence.participant(first.trim()),
+                sequence.participant(last.trim()),
+            );
+            Placement::Over(first.min(last), first.max(last))
+        }
+        _ => return None,
+    };
+    Some(Event::Note {
+        placement,
+        label: clean_label(label),
+    })
+}
+
+/// Parse a message such as `Alice->>+Bob: Hello`, returning an error position and text
+fn message(
+    sequence: &mut Sequence,
+    text: &str,
+    active: &mut Vec<usize>,
+) -> Result<Event, (usize, String)> {
+    let (start, token, stroke, head, both) = text
+        .char_indices()
+        .find_map(|(i, _)| {
+            ARROWS
+                .iter()
+                .find(|(token, ..)| text[i..].starts_with(token))
+                .map(|&(token, stroke, head, both)| (i, token, stroke, head, both))
+        })
+        .ok_or_else(|| (0, format!("unrecognized statement '{}'", text)))?;
+    let column = |byte: usize| text[..byte].chars().count();
+
+    let from = text[..start].trim();
+    if from.is_empty() {
+        return Err((0, "message has no sender".to_string()));
+    }
+    let after = start + token.len();
+    let (target, label) = match text[after..].split_once(':') {
+        Some((target, label)) => (target, clean_label(label)),
+        None => (&text[after..], String::new()),
+    };
+    let (activate, deactivate_sender, to) = match target.trim_start().chars().next() {
+        Some('+') => (true, false, target.trim_start()[1..].trim()),
+        Some('-') => (false, true, target.trim_start()[1..].trim()),
+        _ => (false, false, target.trim()),
+    };
+    if to.is_empty() {
+        return Err((column(after), "message has no receiver".to_string()));
+    }
+
+    let from = sequence.participant(from);
+    let to = sequence.participant(to);
+    if activate {
+        active.push(to);
+    }
+    if deactivate_sender && deactivate(active, from).is_none() {
+        let name = sequence.participants[from].id.clone();
+        return Err((column(after), format!("'{}' is not active", name)));
+    }
+    Ok(Event::Message {
+        from,
+        to,
+        stroke,
+        head,
+        both,
+        label,
+        activate,
+        deactivate: deactivate_sender,
+    })
+}
+
+/// Something to draw, with columns that may be negative until placed
+enum Item {
+    Frame {
+        top: usize,
+        bottom: usize,
+        depth: usize,
+        title: String,
+        sections: Vec<(usize, String)>,
+    },
+    Line {
+        points: Vec<(isize, usize)>,
+        stroke: Stroke,
+    },
+    Box {
+        x: isize,
+        y: usize,
+        width: usize,
+        height: usize,
+        rounded: bool,
+        lines: Vec<String>,
+    },
+    Text {
+        x: isize,
+        y: usize,
+        text: String,
+    },
+    Mark {
+        x: isize,
+        y: usize,
+        unicode: char,
+        ascii: char,
+    },
+}
+
+/// Width of the widest line
+fn widest(lines: &[String]) -> usize {
+    lines.iter().map(|l| text_width(l)).max().unwrap_or(0)
+}
+
+/// Glyphs for a message head pointing right or left
+fn head_glyph(head: Head, right: bool) -> Option<(char, char)> {
+    match (head, right) {
+        (Head::None, _) => None,
+        (Head::Arrow, true) => Some(('▶', '>')),
+        (Head::Arrow, false) => Some(('◀', '<')),
+        (Head::Cross, _) => Some(('x', 'x')),
+        (Head::Open, true) => Some(('>', '>')),
+        (Head::Open, false) => Some(('<', '<')),
+    }
+}
+
+/// Lay out the diagram with labels wrapped to `wrap` columns and draw it
+fn draw(sequence: &Sequence, wrap: Option<usize>, unicode: bool) -> Canvas {
+    let count = sequence.participants.len();
+    let names: Vec<Vec<String>> = sequence
+        .participants
+        .iter()
+        .map(|p| wrap_text(&p.label, wrap))
+        .collect();
+    let widths: Vec<usize> = names.iter().map(|lines| widest(lines) + 4).collect();
+    let box_height = names.iter().map(Vec::len).max().unwrap_or(1) + 2;
+
+    // Wrapped labels, numbered if requested
+    let mut number = 0;
+    let labels: Vec<Vec<String>> = sequence
+        .events
+        .iter()
+        .map(|event| match event {
+            Event::Message { label, .. } => {
+                number += 1;
+                let label = if sequence.autonumber {
+                    format!("{}. {}", number, label)
+                } else {
+                    label.clone()
+                };
+                wrap_text(label.trim(), wrap)
+            }
+            Event::Note { label, .. } => wrap_text(label, wrap),
+            _ => Vec::new(),
+        })
+        .collect();
+
+    // Minimum distance between lifeline pairs
+    let mut spans: Vec<(usize, usize, usize)> = (1..count)
+        .map(|i| {
+            let (left, right) = (widths[i - 1], widths[i]);
+            (i - 1, i, left - left / 2 + right / 2 + 2)
+        })
+        .collect();
+    for (event, lines) in sequence.events.iter().zip(&labels) {
+        let width = widest(lines);
+        match *event {
+            Event::Message { from, to, .. } if from != to => {
+                spans.push((from.min(to), from.max(to), width + 4));
+            }
+            Event::Message { from, .. } if from + 1 < count => {
+                spans.push((from, from + 1, width + 8));
+            }
+            Event::Note { placement, .. } => match placement {
+                Placement::RightOf(idx) if idx + 1 < count => {
+                    spans.push((idx, idx + 1, width + 7));
+                }
+                Placement::LeftOf(idx) if idx > 0 => spans.push((idx - 1, idx, width + 7)),
+                Placement::Over(first, last) if first != last => {
+                    spans.push((first, last, width.saturating_sub(4)));
+                }
+                _ => {}
+            },
+            _ => {}
+        }
+    }
+    spans.sort_by_key(|&(first, last, _)| last - first);
+
+    let mut gaps = vec![0usize; count.saturating_sub(1)];
+    for (first, last, need) in spans {
+        let have: usize = gaps[first..last].iter().sum();
+        if have < need {
+            let (n, deficit) = (last - first, need - have);
+            for (k, gap) in gaps[first..last].iter_mut().enumerate() {
+                *gap += deficit / n + usize::from(k < deficit % n);
+            }
+        }
+    }
+    let mut centers = vec![0isize];
+    for gap in &gaps {
+        centers.push(centers[centers.len() - 1] + *gap as isize);
+    }
+
+    let mut items = Vec::new();
+    let mut row = box_height + 1;
+    let mut depth = 0;
+    // Open frames: index into `items`
+    let mut frames: Vec<usize> = Vec::new();
+    // Rows where each participant's current activation started
+    let mut activations: Vec<Vec<usize>> = vec![Vec::new(); count];
+    let mut bars: Vec<(usize, usize, usize)> = Vec::new();
+
+    for (event, lines) in sequence.events.iter().zip(&labels) {
+        match event {
+            Event::Message {
+                from,
+                to,
+                stroke,
+                head,
+                both,
+                activate,
+                deactivate,
+                ..
+            } => {
+                let (from, to) = (*from, *to);
+                let x = centers[from];
+                let label_rows = lines.len();
+                if from == to {
+                    // Loop back to the same lifeline, label alongside
+                    let rows = label_rows.max(2);
+                    let bottom = row + rows - 1;
+                    items.push(Item::Line {
+                        points: vec![(x, row), (x + 4, row), (x + 4, bottom), (x + 1, bottom)],
+                        stroke: *stroke,
+                    });
+                    if let Some((unicode, ascii)) = head_glyph(*head, false) {
+                        items.push(Item::Mark {
+                            x: x + 1,
+                            y: bottom,
+                            unicode,
+                            ascii,
+                        });
+                    }
+                    for (i, line) in lines.iter().enumerate() {
+                        items.push(Item::Text {
+                            x: x + 6,
+                            y: row + i,
+                            text: line.clone(),
+                        });
+                    }
+                    row = bottom;
+                } else {
+                    let target = centers[to];
+                    let (lo, hi) = (x.min(target), x.max(target));
+                    for (i, line) in lines.iter().enumerate() {
+                        let indent = (hi - lo - text_width(line) as isize) / 2;
+                        items.push(Item::Text {
+                            x: lo + indent.max(1),
+                            y: row + i,
+                            text: line.clone(),
+                        });
+                    }
+                    row += label_rows;
+                    let right = target > x;
+                    let step = if right { 1 } else { -1 };
+                    let start = if *both { x + step } else { x };
+                    items.push(Item::Line {
+                        points: vec![(start, row), (target - step, row)],
+                        stroke: *stroke,
+                    });
+                    if let Some((unicode, ascii)) = head_glyph(*head, right) {
+                        items.push(Item::Mark {
+                            x: target - step,
+                            y: row,
+                            unicode,
+                            ascii,
+                        });
+                    }
+                    if *both {
+                        if let Some((unicode, ascii)) = head_glyph(*head, !right) {
+                            items.push(Item::Mark {
+                                x: x + step,
+                                y: row,
+                                unicode,
+                                ascii,
+                            });
+                        }
+                    }
+                }
+                if *activate {
+                    activations[to].push(row);
+                }
+                if *deactivate {
+                    if let Some(start) = activations[from].pop() {
+                        bars.push((from, start, row));
+                    }
+                }
+                row += 1;
+            }
+            Event::Note { placement, .. } => {
+                let width = widest(lines) + 4;
+                let (x, width) = match *placement {
+                    Placement::LeftOf(idx) => (centers[idx] - 2 - width as isize, width),
+                    Placement::RightOf(idx) => (centers[idx] + 2, width),
+                    Placement::Over(first, last) => {
+                        let span = (centers[last] - centers[first]) as usize + 5;
+                        let width = width.max(span);
+                        let middle = (centers[first] + centers[last]) / 2;
+                        (middle - width as isize / 2, width)
+                    }
+                };
+                let height = lines.len() + 2;
+                items.push(Item::Box {
+                    x,
+                    y: row,
+                    width,
+                    height,
+                    rounded: false,
+                    lines: lines.clone(),
+                });
+                row += height;
+            }
+            Event::Activate(idx) => activations[*idx].push(row),
+            Event::Deactivate(idx) => {
+                if let Some(start) = activations[*idx].pop() {
+                    bars.push((*idx, start, row.saturating_sub(1)));
+                }
+            }
+            Event::Start { kind, label } => {
+                let title = if label.is_empty() {
+                    kind.clone()
+                } else {
+                    format!("{} [{}]", kind, label)
+                };
+                frames.push(items.len());
+                items.push(Item::Frame {
+                    top: row,
+                    bottom: row,
+                    depth,
+                    title,
+                    sections: Vec::new(),
+                });
+                depth += 1;
+                row += 1;
+            }
+            Event::Section { label } => {
+                if let Some(Item::Frame { sections, .. }) = frames.last().map(|&i| &mut items[i]) {
+                    sections.push((row, format!("[{}]", label)));
+                }
+                row += 1;
+            }
+            Event::End => {
+                if let Some(Item::Frame { bottom, .. }) = frames.pop().map(|i| &mut items[i]) {
+                    *bottom = row;
+                }
+                depth = depth.saturating_sub(1);
+                row += 1;
+            }
+        }
+    }
+    let bottom_row = row + 1;
+    for (idx, starts) in activations.iter().enumerate() {
+        for &start in starts {
+            bars.push((idx, start, bottom_row - 1));
+        }
+    }
+
+    // Lifelines and participant boxes, top and bottom
+    for &x in &centers {
+        items.push(Item::Line {
+            points: vec![(x, box_height - 1), (x, bottom_row)],
+            stroke: Stroke::Solid,
+        });
+    }
+    for &(idx, start, end) in &bars {
+        items.push(Item::Line {
+            points: vec![(centers[idx], start), (centers[idx], end)],
+            stroke: Stroke::Thick,
+        });
+    }
+    // Lines go under boxes and text
+    items.sort_by_key(|item| !matches!(item, Item::Frame { .. } | Item::Line { .. }));
+    for (idx, &x) in centers.iter().enumerate() {
+        for (y, unicode) in [(0, '┬'), (bottom_row, '┴')] {
+            let width = widths[idx];
+            items.push(Item::Box {
+                x: x - width as isize / 2,
+                y,
+                width,
+                height: box_height,
+                rounded: sequence.participants[idx].actor,
+                lines: names[idx].clone(),
+            });
+            let y = if y == 0 { box_height - 1 } else { bottom_row };
+            items.push(Item::Mark {
+                x,
+                y,
+                unicode,
+                ascii: '+',
+            });
+        }
+    }
+
+    place(items, bottom_row + box_height, unicode)
+}
+
+/// Draw items onto a canvas just wide enough for them
+fn place(items: Vec<Item>, height: usize, unicode: bool) -> Canvas {
+    let mut left = isize::MAX;
+    let mut right = isize::MIN;
+    let mut depth = 0;
+    for item in &items {
+        let (lo, hi) = match item {
+            Item::Frame { depth: d, .. } => {
+                depth = depth.max(d + 1);
+                continue;
+            }
+            Item::Line { points, .. } => (
+                points.iter().map(|p| p.0).min().unwrap_or(0),
+                points.iter().map(|p| p.0).max().unwrap_or(0),
+            ),
+            Item::Box { x, width, .. } => (*x, x + *width as isize - 1),
+            Item::Text { x, text, .. } => (*x, x + text_width(text) as isize - 1),
+            Item::Mark { x, .. } => (*x, *x),
+        };
+        left = left.min(lo);
+        right = right.max(hi);
+    }
+    // Nested frames sit two columns inside their parent
+    let margin = 2 * depth as isize;
+    let (left, right) = (left - margin, right + margin);
+
+    let width = (right - left + 1).max(0) as usize;
+    let mut canvas = Canvas::new(width, height, unicode);
+    let column = |x: isize| (x - left) as usize;
+    // Block titles go over the lifelines crossing the frame
+    let mut titles = Vec::new();
+    for item in items {
+        match item {
+            Item::Frame {
+                top,
+                bottom,
+                depth,
+                title,
+                sections,
+            } => {
+                let inset = 2 * depth;
+                let frame_width = width - 2 * inset;
+                canvas.frame(inset, top, frame_width, bottom - top + 1);
+                for (row, _) in &sections {
+                    canvas.line(
+                        &[(inset, *row), (inset + frame_width - 1, *row)],
+                        Stroke::Dotted,
+                    );
+                }
+                let room = frame_width.saturating_sub(6).max(1);
+                for (row, text) in std::iter::once((top, title)).chain(sections) {
+                    let text = wrap_text(&text, Some(room)).remove(0);
+                    titles.push((inset + 2, row, format!(" {} ", text)));
+                }
+            }
+            Item::Line { points, stroke } => {
+                let points: Vec<(usize, usize)> =
+                    points.into_iter().map(|(x, y)| (column(x), y)).collect();
+                canvas.line(&points, stroke);
+            }
+            Item::Box {
+                x,
+                y,
+                width,
+                height,
+                rounded,
+                lines,
+            } => {
+                let corners = match (rounded, unicode) {
+                    (true, true) => ['╭', '╮', '╰', '╯'],
+                    (false, true) => ['┌', '┐', '└', '┘'],
+                    (true, false) => ['.', '.', '\'', '\''],
+                    (false, false) => ['+', '+', '+', '+'],
+                };
+                let side = if unicode { '│' } else { '|' };
+                canvas.boxed(column(x), y, width, height, corners, [side, side], &lines);
+            }
+            Item::Text { x, y, text } => canvas.text(column(x), y, &text),
+            Item::Mark {
+                x,
+                y,
+                unicode,
+                ascii,
+            } => canvas.put(column(x), y, unicode, ascii),
+        }
+    }
+    for (x, y, text) in titles {
+        canvas.text(x, y, &text);
+    }
+    canvas
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn draw(source: &str) -> String {
+        render(source, 200, true).unwrap()
+    }
+
+    #[test]
+    fn test_parse_messages_and_blocks() {
+        let sequence = parse(
+            "sequenceDiagram\n  participant A as Alice\n  actor B as Bob\n  A->>+B: Hello\n  loop Every minute\n    B-->>-A: Hi\n  end\n  alt ok\n    A-xB: bye\n  else failed\n    A-)C: retry\n  end\n  Note over A,B: done",
+        )
+        .unwrap();
+        let names: Vec<&str> = sequence
+            .participants
+            .iter()
+            .map(|p| p.label.as_str())
+            .collect();
+        assert_eq!(names, ["Alice", "Bob", "C"]);
+        assert!(sequence.participants[1].actor);
+        assert!(matches!(
+            sequence.events[0],
+            Event::Message {
+                from: 0,
+                to: 1,
+                stroke: Stroke::Solid,
+                head: Head::Arrow,
+                activate: true,
+                ..
+            }
+        ));
+        assert!(matches!(
+            sequence.events[2],
+            Event::Message {
+                stroke: Stroke::Dotted,
+                deactivate: true,
+                ..
+            }
+        ));
+        assert!(matches!(
+            sequence.events[5],
+            Event::Message {
+                head: Head::Cross,
+                ..
+            }
+        ));
+        assert!(matches!(sequence.events[6], Event::Section { .. }));
+        assert!(matches!(
+            sequence.events[9],
+            Event::Note {
+                placement: Placement::Over(0, 1),
+                ..
+            }
+        ));
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        let err = parse("sequenceDiagram\n  A->>B: hi\n  end").unwrap_err();
+        assert_eq!(err.position(), Some((3, 3)));
+
+        let err = parse("sequenceDiagram\n  loop forever\n  A->>B: hi").unwrap_err();
+        assert_eq!(err.position(), Some((2, 1)));
+
+        let err = parse("sequenceDiagram\n  A->>B: hi\n  B-->>-A: bye").unwrap_err();
+        assert_eq!(err.position(), Some((3, 8)));
+
+        let err = parse("sequenceDiagram\n  Alice waves").unwrap_err();
+        assert_eq!(err.position(), Some((2, 3)));
+    }
+
+    #[test]
+    fn test_simple_message() {
+        assert_eq!(
+            draw("sequenceDiagram\n  Alice->>Bob: Hi"),
+            "┌───────┐  ┌─────┐\n\
+             │ Alice │  │ Bob │\n\
+             └───┬───┘  └──┬──┘\n    \
+             │         │\n    \
+             │   Hi    │\n    \
+             ├────────▶│\n    \
+             │         │\n\
+             ┌───┴───┐  ┌──┴──┐\n\
+             │ Alice │  │ Bob │\n\
+             └───────┘  └─────┘"
+        );
+    }
+
+    #[test]
+    fn test_reply_self_message_and_note() {
+        let out = draw(
+            "sequenceDiagram\n  A->>B: ping\n  B-->>A: pong\n  B->>B: think\n  Note right of B: busy",
+        );
+        assert!(out.contains("◀┄┄┄┄┄┄┤"), "{}", out);
+        assert!(out.contains("├───┐ think"), "{}", out);
+        assert!(out.contains("◀──┘"), "{}", out);
+        assert!(out.contains("│ busy │"), "{}", out);
+    }
+
+    #[test]
+    fn test_blocks_and_activation() {
+        let out = draw(
+            "sequenceDiagram\n  A->>+B: start\n  alt ok\n    B-->>A: yes\n  else no\n    B-->>A: no\n  end\n  B-->>-A: done",
+        );
+        assert!(out.contains(" alt [ok] "), "{}", out);
+        assert!(out.contains(" [no] "), "{}", out);
+        assert!(out.contains('┃'), "{}", out);
+    }
+
+    #[test]
+    fn test_autonumber_and_width() {
+        let out = render(
+            "sequenceDiagram\n  autonumber\n  Client->>Server: a fairly long request message label\n  Server-->>Client: ok",
+            40,
+            false,
+        )
+        .unwrap();
+        assert!(out.lines().all(|l| text_width(l) <= 40), "{}", out);
+        assert!(out.contains("1. a"), "{}", out);
+        assert!(out.contains("2. ok"), "{}", out);
+        assert!(out.is_ascii(), "{}", out);
+    }
+
+    #[test]
+    fn test_clipped_rows_are_marked() {
+        let out = render(
+            "sequenceDiagram\n  A->>B: x\n  B->>C: x\n  C->>D: x\n  D->>E: x",
+            20,
+            true,
+        )
+        .unwrap();
+        assert!(out.lines().all(|l| text_width(l) <= 20), "{}", out);
+        assert!(out.lines().next().unwrap().ends_with('…'), "{}", out);
+        assert!(out.lines().all(|l| !l.contains('E')), "{}", out);
+    }
+}
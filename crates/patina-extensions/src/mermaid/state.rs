@@ -0,0 +1,405 @@
+//! State diagrams (`stateDiagram` / `stateDiagram-v2`).
+//!
+//! States become rounded boxes in the flowchart layout; composite states are
+//! drawn as frames around their inner states, and transitions into or out of
+//! a composite state attach to its own start and end points.
+
+use super::canvas::Stroke;
+use super::flowchart::{Direction, Flowchart, Head, Link, Shape};
+use super::{clean_label, content_lines, MermaidError, SourceLine};
+use std::collections::HashMap;
+
+/// Render state diagram source as text no wider than `max_width`
+pub(crate) fn render(
+    source: &str,
+    max_width: usize,
+    unicode: bool,
+) -> Result<String, MermaidError> {
+    Ok(parse(source)?.draw(max_width, unicode))
+}
+
+/// A state as declared, before the graph is built
+#[derive(Debug)]
+struct State {
+    id: String,
+    label: String,
+    shape: Shape,
+    /// Innermost composite state containing it
+    parent: Option<String>,
+    descriptions: Vec<String>,
+}
+
+/// Statements collected from the source
+#[derive(Default)]
+struct Parser {
+    direction: Option<Direction>,
+    states: Vec<State>,
+    /// Composite states in declaration order, with their parents
+    composites: Vec<(String, Option<String>)>,
+    /// Open composite states and the line each was opened on
+    open: Vec<(String, usize)>,
+    /// Source, target and label; `[*]` is tagged with its composite state
+    transitions: Vec<(String, String, Option<String>)>,
+    /// State and text of each note
+    notes: Vec<(String, String)>,
+}
+
+/// Parse state diagram source into a graph
+fn parse(source: &str) -> Result<Flowchart, MermaidError> {
+    let mut lines = content_lines(source).into_iter();
+    if lines.next().is_none() {
+        return Err(MermaidError::parse(1, 1, "empty diagram"));
+    }
+
+    let mut parser = Parser::default();
+    // Multi-line note being collected: its state, line and text so far
+    let mut note: Option<(String, usize, Vec<String>)> = None;
+    for line in lines {
+        if let Some((target, _, text)) = &mut note {
+            if line.text == "end note" {
+                parser.notes.push((target.clone(), text.join("\n")));
+                note = None;
+            } else {
+                text.push(line.text.to_string());
+            }
+            continue;
+        }
+        if let Some(target) = parser.statement(&line)? {
+            note = Some((target, line.number, Vec::new()));
+        }
+    }
+
+    if let Some((name, line)) = parser.open.last() {
+        return Err(MermaidError::parse(
+            *line,
+            1,
+            format!("state '{}' is never closed with '}}'", name),
+        ));
+    }
+    if let Some((_, line, _)) = note {
+        return Err(MermaidError::parse(
+            line,
+            1,
+            "note is never closed with 'end note'",
+        ));
+    }
+    Ok(parser.build())
+}
+
+impl Parser {
+    /// Innermost open composite state
+    fn parent(&self) -> Option<String> {
+        self.open.last().map(|(name, _)| name.clone())
+    }
+
+    /// A state, declared in the current composite state if new
+    fn state(&mut self, id: &str) -> &mut State {
+        let idx = match self.states.iter().position(|s| s.id == id) {
+            Some(idx) => idx,
+            None => {
+                self.states.push(State {
+                    id: id.to_string(),
+                    label: id.to_string(),
+                    shape: Shape::Round,
+                    parent: self.parent(),
+                    descriptions: Vec::new(),
+                });
+                self.states.len() - 1
+            }
+        };
+        &mut self.states[idx]
+    }
+
+    /// Parse one statement; returns the state of a multi-line note it opens
+    fn statement(&mut self, line: &SourceLine) -> Result<Option<String>, MermaidError> {
+        let text = line.text;
+        let keyword = text.split_whitespace().next().unwrap_or("");
+        match keyword {
+            "}" => {
+                if self.open.pop().is_none() {
+                    return Err(line.error(0, "'}' without a matching 'state ... {'"));
+                }
+            }
+            "direction" => {
+                let name = text["direction".len()..].trim();
+                let direction = Direction::from_name(name)
+                    .ok_or_else(|| line.error(10, format!("unknown direction '{}'", name)))?;
+                if self.open.is_empty() {
+                    self.direction = Some(direction);
+                }
+            }
+            "state" => self.declare(line)?,
+            "note" => return self.note(line),
+            // Concurrency separators and styling don't affect text output
+            "--" | "classDef" | "class" | "style" | "hide" | "scale" | "title" => {}
+            _ if keyword.starts_with("accTitle") || keyword.starts_with("accDescr") => {}
+            _ => self.transition(line)?,
+        }
+        Ok(None)
+    }
+
+    /// `state "Description" as Id`, `state Id <<fork>>` or `state Id {`
+    fn declare(&mut self, line: &SourceLine) -> Result<(), MermaidError> {
+        let rest = line.text["state".len()..].trim();
+        let (rest, composite) = match rest.strip_suffix('{') {
+            Some(rest) => (rest.trim(), true),
+            None => (rest, false),
+        };
+        let (id, label, stereotype) = if let Some(quoted) = rest.strip_prefix('"') {
+            let Some((label, after)) = quoted.split_once('"') else {
+                return Err(line.error(6, "state description is never closed with '\"'"));
+            };
+            let id = after.trim().strip_prefix("as").map(str::trim).unwrap_or("");
+            (id, Some(clean_label(label)), None)
+        } else if let Some((id, label)) = rest.split_once(" as ") {
+            (id.trim(), Some(clean_label(label)), None)
+        } else {
+            match rest.split_once("<<") {
+                Some((id, stereotype)) => {
+                    (id.trim(), None, Some(stereotype.trim_end_matches(">>")))
+                }
+                None => (rest, None, None),
+            }
+        };
+        if id.is_empty() {
+            return Err(line.error(0, "expected a state name"));
+        }
+
+        let state = self.state(id);
+        if let Some(label) = label {
+            state.label = label;
+        }
+        match stereotype {
+            Some("choice") => {
+                state.shape = Shape::Rhombus;
+                state.label = String::new();
+            }
+            Some("fork") | Some("join") => {
+                state.shape = Shape::Rect;
+                state.label = String::new();
+            }
+            _ => {}
+        }
+        if composite {
+            let parent = state.parent.clone();
+            self.composites.push((id.to_string(), parent));
+            self.open.push((id.to_string(), line.number));
+        }
+        Ok(())
+    }
+
+    /// `note left of State : text`, or the start of a multi-line note
+    fn note(&mut self, line: &SourceLine) -> Result<Option<String>, MermaidError> {
+        let rest = line.text["note".len()..].trim();
+        let (target, text) = match rest.split_once(':') {
+            Some((target, text)) => (target.trim(), Some(text)),
+            None => (rest, None),
+        };
+        let Some(id) = ["left of", "right of"]
+            .iter()
+            .find_map(|side| target.strip_prefix(side))
+            .map(str::trim)
+            .filter(|id| !id.is_empty())
+        else {
+            return Err(line.error(5, "expected 'left of' or 'right of' and a state"));
+        };
+        self.state(id);
+        match text {
+            Some(text) => {
+                self.notes.push((id.to_string(), text.to_string()));
+                Ok(None)
+            }
+            None => Ok(Some(id.to_string())),
+        }
+    }
+
+    /// `A --> B`, `A --> B : label`, or a state description `A : text`
+    fn transition(&mut self, line: &SourceLine) -> Result<(), MermaidError> {
+        let text = line.text;
+        let (body, label) = match text.split_once(':') {
+            Some((body, label)) => (body.trim(), Some(clean_label(label))),
+            None => (text, None),
+        };
+        let Some((from, to)) = body.split_once("-->") else {
+            if body.is_empty() || body.contains(char::is_whitespace) {
+                return Err(line.error(0, format!("unrecognized statement '{}'", text)));
+            }
+            let state = self.state(body);
+            state.descriptions.extend(label);
+            return Ok(());
+        };
+        let (from, to) = (from.trim(), to.trim());
+        if from.is_empty() || to.is_empty() {
+            return Err(line.error(0, "transition needs a state on both sides of '-->'"));
+        }
+        for id in [from, to].into_iter().filter(|id| *id != "[*]") {
+            self.state(id);
+        }
+        let scope = self.parent().unwrap_or_default();
+        let scoped = |id: &str| {
+            if id == "[*]" {
+                format!("[*]{}", scope)
+            } else {
+                id.to_string()
+            }
+        };
+        self.transitions
+            .push((scoped(from), scoped(to), label.filter(|l| !l.is_empty())));
+        Ok(())
+    }
+
+    /// Build the graph: composite states become frames around their states
+    fn build(self) -> Flowchart {
+        let mut chart = Flowchart::new(self.direction.unwrap_or(Direction::TopDown));
+        let mut subgraphs: HashMap<String, usize> = HashMap::new();
+        for (name, parent) in &self.composites {
+            let title = self
+                .states
+                .iter()
+                .find(|s| &s.id == name)
+                .map_or_else(|| name.clone(), |s| s.label.clone());
+            let parent = parent.as_ref().and_then(|p| subgraphs.get(p).copied());
+            subgraphs.insert(name.clone(), chart.subgraph(title, parent));
+        }
+        let subgraph_of =
+            |name: &Option<String>| name.as_ref().and_then(|n| subgraphs.get(n).copied());
+
+        for state in self
+            .states
+            .iter()
+            .filter(|s| !subgraphs.contains_key(&s.id))
+        {
+            let idx = chart.node(&state.id, subgraph_of(&state.parent));
+            let node = &mut chart.nodes[idx];
+            node.label = state.label.clone();
+            node.shape = state.shape;
+            if !state.descriptions.is_empty() {
+                node.members.push(state.descriptions.clone());
+            }
+        }
+
+        // `[*]` is the start point as a source and the end point as a target
+        let point = |chart: &mut Flowchart, scope: &str, end: bool| {
+            let key = format!("\0{}{}", if end { "end" } else { "start" }, scope);
+            let parent = subgraphs.get(scope).copied();
+            let idx = chart.node(&key, parent);
+            chart.nodes[idx].shape = if end { Shape::End } else { Shape::Start };
+            chart.nodes[idx].label = String::new();
+            idx
+        };
+        for (from, to, label) in self.transitions {
+            // Composite states are entered at their start point and left from their end point
+            let from = match from.strip_prefix("[*]") {
+                Some(scope) => point(&mut chart, scope, false),
+                None if subgraphs.contains_key(&from) => point(&mut chart, &from, true),
+                None => chart.node(&from, None),
+            };
+            let to = match to.strip_prefix("[*]") {
+                Some(scope) => point(&mut chart, scope, true),
+                None if subgraphs.contains_key(&to) => point(&mut chart, &to, false),
+                None => chart.node(&to, None),
+            };
+            chart.edge(from, to, link(Stroke::Solid, Head::Arrow, label));
+        }
+
+        for (idx, (id, text)) in self.notes.into_iter().enumerate() {
+            let state = chart.node(&id, None);
+            let parent = chart.nodes[state].subgraph;
+            let note = chart.node(&format!("\0note{}", idx), parent);
+            chart.nodes[note].label = clean_label(&text);
+            chart.edge(state, note, link(Stroke::Dotted, Head::None, None));
+        }
+        chart
+    }
+}
+
+fn link(stroke: Stroke, head: Head, label: Option<String>) -> Link {
+    Link {
+        stroke,
+        head,
+        tail: Head::None,
+        label,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_states_and_transitions() {
+        let out = render(
+            "stateDiagram-v2\n  [*] --> Still\n  Still --> Moving : push\n  Moving --> [*]",
+            80,
+            true,
+        )
+        .unwrap();
+        assert_eq!(out.lines().next().map(str::trim), Some("●"), "{}", out);
+        assert!(out.contains("│ Still │"), "{}", out);
+        assert!(out.contains("push"), "{}", out);
+        assert!(out.contains('◉'), "{}", out);
+        assert!(out.contains('╭'), "{}", out);
+    }
+
+    #[test]
+    fn test_composite_state_and_description() {
+        let chart = parse(
+            "stateDiagram\n  [*] --> Active\n  state Active {\n    [*] --> Idle\n    Idle --> Busy\n  }\n  Active --> Done\n  Busy : working hard",
+        )
+        .unwrap();
+        let busy = chart.find("Busy").unwrap();
+        assert_eq!(
+            chart.nodes[busy].members,
+            [vec!["working hard".to_string()]]
+        );
+        assert_eq!(chart.nodes[busy].subgraph, Some(0));
+        // The composite state is entered through its inner start point
+        let inner_start = chart.find("\0startActive").unwrap();
+        assert!(chart.find("Active").is_none());
+        assert_eq!(chart.nodes[inner_start].subgraph, Some(0));
+
+        let out = chart.draw(80, false);
+        assert!(out.contains("+- Active -"), "{}", out);
+        assert!(out.is_ascii(), "{}", out);
+    }
+
+    #[test]
+    fn test_composite_title_and_outer_end() {
+        let out = render(
+            "stateDiagram-v2\n  [*] --> Still\n  Still --> Moving\n  state Moving {\n    [*] --> Slow\n    Slow --> Fast\n    Fast --> [*]\n  }\n  Moving --> Still\n  Still --> [*]",
+            80,
+            true,
+        )
+        .unwrap();
+        let lines: Vec<Vec<char>> = out.lines().map(|l| l.chars().collect()).collect();
+        let top = out.lines().position(|l| l.contains("Moving")).unwrap();
+        let title: String = lines[top].iter().collect();
+        assert!(title.starts_with("┌─ Moving ─"), "{}", out);
+
+        // The outer end point sits right of the frame, the inner one inside it
+        let right = lines[top].iter().position(|&c| c == '┐').unwrap();
+        let ends: Vec<(usize, usize)> = lines
+            .iter()
+            .enumerate()
+            .flat_map(|(row, l)| l.iter().enumerate().map(move |(col, &c)| (row, col, c)))
+            .filter(|&(_, _, c)| c == '◉')
+            .map(|(row, col, _)| (row, col))
+            .collect();
+        assert_eq!(ends.len(), 2, "{}", out);
+        assert!(ends.iter().any(|&(_, col)| col > right), "{}", out);
+        assert!(
+            ends.iter().any(|&(row, col)| row > top && col < right),
+            "{}",
+            out
+        );
+    }
+
+    #[test]
+    fn test_unclosed_composite() {
+        let err = parse("stateDiagram\n  state A {\n    B --> C").unwrap_err();
+        assert_eq!(err.position(), Some((2, 1)));
+
+        let err = parse("stateDiagram\n  A --> ").unwrap_err();
+        assert_eq!(err.position(), Some((2, 3)));
+    }
+}
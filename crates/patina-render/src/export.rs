@@ -0,0 +1,376 @@
+//! Standalone HTML export.
+//!
+//! The page embeds everything it needs: CSS built from the theme colors,
+//! code highlighted with inline styles, math as MathML and Mermaid diagrams
+//! as preformatted text, so it can be opened or mailed as a single file.
+
+use crate::{code_highlighter, PreviewOptions, Theme};
+use comrak::nodes::{AstNode, NodeValue};
+use comrak::Arena;
+use patina_core::{Document, Highlighter, SyntaxAssets};
+use patina_extensions::{EmojiExpander, LatexRenderer, MermaidRenderer};
+use serde_json::Value;
+use syntect::html::{styled_line_to_highlighted_html, IncludeBackground};
+use syntect::util::LinesWithEndings;
+
+/// Layout rules; colors come from the theme variables set on `:root`
+const STYLESHEET: &str = r#"
+*, *::before, *::after { box-sizing: border-box; }
+html { color-scheme: var(--color-scheme); }
+body {
+  margin: 0;
+  background: var(--bg-primary);
+  color: var(--fg-primary);
+  font: 16px/1.6 -apple-system, BlinkMacSystemFont, "Segoe UI", Helvetica, Arial, sans-serif;
+}
+main { max-width: 50rem; margin: 0 auto; padding: 2rem 1.5rem 4rem; }
+h1, h2, h3, h4, h5, h6 { color: var(--md-heading); line-height: 1.25; margin: 1.6em 0 0.6em; }
+h1, h2 { border-bottom: 1px solid var(--ui-border); padding-bottom: 0.3em; }
+h1 .anchor, h2 .anchor, h3 .anchor, h4 .anchor, h5 .anchor, h6 .anchor { margin-left: -1em; padding-right: 0.2em; }
+a { color: var(--md-link); }
+strong { color: var(--md-bold); }
+em { color: var(--md-italic); }
+code, pre, kbd { font-family: "JetBrains Mono", "Fira Code", Menlo, Consolas, monospace; font-size: 0.9em; }
+:not(pre) > code { color: var(--md-code); background: var(--bg-secondary); padding: 0.15em 0.35em; border-radius: 4px; }
+pre { background: var(--bg-secondary); padding: 1em; border-radius: 6px; overflow-x: auto; line-height: 1.45; }
+figure.diagram { margin: 1em 0; }
+figure.diagram pre { line-height: 1.2; }
+blockquote { margin: 1em 0; padding: 0 1em; color: var(--md-blockquote); border-left: 4px solid var(--md-blockquote); }
+hr { border: 0; border-top: 1px solid var(--ui-border); }
+table { border-collapse: collapse; margin: 1em 0; display: block; overflow-x: auto; }
+th, td { border: 1px solid var(--ui-border); padding: 0.4em 0.8em; }
+th { background: var(--bg-secondary); }
+tr:nth-child(even) td { background: var(--bg-line-highlight); }
+img { max-width: 100%; }
+ul.contains-task-list { list-style: none; padding-left: 1.2em; }
+.footnotes { color: var(--fg-secondary); font-size: 0.9em; border-top: 1px solid var(--ui-border); margin-top: 3em; }
+math[display="block"] { margin: 1em 0; overflow-x: auto; }
+::selection { background: var(--bg-selection); }
+"#;
+
+/// Renders a document to a complete HTML page
+pub struct HtmlExporter<'a> {
+    theme: &'a Theme,
+    code_highlighter: Option<&'a Highlighter>,
+    options: PreviewOptions,
+    sanitize: bool,
+}
+
+impl<'a> HtmlExporter<'a> {
+    /// Create an exporter with the given theme
+    pub fn new(theme: &'a Theme) -> Self {
+        Self {
+            theme,
+            code_highlighter: None,
+            options: PreviewOptions::default(),
+            sanitize: false,
+        }
+    }
+
+    /// Highlight fenced code with this highlighter instead of one built from the theme
+    pub fn code_highlighter(mut self, highlighter: &'a Highlighter) -> Self {
+        self.code_highlighter = Some(highlighter);
+        self
+    }
+
+    /// Set which extensions are rendered
+    pub fn options(mut self, options: PreviewOptions) -> Self {
+        self.options = options;
+        self
+    }
+
+    /// Escape raw HTML and drop `javascript:` and similar link targets
+    pub fn sanitize(mut self, sanitize: bool) -> Self {
+        self.sanitize = sanitize;
+        self
+    }
+
+    /// The document as a standalone page
+    pub fn export(&self, doc: &Document) -> String {
+        let mut page = String::from("<!DOCTYPE html>\n");
+        let lang = frontmatter_string(doc, "lang").unwrap_or_else(|| "en".to_string());
+        page.push_str(&format!("<html lang=\"{}\">\n<head>\n", escape(&lang)));
+        page.push_str("<meta charset=\"utf-8\">\n");
+        page.push_str("<meta name=\"viewport\" content=\"width=device-width, initial-scale=1\">\n");
+        page.push_str("<meta name=\"generator\" content=\"Patina\">\n");
+        page.push_str(&format!("<title>{}</title>\n", escape(&title(doc))));
+        for (name, keys) in [
+            ("description", &["description", "summary"][..]),
+            ("author", &["author", "authors"][..]),
+            ("keywords", &["keywords", "tags"][..]),
+            ("date", &["date"][..]),
+        ] {
+            if let Some(content) = keys.iter().find_map(|key| frontmatter_string(doc, key)) {
+                page.push_str(&format!(
+                    "<meta name=\"{}\" content=\"{}\">\n",
+                    name,
+                    escape(&content)
+                ));
+            }
+        }
+        page.push_str("<style>\n");
+        page.push_str(&self.css());
+        page.push_str("</style>\n</head>\n<body>\n<main>\n");
+        page.push_str(&self.body(doc));
+        page.push_str("</main>\n</body>\n</html>\n");
+        page
+    }
+
+    /// The page's CSS: the theme colors as variables, then the layout rules
+    pub fn css(&self) -> String {
+        let mut css = String::from(":root {\n");
+        let scheme = if self.theme.is_dark { "dark" } else { "light" };
+        css.push_str(&format!("  --color-scheme: {};\n", scheme));
+        for field in Theme::COLOR_FIELDS {
+            if let Some(color) = self.theme.color(field) {
+                css.push_str(&format!(
+                    "  --{}: {};\n",
+                    field.replace('_', "-"),
+                    color.to_hex()
+                ));
+            }
+        }
+        css.push('}');
+        css.push_str(STYLESHEET);
+        css
+    }
+
+    /// The rendered body of the document, without the page around it
+    pub fn body(&self, doc: &Document) -> String {
+        let own_highlighter;
+        let highlighter = match self.code_highlighter {
+            Some(highlighter) => highlighter,
+            None => {
+                own_highlighter = code_highlighter(self.theme, &SyntaxAssets::default());
+                &own_highlighter
+            }
+        };
+
+        let arena = Arena::new();
+        let root = doc.parse(&arena);
+        // Collected first: rewriting a node must not visit the HTML it produced
+        let nodes: Vec<&AstNode> = root.descendants().collect();
+        for node in nodes {
+            let mut ast = node.data.borrow_mut();
+            if let Some(value) = self.rewrite(&ast.value, highlighter) {
+                ast.value = value;
+            }
+        }
+
+        let mut options = doc.parser().options().clone();
+        options.extension.header_ids = Some(String::new());
+        options.render.unsafe_ = true;
+        let mut html = Vec::new();
+        comrak::format_html(root, &options, &mut html).expect("writing to a Vec cannot fail");
+        String::from_utf8(html).unwrap_or_default()
+    }
+
+    /// Replacement for a node, if the export renders it differently from comrak
+    fn rewrite(&self, value: &NodeValue, highlighter: &Highlighter) -> Option<NodeValue> {
+        let value = match value {
+            NodeValue::Text(text) => {
+                let expanded = EmojiExpander::new().expand_all(text);
+                (expanded != *text).then_some(NodeValue::Text(expanded))?
+            }
+            NodeValue::Math(math) if self.options.math => NodeValue::HtmlInline(
+                LatexRenderer::new().render_mathml(&math.literal, math.display_math),
+            ),
+            NodeValue::CodeBlock(block) => {
+                let lang = block.info.split_whitespace().next().unwrap_or("");
+                html_block(self.code_block(lang, &block.literal, highlighter)?)
+            }
+            NodeValue::HtmlBlock(block) if self.sanitize => html_block(escape(&block.literal)),
+            NodeValue::HtmlInline(html) if self.sanitize => NodeValue::HtmlInline(escape(html)),
+            NodeValue::Link(link) | NodeValue::Image(link)
+                if self.sanitize && dangerous_url(&link.url) =>
+            {
+                let mut value = value.clone();
+                if let NodeValue::Link(link) | NodeValue::Image(link) = &mut value {
+                    link.url.clear();
+                }
+                value
+            }
+            _ => return None,
+        };
+        Some(value)
+    }
+
+    /// HTML for a fenced block, or `None` to leave it to comrak
+    fn code_block(&self, lang: &str, code: &str, highlighter: &Highlighter) -> Option<String> {
+        if self.options.math && lang == "math" {
+            let math = LatexRenderer::new().render_mathml(code, true);
+            return Some(format!("<p>{}</p>\n", math));
+        }
+        if self.options.mermaid && lang.eq_ignore_ascii_case("mermaid") {
+            // An invalid diagram is shown as its source, like in the preview
+            if let Ok(diagram) = MermaidRenderer::new().max_width(100).render(code) {
+                return Some(format!(
+                    "<figure class=\"diagram mermaid\"><pre>{}</pre></figure>\n",
+                    escape(&diagram)
+                ));
+            }
+        }
+        let syntax = highlighter.syntax_for_language(lang)?;
+        let background = highlighter
+            .theme()
+            .settings
+            .background
+            .map(|c| format!(" style=\"background: #{:02x}{:02x}{:02x}\"", c.r, c.g, c.b))
+            .unwrap_or_default();
+        let mut html = format!(
+            "<pre class=\"highlight\"{}><code class=\"language-{}\">",
+            background,
+            escape(lang)
+        );
+        let mut lines = highlighter.start(syntax);
+        for line in LinesWithEndings::from(code) {
+            let regions = lines.highlight(line);
+            match styled_line_to_highlighted_html(&regions, IncludeBackground::No) {
+                Ok(spans) => html.push_str(&spans),
+                Err(_) => html.push_str(&escape(line)),
+            }
+        }
+        html.push_str("</code></pre>\n");
+        Some(html)
+    }
+}
+
+/// A block of HTML written as is
+fn html_block(literal: String) -> NodeValue {
+    NodeValue::HtmlBlock(comrak::nodes::NodeHtmlBlock {
+        block_type: 0,
+        literal,
+    })
+}
+
+/// Page title: the frontmatter title, the first top-level heading or the file name
+fn title(doc: &Document) -> String {
+    frontmatter_string(doc, "title")
+        .or_else(|| {
+            let headings = doc.headings();
+            let heading = headings.iter().min_by_key(|heading| heading.level)?;
+            Some(heading.text.clone())
+        })
+        .or_else(|| {
+            let stem = doc.path.as_deref()?.file_stem()?;
+            Some(stem.to_string_lossy().into_owned())
+        })
+        .unwrap_or_else(|| "Untitled".to_string())
+}
+
+/// A frontmatter value as text; lists are joined with commas
+fn frontmatter_string(doc: &Document, key: &str) -> Option<String> {
+    let value = doc.frontmatter.as_ref()?.data.get(key)?;
+    let text = match value {
+        Value::String(s) => s.clone(),
+        Value::Number(n) => n.to_string(),
+        Value::Array(items) => items
+            .iter()
+            .filter_map(|item| match item {
+                Value::String(s) => Some(s.clone()),
+                Value::Number(n) => Some(n.to_string()),
+                _ => None,
+            })
+            .collect::<Vec<_>>()
+            .join(", "),
+        _ => return None,
+    };
+    (!text.trim().is_empty()).then_some(text)
+}
+
+/// Escape text for HTML content and attribute values
+fn escape(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => out.push_str("&amp;"),
+            '<' => out.push_str("&lt;"),
+            '>' => out.push_str("&gt;"),
+            '"' => out.push_str("&quot;"),
+            _ => out.push(c),
+        }
+    }
+    out
+}
+
+/// Link targets that run code when followed, as filtered by comrak's safe mode
+fn dangerous_url(url: &str) -> bool {
+    let url = url.trim_start().to_ascii_lowercase();
+    if let Some(data) = url.strip_prefix("data:") {
+        return !["image/png", "image/gif", "image/jpeg", "image/webp"]
+            .iter()
+            .any(|kind| data.starts_with(kind));
+    }
+    ["javascript:", "vbscript:", "file:"]
+        .iter()
+        .any(|scheme| url.starts_with(scheme))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn export(markdown: &str) -> String {
+        HtmlExporter::new(&Theme::dracula()).export(&Document::from_content(markdown))
+    }
+
+    #[test]
+    fn test_page_with_theme_css() {
+        let html = export("# Notes\n\nHello *world*");
+        assert!(html.starts_with("<!DOCTYPE html>\n<html lang=\"en\">"));
+        assert!(html.contains("<title>Notes</title>"));
+        assert!(html.contains("--bg-primary: #282a36;"));
+        assert!(html.contains("--color-scheme: dark;"));
+        assert!(html.contains("<em>world</em>"));
+        assert!(html.contains("id=\"notes\""));
+        assert!(html.trim_end().ends_with("</html>"));
+    }
+
+    #[test]
+    fn test_frontmatter_meta() {
+        let html = export(
+            "---\ntitle: A & B\nauthor: Ada\ntags: [rust, \"md\"]\nlang: fr\n---\n# Heading\n",
+        );
+        assert!(html.contains("<html lang=\"fr\">"));
+        assert!(html.contains("<title>A &amp; B</title>"));
+        assert!(html.contains("<meta name=\"author\" content=\"Ada\">"));
+        assert!(html.contains("<meta name=\"keywords\" content=\"rust, md\">"));
+        assert!(!html.contains("title: A"));
+    }
+
+    #[test]
+    fn test_code_math_and_diagrams() {
+        let html = export(
+            "```rust\nfn main() {}\n```\n\nEnergy $E = mc^2$\n\n```mermaid\ngraph TD\n  A --> B\n```\n",
+        );
+        assert!(html.contains(
+            "<pre class=\"highlight\" style=\"background: #44475a\"><code class=\"language-rust\">"
+        ));
+        // Function names in Dracula's `syntax_function` green
+        assert!(html.contains("<span style=\"color:#50fa7b;\">main</span>"));
+        assert!(
+            html.contains("<math xmlns=\"http://www.w3.org/1998/Math/MathML\" display=\"inline\">")
+        );
+        assert!(html.contains("<figure class=\"diagram mermaid\"><pre>"));
+        assert!(!html.contains("language-mermaid"));
+    }
+
+    #[test]
+    fn test_sanitize() {
+        let markdown =
+            "<script>alert(1)</script>\n\nHi <b>there</b> [x](javascript:alert(1)) :tada:\n";
+        let doc = Document::from_content(markdown);
+        let theme = Theme::dracula();
+
+        let raw = HtmlExporter::new(&theme).body(&doc);
+        assert!(raw.contains("<script>alert(1)</script>"));
+        assert!(raw.contains("<b>there</b>"));
+
+        let clean = HtmlExporter::new(&theme).sanitize(true).body(&doc);
+        assert!(!clean.contains("<script>"));
+        assert!(clean.contains("&lt;script&gt;alert(1)&lt;/script&gt;"));
+        assert!(clean.contains("&lt;b&gt;there&lt;/b&gt;"));
+        assert!(clean.contains("<a href=\"\">x</a>"));
+        assert!(clean.contains("🎉"));
+    }
+}
@@ -9,12 +9,20 @@ pub mod tui;
 #[cfg(feature = "gui")]
 pub mod gui;
 
+pub mod export;
 pub mod style;
+pub mod syntax_theme;
 pub mod theme;
+pub mod themes;
+pub mod wrap;
 
 // Re-exports
-pub use style::EditorStyle;
+pub use export::HtmlExporter;
+pub use style::{EditorStyle, PreviewOptions};
+pub use syntax_theme::code_highlighter;
 pub use theme::Theme;
+pub use themes::{ThemeError, ThemeRegistry};
+pub use wrap::{LineLayout, VisualRow, WrapOptions};
 
 /// Color representation (RGBA)
 #[derive(Debug, Clone, Copy, PartialEq)]
@@ -34,6 +42,26 @@ impl Color {
         Self { r, g, b, a }
     }
 
+    /// Parse `#rrggbb` or `#rrggbbaa`
+    pub fn from_hex(hex: &str) -> Option<Self> {
+        let hex = hex.strip_prefix('#')?;
+        if !matches!(hex.len(), 6 | 8) || !hex.is_ascii() {
+            return None;
+        }
+        let byte = |i: usize| u8::from_str_radix(&hex[i..i + 2], 16).ok();
+        let a = if hex.len() == 8 { byte(6)? } else { 255 };
+        Some(Self::rgba(byte(0)?, byte(2)?, byte(4)?, a))
+    }
+
+    /// Format as `#rrggbb`, or `#rrggbbaa` when not opaque
+    pub fn to_hex(&self) -> String {
+        if self.a == 255 {
+            format!("#{:02x}{:02x}{:02x}", self.r, self.g, self.b)
+        } else {
+            format!("#{:02x}{:02x}{:02x}{:02x}", self.r, self.g, self.b, self.a)
+        }
+    }
+
     /// Convert to ratatui color
     #[cfg(feature = "tui")]
     pub fn to_ratatui(&self) -> ratatui::style::Color {
@@ -5,6 +5,8 @@
 pub struct EditorStyle {
     /// Show line numbers
     pub line_numbers: bool,
+    /// Number lines relative to the cursor line
+    pub relative_line_numbers: bool,
     /// Show minimap
     pub minimap: bool,
     /// Highlight current line
@@ -27,6 +29,7 @@ impl Default for EditorStyle {
     fn default() -> Self {
         Self {
             line_numbers: true,
+            relative_line_numbers: false,
             minimap: false,
             highlight_line: true,
             indent_guides: true,
@@ -38,3 +41,21 @@ impl Default for EditorStyle {
         }
     }
 }
+
+/// Preview rendering options
+#[derive(Debug, Clone, PartialEq)]
+pub struct PreviewOptions {
+    /// Render `$...$`, `$$...$$` and ```` ```math ```` as formulas
+    pub math: bool,
+    /// Draw ```` ```mermaid ```` fences as diagrams
+    pub mermaid: bool,
+}
+
+impl Default for PreviewOptions {
+    fn default() -> Self {
+        Self {
+            math: true,
+            mermaid: true,
+        }
+    }
+}
@@ -0,0 +1,133 @@
+//! Syntect themes for highlighting fenced code in the current Patina theme.
+
+use crate::{Color, Theme};
+use patina_core::{Highlighter, SyntaxAssets};
+use std::str::FromStr;
+use std::sync::Arc;
+use syntect::highlighting::{
+    self, FontStyle, ScopeSelectors, StyleModifier, ThemeItem, ThemeSettings,
+};
+
+/// Scope selectors colored by each syntax color of a generated theme
+fn scope_colors(theme: &Theme) -> [(&'static str, Color); 7] {
+    [
+        (
+            "comment, punctuation.definition.comment",
+            theme.syntax_comment,
+        ),
+        ("string, constant.character.escape", theme.syntax_string),
+        (
+            "constant.numeric, constant.language, constant.character",
+            theme.syntax_number,
+        ),
+        ("keyword, storage, variable.language", theme.syntax_keyword),
+        (
+            "keyword.operator, punctuation.accessor",
+            theme.syntax_operator,
+        ),
+        (
+            "entity.name.function, support.function, meta.function-call",
+            theme.syntax_function,
+        ),
+        (
+            "entity.name.type, entity.name.class, entity.other.inherited-class, \
+             support.type, support.class, storage.type",
+            theme.syntax_type,
+        ),
+    ]
+}
+
+/// Highlighter for fenced code in `theme`.
+///
+/// Uses the theme's named `syntax_theme` when `assets` has it, otherwise a
+/// syntect theme generated from the `syntax_*` colors.
+pub fn code_highlighter(theme: &Theme, assets: &SyntaxAssets) -> Highlighter {
+    let named = theme.syntax_theme.as_deref().and_then(|name| {
+        let found = assets.theme(name);
+        if found.is_none() {
+            log::warn!("Theme {}: unknown syntax theme '{}'", theme.name, name);
+        }
+        found
+    });
+    let syntect_theme = named.unwrap_or_else(|| Arc::new(generate(theme)));
+    assets.highlighter(syntect_theme)
+}
+
+/// Build a syntect theme from the syntax colors of `theme`
+pub fn generate(theme: &Theme) -> highlighting::Theme {
+    let scopes = scope_colors(theme)
+        .into_iter()
+        .map(|(selector, color)| ThemeItem {
+            scope: ScopeSelectors::from_str(selector).expect("valid scope selector"),
+            style: StyleModifier {
+                foreground: Some(to_syntect(color)),
+                background: None,
+                font_style: selector.starts_with("comment").then_some(FontStyle::ITALIC),
+            },
+        })
+        .collect();
+
+    highlighting::Theme {
+        name: Some(theme.name.clone()),
+        settings: ThemeSettings {
+            foreground: Some(to_syntect(theme.fg_primary)),
+            background: Some(to_syntect(theme.bg_secondary)),
+            ..ThemeSettings::default()
+        },
+        scopes,
+        ..highlighting::Theme::default()
+    }
+}
+
+/// Convert a color, dropping transparency (code is drawn on an opaque background)
+fn to_syntect(color: Color) -> highlighting::Color {
+    highlighting::Color {
+        r: color.r,
+        g: color.g,
+        b: color.b,
+        a: 0xff,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Foreground of the first highlighted span of `code`
+    fn first_color(highlighter: &Highlighter, lang: &str, code: &str) -> (u8, u8, u8) {
+        let syntax = highlighter.syntax_for_language(lang).unwrap();
+        let spans = highlighter.highlight_line(code, syntax);
+        let fg = spans[0].0.foreground;
+        (fg.r, fg.g, fg.b)
+    }
+
+    #[test]
+    fn test_generated_theme_uses_syntax_colors() {
+        let theme = Theme::dracula();
+        let highlighter = code_highlighter(&theme, &SyntaxAssets::default());
+        let keyword = theme.syntax_keyword;
+        assert_eq!(
+            first_color(&highlighter, "rust", "if x {}\n"),
+            (keyword.r, keyword.g, keyword.b)
+        );
+        let bg = highlighter.theme().settings.background.unwrap();
+        assert_eq!((bg.r, bg.g, bg.b), (68, 71, 90));
+    }
+
+    #[test]
+    fn test_named_syntax_theme() {
+        let highlighter = code_highlighter(&Theme::solarized_light(), &SyntaxAssets::default());
+        assert_eq!(
+            highlighter.theme().name.as_deref(),
+            Some("Solarized (light)")
+        );
+
+        // Unknown names fall back to a generated theme
+        let theme = Theme {
+            syntax_theme: Some("Nope".to_string()),
+            ..Theme::one_dark()
+        };
+        let highlighter = code_highlighter(&theme, &SyntaxAssets::default());
+        assert_eq!(highlighter.theme().name.as_deref(), Some("One Dark"));
+    }
+}
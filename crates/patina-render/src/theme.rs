@@ -1,12 +1,72 @@
 //! Theme definitions for the editor.
 
-use crate::Color;
+use crate::{Color, ThemeRegistry};
+
+/// Generate name-based access to the theme's color fields
+macro_rules! color_fields {
+    ($($field:ident),* $(,)?) => {
+        impl Theme {
+            /// Names of all color fields, as used in theme files
+            pub const COLOR_FIELDS: &'static [&'static str] = &[$(stringify!($field)),*];
+
+            /// Look up a color by field name
+            pub fn color(&self, field: &str) -> Option<Color> {
+                match field {
+                    $(stringify!($field) => Some(self.$field),)*
+                    _ => None,
+                }
+            }
+
+            /// Set a color by field name, returning false for unknown fields
+            pub fn set_color(&mut self, field: &str, color: Color) -> bool {
+                match field {
+                    $(stringify!($field) => {
+                        self.$field = color;
+                        true
+                    })*
+                    _ => false,
+                }
+            }
+        }
+    };
+}
+
+color_fields!(
+    bg_primary,
+    bg_secondary,
+    bg_selection,
+    bg_line_highlight,
+    fg_primary,
+    fg_secondary,
+    fg_muted,
+    syntax_keyword,
+    syntax_string,
+    syntax_number,
+    syntax_comment,
+    syntax_function,
+    syntax_type,
+    syntax_operator,
+    md_heading,
+    md_bold,
+    md_italic,
+    md_link,
+    md_code,
+    md_blockquote,
+    ui_border,
+    ui_cursor,
+    ui_line_number,
+    ui_status_bar,
+    ui_error,
+    ui_warning,
+);
 
 /// Editor color theme
 #[derive(Debug, Clone)]
 pub struct Theme {
     pub name: String,
     pub is_dark: bool,
+    /// syntect theme for fenced code; `None` derives one from the syntax colors
+    pub syntax_theme: Option<String>,
 
     // Background colors
     pub bg_primary: Color,
@@ -41,6 +101,10 @@ pub struct Theme {
     pub ui_cursor: Color,
     pub ui_line_number: Color,
     pub ui_status_bar: Color,
+
+    // Diagnostics
+    pub ui_error: Color,
+    pub ui_warning: Color,
 }
 
 impl Theme {
@@ -49,6 +113,7 @@ impl Theme {
         Self {
             name: "Dracula".to_string(),
             is_dark: true,
+            syntax_theme: None,
 
             bg_primary: Color::rgb(40, 42, 54),
             bg_secondary: Color::rgb(68, 71, 90),
@@ -78,6 +143,9 @@ impl Theme {
             ui_cursor: Color::rgb(248, 248, 242),
             ui_line_number: Color::rgb(98, 114, 164),
             ui_status_bar: Color::rgb(68, 71, 90),
+
+            ui_error: Color::rgb(255, 85, 85),
+            ui_warning: Color::rgb(255, 184, 108),
         }
     }
 
@@ -86,6 +154,7 @@ impl Theme {
         Self {
             name: "One Dark".to_string(),
             is_dark: true,
+            syntax_theme: None,
 
             bg_primary: Color::rgb(40, 44, 52),
             bg_secondary: Color::rgb(33, 37, 43),
@@ -115,6 +184,9 @@ impl Theme {
             ui_cursor: Color::rgb(171, 178, 191),
             ui_line_number: Color::rgb(76, 82, 99),
             ui_status_bar: Color::rgb(33, 37, 43),
+
+            ui_error: Color::rgb(224, 108, 117),
+            ui_warning: Color::rgb(229, 192, 123),
         }
     }
 
@@ -123,6 +195,7 @@ impl Theme {
         Self {
             name: "Solarized Light".to_string(),
             is_dark: false,
+            syntax_theme: Some("Solarized (light)".to_string()),
 
             bg_primary: Color::rgb(253, 246, 227),
             bg_secondary: Color::rgb(238, 232, 213),
@@ -152,22 +225,35 @@ impl Theme {
             ui_cursor: Color::rgb(101, 123, 131),
             ui_line_number: Color::rgb(147, 161, 161),
             ui_status_bar: Color::rgb(238, 232, 213),
+
+            ui_error: Color::rgb(220, 50, 47),
+            ui_warning: Color::rgb(181, 137, 0),
         }
     }
 
-    /// Get theme by name
+    /// Get a built-in or bundled theme by name
     pub fn by_name(name: &str) -> Self {
         match name.to_lowercase().as_str() {
             "dracula" => Self::dracula(),
             "one dark" | "one_dark" | "onedark" => Self::one_dark(),
             "solarized light" | "solarized_light" => Self::solarized_light(),
-            _ => Self::dracula(), // Default
+            _ => ThemeRegistry::bundled()
+                .get(name)
+                .cloned()
+                .unwrap_or_else(Self::dracula), // Default
         }
     }
 
-    /// List available themes
+    /// List available built-in and bundled themes
     pub fn available() -> Vec<&'static str> {
-        vec!["Dracula", "One Dark", "Solarized Light"]
+        vec![
+            "Dracula",
+            "One Dark",
+            "Solarized Light",
+            "Solarized Dark",
+            "Gruvbox",
+            "Nord",
+        ]
     }
 }
 
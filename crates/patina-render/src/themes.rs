@@ -0,0 +1,413 @@
+//! Theme files and the registry of available themes.
+//!
+//! A theme file is TOML naming the theme, optionally a `base` theme to inherit
+//! from, and colors keyed by `Theme` field name:
+//!
+//! ```toml
+//! name = "Midnight"
+//! base = "Nord"
+//! is_dark = true
+//! syntax_theme = "Monokai"  # optional: a syntect or user .tmTheme for code
+//!
+//! [colors]
+//! bg_primary = "#101418"
+//! md_heading = "#ffcc66"
+//! ```
+//!
+//! Without a `base`, every color field must be given, except the diagnostic
+//! colors `ui_error` and `ui_warning`, which default to Dracula's.
+
+use crate::{Color, Theme};
+use serde::Deserialize;
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+/// Colors a theme without a base may leave out, taken from the default theme
+const OPTIONAL_COLORS: [&str; 2] = ["ui_error", "ui_warning"];
+
+/// Themes shipped with Patina as theme files
+const BUNDLED: &[(&str, &str)] = &[
+    ("gruvbox.toml", include_str!("../themes/gruvbox.toml")),
+    ("nord.toml", include_str!("../themes/nord.toml")),
+    (
+        "solarized-dark.toml",
+        include_str!("../themes/solarized-dark.toml"),
+    ),
+];
+
+/// Errors loading a theme file
+#[derive(Debug, thiserror::Error)]
+pub enum ThemeError {
+    #[error("{path}: {source}")]
+    Io {
+        path: PathBuf,
+        source: std::io::Error,
+    },
+
+    #[error("{name}: {source}")]
+    Parse {
+        name: String,
+        source: toml::de::Error,
+    },
+
+    #[error("{theme}: unknown base theme '{base}'")]
+    UnknownBase { theme: String, base: String },
+
+    #[error("{theme}: unknown color '{field}'")]
+    UnknownColor { theme: String, field: String },
+
+    #[error("{theme}: invalid color '{value}' for {field} (expected #rrggbb or #rrggbbaa)")]
+    InvalidColor {
+        theme: String,
+        field: String,
+        value: String,
+    },
+
+    #[error("{theme}: missing color '{field}' (set it or add a base theme)")]
+    MissingColor { theme: String, field: String },
+}
+
+/// Parsed contents of a theme file
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ThemeFile {
+    name: String,
+    base: Option<String>,
+    is_dark: Option<bool>,
+    syntax_theme: Option<String>,
+    #[serde(default)]
+    colors: BTreeMap<String, String>,
+}
+
+impl ThemeFile {
+    /// Parse a theme file; `source_name` identifies it in errors
+    fn parse(source: &str, source_name: &str) -> Result<Self, ThemeError> {
+        toml::from_str(source).map_err(|source| ThemeError::Parse {
+            name: source_name.to_string(),
+            source,
+        })
+    }
+
+    /// Build the theme on top of `base` (required when no base is named)
+    fn resolve(&self, base: Option<&Theme>) -> Result<Theme, ThemeError> {
+        let mut theme = match base {
+            Some(base) => base.clone(),
+            None => {
+                if let Some(field) = Theme::COLOR_FIELDS
+                    .iter()
+                    .find(|f| !self.colors.contains_key(**f) && !OPTIONAL_COLORS.contains(f))
+                {
+                    return Err(ThemeError::MissingColor {
+                        theme: self.name.clone(),
+                        field: field.to_string(),
+                    });
+                }
+                Theme::default()
+            }
+        };
+
+        theme.name = self.name.clone();
+        if let Some(is_dark) = self.is_dark {
+            theme.is_dark = is_dark;
+        }
+        // A base's code theme no longer fits once the syntax colors change
+        if self.syntax_theme.is_some() || self.colors.keys().any(|f| f.starts_with("syntax_")) {
+            theme.syntax_theme = self.syntax_theme.clone();
+        }
+        for (field, value) in &self.colors {
+            let color = Color::from_hex(value).ok_or_else(|| ThemeError::InvalidColor {
+                theme: self.name.clone(),
+                field: field.clone(),
+                value: value.clone(),
+            })?;
+            if !theme.set_color(field, color) {
+                return Err(ThemeError::UnknownColor {
+                    theme: self.name.clone(),
+                    field: field.clone(),
+                });
+            }
+        }
+        Ok(theme)
+    }
+}
+
+/// Normalize a theme name for lookup (`One_Dark` and `one dark` match)
+fn normalize(name: &str) -> String {
+    name.trim().to_lowercase().replace(['_', '-'], " ")
+}
+
+/// All themes available by name: built-in, bundled and user-defined
+#[derive(Debug, Clone)]
+pub struct ThemeRegistry {
+    themes: Vec<Theme>,
+}
+
+impl ThemeRegistry {
+    /// Registry with the built-in themes only
+    fn builtin() -> Self {
+        Self {
+            themes: vec![
+                Theme::dracula(),
+                Theme::one_dark(),
+                Theme::solarized_light(),
+            ],
+        }
+    }
+
+    /// Registry with the built-in and bundled themes
+    pub fn bundled() -> Self {
+        let mut registry = Self::builtin();
+        let files = BUNDLED
+            .iter()
+            .map(|(name, source)| ThemeFile::parse(source, name))
+            .collect::<Result<Vec<_>, _>>()
+            .expect("bundled themes parse");
+        let errors = registry.add_files(files);
+        debug_assert!(errors.is_empty(), "bundled themes resolve: {:?}", errors);
+        registry
+    }
+
+    /// Bundled themes plus every `*.toml` theme in `dir`.
+    ///
+    /// Files that fail to load are skipped and returned as errors.
+    pub fn load(dir: &Path) -> (Self, Vec<ThemeError>) {
+        let mut registry = Self::bundled();
+        let mut errors = Vec::new();
+
+        let mut paths: Vec<PathBuf> = match std::fs::read_dir(dir) {
+            Ok(entries) => entries
+                .filter_map(|entry| entry.ok().map(|e| e.path()))
+                .filter(|path| path.extension().is_some_and(|ext| ext == "toml"))
+                .collect(),
+            Err(_) => Vec::new(), // No themes directory
+        };
+        paths.sort();
+
+        let mut files = Vec::new();
+        for path in paths {
+            let parsed = std::fs::read_to_string(&path)
+                .map_err(|source| ThemeError::Io {
+                    path: path.clone(),
+                    source,
+                })
+                .and_then(|source| ThemeFile::parse(&source, &path.display().to_string()));
+            match parsed {
+                Ok(file) => files.push(file),
+                Err(e) => errors.push(e),
+            }
+        }
+
+        errors.extend(registry.add_files(files));
+        (registry, errors)
+    }
+
+    /// Add a theme from theme-file source, resolving its base from this registry
+    pub fn add_source(&mut self, source: &str, source_name: &str) -> Result<(), ThemeError> {
+        let file = ThemeFile::parse(source, source_name)?;
+        self.add_files(vec![file])
+            .into_iter()
+            .next()
+            .map_or(Ok(()), Err)
+    }
+
+    /// Resolve theme files in dependency order, so themes may build on each other
+    fn add_files(&mut self, mut pending: Vec<ThemeFile>) -> Vec<ThemeError> {
+        let mut errors = Vec::new();
+        loop {
+            let ready = pending.iter().position(|file| {
+                file.base
+                    .as_ref()
+                    .is_none_or(|base| self.get(base).is_some())
+            });
+            let Some(idx) = ready else {
+                break;
+            };
+            let file = pending.remove(idx);
+            let base = file.base.as_ref().and_then(|base| self.get(base));
+            match file.resolve(base) {
+                Ok(theme) => self.insert(theme),
+                Err(e) => errors.push(e),
+            }
+        }
+
+        // Whatever is left names a base that never appeared (or a cycle)
+        errors.extend(pending.into_iter().map(|file| ThemeError::UnknownBase {
+            base: file.base.unwrap_or_default(),
+            theme: file.name,
+        }));
+        errors
+    }
+
+    /// Add a theme, replacing any with the same name
+    pub fn insert(&mut self, theme: Theme) {
+        let key = normalize(&theme.name);
+        match self.themes.iter_mut().find(|t| normalize(&t.name) == key) {
+            Some(existing) => *existing = theme,
+            None => self.themes.push(theme),
+        }
+    }
+
+    /// Look up a theme by name
+    pub fn get(&self, name: &str) -> Option<&Theme> {
+        let key = normalize(name);
+        let compact = key.replace(' ', "");
+        self.themes.iter().find(|t| {
+            let candidate = normalize(&t.name);
+            candidate == key || candidate.replace(' ', "") == compact
+        })
+    }
+
+    /// Theme names, in registration order
+    pub fn names(&self) -> Vec<&str> {
+        self.themes.iter().map(|t| t.name.as_str()).collect()
+    }
+}
+
+impl Default for ThemeRegistry {
+    fn default() -> Self {
+        Self::bundled()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_bundled_themes() {
+        let registry = ThemeRegistry::bundled();
+        for name in Theme::available() {
+            assert!(registry.get(name).is_some(), "missing {}", name);
+        }
+        let solarized = registry.get("solarized_dark").unwrap();
+        assert!(solarized.is_dark);
+        // Accents are inherited from Solarized Light
+        assert_eq!(solarized.md_link, Theme::solarized_light().md_link);
+        assert_eq!(solarized.bg_primary, Color::rgb(0, 43, 54));
+        assert_eq!(solarized.syntax_theme.as_deref(), Some("Solarized (dark)"));
+    }
+
+    #[test]
+    fn test_user_theme_inherits_from_user_theme() {
+        let mut registry = ThemeRegistry::bundled();
+        let child = r##"
+            name = "Child"
+            base = "Parent"
+            [colors]
+            md_heading = "#010203"
+        "##;
+        let parent = r##"
+            name = "Parent"
+            base = "Nord"
+            [colors]
+            bg_primary = "#000000"
+        "##;
+        let files = vec![
+            ThemeFile::parse(child, "child.toml").unwrap(),
+            ThemeFile::parse(parent, "parent.toml").unwrap(),
+        ];
+        assert!(registry.add_files(files).is_empty());
+
+        let child = registry.get("child").unwrap();
+        assert_eq!(child.md_heading, Color::rgb(1, 2, 3));
+        assert_eq!(child.bg_primary, Color::rgb(0, 0, 0));
+        assert_eq!(child.md_link, registry.get("Nord").unwrap().md_link);
+    }
+
+    #[test]
+    fn test_syntax_colors_replace_inherited_syntax_theme() {
+        let mut registry = ThemeRegistry::bundled();
+        registry
+            .add_source(
+                "name = \"Warm\"\nbase = \"Solarized Light\"\n[colors]\nmd_heading = \"#ff0000\"",
+                "warm",
+            )
+            .unwrap();
+        registry
+            .add_source(
+                "name = \"Loud\"\nbase = \"Solarized Light\"\n[colors]\nsyntax_keyword = \"#ff0000\"",
+                "loud",
+            )
+            .unwrap();
+        let warm = registry.get("warm").unwrap();
+        assert_eq!(warm.syntax_theme.as_deref(), Some("Solarized (light)"));
+        assert_eq!(registry.get("loud").unwrap().syntax_theme, None);
+    }
+
+    #[test]
+    fn test_theme_errors() {
+        let mut registry = ThemeRegistry::bundled();
+        let err = registry
+            .add_source("name = \"X\"\nbase = \"Nope\"", "x.toml")
+            .unwrap_err();
+        assert!(matches!(err, ThemeError::UnknownBase { .. }));
+
+        let err = registry
+            .add_source(
+                "name = \"X\"\nbase = \"Nord\"\n[colors]\nbg_nope = \"#000000\"",
+                "x",
+            )
+            .unwrap_err();
+        assert!(matches!(err, ThemeError::UnknownColor { .. }));
+
+        let err = registry
+            .add_source(
+                "name = \"X\"\nbase = \"Nord\"\n[colors]\nbg_primary = \"red\"",
+                "x",
+            )
+            .unwrap_err();
+        assert!(matches!(err, ThemeError::InvalidColor { .. }));
+
+        let err = registry.add_source("name = \"X\"", "x").unwrap_err();
+        assert!(matches!(err, ThemeError::MissingColor { .. }));
+
+        // Diagnostic colors may be left out
+        let full: String = Theme::COLOR_FIELDS
+            .iter()
+            .filter(|f| !OPTIONAL_COLORS.contains(f))
+            .map(|f| format!("{} = \"#102030\"\n", f))
+            .collect();
+        registry
+            .add_source(&format!("name = \"Full\"\n[colors]\n{}", full), "full")
+            .unwrap();
+        assert_eq!(
+            registry.get("full").unwrap().ui_error,
+            Theme::default().ui_error
+        );
+
+        let err = registry.add_source("name = ", "x").unwrap_err();
+        assert!(matches!(err, ThemeError::Parse { .. }));
+    }
+
+    #[test]
+    fn test_load_dir_reports_bad_files() {
+        let dir = std::env::temp_dir().join(format!("patina-themes-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(
+            dir.join("mine.toml"),
+            "name = \"Mine\"\nbase = \"Gruvbox\"\n[colors]\nmd_code = \"#112233\"",
+        )
+        .unwrap();
+        std::fs::write(dir.join("broken.toml"), "name = [").unwrap();
+        std::fs::write(dir.join("notes.txt"), "ignored").unwrap();
+
+        let (registry, errors) = ThemeRegistry::load(&dir);
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        assert_eq!(errors.len(), 1);
+        assert_eq!(
+            registry.get("mine").unwrap().md_code,
+            Color::rgb(0x11, 0x22, 0x33)
+        );
+    }
+
+    #[test]
+    fn test_hex_round_trip() {
+        let color = Color::from_hex("#eee8d528").unwrap();
+        assert_eq!(color, Color::rgba(238, 232, 213, 40));
+        assert_eq!(color.to_hex(), "#eee8d528");
+        assert_eq!(Color::from_hex("#ABCDEF").unwrap().to_hex(), "#abcdef");
+        assert!(Color::from_hex("abcdef").is_none());
+        assert!(Color::from_hex("#abcd").is_none());
+    }
+}
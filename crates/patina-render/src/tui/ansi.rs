@@ -0,0 +1,155 @@
+//! Rendered lines as text with ANSI escapes, for printing outside the TUI.
+
+use super::{ColorMode, StyledLine};
+use crate::Theme;
+use ratatui::{
+    buffer::{Buffer, Cell},
+    layout::Rect,
+    style::{Color, Modifier},
+};
+use unicode_width::UnicodeWidthStr;
+
+/// SGR codes of text attributes
+const MODIFIER_CODES: [(Modifier, u8); 9] = [
+    (Modifier::BOLD, 1),
+    (Modifier::DIM, 2),
+    (Modifier::ITALIC, 3),
+    (Modifier::UNDERLINED, 4),
+    (Modifier::SLOW_BLINK, 5),
+    (Modifier::RAPID_BLINK, 6),
+    (Modifier::REVERSED, 7),
+    (Modifier::HIDDEN, 8),
+    (Modifier::CROSSED_OUT, 9),
+];
+
+/// Lines with ANSI styling, colors reduced to what `mode` can display
+pub fn to_ansi(lines: &[StyledLine], mode: ColorMode, theme: &Theme) -> String {
+    let mut out = String::new();
+    for line in lines {
+        let width = line.width().min(u16::MAX as usize) as u16;
+        let mut buf = Buffer::empty(Rect::new(0, 0, width, 1));
+        buf.set_line(0, 0, line, width);
+        mode.apply(&mut buf, theme);
+        write_row(&buf.content, &mut out);
+        out.push('\n');
+    }
+    out
+}
+
+/// Lines as plain text, without trailing spaces
+pub fn to_plain(lines: &[StyledLine]) -> String {
+    let mut out = String::new();
+    for line in lines {
+        let text: String = line
+            .spans
+            .iter()
+            .map(|span| span.content.as_ref())
+            .collect();
+        out.push_str(text.trim_end());
+        out.push('\n');
+    }
+    out
+}
+
+/// Write one row of cells, dropping trailing unstyled blanks
+fn write_row(cells: &[Cell], out: &mut String) {
+    let blank = |cell: &Cell| cell.symbol() == " " && cell.bg == Color::Reset;
+    let end = cells
+        .iter()
+        .rposition(|cell| !blank(cell))
+        .map_or(0, |i| i + 1);
+
+    let mut current: Option<String> = None;
+    let mut skip = 0;
+    for cell in &cells[..end] {
+        // The cells after a wide character are covered by it
+        if skip > 0 {
+            skip -= 1;
+            continue;
+        }
+        let sgr = sgr(cell);
+        if current.as_deref() != Some(sgr.as_str()) {
+            if current.as_ref().is_some_and(|c| !c.is_empty()) {
+                out.push_str("\x1b[0m");
+            }
+            if !sgr.is_empty() {
+                out.push_str(&format!("\x1b[{}m", sgr));
+            }
+            current = Some(sgr);
+        }
+        out.push_str(cell.symbol());
+        skip = cell.symbol().width().saturating_sub(1);
+    }
+    if current.is_some_and(|c| !c.is_empty()) {
+        out.push_str("\x1b[0m");
+    }
+}
+
+/// SGR parameters for a cell's style, empty for the terminal default
+fn sgr(cell: &Cell) -> String {
+    let mut codes: Vec<String> = MODIFIER_CODES
+        .iter()
+        .filter(|(modifier, _)| cell.modifier.contains(*modifier))
+        .map(|(_, code)| code.to_string())
+        .collect();
+    codes.extend(color_code(cell.fg, false));
+    codes.extend(color_code(cell.bg, true));
+    codes.join(";")
+}
+
+fn color_code(color: Color, background: bool) -> Option<String> {
+    let offset = if background { 10 } else { 0 };
+    let basic = |code: u8| Some((code + offset).to_string());
+    match color {
+        Color::Reset => None,
+        Color::Black => basic(30),
+        Color::Red => basic(31),
+        Color::Green => basic(32),
+        Color::Yellow => basic(33),
+        Color::Blue => basic(34),
+        Color::Magenta => basic(35),
+        Color::Cyan => basic(36),
+        Color::Gray => basic(37),
+        Color::DarkGray => basic(90),
+        Color::LightRed => basic(91),
+        Color::LightGreen => basic(92),
+        Color::LightYellow => basic(93),
+        Color::LightBlue => basic(94),
+        Color::LightMagenta => basic(95),
+        Color::LightCyan => basic(96),
+        Color::White => basic(97),
+        Color::Indexed(i) => Some(format!("{};5;{}", 38 + offset, i)),
+        Color::Rgb(r, g, b) => Some(format!("{};2;{};{};{}", 38 + offset, r, g, b)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ratatui::style::{Style, Stylize};
+    use ratatui::text::{Line, Span};
+
+    fn sample() -> Vec<StyledLine> {
+        vec![
+            Line::from(vec![
+                Span::styled("Title", Style::default().fg(Color::Rgb(255, 0, 0)).bold()),
+                Span::raw(" plain   "),
+            ]),
+            Line::from("日本 ok"),
+        ]
+    }
+
+    #[test]
+    fn test_ansi() {
+        let out = to_ansi(&sample(), ColorMode::TrueColor, &Theme::dracula());
+        assert_eq!(out, "\x1b[1;38;2;255;0;0mTitle\x1b[0m plain\n日本 ok\n");
+
+        let out = to_ansi(&sample(), ColorMode::Ansi256, &Theme::dracula());
+        assert!(out.starts_with("\x1b[1;38;5;196mTitle"));
+    }
+
+    #[test]
+    fn test_plain() {
+        assert_eq!(to_plain(&sample()), "Title plain\n日本 ok\n");
+    }
+}
@@ -1,7 +1,11 @@
 //! TUI Application state and main loop.
 
-use crate::Theme;
-use patina_core::Document;
+use super::{
+    ColorMode, Completion, DiagnosticsState, DiagramCache, Jump, JumpList, OutlineState,
+    SourceMapCache, ThemePicker,
+};
+use crate::{code_highlighter, EditorStyle, PreviewOptions, Theme};
+use patina_core::{Document, Highlighter, SyntaxAssets};
 
 /// TUI Application state
 pub struct App {
@@ -9,8 +13,22 @@ pub struct App {
     pub documents: Vec<Document>,
     /// Active document index
     pub active_doc: usize,
-    /// Current theme
+    /// Current theme (change it with `set_theme` to keep code highlighting in sync)
     pub theme: Theme,
+    /// Syntaxes and syntect themes, including user files
+    pub syntax_assets: SyntaxAssets,
+    /// Highlighter for fenced code in the current theme
+    pub code_highlighter: Highlighter,
+    /// Editor rendering options
+    pub style: EditorStyle,
+    /// Preview rendering options
+    pub preview_options: PreviewOptions,
+    /// Diagrams already rendered in the preview
+    pub diagram_cache: DiagramCache,
+    /// Source map of the last preview render
+    pub source_map_cache: SourceMapCache,
+    /// Colors the terminal can display
+    pub color_mode: ColorMode,
     /// Should quit
     pub should_quit: bool,
     /// View mode
@@ -23,6 +41,18 @@ pub struct App {
     pub input_mode: InputMode,
     /// Input prompt state (when in input mode)
     pub input_prompt: Option<InputPrompt>,
+    /// Preview scroll set by scrolling the preview directly (None follows the editor cursor)
+    pub preview_scroll: Option<usize>,
+    /// Outline sidebar state
+    pub outline: OutlineState,
+    /// Lint diagnostics and the panel listing them
+    pub diagnostics: DiagnosticsState,
+    /// Back/forward history of large cursor movements
+    pub jumps: JumpList,
+    /// Theme picker popup, when open
+    pub theme_picker: Option<ThemePicker>,
+    /// Completion popup at the cursor, when open
+    pub completion: Option<Completion>,
 }
 
 /// Editor view modes
@@ -36,6 +66,18 @@ pub enum ViewMode {
     Split,
 }
 
+impl ViewMode {
+    /// Look up a view mode by its config name (`raw`, `preview`/`rendered`, `split`)
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name.to_lowercase().as_str() {
+            "raw" | "editor" => Some(Self::Raw),
+            "preview" | "rendered" => Some(Self::Rendered),
+            "split" => Some(Self::Split),
+            _ => None,
+        }
+    }
+}
+
 /// Input mode for prompts
 #[derive(Debug, Clone, PartialEq)]
 pub enum InputMode {
@@ -45,6 +87,8 @@ pub enum InputMode {
     OpenFile,
     /// Prompting for save path (Save As)
     SaveAs,
+    /// Prompting for a go-to target
+    GoTo,
 }
 
 /// Input prompt state
@@ -61,16 +105,31 @@ pub struct InputPrompt {
 impl App {
     /// Create a new app with an empty document list
     pub fn new() -> Self {
+        let theme = Theme::default();
+        let syntax_assets = SyntaxAssets::default();
         Self {
             documents: Vec::new(),
             active_doc: 0,
-            theme: Theme::default(),
+            code_highlighter: code_highlighter(&theme, &syntax_assets),
+            theme,
+            syntax_assets,
+            style: EditorStyle::default(),
+            preview_options: PreviewOptions::default(),
+            diagram_cache: DiagramCache::new(),
+            source_map_cache: SourceMapCache::new(),
+            color_mode: ColorMode::default(),
             should_quit: false,
             view_mode: ViewMode::Split,
             zen_mode: false,
             status_message: None,
             input_mode: InputMode::Normal,
             input_prompt: None,
+            preview_scroll: None,
+            outline: OutlineState::default(),
+            diagnostics: DiagnosticsState::default(),
+            jumps: JumpList::new(),
+            theme_picker: None,
+            completion: None,
         }
     }
 
@@ -79,6 +138,18 @@ impl App {
         self.documents.iter().any(|doc| doc.is_modified())
     }
 
+    /// Switch theme, rebuilding the code highlighter
+    pub fn set_theme(&mut self, theme: Theme) {
+        self.code_highlighter = code_highlighter(&theme, &self.syntax_assets);
+        self.theme = theme;
+    }
+
+    /// Replace the syntax assets, rebuilding the code highlighter
+    pub fn set_syntax_assets(&mut self, assets: SyntaxAssets) {
+        self.code_highlighter = code_highlighter(&self.theme, &assets);
+        self.syntax_assets = assets;
+    }
+
     /// Set a status message
     pub fn set_status(&mut self, msg: impl Into<String>) {
         self.status_message = Some(msg.into());
@@ -109,6 +180,7 @@ impl App {
     pub fn close_active_document(&mut self) {
         if self.documents.len() > 1 {
             self.documents.remove(self.active_doc);
+            self.jumps.remove_document(self.active_doc);
             if self.active_doc >= self.documents.len() {
                 self.active_doc = self.documents.len() - 1;
             }
@@ -133,6 +205,27 @@ impl App {
         }
     }
 
+    /// Current location, for recording in the jump list
+    pub fn current_jump(&self) -> Jump {
+        Jump {
+            doc: self.active_doc,
+            cursor: self.active_document().cursor,
+        }
+    }
+
+    /// Switch to a remembered location, clamped to the document's current contents
+    pub fn jump_to(&mut self, jump: Jump) {
+        self.active_doc = jump.doc.min(self.documents.len() - 1);
+        let doc = self.active_document_mut();
+        let line = jump.cursor.0.min(doc.buffer.len_lines().saturating_sub(1));
+        let line_len = doc
+            .buffer
+            .line(line)
+            .map(|l| l.trim_end_matches('\n').chars().count())
+            .unwrap_or(0);
+        doc.cursor = (line, jump.cursor.1.min(line_len));
+    }
+
     /// Toggle zen mode
     pub fn toggle_zen_mode(&mut self) {
         self.zen_mode = !self.zen_mode;
@@ -180,6 +273,16 @@ impl App {
         });
     }
 
+    /// Start prompting for a go-to target
+    pub fn start_goto_prompt(&mut self) {
+        self.input_mode = InputMode::GoTo;
+        self.input_prompt = Some(InputPrompt {
+            prompt: "Go to (line[:col], #heading, @symbol): ".to_string(),
+            buffer: String::new(),
+            cursor: 0,
+        });
+    }
+
     /// Cancel the current input prompt
     pub fn cancel_input(&mut self) {
         self.input_mode = InputMode::Normal;
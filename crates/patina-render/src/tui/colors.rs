@@ -0,0 +1,285 @@
+//! Terminal color support and downsampling of truecolor output.
+
+use crate::Theme;
+use ratatui::{
+    buffer::{Buffer, Cell},
+    style::{Color, Modifier},
+};
+
+/// Cube levels of the xterm-256 6x6x6 color cube
+const CUBE_LEVELS: [u8; 6] = [0, 95, 135, 175, 215, 255];
+
+/// The 16 ANSI colors in palette order
+const ANSI_16: [Color; 16] = [
+    Color::Black,
+    Color::Red,
+    Color::Green,
+    Color::Yellow,
+    Color::Blue,
+    Color::Magenta,
+    Color::Cyan,
+    Color::Gray,
+    Color::DarkGray,
+    Color::LightRed,
+    Color::LightGreen,
+    Color::LightYellow,
+    Color::LightBlue,
+    Color::LightMagenta,
+    Color::LightCyan,
+    Color::White,
+];
+
+/// Colors the terminal can display
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ColorMode {
+    /// 24-bit RGB
+    #[default]
+    TrueColor,
+    /// xterm 256-color palette
+    Ansi256,
+    /// The 16 basic ANSI colors
+    Ansi16,
+    /// No colors; emphasis with bold, underline and reverse video only
+    Monochrome,
+}
+
+impl ColorMode {
+    /// Look up a mode by its config name; `auto` detects it from the environment
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name.to_lowercase().as_str() {
+            "auto" => Some(Self::detect()),
+            "truecolor" | "24bit" => Some(Self::TrueColor),
+            "256" | "ansi256" => Some(Self::Ansi256),
+            "16" | "ansi16" => Some(Self::Ansi16),
+            "mono" | "monochrome" | "none" => Some(Self::Monochrome),
+            _ => None,
+        }
+    }
+
+    /// Detect color support from `NO_COLOR`, `COLORTERM` and `TERM`
+    pub fn detect() -> Self {
+        let var = |name| std::env::var(name).ok();
+        Self::from_env(
+            var("NO_COLOR").is_some_and(|v| !v.is_empty()),
+            var("COLORTERM").as_deref(),
+            var("TERM").as_deref(),
+        )
+    }
+
+    /// Color support implied by the given environment
+    fn from_env(no_color: bool, colorterm: Option<&str>, term: Option<&str>) -> Self {
+        if no_color {
+            return Self::Monochrome;
+        }
+        if matches!(colorterm, Some("truecolor" | "24bit")) {
+            return Self::TrueColor;
+        }
+        match term {
+            // Windows consoles don't set TERM and support truecolor
+            None if cfg!(windows) => Self::TrueColor,
+            None | Some("") => Self::Ansi16,
+            Some("dumb") => Self::Monochrome,
+            Some(term)
+                if ["truecolor", "24bit", "direct"]
+                    .iter()
+                    .any(|t| term.contains(t)) =>
+            {
+                Self::TrueColor
+            }
+            Some(term) if term.contains("256color") => Self::Ansi256,
+            Some(_) => Self::Ansi16,
+        }
+    }
+
+    /// Convert a color to one this mode can display
+    pub fn convert(self, color: Color) -> Color {
+        let Color::Rgb(r, g, b) = color else {
+            return color;
+        };
+        match self {
+            Self::TrueColor => color,
+            Self::Ansi256 => Color::Indexed(nearest_256(r, g, b)),
+            Self::Ansi16 => nearest_16(r, g, b),
+            Self::Monochrome => Color::Reset,
+        }
+    }
+
+    /// Downsample every cell of a drawn frame.
+    ///
+    /// In monochrome, highlighted backgrounds (selection, status bar) become
+    /// reverse video, headings bold and links underlined.
+    pub fn apply(self, buf: &mut Buffer, theme: &Theme) {
+        match self {
+            Self::TrueColor => {}
+            Self::Ansi256 | Self::Ansi16 => {
+                for cell in &mut buf.content {
+                    cell.fg = self.convert(cell.fg);
+                    cell.bg = self.convert(cell.bg);
+                    cell.underline_color = self.convert(cell.underline_color);
+                }
+            }
+            Self::Monochrome => {
+                for cell in &mut buf.content {
+                    monochrome(cell, theme);
+                }
+            }
+        }
+    }
+}
+
+/// Replace a cell's colors with text attributes
+fn monochrome(cell: &mut Cell, theme: &Theme) {
+    // Selection wins when a theme reuses its color for another background
+    let plain_backgrounds = [
+        theme.bg_primary,
+        theme.bg_secondary,
+        theme.bg_line_highlight,
+    ]
+    .map(|c| c.to_ratatui());
+    let highlighted = cell.bg == theme.bg_selection.to_ratatui()
+        || (matches!(cell.bg, Color::Rgb(..)) && !plain_backgrounds.contains(&cell.bg));
+    if highlighted {
+        cell.modifier.insert(Modifier::REVERSED);
+    }
+    if cell.fg == theme.md_heading.to_ratatui() {
+        cell.modifier.insert(Modifier::BOLD);
+    } else if cell.fg == theme.md_link.to_ratatui() {
+        cell.modifier.insert(Modifier::UNDERLINED);
+    }
+    cell.fg = Color::Reset;
+    cell.bg = Color::Reset;
+    cell.underline_color = Color::Reset;
+}
+
+/// Squared distance between two RGB colors
+fn distance(a: (u8, u8, u8), b: (u8, u8, u8)) -> u32 {
+    let d = |x: u8, y: u8| (x as i32 - y as i32).pow(2) as u32;
+    d(a.0, b.0) + d(a.1, b.1) + d(a.2, b.2)
+}
+
+/// Closest xterm-256 color, from the color cube or the grayscale ramp.
+///
+/// The first 16 entries are skipped since terminals often redefine them.
+fn nearest_256(r: u8, g: u8, b: u8) -> u8 {
+    let level = |v: u8| {
+        (0..CUBE_LEVELS.len())
+            .min_by_key(|&i| (CUBE_LEVELS[i] as i32 - v as i32).abs())
+            .unwrap_or(0)
+    };
+    let (ri, gi, bi) = (level(r), level(g), level(b));
+    let cube = (CUBE_LEVELS[ri], CUBE_LEVELS[gi], CUBE_LEVELS[bi]);
+    let cube_index = 16 + 36 * ri + 6 * gi + bi;
+
+    // Grayscale ramp 232..=255 covers 8, 18, ..., 238
+    let average = (r as u32 + g as u32 + b as u32) / 3;
+    let gray_step = (average.saturating_sub(3) / 10).min(23);
+    let gray_level = (8 + 10 * gray_step) as u8;
+    let gray_index = 232 + gray_step as usize;
+
+    if distance((r, g, b), (gray_level, gray_level, gray_level)) < distance((r, g, b), cube) {
+        gray_index as u8
+    } else {
+        cube_index as u8
+    }
+}
+
+/// Closest of the 16 ANSI colors.
+///
+/// Palettes differ between terminals, so this keeps the hue rather than
+/// matching RGB values: low-saturation colors map to the grays, others to the
+/// channels above their midpoint, bright when the strongest channel is.
+fn nearest_16(r: u8, g: u8, b: u8) -> Color {
+    let max = r.max(g).max(b);
+    let min = r.min(g).min(b);
+    let index = if max - min < 48 {
+        match (r as u32 + g as u32 + b as u32) / 3 {
+            0..48 => 0,
+            48..144 => 8,
+            144..216 => 7,
+            _ => 15,
+        }
+    } else {
+        let mid = (max as u32 + min as u32) / 2;
+        let on = |v: u8| (v as u32 > mid) as usize;
+        let hue = on(r) | on(g) << 1 | on(b) << 2;
+        hue + if max >= 200 { 8 } else { 0 }
+    };
+    ANSI_16[index]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ratatui::layout::Rect;
+
+    #[test]
+    fn test_detect_from_env() {
+        use ColorMode::*;
+        assert_eq!(
+            ColorMode::from_env(true, Some("truecolor"), None),
+            Monochrome
+        );
+        assert_eq!(
+            ColorMode::from_env(false, Some("24bit"), Some("xterm")),
+            TrueColor
+        );
+        assert_eq!(
+            ColorMode::from_env(false, None, Some("tmux-256color")),
+            Ansi256
+        );
+        assert_eq!(
+            ColorMode::from_env(false, None, Some("xterm-direct")),
+            TrueColor
+        );
+        assert_eq!(ColorMode::from_env(false, None, Some("linux")), Ansi16);
+        assert_eq!(ColorMode::from_env(false, None, Some("dumb")), Monochrome);
+    }
+
+    #[test]
+    fn test_quantize_256() {
+        assert_eq!(nearest_256(0, 0, 0), 16);
+        assert_eq!(nearest_256(255, 255, 255), 231);
+        assert_eq!(nearest_256(255, 0, 0), 196);
+        // Near-grays use the grayscale ramp
+        assert_eq!(nearest_256(40, 42, 54), 236);
+        assert_eq!(nearest_256(128, 128, 128), 244);
+    }
+
+    #[test]
+    fn test_quantize_16() {
+        assert_eq!(nearest_16(40, 42, 54), Color::Black);
+        assert_eq!(nearest_16(248, 248, 242), Color::White);
+        assert_eq!(nearest_16(80, 180, 80), Color::Green);
+        assert_eq!(nearest_16(255, 121, 198), Color::LightMagenta);
+        assert_eq!(nearest_16(139, 233, 253), Color::LightCyan);
+        assert_eq!(
+            ColorMode::Ansi16.convert(Color::Indexed(3)),
+            Color::Indexed(3)
+        );
+    }
+
+    #[test]
+    fn test_monochrome_uses_attributes() {
+        let theme = Theme::default();
+        let mut buf = Buffer::empty(Rect::new(0, 0, 3, 1));
+        buf[(0, 0)].set_fg(theme.md_heading.to_ratatui());
+        buf[(1, 0)].set_bg(theme.bg_selection.to_ratatui());
+        buf[(2, 0)]
+            .set_fg(theme.fg_primary.to_ratatui())
+            .set_bg(theme.bg_primary.to_ratatui());
+        buf[(2, 0)].underline_color = theme.ui_error.to_ratatui();
+
+        let mut ansi = buf.clone();
+        ColorMode::Ansi16.apply(&mut ansi, &theme);
+        assert_eq!(ansi[(2, 0)].underline_color, Color::LightRed);
+
+        ColorMode::Monochrome.apply(&mut buf, &theme);
+
+        assert!(buf[(0, 0)].modifier.contains(Modifier::BOLD));
+        assert!(buf[(1, 0)].modifier.contains(Modifier::REVERSED));
+        assert!(buf[(2, 0)].modifier.is_empty());
+        assert!(buf.content.iter().all(|c| c.fg == Color::Reset
+            && c.bg == Color::Reset
+            && c.underline_color == Color::Reset));
+    }
+}
@@ -0,0 +1,506 @@
+//! Inline completion popup for emoji, heading anchors, paths and footnotes.
+
+use crate::Theme;
+use patina_core::navigation::heading_slugs;
+use patina_core::Document;
+use patina_extensions::EmojiExpander;
+use ratatui::{
+    buffer::Buffer,
+    layout::Rect,
+    style::{Modifier, Style},
+    text::{Line, Span},
+    widgets::{Block, Borders, Clear, Paragraph, Widget},
+};
+use std::path::{Path, PathBuf};
+use unicode_width::UnicodeWidthStr;
+
+/// Most candidates offered at once
+const MAX_ITEMS: usize = 50;
+
+/// Rows of candidates shown in the popup
+const VISIBLE_ITEMS: usize = 8;
+
+/// Shortest emoji query that opens the popup, so `a:b` in prose stays quiet
+const MIN_EMOJI_QUERY: usize = 2;
+
+/// What is being completed
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CompletionKind {
+    /// `:ro` — an emoji shortcode
+    Emoji,
+    /// `](#` — a heading anchor in the document
+    Heading,
+    /// `](./` — a path relative to the document
+    Path,
+    /// `[^` — a footnote label defined in the document
+    Footnote,
+}
+
+/// A candidate in the popup
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CompletionItem {
+    /// Text shown in the list
+    pub label: String,
+    /// Dimmed text after the label, like the emoji or heading title
+    pub detail: String,
+    /// Text that replaces the query when accepted
+    pub insert: String,
+}
+
+/// Open completion popup state
+#[derive(Debug, Clone)]
+pub struct Completion {
+    pub kind: CompletionKind,
+    /// Line being completed
+    pub line: usize,
+    /// Char column where the replaced text starts; it ends at the cursor
+    pub start: usize,
+    /// Text typed so far
+    pub query: String,
+    /// Candidates, best match first
+    pub items: Vec<CompletionItem>,
+    /// Index of the highlighted candidate
+    pub selected: usize,
+}
+
+impl Completion {
+    /// Completion for the text before the cursor, if it ends in a trigger and
+    /// anything matches.
+    ///
+    /// Paths are listed relative to the document's directory, or the current
+    /// directory for an unsaved document.
+    pub fn at_cursor(doc: &Document) -> Option<Self> {
+        let (line, col) = doc.cursor;
+        let text = doc.buffer.line(line)?;
+        let text = text.trim_end_matches('\n');
+        let split = text.char_indices().nth(col).map_or(text.len(), |(i, _)| i);
+        let (before, after) = text.split_at(split);
+        let next = after.chars().next();
+
+        let (kind, query) = trigger(before)?;
+        let start = col - query.chars().count();
+        let items = match kind {
+            CompletionKind::Emoji => emoji_items(query),
+            CompletionKind::Heading => heading_items(doc, query, next != Some(')')),
+            CompletionKind::Footnote => footnote_items(doc, query, next != Some(']')),
+            CompletionKind::Path => {
+                let base = doc
+                    .path
+                    .as_deref()
+                    .and_then(Path::parent)
+                    .map_or_else(|| PathBuf::from("."), Path::to_path_buf);
+                let name_start = query.rfind('/').map_or(0, |i| i + 1);
+                let (dir, name) = query.split_at(name_start);
+                let items = path_items(&base.join(dir), name, next != Some(')'));
+                return (!items.is_empty()).then(|| Self {
+                    kind,
+                    line,
+                    start: start + dir.chars().count(),
+                    query: name.to_string(),
+                    items,
+                    selected: 0,
+                });
+            }
+        };
+        // The emoji query includes its colon
+        let start = if kind == CompletionKind::Emoji {
+            start - 1
+        } else {
+            start
+        };
+
+        (!items.is_empty()).then(|| Self {
+            kind,
+            line,
+            start,
+            query: query.to_string(),
+            items,
+            selected: 0,
+        })
+    }
+
+    /// Move the highlight by `delta`, wrapping around
+    pub fn move_selection(&mut self, delta: isize) {
+        if self.items.is_empty() {
+            return;
+        }
+        let len = self.items.len() as isize;
+        self.selected = (self.selected as isize + delta).rem_euclid(len) as usize;
+    }
+
+    /// The highlighted candidate
+    pub fn selected_item(&self) -> Option<&CompletionItem> {
+        self.items.get(self.selected)
+    }
+}
+
+/// Trigger at the end of `before` and the query typed after it
+fn trigger(before: &str) -> Option<(CompletionKind, &str)> {
+    let ends_query = |c: char| c.is_whitespace() || matches!(c, ']' | ')' | '[' | '(');
+
+    if let Some(i) = before.rfind("[^") {
+        let query = &before[i + 2..];
+        if !query.contains(ends_query) {
+            return Some((CompletionKind::Footnote, query));
+        }
+    }
+    if let Some(i) = before.rfind("](") {
+        let target = &before[i + 2..];
+        if !target.contains(ends_query) {
+            if let Some(query) = target.strip_prefix('#') {
+                return Some((CompletionKind::Heading, query));
+            }
+            if target.starts_with("./") || target.starts_with("../") {
+                return Some((CompletionKind::Path, target));
+            }
+        }
+    }
+
+    let colon = before.rfind(':')?;
+    let query = &before[colon + 1..];
+    let starts_word = before[..colon]
+        .chars()
+        .next_back()
+        .is_none_or(|c| !c.is_alphanumeric() && c != ':');
+    let is_shortcode = query
+        .chars()
+        .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || matches!(c, '_' | '+' | '-'));
+    (starts_word && is_shortcode && query.len() >= MIN_EMOJI_QUERY)
+        .then_some((CompletionKind::Emoji, query))
+}
+
+/// How well `query` matches `candidate` as a subsequence, higher is better.
+///
+/// Matches at the start of words and runs of consecutive matches score
+/// higher, skipped characters cost a little. `None` if it doesn't match.
+pub fn fuzzy_score(query: &str, candidate: &str) -> Option<i64> {
+    let candidate: Vec<char> = candidate.chars().flat_map(char::to_lowercase).collect();
+    let mut score = 0;
+    let mut pos = 0;
+    let mut previous: Option<usize> = None;
+    for q in query.chars().flat_map(char::to_lowercase) {
+        let found = pos + candidate[pos..].iter().position(|&c| c == q)?;
+        score += 10;
+        if found == 0 || matches!(candidate[found - 1], '_' | '-' | '/' | ' ' | '.') {
+            score += 20;
+        }
+        match previous {
+            Some(prev) if prev + 1 == found => score += 15,
+            Some(prev) => score -= (found - prev - 1) as i64,
+            None => score -= found as i64,
+        }
+        previous = Some(found);
+        pos = found + 1;
+    }
+    Some(score)
+}
+
+/// Candidates that match `query`, best first, keyed by the text matched
+fn ranked(
+    query: &str,
+    candidates: impl Iterator<Item = (String, CompletionItem)>,
+) -> Vec<CompletionItem> {
+    let mut scored: Vec<(i64, String, CompletionItem)> = candidates
+        .filter_map(|(key, item)| Some((fuzzy_score(query, &key)?, key, item)))
+        .collect();
+    scored.sort_by(|a, b| {
+        b.0.cmp(&a.0)
+            .then(a.1.len().cmp(&b.1.len()))
+            .then(a.1.cmp(&b.1))
+    });
+    scored
+        .into_iter()
+        .take(MAX_ITEMS)
+        .map(|(_, _, item)| item)
+        .collect()
+}
+
+fn emoji_items(query: &str) -> Vec<CompletionItem> {
+    let candidates = EmojiExpander::all().iter().flat_map(|emoji| {
+        emoji.aliases.iter().map(move |alias| {
+            let item = CompletionItem {
+                label: format!(":{}:", alias),
+                detail: format!("{} {}", emoji.emoji, emoji.description),
+                insert: format!(":{}:", alias),
+            };
+            (alias.to_string(), item)
+        })
+    });
+    ranked(query, candidates)
+}
+
+fn heading_items(doc: &Document, query: &str, close: bool) -> Vec<CompletionItem> {
+    let headings = doc.headings();
+    let candidates = heading_slugs(&headings)
+        .into_iter()
+        .zip(&headings)
+        .map(|(slug, heading)| {
+            let item = CompletionItem {
+                label: format!("#{}", slug),
+                detail: heading.text.clone(),
+                insert: if close {
+                    format!("{})", slug)
+                } else {
+                    slug.clone()
+                },
+            };
+            (slug, item)
+        });
+    ranked(query, candidates)
+}
+
+fn footnote_items(doc: &Document, query: &str, close: bool) -> Vec<CompletionItem> {
+    let mut labels: Vec<String> = Vec::new();
+    for line in doc.buffer.text().lines() {
+        let Some(rest) = line.trim_start().strip_prefix("[^") else {
+            continue;
+        };
+        if let Some((label, _)) = rest.split_once("]:") {
+            if !label.is_empty() && !labels.iter().any(|l| l == label) {
+                labels.push(label.to_string());
+            }
+        }
+    }
+    let candidates = labels.into_iter().map(|label| {
+        let item = CompletionItem {
+            label: format!("[^{}]", label),
+            detail: String::new(),
+            insert: if close {
+                format!("{}]", label)
+            } else {
+                label.clone()
+            },
+        };
+        (label, item)
+    });
+    ranked(query, candidates)
+}
+
+/// Entries of `dir` matching `name`; directories end in `/` so completion can
+/// continue into them
+fn path_items(dir: &Path, name: &str, close: bool) -> Vec<CompletionItem> {
+    let Ok(entries) = std::fs::read_dir(dir) else {
+        return Vec::new();
+    };
+    let candidates = entries.flatten().filter_map(|entry| {
+        let file_name = entry.file_name().to_string_lossy().to_string();
+        // Hidden files only when asked for
+        if file_name.starts_with('.') && !name.starts_with('.') {
+            return None;
+        }
+        let is_dir = entry.file_type().is_ok_and(|t| t.is_dir());
+        let item = CompletionItem {
+            label: if is_dir {
+                format!("{}/", file_name)
+            } else {
+                file_name.clone()
+            },
+            detail: if is_dir { "dir" } else { "file" }.to_string(),
+            insert: match (is_dir, close) {
+                (true, _) => format!("{}/", file_name),
+                (false, true) => format!("{})", file_name),
+                (false, false) => file_name.clone(),
+            },
+        };
+        Some((file_name, item))
+    });
+    ranked(name, candidates)
+}
+
+/// Popup listing completions under the text being completed
+pub struct CompletionWidget<'a> {
+    completion: &'a Completion,
+    theme: &'a Theme,
+    anchor: (u16, u16),
+}
+
+impl<'a> CompletionWidget<'a> {
+    /// Create the popup for `completion`, drawn below the screen cell `anchor`
+    /// (or above it when there is no room below)
+    pub fn new(completion: &'a Completion, theme: &'a Theme, anchor: (u16, u16)) -> Self {
+        Self {
+            completion,
+            theme,
+            anchor,
+        }
+    }
+}
+
+impl<'a> Widget for CompletionWidget<'a> {
+    fn render(self, area: Rect, buf: &mut Buffer) {
+        let items = &self.completion.items;
+        let content_width = items
+            .iter()
+            .map(|item| item.label.width() + item.detail.width() + 3)
+            .max()
+            .unwrap_or(0)
+            .min(60) as u16;
+        let width = (content_width + 2).min(area.width);
+        let height = (items.len().min(VISIBLE_ITEMS) as u16 + 2).min(area.height);
+        if width < 4 || height < 3 {
+            return;
+        }
+
+        let (x, y) = self.anchor;
+        let x = x.min(area.right().saturating_sub(width)).max(area.x);
+        let y = if y + 1 + height <= area.bottom() {
+            y + 1
+        } else {
+            y.saturating_sub(height).max(area.y)
+        };
+        let popup = Rect {
+            x,
+            y,
+            width,
+            height,
+        };
+
+        Clear.render(popup, buf);
+        let block = Block::default()
+            .borders(Borders::ALL)
+            .border_style(Style::default().fg(self.theme.ui_border.to_ratatui()))
+            .style(
+                Style::default()
+                    .bg(self.theme.bg_secondary.to_ratatui())
+                    .fg(self.theme.fg_primary.to_ratatui()),
+            );
+        let inner = block.inner(popup);
+        block.render(popup, buf);
+
+        // Keep the selection in view
+        let visible = inner.height as usize;
+        let skip = (self.completion.selected + 1).saturating_sub(visible);
+        let detail_style = Style::default().fg(self.theme.fg_muted.to_ratatui());
+        let lines: Vec<Line> = items
+            .iter()
+            .enumerate()
+            .skip(skip)
+            .take(visible)
+            .map(|(idx, item)| {
+                let line = Line::from(vec![
+                    Span::raw(format!(" {} ", item.label)),
+                    Span::styled(format!("{} ", item.detail), detail_style),
+                ]);
+                if idx == self.completion.selected {
+                    line.style(
+                        Style::default()
+                            .bg(self.theme.bg_selection.to_ratatui())
+                            .add_modifier(Modifier::BOLD),
+                    )
+                } else {
+                    line
+                }
+            })
+            .collect();
+        Paragraph::new(lines).render(inner, buf);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn complete(text: &str) -> Option<Completion> {
+        let (line, col) = text
+            .lines()
+            .enumerate()
+            .find_map(|(i, l)| l.find('|').map(|c| (i, l[..c].chars().count())))
+            .expect("cursor marker");
+        let mut doc = Document::from_content(&text.replacen('|', "", 1));
+        doc.cursor = (line, col);
+        Completion::at_cursor(&doc)
+    }
+
+    fn labels(completion: &Completion) -> Vec<&str> {
+        completion
+            .items
+            .iter()
+            .map(|item| item.label.as_str())
+            .collect()
+    }
+
+    #[test]
+    fn test_fuzzy_ranking() {
+        assert!(fuzzy_score("rkt", "rocket").is_some());
+        assert_eq!(fuzzy_score("xyz", "rocket"), None);
+        // Prefix beats a scattered match, word starts beat mid-word matches
+        assert!(fuzzy_score("ro", "rocket") > fuzzy_score("ro", "heavy_dollar"));
+        assert!(fuzzy_score("smi", "smile") > fuzzy_score("smi", "sweat_smile"));
+    }
+
+    #[test]
+    fn test_emoji_completion() {
+        let completion = complete("Launch :roc|").unwrap();
+        assert_eq!(completion.kind, CompletionKind::Emoji);
+        assert_eq!(completion.start, 7);
+        assert_eq!(completion.items[0].insert, ":rock:");
+        let rocket = completion
+            .items
+            .iter()
+            .find(|item| item.insert == ":rocket:");
+        assert!(rocket.unwrap().detail.starts_with('🚀'));
+
+        assert!(complete("at 10:30|").is_none());
+        assert!(complete("word:ro|").is_none());
+        assert!(complete(":r|").is_none());
+    }
+
+    #[test]
+    fn test_heading_completion() {
+        let completion = complete("# Intro\n\n## Set Up\n\nSee [setup](#se|\n").unwrap();
+        assert_eq!(completion.kind, CompletionKind::Heading);
+        assert_eq!(labels(&completion), ["#set-up"]);
+        assert_eq!(completion.items[0].insert, "set-up)");
+        assert_eq!(completion.items[0].detail, "Set Up");
+
+        let completion = complete("# Intro\n\n[x](#|)\n").unwrap();
+        assert_eq!(completion.items[0].insert, "intro");
+    }
+
+    #[test]
+    fn test_footnote_completion() {
+        let completion = complete("Text[^|\n\n[^note]: A\n[^other]: B\n").unwrap();
+        assert_eq!(completion.kind, CompletionKind::Footnote);
+        assert_eq!(labels(&completion), ["[^note]", "[^other]"]);
+        assert_eq!(completion.items[0].insert, "note]");
+        let completion = complete("Text[^ot|]\n\n[^note]: A\n[^other]: B\n").unwrap();
+        assert_eq!(completion.items[0].insert, "other");
+    }
+
+    #[test]
+    fn test_path_completion() {
+        let dir = std::env::temp_dir().join(format!("patina-complete-{}", std::process::id()));
+        std::fs::create_dir_all(dir.join("docs")).unwrap();
+        std::fs::write(dir.join("docs/guide.md"), "").unwrap();
+        std::fs::write(dir.join("readme.md"), "").unwrap();
+        std::fs::write(dir.join(".hidden"), "").unwrap();
+
+        let mut doc = Document::from_content("[a](./");
+        doc.path = Some(dir.join("index.md"));
+        doc.cursor = (0, 6);
+        let completion = Completion::at_cursor(&doc).unwrap();
+        assert_eq!(completion.kind, CompletionKind::Path);
+        assert_eq!(completion.start, 6);
+        let mut found = labels(&completion);
+        found.sort();
+        assert_eq!(found, ["docs/", "readme.md"]);
+
+        doc.buffer = patina_core::Buffer::from_text("[a](./docs/gu");
+        doc.cursor = (0, 13);
+        let completion = Completion::at_cursor(&doc).unwrap();
+        assert_eq!(completion.start, 11);
+        assert_eq!(completion.items[0].insert, "guide.md)");
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_selection_wraps() {
+        let mut completion = complete("# A\n# B\n[x](#|\n").unwrap();
+        assert_eq!(completion.items.len(), 2);
+        completion.move_selection(-1);
+        assert_eq!(completion.selected, 1);
+        completion.move_selection(1);
+        assert_eq!(completion.selected_item().unwrap().label, "#a");
+    }
+}
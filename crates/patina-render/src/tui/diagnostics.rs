@@ -0,0 +1,224 @@
+//! Lint diagnostics of the active document and the panel listing them.
+
+use crate::Theme;
+use patina_core::{Diagnostic, Document, Linter, Severity};
+use ratatui::{
+    buffer::Buffer,
+    layout::Rect,
+    style::{Color, Modifier, Style},
+    text::{Line, Span},
+    widgets::{Block, Borders, Paragraph, Widget},
+};
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
+
+/// Theme color of markers and underlines for a severity
+pub fn severity_color(theme: &Theme, severity: Severity) -> Color {
+    match severity {
+        Severity::Error => theme.ui_error,
+        Severity::Warning => theme.ui_warning,
+    }
+    .to_ratatui()
+}
+
+/// Diagnostics panel state, with the diagnostics of the active document
+#[derive(Debug, Clone, Default)]
+pub struct DiagnosticsState {
+    /// Whether the panel is shown
+    pub visible: bool,
+    /// Whether the panel receives key input
+    pub focused: bool,
+    /// Selected index into the diagnostics
+    pub selected: usize,
+    /// Diagnostics found by the last lint, lines in the document body
+    pub items: Vec<Diagnostic>,
+    /// Linter to run, `None` when linting is off
+    linter: Option<Linter>,
+    /// Hash of the text the diagnostics were found in
+    linted: Option<u64>,
+}
+
+impl DiagnosticsState {
+    /// Use `linter` from now on, re-checking on the next refresh
+    pub fn set_linter(&mut self, linter: Option<Linter>) {
+        self.linter = linter;
+        self.linted = None;
+    }
+
+    /// Lint the document again if its text changed since the last refresh
+    pub fn refresh(&mut self, doc: &Document) {
+        let Some(linter) = &self.linter else {
+            self.items.clear();
+            return;
+        };
+        let mut hasher = DefaultHasher::new();
+        doc.buffer.text().hash(&mut hasher);
+        let hash = hasher.finish();
+        if self.linted != Some(hash) {
+            self.items = linter.lint(doc);
+            self.linted = Some(hash);
+            self.selected = self.selected.min(self.items.len().saturating_sub(1));
+        }
+    }
+
+    /// Show and focus the panel, or hide it if it is already focused
+    pub fn toggle(&mut self) {
+        if self.visible && self.focused {
+            self.visible = false;
+            self.focused = false;
+        } else {
+            self.visible = true;
+            self.focused = true;
+        }
+    }
+
+    /// Return focus to the editor, keeping the panel open
+    pub fn unfocus(&mut self) {
+        self.focused = false;
+    }
+
+    /// The selected diagnostic, if there are any
+    pub fn selected_item(&self) -> Option<&Diagnostic> {
+        self.items
+            .get(self.selected.min(self.items.len().saturating_sub(1)))
+    }
+
+    /// Move the selection by `delta`
+    pub fn move_selection(&mut self, delta: isize) {
+        self.selected = self
+            .selected
+            .saturating_add_signed(delta)
+            .min(self.items.len().saturating_sub(1));
+    }
+
+    /// Select the first diagnostic at or after `line` (0-indexed), wrapping around
+    pub fn select_from_line(&mut self, line: usize) {
+        self.selected = self.items.iter().position(|d| d.line > line).unwrap_or(0);
+    }
+
+    /// Number of errors and warnings
+    pub fn counts(&self) -> (usize, usize) {
+        let errors = self
+            .items
+            .iter()
+            .filter(|d| d.severity == Severity::Error)
+            .count();
+        (errors, self.items.len() - errors)
+    }
+}
+
+/// Panel listing the diagnostics of the active document
+pub struct DiagnosticsWidget<'a> {
+    state: &'a DiagnosticsState,
+    theme: &'a Theme,
+}
+
+impl<'a> DiagnosticsWidget<'a> {
+    pub fn new(state: &'a DiagnosticsState, theme: &'a Theme) -> Self {
+        Self { state, theme }
+    }
+}
+
+impl<'a> Widget for DiagnosticsWidget<'a> {
+    fn render(self, area: Rect, buf: &mut Buffer) {
+        let border_color = if self.state.focused {
+            self.theme.md_heading
+        } else {
+            self.theme.ui_border
+        };
+        let (errors, warnings) = self.state.counts();
+        let block = Block::default()
+            .borders(Borders::ALL)
+            .border_style(Style::default().fg(border_color.to_ratatui()))
+            .title(Span::styled(
+                format!(" Problems: {} errors, {} warnings ", errors, warnings),
+                Style::default()
+                    .fg(self.theme.fg_primary.to_ratatui())
+                    .add_modifier(Modifier::BOLD),
+            ));
+        let inner = block.inner(area);
+        block.render(area, buf);
+
+        let items = &self.state.items;
+        if items.is_empty() {
+            let empty = Span::styled(
+                "No problems",
+                Style::default().fg(self.theme.fg_muted.to_ratatui()),
+            );
+            Paragraph::new(Line::from(empty)).render(inner, buf);
+            return;
+        }
+
+        let selected = self.state.selected.min(items.len() - 1);
+        let height = (inner.height as usize).max(1);
+        let skip = (selected + 1).saturating_sub(height);
+        let muted = Style::default().fg(self.theme.fg_muted.to_ratatui());
+
+        let mut lines = Vec::new();
+        for (idx, diagnostic) in items.iter().enumerate().skip(skip).take(height) {
+            let mut line = Line::from(vec![
+                Span::styled(
+                    format!(" {:>5}:{:<4}", diagnostic.line, diagnostic.column),
+                    muted,
+                ),
+                Span::styled(
+                    format!("{:<8}", diagnostic.severity),
+                    Style::default().fg(severity_color(self.theme, diagnostic.severity)),
+                ),
+                Span::styled(
+                    diagnostic.message.clone(),
+                    Style::default().fg(self.theme.fg_primary.to_ratatui()),
+                ),
+                Span::styled(format!("  [{}]", diagnostic.rule), muted),
+            ]);
+            if self.state.focused && idx == selected {
+                line = line.style(Style::default().bg(self.theme.bg_selection.to_ratatui()));
+            }
+            lines.push(line);
+        }
+        Paragraph::new(lines).render(inner, buf);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn state(content: &str) -> DiagnosticsState {
+        let mut state = DiagnosticsState::default();
+        state.set_linter(Some(Linter::new()));
+        state.refresh(&Document::from_content(content));
+        state
+    }
+
+    #[test]
+    fn test_refresh_only_when_text_changes() {
+        let mut state = state("# A\n\n# A\n");
+        assert_eq!(state.items.len(), 1);
+
+        state.items.clear();
+        state.refresh(&Document::from_content("# A\n\n# A\n"));
+        assert!(state.items.is_empty());
+
+        state.refresh(&Document::from_content("# A\n\n### B\n\n# A\n"));
+        assert_eq!(state.items.len(), 2);
+
+        state.set_linter(None);
+        state.refresh(&Document::from_content("# A\n\n# A\n"));
+        assert!(state.items.is_empty());
+    }
+
+    #[test]
+    fn test_selection() {
+        let mut state = state("# A\n\n# A\n\n![](x.png)\n\n# A\n");
+        assert_eq!(state.items.len(), 3);
+        state.select_from_line(3);
+        assert_eq!(state.selected_item().unwrap().line, 5);
+        state.select_from_line(6);
+        assert_eq!(state.selected_item().unwrap().line, 7);
+        state.select_from_line(7);
+        assert_eq!(state.selected, 0);
+        state.move_selection(10);
+        assert_eq!(state.selected, 2);
+    }
+}
@@ -0,0 +1,74 @@
+//! Cache of rendered diagrams for the preview.
+
+use patina_extensions::MermaidRenderer;
+use std::cell::RefCell;
+use std::collections::HashMap;
+
+/// Entries kept before the cache starts over
+const MAX_ENTRIES: usize = 64;
+
+/// Rendered text or error message for a diagram
+pub type DiagramResult = Result<String, String>;
+
+/// Rendered Mermaid diagrams, keyed by source and width.
+///
+/// The preview is redrawn on every frame; this keeps unchanged diagrams from
+/// being laid out again.
+#[derive(Debug, Default)]
+pub struct DiagramCache {
+    entries: RefCell<HashMap<(String, usize), DiagramResult>>,
+}
+
+impl DiagramCache {
+    /// Create an empty cache
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Render a Mermaid diagram no wider than `width`, reusing an earlier result
+    pub fn mermaid(&self, source: &str, width: usize) -> DiagramResult {
+        let key = (source.to_string(), width);
+        if let Some(result) = self.entries.borrow().get(&key) {
+            return result.clone();
+        }
+
+        let result = MermaidRenderer::new()
+            .max_width(width)
+            .render(source)
+            .map_err(|err| err.to_string());
+        let mut entries = self.entries.borrow_mut();
+        if entries.len() >= MAX_ENTRIES {
+            entries.clear();
+        }
+        entries.insert(key, result.clone());
+        result
+    }
+
+    /// Number of cached diagrams
+    pub fn len(&self) -> usize {
+        self.entries.borrow().len()
+    }
+
+    /// Check if the cache is empty
+    pub fn is_empty(&self) -> bool {
+        self.entries.borrow().is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_results_are_cached_per_source_and_width() {
+        let cache = DiagramCache::new();
+        let first = cache.mermaid("graph TD\n  A --> B", 40).unwrap();
+        assert!(first.contains('A'));
+        assert_eq!(cache.mermaid("graph TD\n  A --> B", 40).unwrap(), first);
+        assert_eq!(cache.len(), 1);
+
+        cache.mermaid("graph TD\n  A --> B", 60).unwrap();
+        assert!(cache.mermaid("nonsense", 40).is_err());
+        assert_eq!(cache.len(), 3);
+    }
+}
@@ -1,7 +1,11 @@
 //! Editor widget for TUI.
 
-use crate::Theme;
-use patina_core::Document;
+use super::severity_color;
+use crate::wrap::{LineLayout, WrapOptions};
+use crate::{EditorStyle, Theme};
+use patina_core::highlight::{LineState, TokenKind};
+use patina_core::syntax::LineHighlighter;
+use patina_core::{Diagnostic, Document, Highlighter, SyntaxAssets};
 use ratatui::{
     buffer::Buffer,
     layout::Rect,
@@ -9,12 +13,26 @@ use ratatui::{
     widgets::Widget,
 };
 
+/// Syntect session for the fenced code block currently being drawn; its
+/// state after each line is cached in the document's source highlighter
+struct FenceSession<'h> {
+    /// Line of the opening fence
+    start_line: usize,
+    /// Next line the session expects to highlight
+    next_line: usize,
+    lines: LineHighlighter<'h>,
+}
+
+/// How far back to look for enclosing list items when drawing indent guides
+const GUIDE_SCAN_LINES: usize = 200;
+
 /// Editor widget that renders a document
 pub struct EditorWidget<'a> {
     document: &'a Document,
     theme: &'a Theme,
-    show_line_numbers: bool,
-    soft_wrap: bool,
+    style: EditorStyle,
+    code_highlighter: Option<&'a Highlighter>,
+    diagnostics: &'a [Diagnostic],
 }
 
 impl<'a> EditorWidget<'a> {
@@ -22,31 +40,272 @@ impl<'a> EditorWidget<'a> {
         Self {
             document,
             theme,
-            show_line_numbers: true,
-            soft_wrap: true,
+            style: EditorStyle::default(),
+            code_highlighter: None,
+            diagnostics: &[],
         }
     }
 
+    /// Use all rendering options from `style`
+    pub fn style(mut self, style: &EditorStyle) -> Self {
+        self.style = style.clone();
+        self
+    }
+
+    /// Highlight fenced code with a shared highlighter (built from the theme otherwise)
+    pub fn code_highlighter(mut self, highlighter: &'a Highlighter) -> Self {
+        self.code_highlighter = Some(highlighter);
+        self
+    }
+
+    /// Mark lines with diagnostics in the gutter and underline what they cover
+    pub fn diagnostics(mut self, diagnostics: &'a [Diagnostic]) -> Self {
+        self.diagnostics = diagnostics;
+        self
+    }
+
     pub fn line_numbers(mut self, show: bool) -> Self {
-        self.show_line_numbers = show;
+        self.style.line_numbers = show;
         self
     }
 
     pub fn soft_wrap(mut self, wrap: bool) -> Self {
-        self.soft_wrap = wrap;
+        self.style.soft_wrap = wrap;
         self
     }
-}
 
-impl<'a> Widget for EditorWidget<'a> {
-    fn render(self, area: Rect, buf: &mut Buffer) {
-        let line_number_width = if self.show_line_numbers {
+    pub fn tab_size(mut self, tab_size: usize) -> Self {
+        self.style.tab_size = tab_size;
+        self
+    }
+
+    /// Width of the line number gutter (0 when hidden)
+    pub fn line_number_width(&self) -> u16 {
+        if self.style.line_numbers {
             // Calculate width needed for line numbers
             let max_line = self.document.buffer.len_lines();
             (max_line.to_string().len() + 2) as u16
         } else {
             0
-        };
+        }
+    }
+
+    /// Width available for text when rendered into an area of `area_width`
+    pub fn text_width(&self, area_width: u16) -> usize {
+        area_width.saturating_sub(self.line_number_width()) as usize
+    }
+
+    /// Style for each char of a line, from the markdown source highlighter
+    fn line_styles<'h>(
+        &self,
+        line_idx: usize,
+        line: &str,
+        base: Style,
+        code_highlighter: &'h Highlighter,
+        fence: &mut Option<FenceSession<'h>>,
+    ) -> Vec<Style> {
+        let buffer = &self.document.buffer;
+        let highlight = self
+            .document
+            .source_highlighter()
+            .highlight(buffer, line_idx);
+        let mut styles = vec![base; line.trim_end_matches(['\n', '\r']).chars().count()];
+
+        if let LineState::Fence {
+            lang, start_line, ..
+        } = &highlight.state
+        {
+            let is_body = highlight
+                .tokens
+                .first()
+                .is_some_and(|t| t.kind == TokenKind::CodeBlock);
+            if let (true, Some(syntax)) = (is_body, code_highlighter.syntax_for_language(lang)) {
+                // Resume the session if it is positioned at this line, otherwise
+                // from the nearest cached state, replaying only the lines after it
+                let source = self.document.source_highlighter();
+                let mut session = match fence.take() {
+                    Some(session)
+                        if session.start_line == *start_line && session.next_line == line_idx =>
+                    {
+                        session
+                    }
+                    _ => {
+                        let (next_line, lines) = source
+                            .code_state(*start_line, line_idx)
+                            .and_then(|(line, state)| {
+                                Some((line, code_highlighter.resume(&state)?))
+                            })
+                            .unwrap_or_else(|| (start_line + 1, code_highlighter.start(syntax)));
+                        let mut session = FenceSession {
+                            start_line: *start_line,
+                            next_line,
+                            lines,
+                        };
+                        while session.next_line < line_idx {
+                            let text = buffer.line(session.next_line).unwrap_or_default();
+                            session.lines.highlight(&text);
+                            session.next_line += 1;
+                            source.set_code_state(session.next_line, session.lines.state());
+                        }
+                        session
+                    }
+                };
+
+                let mut col = 0;
+                for (style, text) in session.lines.highlight(line) {
+                    let fg = Color::Rgb(style.foreground.r, style.foreground.g, style.foreground.b);
+                    for _ in text.chars() {
+                        if let Some(slot) = styles.get_mut(col) {
+                            *slot = base.fg(fg);
+                        }
+                        col += 1;
+                    }
+                }
+
+                session.next_line = line_idx + 1;
+                source.set_code_state(session.next_line, session.lines.state());
+                *fence = Some(session);
+                return styles;
+            }
+        }
+
+        for token in &highlight.tokens {
+            let style = self.token_style(token.kind, base);
+            for slot in styles.iter_mut().take(token.end).skip(token.start) {
+                *slot = style;
+            }
+        }
+        styles
+    }
+
+    /// Theme style for a source token
+    fn token_style(&self, kind: TokenKind, base: Style) -> Style {
+        let theme = self.theme;
+        match kind {
+            TokenKind::HeadingMarker | TokenKind::Heading => base
+                .fg(theme.md_heading.to_ratatui())
+                .add_modifier(Modifier::BOLD),
+            TokenKind::Emphasis => base
+                .fg(theme.md_italic.to_ratatui())
+                .add_modifier(Modifier::ITALIC),
+            TokenKind::Strong => base
+                .fg(theme.md_bold.to_ratatui())
+                .add_modifier(Modifier::BOLD),
+            TokenKind::Strikethrough => base
+                .fg(theme.fg_muted.to_ratatui())
+                .add_modifier(Modifier::CROSSED_OUT),
+            TokenKind::CodeSpan | TokenKind::CodeBlock => base.fg(theme.md_code.to_ratatui()),
+            TokenKind::CodeFence => base.fg(theme.fg_muted.to_ratatui()),
+            TokenKind::LinkText => base
+                .fg(theme.md_link.to_ratatui())
+                .add_modifier(Modifier::UNDERLINED),
+            TokenKind::LinkUrl => base.fg(theme.fg_muted.to_ratatui()),
+            TokenKind::ListMarker | TokenKind::TaskMarker => {
+                base.fg(theme.fg_secondary.to_ratatui())
+            }
+            TokenKind::BlockquoteMarker => base.fg(theme.md_blockquote.to_ratatui()),
+            TokenKind::HorizontalRule => base.fg(theme.ui_border.to_ratatui()),
+            TokenKind::FrontmatterDelimiter => base.fg(theme.fg_muted.to_ratatui()),
+            TokenKind::FrontmatterKey => base.fg(theme.syntax_keyword.to_ratatui()),
+            TokenKind::FrontmatterValue => base.fg(theme.syntax_string.to_ratatui()),
+        }
+    }
+
+    /// Wrap options used to lay out lines at the given text width
+    pub fn wrap_options(&self, text_width: usize) -> WrapOptions {
+        WrapOptions::new(text_width)
+            .tab_size(self.style.tab_size)
+            .soft_wrap(self.style.soft_wrap)
+    }
+
+    /// Guide columns for a line: `(all rows, continuation rows only)`.
+    ///
+    /// Every row gets a guide under each enclosing list item's marker; wrapped
+    /// continuation rows also get one under the line's own marker and each `>`.
+    fn indent_guides(&self, line_idx: usize, chars: &[char]) -> (Vec<usize>, Vec<usize>) {
+        let tab_size = self.style.tab_size;
+        let indent = leading_whitespace(chars);
+        let indent_x = display_x(chars, indent, tab_size);
+
+        // Walk up to each less-indented line; list items among them get a guide
+        let mut ancestors = Vec::new();
+        let mut limit = indent_x;
+        for idx in (line_idx.saturating_sub(GUIDE_SCAN_LINES)..line_idx).rev() {
+            if limit == 0 {
+                break;
+            }
+            let line: Vec<char> = self
+                .document
+                .buffer
+                .line(idx)
+                .unwrap_or_default()
+                .chars()
+                .collect();
+            let ws = leading_whitespace(&line);
+            if line[ws..].iter().all(|c| c.is_whitespace()) {
+                continue;
+            }
+            let x = display_x(&line, ws, tab_size);
+            if x < limit {
+                if list_marker_end(&line[ws..]).is_some() {
+                    ancestors.push(x);
+                }
+                limit = x;
+            }
+        }
+
+        // Blockquote markers, then the list marker after them
+        let mut continuation = Vec::new();
+        let mut i = 0;
+        loop {
+            let ws = i + leading_whitespace(&chars[i..]);
+            if chars.get(ws) != Some(&'>') {
+                if list_marker_end(&chars[ws..]).is_some() {
+                    continuation.push(display_x(chars, ws, tab_size));
+                }
+                break;
+            }
+            continuation.push(display_x(chars, ws, tab_size));
+            i = ws + 1;
+        }
+
+        (ancestors, continuation)
+    }
+}
+
+/// Number of leading spaces and tabs
+fn leading_whitespace(chars: &[char]) -> usize {
+    chars.iter().take_while(|c| matches!(c, ' ' | '\t')).count()
+}
+
+/// Display column of char `idx`, expanding tabs
+fn display_x(chars: &[char], idx: usize, tab_size: usize) -> usize {
+    chars[..idx.min(chars.len())]
+        .iter()
+        .fold(0, |x, &c| match c {
+            '\t' => x + tab_size.max(1) - x % tab_size.max(1),
+            c => x + unicode_width::UnicodeWidthChar::width(c).unwrap_or(0),
+        })
+}
+
+/// End of a list marker (`-`, `*`, `+`, `1.` or `1)`) followed by a space
+fn list_marker_end(chars: &[char]) -> Option<usize> {
+    let end = match chars.first() {
+        Some('-' | '*' | '+') => 1,
+        _ => {
+            let digits = chars.iter().take_while(|c| c.is_ascii_digit()).count();
+            if digits == 0 || digits > 9 || !matches!(chars.get(digits), Some('.' | ')')) {
+                return None;
+            }
+            digits + 1
+        }
+    };
+    matches!(chars.get(end), Some(' ' | '\t')).then_some(end)
+}
+
+impl<'a> Widget for EditorWidget<'a> {
+    fn render(self, area: Rect, buf: &mut Buffer) {
+        let line_number_width = self.line_number_width();
 
         let text_area = Rect {
             x: area.x + line_number_width,
@@ -72,6 +331,11 @@ impl<'a> Widget for EditorWidget<'a> {
             self.theme.ui_line_number.b,
         ));
 
+        let current_line_num_style = Style::default().fg(self.theme.fg_primary.to_ratatui());
+        let guide_style = Style::default().fg(self.theme.ui_border.to_ratatui());
+        let line_highlight = Style::default().bg(self.theme.bg_line_highlight.to_ratatui());
+
+        let options = self.wrap_options(text_width);
         let (cursor_line, cursor_col) = self.document.cursor;
         let mut screen_row: u16 = 0;
         let mut doc_line = self.document.scroll_offset;
@@ -79,81 +343,129 @@ impl<'a> Widget for EditorWidget<'a> {
         // Track where cursor should be rendered
         let mut cursor_screen_pos: Option<(u16, u16)> = None;
 
+        // Fenced code is highlighted with syntect, carrying its state across rows
+        let own_highlighter;
+        let code_highlighter = match self.code_highlighter {
+            Some(highlighter) => highlighter,
+            None => {
+                own_highlighter = crate::code_highlighter(self.theme, &SyntaxAssets::default());
+                &own_highlighter
+            }
+        };
+        let mut fence: Option<FenceSession> = None;
+
         while screen_row < text_area.height && doc_line < self.document.buffer.len_lines() {
             let line_content = self.document.buffer.line(doc_line).unwrap_or_default();
-            let line_chars: Vec<char> = line_content.trim_end_matches('\n').chars().collect();
-
-            if self.soft_wrap && !line_chars.is_empty() {
-                // Soft wrap: split line into chunks
-                let chunks: Vec<&[char]> = line_chars.chunks(text_width).collect();
-                let num_chunks = chunks.len().max(1);
-
-                for (chunk_idx, chunk) in chunks.iter().enumerate() {
-                    if screen_row >= text_area.height {
-                        break;
-                    }
+            let layout = LineLayout::new(&line_content, &options);
+            let styles = self.line_styles(
+                doc_line,
+                &line_content,
+                text_style,
+                code_highlighter,
+                &mut fence,
+            );
 
-                    // Line number only on first chunk
-                    if self.show_line_numbers && chunk_idx == 0 {
-                        let num_str = format!(
-                            "{:>width$} ",
-                            doc_line + 1,
-                            width = line_number_width as usize - 2
-                        );
-                        buf.set_string(area.x, area.y + screen_row, &num_str, line_num_style);
-                    }
+            let is_cursor_line = doc_line == cursor_line;
+            let chars: Vec<char> = line_content.chars().collect();
+            let line_diagnostics: Vec<&Diagnostic> = self
+                .diagnostics
+                .iter()
+                .filter(|d| d.line == doc_line + 1)
+                .collect();
+            let (guides, continuation_guides) = if self.style.indent_guides {
+                self.indent_guides(doc_line, &chars)
+            } else {
+                Default::default()
+            };
 
-                    // Render text chunk (convert tabs to visible glyph)
-                    let display: String = chunk
-                        .iter()
-                        .map(|&c| if c == '\t' { '→' } else { c })
-                        .collect();
-                    buf.set_string(text_area.x, text_area.y + screen_row, &display, text_style);
-
-                    // Check if cursor is in this chunk
-                    if doc_line == cursor_line {
-                        let chunk_start = chunk_idx * text_width;
-                        let chunk_end = chunk_start + chunk.len();
-                        if cursor_col >= chunk_start && cursor_col <= chunk_end {
-                            let cursor_x = (cursor_col - chunk_start) as u16;
-                            cursor_screen_pos = Some((cursor_x, screen_row));
-                        }
-                    }
+            if is_cursor_line {
+                let row = layout.row_of_col(cursor_col) as u16;
+                let x = layout.x_of_col(cursor_col).min(text_width - 1) as u16;
+                cursor_screen_pos = Some((x, screen_row + row));
+            }
 
-                    screen_row += 1;
+            for row_idx in 0..layout.row_count() {
+                if screen_row >= text_area.height {
+                    break;
                 }
 
-                // Handle cursor at end of line (past last char)
-                if doc_line == cursor_line && cursor_col >= line_chars.len() {
-                    let last_chunk_idx = num_chunks.saturating_sub(1);
-                    let chunk_start = last_chunk_idx * text_width;
-                    let cursor_x = (cursor_col - chunk_start) as u16;
-                    if cursor_x < text_width as u16 {
-                        cursor_screen_pos =
-                            Some((cursor_x, (screen_row - 1).min(text_area.height - 1)));
-                    }
+                if is_cursor_line && self.style.highlight_line {
+                    let row_area = Rect::new(area.x, area.y + screen_row, area.width, 1);
+                    buf.set_style(row_area, line_highlight);
                 }
-            } else {
-                // No wrap or empty line
-                if self.show_line_numbers {
+
+                // Line number only on first row; relative numbers count from the cursor line
+                if self.style.line_numbers && row_idx == 0 {
+                    let number = if self.style.relative_line_numbers && !is_cursor_line {
+                        doc_line.abs_diff(cursor_line)
+                    } else {
+                        doc_line + 1
+                    };
                     let num_str = format!(
                         "{:>width$} ",
-                        doc_line + 1,
+                        number,
                         width = line_number_width as usize - 2
                     );
-                    buf.set_string(area.x, area.y + screen_row, &num_str, line_num_style);
+                    let style = if is_cursor_line {
+                        current_line_num_style
+                    } else {
+                        line_num_style
+                    };
+                    buf.set_string(area.x, area.y + screen_row, &num_str, style);
+
+                    // The most severe diagnostic marks the line after its number
+                    if let Some(severity) = line_diagnostics.iter().map(|d| d.severity).max() {
+                        buf.set_string(
+                            area.x + line_number_width - 2,
+                            area.y + screen_row,
+                            "●",
+                            Style::default().fg(severity_color(self.theme, severity)),
+                        );
+                    }
                 }
 
-                let display: String = line_chars
-                    .iter()
-                    .take(text_width)
-                    .map(|&c| if c == '\t' { '→' } else { c })
-                    .collect();
-                buf.set_string(text_area.x, text_area.y + screen_row, &display, text_style);
+                for (col, x, glyph) in layout.row_glyphs(row_idx) {
+                    if x >= text_width {
+                        break;
+                    }
+                    let mut style = styles.get(col).copied().unwrap_or(text_style);
+                    let underline = line_diagnostics
+                        .iter()
+                        .filter(|d| (d.column.saturating_sub(1)..d.end_column).contains(&col))
+                        .map(|d| d.severity)
+                        .max();
+                    if let Some(severity) = underline {
+                        style = style
+                            .add_modifier(Modifier::UNDERLINED)
+                            .underline_color(severity_color(self.theme, severity));
+                    }
+                    buf.set_stringn(
+                        text_area.x + x as u16,
+                        text_area.y + screen_row,
+                        glyph,
+                        text_width - x,
+                        style,
+                    );
+                }
 
-                // Cursor position for non-wrapped line
-                if doc_line == cursor_line && cursor_col < text_width {
-                    cursor_screen_pos = Some((cursor_col as u16, screen_row));
+                // Guides only go in blank cells of the row's indentation
+                let row_indent = match row_idx {
+                    0 => display_x(&chars, leading_whitespace(&chars), self.style.tab_size),
+                    _ => layout.rows()[row_idx].indent,
+                };
+                let row_guides: &[usize] = if row_idx == 0 {
+                    &[]
+                } else {
+                    &continuation_guides
+                };
+                for &x in guides.iter().chain(row_guides) {
+                    if x >= row_indent || x >= text_width {
+                        continue;
+                    }
+                    let cell = &mut buf[(text_area.x + x as u16, text_area.y + screen_row)];
+                    if cell.symbol() == " " {
+                        cell.set_symbol("│").set_style(guide_style);
+                    }
                 }
 
                 screen_row += 1;
@@ -171,3 +483,144 @@ impl<'a> Widget for EditorWidget<'a> {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn render(doc: &Document, style: &EditorStyle, width: u16, height: u16) -> Buffer {
+        let theme = Theme::default();
+        let area = Rect::new(0, 0, width, height);
+        let mut buf = Buffer::empty(area);
+        EditorWidget::new(doc, &theme)
+            .style(style)
+            .render(area, &mut buf);
+        buf
+    }
+
+    fn row_text(buf: &Buffer, y: u16) -> String {
+        (0..buf.area.width)
+            .map(|x| buf[(x, y)].symbol().to_string())
+            .collect()
+    }
+
+    #[test]
+    fn test_fence_resumes_from_cached_state() {
+        let mut content = String::from("```rust\n/* long\n");
+        for _ in 0..50 {
+            content.push_str("still comment\n");
+        }
+        content.push_str("*/ let x = 1;\n```\n");
+        let theme = Theme::default();
+        let highlighter = crate::code_highlighter(&theme, &SyntaxAssets::default());
+        let draw = |doc: &Document| {
+            let area = Rect::new(0, 0, 30, 3);
+            let mut buf = Buffer::empty(area);
+            EditorWidget::new(doc, &theme)
+                .code_highlighter(&highlighter)
+                .render(area, &mut buf);
+            buf
+        };
+
+        let mut doc = Document::from_content(&content);
+        doc.scroll_offset = 51;
+        let cold = draw(&doc);
+        assert!(doc.source_highlighter().code_state(0, 52).is_some());
+
+        // A second frame resumes at the first visible line
+        let warm = draw(&doc);
+        assert_eq!(cold, warm);
+        assert_eq!(doc.source_highlighter().code_state(0, 51).unwrap().0, 51);
+
+        doc.invalidate_from_line(10);
+        assert_eq!(doc.source_highlighter().code_state(0, 52).unwrap().0, 10);
+    }
+
+    #[test]
+    fn test_relative_line_numbers() {
+        let mut doc = Document::from_content("a\nb\nc\nd");
+        doc.cursor = (2, 0);
+        let style = EditorStyle {
+            relative_line_numbers: true,
+            ..EditorStyle::default()
+        };
+        let buf = render(&doc, &style, 10, 4);
+        assert!(row_text(&buf, 0).starts_with("2 "));
+        assert!(row_text(&buf, 1).starts_with("1 "));
+        assert!(row_text(&buf, 2).starts_with("3 "));
+        assert!(row_text(&buf, 3).starts_with("1 "));
+    }
+
+    #[test]
+    fn test_current_line_highlight() {
+        let mut doc = Document::from_content("one\ntwo");
+        doc.cursor = (1, 0);
+        let theme = Theme::default();
+        let highlight = theme.bg_line_highlight.to_ratatui();
+
+        let buf = render(&doc, &EditorStyle::default(), 10, 2);
+        assert_eq!(buf[(8, 1)].bg, highlight);
+        assert_ne!(buf[(8, 0)].bg, highlight);
+
+        let style = EditorStyle {
+            highlight_line: false,
+            ..EditorStyle::default()
+        };
+        let buf = render(&doc, &style, 10, 2);
+        assert_ne!(buf[(8, 1)].bg, highlight);
+    }
+
+    #[test]
+    fn test_diagnostic_markers() {
+        let doc = Document::from_content("ok\nbad line");
+        let diagnostics = [Diagnostic::warning(2, 5, "rule", "bad".to_string()).until(8)];
+        let theme = Theme::default();
+        let area = Rect::new(0, 0, 14, 2);
+        let mut buf = Buffer::empty(area);
+        EditorWidget::new(&doc, &theme)
+            .diagnostics(&diagnostics)
+            .render(area, &mut buf);
+
+        assert_eq!(row_text(&buf, 0), "1  ok         ");
+        assert_eq!(row_text(&buf, 1), "2● bad line   ");
+        assert_eq!(buf[(1, 1)].fg, theme.ui_warning.to_ratatui());
+        // Text starts after the 3-column gutter; "line" is underlined
+        let underlined: Vec<bool> = (3..12)
+            .map(|x| buf[(x, 1)].modifier.contains(Modifier::UNDERLINED))
+            .collect();
+        assert_eq!(
+            underlined,
+            [false, false, false, false, true, true, true, true, false]
+        );
+        assert_eq!(buf[(7, 1)].underline_color, theme.ui_warning.to_ratatui());
+
+        // A diagnostic at column 0 is treated as column 1 rather than underflowing
+        let diagnostics = [Diagnostic::error(1, 0, "rule", "bad".to_string()).until(1)];
+        EditorWidget::new(&doc, &theme)
+            .diagnostics(&diagnostics)
+            .render(area, &mut buf);
+        assert!(buf[(3, 0)].modifier.contains(Modifier::UNDERLINED));
+    }
+
+    #[test]
+    fn test_indent_guides_under_parent_items() {
+        let doc = Document::from_content("- a\n  - b\n    - c\n> quoted text that wraps");
+        let style = EditorStyle {
+            line_numbers: false,
+            ..EditorStyle::default()
+        };
+        let buf = render(&doc, &style, 14, 6);
+        assert_eq!(row_text(&buf, 1), "│ - b         ");
+        assert_eq!(row_text(&buf, 2), "│ │ - c       ");
+        // Continuation rows of a blockquote keep a guide under the `>`
+        assert!(row_text(&buf, 3).starts_with("> quoted"));
+        assert!(row_text(&buf, 4).starts_with("│ "));
+
+        let style = EditorStyle {
+            indent_guides: false,
+            ..style
+        };
+        let buf = render(&doc, &style, 14, 6);
+        assert_eq!(row_text(&buf, 2), "    - c       ");
+    }
+}
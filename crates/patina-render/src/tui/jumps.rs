@@ -0,0 +1,140 @@
+//! Back/forward jump list across open documents.
+
+/// Maximum number of remembered jumps
+const MAX_JUMPS: usize = 100;
+
+/// A remembered cursor location
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Jump {
+    /// Index of the document in the app's tab list
+    pub doc: usize,
+    /// Cursor position (line, column)
+    pub cursor: (usize, usize),
+}
+
+/// History of large cursor movements, navigable like a browser's back/forward
+#[derive(Debug, Clone, Default)]
+pub struct JumpList {
+    entries: Vec<Jump>,
+    /// Position in `entries`; equal to `entries.len()` when not navigating
+    index: usize,
+}
+
+impl JumpList {
+    /// Create an empty jump list
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Record the location being jumped away from, dropping any forward history
+    pub fn record(&mut self, from: Jump) {
+        self.entries.truncate(self.index);
+        if self.entries.last() != Some(&from) {
+            self.entries.push(from);
+        }
+        if self.entries.len() > MAX_JUMPS {
+            self.entries.remove(0);
+        }
+        self.index = self.entries.len();
+    }
+
+    /// Step back, remembering `current` so forward can return to it
+    pub fn back(&mut self, current: Jump) -> Option<Jump> {
+        if self.index == self.entries.len() {
+            self.record(current);
+            self.index = self.entries.len() - 1;
+        }
+        if self.index == 0 {
+            return None;
+        }
+        self.index -= 1;
+        Some(self.entries[self.index])
+    }
+
+    /// Step forward after going back
+    pub fn forward(&mut self) -> Option<Jump> {
+        if self.index + 1 >= self.entries.len() {
+            return None;
+        }
+        self.index += 1;
+        Some(self.entries[self.index])
+    }
+
+    /// Forget jumps into a closed document and shift later document indices
+    pub fn remove_document(&mut self, doc: usize) {
+        let before_index = self.entries[..self.index]
+            .iter()
+            .filter(|j| j.doc == doc)
+            .count();
+        self.entries.retain(|j| j.doc != doc);
+        for jump in &mut self.entries {
+            if jump.doc > doc {
+                jump.doc -= 1;
+            }
+        }
+        self.index -= before_index;
+    }
+
+    /// Number of remembered jumps
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// Check if no jumps are remembered
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn at(doc: usize, line: usize) -> Jump {
+        Jump {
+            doc,
+            cursor: (line, 0),
+        }
+    }
+
+    #[test]
+    fn test_back_and_forward() {
+        let mut jumps = JumpList::new();
+        jumps.record(at(0, 1));
+        jumps.record(at(0, 50));
+
+        assert_eq!(jumps.back(at(1, 7)), Some(at(0, 50)));
+        assert_eq!(jumps.back(at(0, 50)), Some(at(0, 1)));
+        assert_eq!(jumps.back(at(0, 1)), None);
+        assert_eq!(jumps.forward(), Some(at(0, 50)));
+        assert_eq!(jumps.forward(), Some(at(1, 7)));
+        assert_eq!(jumps.forward(), None);
+    }
+
+    #[test]
+    fn test_record_drops_forward_history() {
+        let mut jumps = JumpList::new();
+        jumps.record(at(0, 1));
+        jumps.record(at(0, 2));
+        jumps.back(at(0, 3));
+        jumps.back(at(0, 2));
+
+        jumps.record(at(0, 1));
+        assert_eq!(jumps.len(), 1);
+        assert_eq!(jumps.forward(), None);
+        assert_eq!(jumps.back(at(0, 9)), Some(at(0, 1)));
+    }
+
+    #[test]
+    fn test_remove_document() {
+        let mut jumps = JumpList::new();
+        jumps.record(at(0, 1));
+        jumps.record(at(1, 2));
+        jumps.record(at(2, 3));
+
+        jumps.remove_document(1);
+        assert_eq!(jumps.len(), 2);
+        assert_eq!(jumps.back(at(0, 0)), Some(at(1, 3)));
+        assert_eq!(jumps.back(at(1, 3)), Some(at(0, 1)));
+    }
+}
@@ -0,0 +1,232 @@
+//! Minimap widget showing the shape of the whole document.
+
+use crate::Theme;
+use patina_core::highlight::{LineState, TokenKind};
+use patina_core::Document;
+use ratatui::{buffer::Buffer, layout::Rect, style::Style, widgets::Widget};
+use unicode_width::UnicodeWidthChar;
+
+/// Text columns represented by one braille dot
+const COLUMNS_PER_DOT: usize = 4;
+
+/// Braille dot bits, indexed by `[dot_row][dot_col]`
+const DOT_BITS: [[u32; 2]; 4] = [[0x01, 0x08], [0x02, 0x10], [0x04, 0x20], [0x40, 0x80]];
+
+/// What a source line contains, for coloring
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+enum LineKind {
+    Text,
+    Code,
+    Heading,
+}
+
+/// Compact braille rendering of the document with the viewport marked
+pub struct MinimapWidget<'a> {
+    document: &'a Document,
+    theme: &'a Theme,
+    tab_size: usize,
+    /// First visible line and number of visible lines in the editor
+    viewport: (usize, usize),
+}
+
+impl<'a> MinimapWidget<'a> {
+    /// Create a minimap for `document`
+    pub fn new(document: &'a Document, theme: &'a Theme) -> Self {
+        Self {
+            document,
+            theme,
+            tab_size: 4,
+            viewport: (document.scroll_offset, 0),
+        }
+    }
+
+    pub fn tab_size(mut self, tab_size: usize) -> Self {
+        self.tab_size = tab_size;
+        self
+    }
+
+    /// Mark `line_count` lines starting at `first_line` as the visible viewport
+    pub fn viewport(mut self, first_line: usize, line_count: usize) -> Self {
+        self.viewport = (first_line, line_count);
+        self
+    }
+
+    /// Source lines covered by one minimap row of a minimap `height` rows tall
+    pub fn lines_per_row(&self, height: u16) -> usize {
+        lines_per_row(self.document.buffer.len_lines(), height)
+    }
+
+    /// Source line shown at `row` of a minimap `height` rows tall
+    pub fn line_at(&self, row: u16, height: u16) -> usize {
+        let line = row as usize * self.lines_per_row(height);
+        line.min(self.document.buffer.len_lines().saturating_sub(1))
+    }
+
+    /// Classify a line and mark which dot columns contain text
+    fn scan_line(&self, line_idx: usize, dot_columns: usize) -> (LineKind, Vec<bool>) {
+        let buffer = &self.document.buffer;
+        let line = buffer.line(line_idx).unwrap_or_default();
+        let highlight = self
+            .document
+            .source_highlighter()
+            .highlight(buffer, line_idx);
+
+        let kind = if highlight
+            .tokens
+            .iter()
+            .any(|t| t.kind == TokenKind::HeadingMarker)
+        {
+            LineKind::Heading
+        } else if matches!(highlight.state, LineState::Fence { .. })
+            || highlight
+                .tokens
+                .iter()
+                .any(|t| matches!(t.kind, TokenKind::CodeFence | TokenKind::CodeBlock))
+        {
+            LineKind::Code
+        } else {
+            LineKind::Text
+        };
+
+        let mut dots = vec![false; dot_columns];
+        let mut x = 0;
+        for ch in line.trim_end_matches('\n').chars() {
+            let width = if ch == '\t' {
+                self.tab_size - x % self.tab_size.max(1)
+            } else {
+                ch.width().unwrap_or(0)
+            };
+            if !ch.is_whitespace() {
+                if let Some(dot) = dots.get_mut(x / COLUMNS_PER_DOT) {
+                    *dot = true;
+                }
+            }
+            x += width;
+        }
+        (kind, dots)
+    }
+}
+
+/// Lines per minimap row: four dot rows per cell, compressed to fit `height`
+fn lines_per_row(total_lines: usize, height: u16) -> usize {
+    let dot_rows = (height as usize * 4).max(1);
+    4 * total_lines.div_ceil(dot_rows).max(1)
+}
+
+impl<'a> Widget for MinimapWidget<'a> {
+    fn render(self, area: Rect, buf: &mut Buffer) {
+        if area.width == 0 || area.height == 0 {
+            return;
+        }
+
+        let total_lines = self.document.buffer.len_lines();
+        let per_row = self.lines_per_row(area.height);
+        let per_dot = per_row / 4;
+        let dot_columns = area.width as usize * 2;
+        let (first_visible, visible) = self.viewport;
+        let last_visible = first_visible + visible.max(1);
+
+        let base = Style::default().bg(self.theme.bg_secondary.to_ratatui());
+        let viewport_style = Style::default().bg(self.theme.bg_line_highlight.to_ratatui());
+
+        for row in 0..area.height {
+            let first_line = row as usize * per_row;
+            let in_viewport = first_line < last_visible && first_line + per_row > first_visible;
+            let row_style = if in_viewport { viewport_style } else { base };
+
+            let mut cells = vec![0u32; area.width as usize];
+            let mut kind = LineKind::Text;
+            // The first line of each dot row stands for the rest, so a frame
+            // scans as many lines however long the document is
+            for (dot_row, bits) in DOT_BITS.iter().enumerate() {
+                let line_idx = first_line + dot_row * per_dot;
+                if line_idx >= total_lines {
+                    break;
+                }
+                let (line_kind, dots) = self.scan_line(line_idx, dot_columns);
+                kind = kind.max(line_kind);
+                for (dot_col, _) in dots.iter().enumerate().filter(|(_, &set)| set) {
+                    cells[dot_col / 2] |= bits[dot_col % 2];
+                }
+            }
+
+            let fg = match kind {
+                LineKind::Heading => self.theme.md_heading,
+                LineKind::Code => self.theme.md_code,
+                LineKind::Text => self.theme.fg_muted,
+            };
+            for (x, bits) in cells.into_iter().enumerate() {
+                let glyph = char::from_u32(0x2800 + bits).unwrap_or(' ');
+                buf.set_string(
+                    area.x + x as u16,
+                    area.y + row,
+                    glyph.to_string(),
+                    row_style.fg(fg.to_ratatui()),
+                );
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_lines_per_row_compresses_long_documents() {
+        // Short documents use one line per dot row
+        assert_eq!(lines_per_row(10, 20), 4);
+        assert_eq!(lines_per_row(80, 20), 4);
+        // Longer documents pack more lines into each dot
+        assert_eq!(lines_per_row(81, 20), 8);
+        assert_eq!(lines_per_row(1000, 10), 100);
+    }
+
+    #[test]
+    fn test_long_documents_sample_one_line_per_dot() {
+        // 800 lines in 10 rows: every 20th line is drawn
+        let lines: Vec<&str> = (0..800)
+            .map(|i| if i % 20 == 0 { "x" } else { "        y" })
+            .collect();
+        let doc = Document::from_content(&lines.join("\n"));
+        let theme = Theme::default();
+        let area = Rect::new(0, 0, 2, 10);
+        let mut buf = Buffer::empty(area);
+        MinimapWidget::new(&doc, &theme).render(area, &mut buf);
+
+        let left = char::from_u32(0x2800 + 0x01 + 0x02 + 0x04 + 0x40).unwrap();
+        for row in 0..10 {
+            assert_eq!(buf.cell((0, row)).unwrap().symbol(), left.to_string());
+            assert_eq!(buf.cell((1, row)).unwrap().symbol(), "\u{2800}");
+        }
+    }
+
+    #[test]
+    fn test_line_at_is_clamped() {
+        let doc = Document::from_content("a\nb\nc");
+        let theme = Theme::default();
+        let minimap = MinimapWidget::new(&doc, &theme);
+        assert_eq!(minimap.line_at(0, 10), 0);
+        assert_eq!(minimap.line_at(5, 10), 2);
+    }
+
+    #[test]
+    fn test_render_colors_headings_and_marks_viewport() {
+        let doc = Document::from_content("# Title\n\ntext here\n\n```\ncode\n```\n");
+        let theme = Theme::default();
+        let area = Rect::new(0, 0, 4, 3);
+        let mut buf = Buffer::empty(area);
+        MinimapWidget::new(&doc, &theme)
+            .viewport(0, 4)
+            .render(area, &mut buf);
+
+        let first = buf.cell((0, 0)).unwrap();
+        assert_ne!(first.symbol(), "\u{2800}");
+        assert_eq!(first.fg, theme.md_heading.to_ratatui());
+        assert_eq!(first.bg, theme.bg_line_highlight.to_ratatui());
+
+        let second = buf.cell((0, 1)).unwrap();
+        assert_eq!(second.fg, theme.md_code.to_ratatui());
+        assert_eq!(second.bg, theme.bg_secondary.to_ratatui());
+    }
+}
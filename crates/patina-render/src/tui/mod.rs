@@ -1,18 +1,36 @@
 //! TUI rendering backend using ratatui.
 
+mod ansi;
 mod app;
+mod colors;
+mod completion;
+mod diagnostics;
+mod diagrams;
 mod editor;
+mod jumps;
+mod minimap;
+mod outline;
+mod picker;
 mod preview;
 mod renderer;
 mod widgets;
 
+pub use ansi::{to_ansi, to_plain};
 pub use app::{App, InputMode, InputPrompt, ViewMode};
+pub use colors::ColorMode;
+pub use completion::{fuzzy_score, Completion, CompletionItem, CompletionKind, CompletionWidget};
+pub use diagnostics::{severity_color, DiagnosticsState, DiagnosticsWidget};
+pub use diagrams::{DiagramCache, DiagramResult};
 pub use editor::EditorWidget;
-pub use preview::PreviewWidget;
-pub use renderer::{MarkdownRenderer, StyledLine};
+pub use jumps::{Jump, JumpList};
+pub use minimap::MinimapWidget;
+pub use outline::{OutlineState, OutlineWidget};
+pub use picker::{ThemePicker, ThemePickerWidget};
+pub use preview::{PreviewWidget, SourceMapCache};
+pub use renderer::{MarkdownRenderer, SourceMap, StyledLine};
 
 use crossterm::{
-    event::{self, Event, KeyEvent},
+    event::{self, DisableMouseCapture, EnableMouseCapture, Event, KeyEvent},
     execute,
     terminal::{disable_raw_mode, enable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen},
 };
@@ -23,7 +41,7 @@ use std::io;
 pub fn init_terminal() -> io::Result<Terminal<CrosstermBackend<io::Stdout>>> {
     enable_raw_mode()?;
     let mut stdout = io::stdout();
-    execute!(stdout, EnterAlternateScreen)?;
+    execute!(stdout, EnterAlternateScreen, EnableMouseCapture)?;
     let backend = CrosstermBackend::new(stdout);
     Terminal::new(backend)
 }
@@ -31,7 +49,11 @@ pub fn init_terminal() -> io::Result<Terminal<CrosstermBackend<io::Stdout>>> {
 /// Restore the terminal to normal mode
 pub fn restore_terminal(terminal: &mut Terminal<CrosstermBackend<io::Stdout>>) -> io::Result<()> {
     disable_raw_mode()?;
-    execute!(terminal.backend_mut(), LeaveAlternateScreen)?;
+    execute!(
+        terminal.backend_mut(),
+        LeaveAlternateScreen,
+        DisableMouseCapture
+    )?;
     terminal.show_cursor()?;
     Ok(())
 }
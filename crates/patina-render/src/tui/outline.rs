@@ -0,0 +1,258 @@
+//! Outline sidebar listing the document's headings.
+
+use crate::Theme;
+use patina_core::parser::Heading;
+use ratatui::{
+    buffer::Buffer,
+    layout::Rect,
+    style::{Modifier, Style},
+    text::{Line, Span},
+    widgets::{Block, Borders, Paragraph, Widget},
+};
+
+/// Outline sidebar state
+#[derive(Debug, Clone, Default)]
+pub struct OutlineState {
+    /// Whether the sidebar is shown
+    pub visible: bool,
+    /// Whether the sidebar receives key input
+    pub focused: bool,
+    /// Filter typed while focused
+    pub filter: String,
+    /// Selected index into the filtered headings
+    pub selected: usize,
+}
+
+impl OutlineState {
+    /// Show and focus the outline, or hide it if it is already focused
+    pub fn toggle(&mut self) {
+        if self.visible && self.focused {
+            self.visible = false;
+            self.focused = false;
+        } else {
+            self.visible = true;
+            self.focused = true;
+        }
+        self.filter.clear();
+    }
+
+    /// Return focus to the editor, keeping the sidebar open
+    pub fn unfocus(&mut self) {
+        self.focused = false;
+        self.filter.clear();
+    }
+
+    /// Headings matching the filter (case-insensitive substring)
+    pub fn matches<'h>(&self, headings: &'h [Heading]) -> Vec<&'h Heading> {
+        let filter = self.filter.to_lowercase();
+        headings
+            .iter()
+            .filter(|h| h.text.to_lowercase().contains(&filter))
+            .collect()
+    }
+
+    /// The selected heading, if any heading matches
+    pub fn selected_heading<'h>(&self, headings: &'h [Heading]) -> Option<&'h Heading> {
+        let matches = self.matches(headings);
+        matches
+            .get(self.selected.min(matches.len().saturating_sub(1)))
+            .copied()
+    }
+
+    /// Move the selection by `delta` within the filtered headings
+    pub fn move_selection(&mut self, headings: &[Heading], delta: isize) {
+        let count = self.matches(headings).len();
+        self.selected = self
+            .selected
+            .saturating_add_signed(delta)
+            .min(count.saturating_sub(1));
+    }
+
+    /// Select the section containing `cursor_line` (0-indexed)
+    pub fn select_section(&mut self, headings: &[Heading], cursor_line: usize) {
+        self.selected = section_at(&self.matches(headings), cursor_line).unwrap_or(0);
+    }
+
+    /// Append a character to the filter
+    pub fn push_filter(&mut self, c: char) {
+        self.filter.push(c);
+        self.selected = 0;
+    }
+
+    /// Remove the last character of the filter
+    pub fn pop_filter(&mut self) {
+        self.filter.pop();
+        self.selected = 0;
+    }
+}
+
+/// Index of the last heading at or above `cursor_line` (0-indexed)
+pub fn section_at(headings: &[&Heading], cursor_line: usize) -> Option<usize> {
+    headings
+        .iter()
+        .rposition(|h| h.line.saturating_sub(1) <= cursor_line)
+}
+
+/// Sidebar widget showing the heading tree
+pub struct OutlineWidget<'a> {
+    headings: &'a [Heading],
+    state: &'a OutlineState,
+    theme: &'a Theme,
+    cursor_line: usize,
+}
+
+impl<'a> OutlineWidget<'a> {
+    /// Create an outline for `headings` with the editor cursor on `cursor_line` (0-indexed)
+    pub fn new(
+        headings: &'a [Heading],
+        state: &'a OutlineState,
+        theme: &'a Theme,
+        cursor_line: usize,
+    ) -> Self {
+        Self {
+            headings,
+            state,
+            theme,
+            cursor_line,
+        }
+    }
+}
+
+impl<'a> Widget for OutlineWidget<'a> {
+    fn render(self, area: Rect, buf: &mut Buffer) {
+        let border_color = if self.state.focused {
+            self.theme.md_heading
+        } else {
+            self.theme.ui_border
+        };
+        let block = Block::default()
+            .borders(Borders::ALL)
+            .border_style(Style::default().fg(border_color.to_ratatui()))
+            .title(Span::styled(
+                " Outline ",
+                Style::default()
+                    .fg(self.theme.fg_primary.to_ratatui())
+                    .add_modifier(Modifier::BOLD),
+            ));
+        let inner = block.inner(area);
+        block.render(area, buf);
+
+        let mut lines = Vec::new();
+        if self.state.focused && !self.state.filter.is_empty() {
+            lines.push(Line::from(vec![
+                Span::styled("/ ", Style::default().fg(self.theme.fg_muted.to_ratatui())),
+                Span::styled(
+                    self.state.filter.clone(),
+                    Style::default().fg(self.theme.fg_primary.to_ratatui()),
+                ),
+            ]));
+        }
+
+        let matches = self.state.matches(self.headings);
+        if matches.is_empty() {
+            lines.push(Line::from(Span::styled(
+                "No headings",
+                Style::default().fg(self.theme.fg_muted.to_ratatui()),
+            )));
+        }
+
+        let min_level = matches.iter().map(|h| h.level).min().unwrap_or(1);
+        let current = section_at(&matches, self.cursor_line);
+        let selected = self
+            .state
+            .focused
+            .then(|| self.state.selected.min(matches.len().saturating_sub(1)));
+
+        // Keep the selection (or the current section) in view
+        let height = (inner.height as usize).saturating_sub(lines.len()).max(1);
+        let anchor = selected.or(current).unwrap_or(0);
+        let skip = (anchor + 1).saturating_sub(height);
+
+        for (idx, heading) in matches.iter().enumerate().skip(skip).take(height) {
+            let indent = "  ".repeat(heading.level.saturating_sub(min_level) as usize);
+            let mut style = Style::default().fg(if heading.level <= min_level {
+                self.theme.md_heading.to_ratatui()
+            } else {
+                self.theme.fg_secondary.to_ratatui()
+            });
+            if Some(idx) == current {
+                style = style
+                    .fg(self.theme.fg_primary.to_ratatui())
+                    .add_modifier(Modifier::BOLD);
+            }
+            if Some(idx) == selected {
+                style = style.bg(self.theme.bg_selection.to_ratatui());
+            }
+            let marker = if Some(idx) == current { "▸ " } else { "  " };
+            lines.push(Line::from(Span::styled(
+                format!("{}{}{}", marker, indent, heading.text),
+                style,
+            )));
+        }
+
+        Paragraph::new(lines).render(inner, buf);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn headings() -> Vec<Heading> {
+        [
+            (1, "Intro", 1),
+            (2, "Setup", 5),
+            (2, "Usage", 12),
+            (1, "FAQ", 20),
+        ]
+        .into_iter()
+        .map(|(level, text, line)| Heading {
+            level,
+            text: text.to_string(),
+            line,
+        })
+        .collect()
+    }
+
+    #[test]
+    fn test_section_at() {
+        let headings = headings();
+        let all: Vec<&Heading> = headings.iter().collect();
+        assert_eq!(section_at(&all, 0), Some(0));
+        assert_eq!(section_at(&all, 4), Some(1));
+        assert_eq!(section_at(&all, 15), Some(2));
+        assert_eq!(section_at(&all, 100), Some(3));
+        assert_eq!(section_at(&all[1..], 0), None);
+    }
+
+    #[test]
+    fn test_filter_is_case_insensitive() {
+        let headings = headings();
+        let mut state = OutlineState::default();
+        state.push_filter('U');
+        let names: Vec<_> = state.matches(&headings).iter().map(|h| &h.text).collect();
+        assert_eq!(names, ["Setup", "Usage"]);
+    }
+
+    #[test]
+    fn test_selection_is_clamped() {
+        let headings = headings();
+        let mut state = OutlineState::default();
+        state.move_selection(&headings, 10);
+        assert_eq!(state.selected_heading(&headings).unwrap().text, "FAQ");
+        state.move_selection(&headings, -10);
+        assert_eq!(state.selected_heading(&headings).unwrap().text, "Intro");
+    }
+
+    #[test]
+    fn test_toggle_focuses_then_hides() {
+        let mut state = OutlineState::default();
+        state.toggle();
+        assert!(state.visible && state.focused);
+        state.unfocus();
+        state.toggle();
+        assert!(state.visible && state.focused);
+        state.toggle();
+        assert!(!state.visible);
+    }
+}
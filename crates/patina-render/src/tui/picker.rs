@@ -0,0 +1,156 @@
+//! Theme picker popup.
+
+use crate::Theme;
+use ratatui::{
+    buffer::Buffer,
+    layout::Rect,
+    style::{Modifier, Style},
+    text::{Line, Span},
+    widgets::{Block, Borders, Clear, Paragraph, Widget},
+};
+
+/// Theme picker state; the highlighted theme is previewed live
+#[derive(Debug, Clone)]
+pub struct ThemePicker {
+    /// Theme names to choose from
+    pub names: Vec<String>,
+    /// Index of the highlighted name
+    pub selected: usize,
+    /// Theme in use when the picker opened, restored on cancel
+    pub original: Theme,
+}
+
+impl ThemePicker {
+    /// Open the picker with the current theme highlighted
+    pub fn new(names: Vec<String>, current: &Theme) -> Self {
+        let selected = names
+            .iter()
+            .position(|name| name.eq_ignore_ascii_case(&current.name))
+            .unwrap_or(0);
+        Self {
+            names,
+            selected,
+            original: current.clone(),
+        }
+    }
+
+    /// Move the highlight by `delta`, wrapping around
+    pub fn move_selection(&mut self, delta: isize) {
+        if self.names.is_empty() {
+            return;
+        }
+        let len = self.names.len() as isize;
+        self.selected = (self.selected as isize + delta).rem_euclid(len) as usize;
+    }
+
+    /// Name of the highlighted theme
+    pub fn selected_name(&self) -> Option<&str> {
+        self.names.get(self.selected).map(String::as_str)
+    }
+}
+
+/// Centered popup listing themes
+pub struct ThemePickerWidget<'a> {
+    picker: &'a ThemePicker,
+    theme: &'a Theme,
+}
+
+impl<'a> ThemePickerWidget<'a> {
+    /// Create the popup, drawn in `theme` (the theme being previewed)
+    pub fn new(picker: &'a ThemePicker, theme: &'a Theme) -> Self {
+        Self { picker, theme }
+    }
+}
+
+impl<'a> Widget for ThemePickerWidget<'a> {
+    fn render(self, area: Rect, buf: &mut Buffer) {
+        let width = self
+            .picker
+            .names
+            .iter()
+            .map(|name| name.chars().count() as u16 + 6)
+            .max()
+            .unwrap_or(0)
+            .max(24)
+            .min(area.width);
+        let height = (self.picker.names.len() as u16 + 2).min(area.height);
+        let popup = Rect {
+            x: area.x + (area.width - width) / 2,
+            y: area.y + (area.height - height) / 2,
+            width,
+            height,
+        };
+
+        Clear.render(popup, buf);
+        let block = Block::default()
+            .borders(Borders::ALL)
+            .border_style(Style::default().fg(self.theme.md_heading.to_ratatui()))
+            .style(
+                Style::default()
+                    .bg(self.theme.bg_primary.to_ratatui())
+                    .fg(self.theme.fg_primary.to_ratatui()),
+            )
+            .title(Span::styled(
+                " Theme ",
+                Style::default().add_modifier(Modifier::BOLD),
+            ));
+        let inner = block.inner(popup);
+        block.render(popup, buf);
+
+        // Keep the selection in view
+        let visible = inner.height as usize;
+        let skip = (self.picker.selected + 1).saturating_sub(visible);
+        let lines: Vec<Line> = self
+            .picker
+            .names
+            .iter()
+            .enumerate()
+            .skip(skip)
+            .take(visible)
+            .map(|(idx, name)| {
+                if idx == self.picker.selected {
+                    Line::from(Span::styled(
+                        format!("▸ {}", name),
+                        Style::default()
+                            .bg(self.theme.bg_selection.to_ratatui())
+                            .add_modifier(Modifier::BOLD),
+                    ))
+                } else {
+                    Line::from(format!("  {}", name))
+                }
+            })
+            .collect();
+        Paragraph::new(lines).render(inner, buf);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn names() -> Vec<String> {
+        ["Dracula", "Nord", "Gruvbox"]
+            .iter()
+            .map(|s| s.to_string())
+            .collect()
+    }
+
+    #[test]
+    fn test_opens_on_current_theme() {
+        let current = Theme {
+            name: "nord".to_string(),
+            ..Theme::default()
+        };
+        let picker = ThemePicker::new(names(), &current);
+        assert_eq!(picker.selected_name(), Some("Nord"));
+    }
+
+    #[test]
+    fn test_selection_wraps() {
+        let mut picker = ThemePicker::new(names(), &Theme::default());
+        picker.move_selection(-1);
+        assert_eq!(picker.selected_name(), Some("Gruvbox"));
+        picker.move_selection(2);
+        assert_eq!(picker.selected_name(), Some("Nord"));
+    }
+}
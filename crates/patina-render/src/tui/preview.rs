@@ -3,8 +3,8 @@
 
 //! Preview widget for displaying rendered Markdown
 
-use comrak::{parse_document, Arena, Options};
-use patina_core::Document;
+use comrak::Arena;
+use patina_core::{Document, Highlighter};
 use ratatui::{
     buffer::Buffer,
     layout::Rect,
@@ -12,15 +12,53 @@ use ratatui::{
     text::{Line, Span},
     widgets::{Block, Borders, Paragraph, Widget, Wrap},
 };
+use std::cell::RefCell;
 
-use super::MarkdownRenderer;
-use crate::Theme;
+use super::{DiagramCache, MarkdownRenderer, SourceMap};
+use crate::{PreviewOptions, Theme};
+
+/// What a source map was rendered from: buffer revision, width and options
+type SourceMapKey = (u64, u16, PreviewOptions);
+
+/// Source map of the last rendered preview.
+///
+/// Looking up preview positions while scrolling would otherwise render the
+/// whole document again, diagrams and math included.
+#[derive(Debug, Default)]
+pub struct SourceMapCache {
+    entry: RefCell<Option<(SourceMapKey, SourceMap)>>,
+}
+
+impl SourceMapCache {
+    /// Create an empty cache
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn get(&self, key: &SourceMapKey) -> Option<SourceMap> {
+        let entry = self.entry.borrow();
+        entry
+            .as_ref()
+            .filter(|(cached, _)| cached == key)
+            .map(|(_, map)| map.clone())
+    }
+
+    fn set(&self, key: SourceMapKey, map: &SourceMap) {
+        *self.entry.borrow_mut() = Some((key, map.clone()));
+    }
+}
 
 /// Preview widget that renders Markdown content
 pub struct PreviewWidget<'a> {
     document: &'a Document,
     theme: &'a Theme,
     scroll_offset: usize,
+    /// Source line (1-indexed) to keep in view, and how many rows to show above it
+    follow: Option<(usize, usize)>,
+    code_highlighter: Option<&'a Highlighter>,
+    options: PreviewOptions,
+    diagram_cache: Option<&'a DiagramCache>,
+    source_map_cache: Option<&'a SourceMapCache>,
 }
 
 impl<'a> PreviewWidget<'a> {
@@ -30,7 +68,71 @@ impl<'a> PreviewWidget<'a> {
             document,
             theme,
             scroll_offset,
+            follow: None,
+            code_highlighter: None,
+            options: PreviewOptions::default(),
+            diagram_cache: None,
+            source_map_cache: None,
+        }
+    }
+
+    /// Highlight fenced code with a shared highlighter (built from the theme otherwise)
+    pub fn code_highlighter(mut self, highlighter: &'a Highlighter) -> Self {
+        self.code_highlighter = Some(highlighter);
+        self
+    }
+
+    /// Set which extensions are rendered
+    pub fn options(mut self, options: PreviewOptions) -> Self {
+        self.options = options;
+        self
+    }
+
+    /// Reuse diagrams rendered by earlier frames
+    pub fn diagram_cache(mut self, cache: &'a DiagramCache) -> Self {
+        self.diagram_cache = Some(cache);
+        self
+    }
+
+    /// Keep the source map of each render, and look it up instead of
+    /// rendering when it's still current
+    pub fn source_map_cache(mut self, cache: &'a SourceMapCache) -> Self {
+        self.source_map_cache = Some(cache);
+        self
+    }
+
+    /// Scroll so the block at `source_line` sits `rows_above` rows from the top,
+    /// overriding the fixed scroll offset
+    pub fn follow_source(mut self, source_line: usize, rows_above: usize) -> Self {
+        self.follow = Some((source_line, rows_above));
+        self
+    }
+
+    /// Map between preview lines and source lines for a widget of the given outer width
+    pub fn source_map(&self, area_width: u16) -> SourceMap {
+        let width = area_width.saturating_sub(2);
+        let cached = self
+            .source_map_cache
+            .and_then(|cache| cache.get(&self.source_map_key(width)));
+        cached.unwrap_or_else(|| self.render_lines(width).1)
+    }
+
+    fn source_map_key(&self, width: u16) -> SourceMapKey {
+        (self.document.buffer.revision(), width, self.options.clone())
+    }
+
+    /// Render frontmatter and content together with their source map
+    fn render_lines(&self, width: u16) -> (Vec<Line<'static>>, SourceMap) {
+        let mut lines = self.render_frontmatter(width);
+        let frontmatter_len = lines.len();
+
+        let (content, mut map) = self.render_content_with_map(width);
+        lines.extend(content);
+        map.prepend(frontmatter_len);
+        if let Some(cache) = self.source_map_cache {
+            cache.set(self.source_map_key(width), &map);
         }
+        (lines, map)
     }
 
     /// Render frontmatter as styled lines
@@ -84,26 +186,26 @@ impl<'a> PreviewWidget<'a> {
     }
 
     /// Get or render the markdown content
+    #[cfg(test)]
     fn render_content(&self, width: u16) -> Vec<Line<'static>> {
+        self.render_content_with_map(width).0
+    }
+
+    /// Render the markdown content, recording the source line of each rendered line
+    fn render_content_with_map(&self, width: u16) -> (Vec<Line<'static>>, SourceMap) {
         // Create arena for parsing (arena must outlive the AST)
         let arena = Arena::new();
-        let mut options = Options::default();
-
-        // Enable GitHub Flavored Markdown extensions
-        options.extension.strikethrough = true;
-        options.extension.table = true;
-        options.extension.autolink = true;
-        options.extension.tasklist = true;
-        options.extension.footnotes = true;
-        options.extension.description_lists = true;
-
-        // Parse the markdown
-        let text = self.document.buffer.text();
-        let root = parse_document(&arena, &text, &options);
+        let root = self.document.parse(&arena);
 
         // Render using MarkdownRenderer
-        let renderer = MarkdownRenderer::new(self.theme, width);
-        renderer.render(root)
+        let mut renderer = MarkdownRenderer::new(self.theme, width).options(self.options.clone());
+        if let Some(highlighter) = self.code_highlighter {
+            renderer = renderer.code_highlighter(highlighter);
+        }
+        if let Some(cache) = self.diagram_cache {
+            renderer = renderer.diagram_cache(cache);
+        }
+        renderer.render_with_source_map(root)
     }
 }
 
@@ -127,13 +229,15 @@ impl<'a> Widget for PreviewWidget<'a> {
         block.render(area, buf);
 
         // Collect all lines: frontmatter + content
-        let mut all_lines = Vec::new();
-
-        // Add frontmatter if present
-        all_lines.extend(self.render_frontmatter(inner.width));
+        let (mut all_lines, map) = self.render_lines(inner.width);
 
-        // Add markdown content
-        all_lines.extend(self.render_content(inner.width));
+        // Follow the editor if requested, otherwise use the fixed offset
+        let scroll_offset = match self.follow {
+            Some((source_line, rows_above)) => {
+                map.preview_line(source_line).saturating_sub(rows_above)
+            }
+            None => self.scroll_offset,
+        };
 
         // Handle empty document
         if all_lines.is_empty() {
@@ -146,7 +250,7 @@ impl<'a> Widget for PreviewWidget<'a> {
         // Apply scroll offset
         let visible_lines: Vec<_> = all_lines
             .into_iter()
-            .skip(self.scroll_offset)
+            .skip(scroll_offset)
             .take(inner.height as usize)
             .collect();
 
@@ -209,4 +313,38 @@ mod tests {
         assert_eq!(widget1.scroll_offset, 0);
         assert_eq!(widget2.scroll_offset, 5);
     }
+
+    #[test]
+    fn test_source_map_cache() {
+        let mut doc = Document::from_content("# One\n\ntext\n");
+        let theme = Theme::default();
+        let cache = SourceMapCache::new();
+        let widget = PreviewWidget::new(&doc, &theme, 0).source_map_cache(&cache);
+        let map = widget.source_map(40);
+        assert_eq!(cache.get(&widget.source_map_key(38)), Some(map.clone()));
+        assert_eq!(widget.source_map(40), map);
+        assert!(cache.get(&widget.source_map_key(50)).is_none());
+
+        // Edits render a fresh map
+        doc.buffer.insert(0, "Intro\n\n");
+        let widget = PreviewWidget::new(&doc, &theme, 0).source_map_cache(&cache);
+        assert!(cache.get(&widget.source_map_key(38)).is_none());
+        assert_eq!(widget.source_map(40).preview_line(3), 2);
+    }
+
+    #[test]
+    fn test_source_map_includes_frontmatter() {
+        let doc = Document::from_content("---\ntitle: Test\n---\n\n# Hello\n\nBody\n");
+        let theme = Theme::default();
+        let widget = PreviewWidget::new(&doc, &theme, 0);
+
+        let fm_len = widget.render_frontmatter(78).len();
+        let map = widget.source_map(80);
+        assert_eq!(map.len(), fm_len + widget.render_content(78).len());
+        // Content blocks map past the frontmatter box
+        assert_eq!(map.preview_line(1), fm_len);
+        assert_eq!(map.preview_line(3), fm_len + 3);
+        assert_eq!(map.source_line(fm_len + 3), Some(3));
+        assert_eq!(map.source_line(0), Some(0));
+    }
 }
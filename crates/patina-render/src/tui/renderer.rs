@@ -5,12 +5,15 @@
 //!
 //! Converts comrak AST to styled ratatui text that can be displayed in the preview pane.
 
-use comrak::nodes::{AstNode, ListType, NodeValue};
+use comrak::nodes::{AstNode, ListType, NodeMath, NodeValue};
 use ratatui::style::{Modifier, Style};
 use ratatui::text::{Line, Span};
 
-use crate::Theme;
+use super::{DiagramCache, DiagramResult};
+use crate::{code_highlighter, PreviewOptions, Theme};
+use patina_core::{Highlighter, SyntaxAssets};
 use patina_extensions::{EmojiExpander, LatexRenderer};
+use std::cell::OnceCell;
 
 /// A styled line for rendering (using owned data)
 pub type StyledLine = Line<'static>;
@@ -21,6 +24,15 @@ pub struct MarkdownRenderer<'a> {
     width: u16,
     latex_renderer: LatexRenderer,
     emoji_expander: EmojiExpander,
+    /// Highlighter for fenced code, shared with the caller
+    code_highlighter: Option<&'a Highlighter>,
+    /// Highlighter built from the theme when none is shared
+    own_highlighter: OnceCell<Highlighter>,
+    options: PreviewOptions,
+    /// Rendered diagrams, shared with the caller
+    diagram_cache: Option<&'a DiagramCache>,
+    /// Diagram cache for this renderer when none is shared
+    own_diagram_cache: DiagramCache,
 }
 
 /// Rendering context for tracking state during AST walk
@@ -31,6 +43,87 @@ struct RenderContext {
     list_number: usize,
     /// Whether we're inside a list
     in_list: bool,
+    /// Source line (1-indexed) of each emitted line, filled innermost node first
+    source_lines: Vec<Option<usize>>,
+}
+
+impl RenderContext {
+    /// Assign `source_line` to emitted lines in `start..end` that have no source yet
+    fn map_lines(&mut self, start: usize, end: usize, source_line: usize) {
+        if self.source_lines.len() < end {
+            self.source_lines.resize(end, None);
+        }
+        for slot in &mut self.source_lines[start..end] {
+            slot.get_or_insert(source_line);
+        }
+    }
+
+    /// Assign `source_line` to a single emitted line
+    fn set_line(&mut self, idx: usize, source_line: usize) {
+        if self.source_lines.len() <= idx {
+            self.source_lines.resize(idx + 1, None);
+        }
+        self.source_lines[idx] = Some(source_line);
+    }
+}
+
+/// Mapping between rendered preview lines and source lines.
+///
+/// Source lines are 1-indexed, as reported by comrak's `sourcepos`.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct SourceMap {
+    /// Source line for each preview line
+    lines: Vec<usize>,
+}
+
+impl SourceMap {
+    /// Build a map from per-line sources; unmapped lines inherit the line above
+    fn from_lines(sources: &[Option<usize>], len: usize) -> Self {
+        let mut last = 1;
+        let lines = (0..len)
+            .map(|i| {
+                if let Some(Some(line)) = sources.get(i) {
+                    last = *line;
+                }
+                last
+            })
+            .collect();
+        Self { lines }
+    }
+
+    /// Number of preview lines
+    pub fn len(&self) -> usize {
+        self.lines.len()
+    }
+
+    /// Check if the map is empty
+    pub fn is_empty(&self) -> bool {
+        self.lines.is_empty()
+    }
+
+    /// Prepend `count` preview lines (e.g. frontmatter) that map to line 0,
+    /// before the first source line
+    pub fn prepend(&mut self, count: usize) {
+        self.lines.splice(0..0, std::iter::repeat_n(0, count));
+    }
+
+    /// Source line rendered at a preview line
+    pub fn source_line(&self, preview_line: usize) -> Option<usize> {
+        self.lines.get(preview_line).copied()
+    }
+
+    /// First preview line of the closest block starting at or before `source_line`
+    pub fn preview_line(&self, source_line: usize) -> usize {
+        // Preview lines follow the source, so their source lines never decrease
+        let end = self.lines.partition_point(|&line| line <= source_line);
+        match end.checked_sub(1) {
+            Some(last) => {
+                let closest = self.lines[last];
+                self.lines.partition_point(|&line| line < closest)
+            }
+            None => 0,
+        }
+    }
 }
 
 impl<'a> MarkdownRenderer<'a> {
@@ -41,20 +134,58 @@ impl<'a> MarkdownRenderer<'a> {
             width,
             latex_renderer: LatexRenderer::new(),
             emoji_expander: EmojiExpander::new(),
+            code_highlighter: None,
+            own_highlighter: OnceCell::new(),
+            options: PreviewOptions::default(),
+            diagram_cache: None,
+            own_diagram_cache: DiagramCache::new(),
         }
     }
 
+    /// Set which extensions are rendered
+    pub fn options(mut self, options: PreviewOptions) -> Self {
+        self.options = options;
+        self
+    }
+
+    /// Reuse diagrams rendered by earlier frames
+    pub fn diagram_cache(mut self, cache: &'a DiagramCache) -> Self {
+        self.diagram_cache = Some(cache);
+        self
+    }
+
+    /// Highlight fenced code with `highlighter` instead of one built from the theme
+    pub fn code_highlighter(mut self, highlighter: &'a Highlighter) -> Self {
+        self.code_highlighter = Some(highlighter);
+        self
+    }
+
+    /// Highlighter for fenced code
+    fn highlighter(&self) -> &Highlighter {
+        self.code_highlighter.unwrap_or_else(|| {
+            self.own_highlighter
+                .get_or_init(|| code_highlighter(self.theme, &SyntaxAssets::default()))
+        })
+    }
+
     /// Render a markdown AST to styled lines
     pub fn render(&self, root: &'a AstNode<'a>) -> Vec<StyledLine> {
+        self.render_with_source_map(root).0
+    }
+
+    /// Render a markdown AST, also returning the source line of each rendered line
+    pub fn render_with_source_map(&self, root: &'a AstNode<'a>) -> (Vec<StyledLine>, SourceMap) {
         let mut lines = Vec::new();
         let mut context = RenderContext {
             list_depth: 0,
             list_number: 0,
             in_list: false,
+            source_lines: Vec::new(),
         };
 
         self.render_node(root, &mut lines, &mut context);
-        lines
+        let map = SourceMap::from_lines(&context.source_lines, lines.len());
+        (lines, map)
     }
 
     /// Render a single AST node and its children
@@ -65,6 +196,8 @@ impl<'a> MarkdownRenderer<'a> {
         context: &mut RenderContext,
     ) {
         let ast = node.data.borrow();
+        let start_idx = lines.len();
+        let source_line = ast.sourcepos.start.line;
 
         match &ast.value {
             NodeValue::Document => {
@@ -78,12 +211,41 @@ impl<'a> MarkdownRenderer<'a> {
                 self.render_heading(node, heading.level, lines);
             }
 
-            NodeValue::Paragraph => {
-                self.render_paragraph(node, lines, context);
-            }
+            NodeValue::Paragraph => match self.display_math(node) {
+                Some(latex) => self.render_display_math(&latex, lines),
+                None => self.render_paragraph(node, lines, context),
+            },
 
             NodeValue::CodeBlock(code_block) => {
-                self.render_code_block(&code_block.info, &code_block.literal, lines);
+                let lang = code_block.info.split_whitespace().next().unwrap_or("");
+                let diagram = self.diagram(&code_block.info, &code_block.literal);
+                if self.options.math && lang == "math" {
+                    // The whole formula maps to its fence
+                    self.render_display_math(&code_block.literal, lines);
+                } else if let Some(Ok(diagram)) = &diagram {
+                    // The whole diagram maps to its fence
+                    self.render_diagram(&code_block.info, diagram, lines);
+                } else {
+                    self.render_code_block(&code_block.info, &code_block.literal, lines);
+                    if let Some(Err(message)) = &diagram {
+                        let error = Line::from(Span::styled(
+                            format!("  ⚠ {}", message),
+                            Style::default().fg(self.theme.syntax_keyword.to_ratatui()),
+                        ));
+                        lines.insert(lines.len() - 1, error);
+                    }
+
+                    // Map each code line to its own source line
+                    let has_label = code_block.info.split_whitespace().next().is_some();
+                    let first_code_line = source_line + usize::from(code_block.fenced);
+                    let code_start = start_idx + usize::from(has_label);
+                    for idx in code_start..lines.len().saturating_sub(1) {
+                        context.set_line(idx, first_code_line + (idx - code_start));
+                    }
+                    if lines.len() > start_idx {
+                        context.set_line(lines.len() - 1, ast.sourcepos.end.line);
+                    }
+                }
             }
 
             NodeValue::BlockQuote => {
@@ -127,6 +289,8 @@ impl<'a> MarkdownRenderer<'a> {
                 }
             }
         }
+
+        context.map_lines(start_idx, lines.len(), source_line);
     }
 
     /// Render a heading
@@ -185,6 +349,42 @@ impl<'a> MarkdownRenderer<'a> {
         }
     }
 
+    /// LaTeX of a paragraph that holds nothing but display math
+    fn display_math(&self, node: &'a AstNode<'a>) -> Option<String> {
+        if !self.options.math {
+            return None;
+        }
+        let mut latex = None;
+        for child in node.children() {
+            match &child.data.borrow().value {
+                NodeValue::Math(math) if math.display_math && latex.is_none() => {
+                    latex = Some(math.literal.clone());
+                }
+                NodeValue::SoftBreak | NodeValue::LineBreak => {}
+                NodeValue::Text(text) if text.trim().is_empty() => {}
+                _ => return None,
+            }
+        }
+        latex
+    }
+
+    /// Render display math as a centered block of lines
+    fn render_display_math(&self, latex: &str, lines: &mut Vec<StyledLine>) {
+        use unicode_width::UnicodeWidthStr;
+
+        let rows = self.latex_renderer.render_display(latex);
+        let width = rows.iter().map(|row| row.width()).max().unwrap_or(0);
+        let indent = " ".repeat((self.width as usize).saturating_sub(width) / 2);
+        let style = Style::default().fg(self.theme.md_code.to_ratatui());
+        for row in rows {
+            lines.push(Line::from(Span::styled(
+                format!("{}{}", indent, row),
+                style,
+            )));
+        }
+        lines.push(Line::from(""));
+    }
+
     /// Render inline content (text with formatting)
     fn render_inline_content(&self, node: &'a AstNode<'a>) -> Vec<Span<'static>> {
         let mut spans = Vec::new();
@@ -196,74 +396,38 @@ impl<'a> MarkdownRenderer<'a> {
         spans
     }
 
-    /// Process text with LaTeX and emoji extensions
-    /// Returns spans with LaTeX rendered to Unicode and emojis expanded
-    fn process_text_with_extensions(&self, text: &str, base_style: Style) -> Vec<Span<'static>> {
-        let mut spans = Vec::new();
-        let mut remaining = text;
-
-        while !remaining.is_empty() {
-            // Check for inline LaTeX ($...$)
-            if let Some(start_pos) = remaining.find('$') {
-                // Add text before the $ as normal text (but process emojis)
-                if start_pos > 0 {
-                    let before = &remaining[..start_pos];
-                    let with_emoji = self.emoji_expander.expand_all(before);
-                    spans.push(Span::styled(
-                        with_emoji,
-                        base_style.fg(self.theme.fg_primary.to_ratatui()),
-                    ));
-                }
+    /// Text with emoji shortcodes expanded
+    fn text_span(&self, text: &str, base_style: Style) -> Span<'static> {
+        Span::styled(
+            self.emoji_expander.expand_all(text),
+            base_style.fg(self.theme.fg_primary.to_ratatui()),
+        )
+    }
 
-                remaining = &remaining[start_pos + 1..];
-
-                // Check if this is display math ($$)
-                let is_display = remaining.starts_with('$');
-                if is_display {
-                    remaining = &remaining[1..]; // Skip second $
-
-                    // Find closing $$
-                    if let Some(end_pos) = remaining.find("$$") {
-                        let latex = &remaining[..end_pos];
-                        let rendered = self.latex_renderer.render(latex);
-                        spans.push(Span::styled(
-                            format!(" {} ", rendered), // Pad display math
-                            base_style
-                                .fg(self.theme.md_code.to_ratatui())
-                                .add_modifier(Modifier::BOLD),
-                        ));
-                        remaining = &remaining[end_pos + 2..]; // Skip closing $$
-                    } else {
-                        // No closing $$, treat as literal
-                        spans.push(Span::styled("$$", base_style.fg(self.theme.fg_primary.to_ratatui())));
-                    }
-                } else {
-                    // Inline math ($...$)
-                    if let Some(end_pos) = remaining.find('$') {
-                        let latex = &remaining[..end_pos];
-                        let rendered = self.latex_renderer.render(latex);
-                        spans.push(Span::styled(
-                            rendered,
-                            base_style.fg(self.theme.md_code.to_ratatui()),
-                        ));
-                        remaining = &remaining[end_pos + 1..]; // Skip closing $
-                    } else {
-                        // No closing $, treat as literal
-                        spans.push(Span::styled("$", base_style.fg(self.theme.fg_primary.to_ratatui())));
-                    }
-                }
-            } else {
-                // No LaTeX found, process remaining text with emoji
-                let with_emoji = self.emoji_expander.expand_all(remaining);
-                spans.push(Span::styled(
-                    with_emoji,
-                    base_style.fg(self.theme.fg_primary.to_ratatui()),
-                ));
-                break;
-            }
+    /// Inline or display math on one line, or as written when math is off
+    fn math_span(&self, math: &NodeMath, base_style: Style) -> Span<'static> {
+        if !self.options.math {
+            // Line breaks inside the formula render like soft breaks
+            let literal = math.literal.replace('\n', " ");
+            let text = match (math.dollar_math, math.display_math) {
+                (true, true) => format!("$${}$$", literal),
+                (true, false) => format!("${}$", literal),
+                (false, _) => format!("$`{}`$", literal),
+            };
+            return Span::styled(text, base_style.fg(self.theme.fg_primary.to_ratatui()));
         }
 
-        spans
+        let rendered = self.latex_renderer.render(&math.literal);
+        if math.display_math {
+            Span::styled(
+                format!(" {} ", rendered),
+                base_style
+                    .fg(self.theme.md_code.to_ratatui())
+                    .add_modifier(Modifier::BOLD),
+            )
+        } else {
+            Span::styled(rendered, base_style.fg(self.theme.md_code.to_ratatui()))
+        }
     }
 
     /// Recursively collect inline spans
@@ -277,9 +441,11 @@ impl<'a> MarkdownRenderer<'a> {
 
         match &ast.value {
             NodeValue::Text(text) => {
-                // Process text with LaTeX and emoji extensions
-                let processed_spans = self.process_text_with_extensions(text, inherited_style);
-                spans.extend(processed_spans);
+                spans.push(self.text_span(text, inherited_style));
+            }
+
+            NodeValue::Math(math) => {
+                spans.push(self.math_span(math, inherited_style));
             }
 
             NodeValue::Code(code) => {
@@ -366,9 +532,36 @@ impl<'a> MarkdownRenderer<'a> {
         }
     }
 
+    /// Rendered diagram for a fenced block, if its language is one we draw
+    fn diagram(&self, info: &str, literal: &str) -> Option<DiagramResult> {
+        let lang = info.split_whitespace().next().unwrap_or("");
+        if !self.options.mermaid || !lang.eq_ignore_ascii_case("mermaid") {
+            return None;
+        }
+        let cache = self.diagram_cache.unwrap_or(&self.own_diagram_cache);
+        let width = (self.width as usize).saturating_sub(2);
+        Some(cache.mermaid(literal, width))
+    }
+
+    /// Render a diagram drawn as text, indented like code
+    fn render_diagram(&self, info: &str, diagram: &str, lines: &mut Vec<StyledLine>) {
+        let lang = info.split_whitespace().next().unwrap_or("");
+        lines.push(Line::from(Span::styled(
+            format!("▸ {}", lang),
+            Style::default()
+                .fg(self.theme.fg_muted.to_ratatui())
+                .add_modifier(Modifier::ITALIC),
+        )));
+
+        let style = Style::default().fg(self.theme.fg_primary.to_ratatui());
+        for line in diagram.lines() {
+            lines.push(Line::from(Span::styled(format!("  {}", line), style)));
+        }
+        lines.push(Line::from(""));
+    }
+
     /// Render a code block (with syntax highlighting)
     fn render_code_block(&self, info: &str, literal: &str, lines: &mut Vec<StyledLine>) {
-        use patina_core::Highlighter;
         use ratatui::style::Color;
 
         // Language label
@@ -383,7 +576,7 @@ impl<'a> MarkdownRenderer<'a> {
         }
 
         // Try syntax highlighting
-        let highlighter = Highlighter::new("base16-ocean.dark");
+        let highlighter = self.highlighter();
         if let Some(syntax) = highlighter.syntax_for_language(lang) {
             // Collect lines as string slices
             let code_lines: Vec<&str> = literal.lines().collect();
@@ -568,24 +761,25 @@ impl<'a> MarkdownRenderer<'a> {
         &self,
         node: &'a AstNode<'a>,
         lines: &mut Vec<StyledLine>,
-        _context: &mut RenderContext,
+        context: &mut RenderContext,
     ) {
+        use patina_core::table::Alignment;
         use unicode_width::UnicodeWidthStr;
-        use comrak::nodes::TableAlignment;
 
         // Extract alignment information from table node
-        let alignments = if let NodeValue::Table(table) = &node.data.borrow().value {
-            table.alignments.clone()
-        } else {
-            Vec::new()
+        let alignments: Vec<Alignment> = match &node.data.borrow().value {
+            NodeValue::Table(table) => table.alignments.iter().map(|&a| a.into()).collect(),
+            _ => Vec::new(),
         };
 
         // Collect all rows and cells
         let mut rows: Vec<Vec<String>> = Vec::new();
+        let mut row_sources: Vec<usize> = Vec::new();
 
         for child in node.children() {
             let ast = child.data.borrow();
             if matches!(ast.value, NodeValue::TableRow(_)) {
+                row_sources.push(ast.sourcepos.start.line);
                 let mut row_cells: Vec<String> = Vec::new();
                 for cell in child.children() {
                     let cell_ast = cell.data.borrow();
@@ -613,32 +807,6 @@ impl<'a> MarkdownRenderer<'a> {
             }
         }
 
-        // Helper to pad string to display width with alignment (handles emojis)
-        let pad_to_width = |text: &str, target_width: usize, alignment: &TableAlignment| -> String {
-            let display_width = UnicodeWidthStr::width(text);
-            if display_width >= target_width {
-                text.to_string()
-            } else {
-                let padding = target_width - display_width;
-                match alignment {
-                    TableAlignment::Left | TableAlignment::None => {
-                        // Left align (default)
-                        format!("{}{}", text, " ".repeat(padding))
-                    }
-                    TableAlignment::Right => {
-                        // Right align
-                        format!("{}{}", " ".repeat(padding), text)
-                    }
-                    TableAlignment::Center => {
-                        // Center align
-                        let left_pad = padding / 2;
-                        let right_pad = padding - left_pad;
-                        format!("{}{}{}", " ".repeat(left_pad), text, " ".repeat(right_pad))
-                    }
-                }
-            }
-        };
-
         // Render header (first row)
         if !rows.is_empty() {
             let header_row = &rows[0];
@@ -646,15 +814,16 @@ impl<'a> MarkdownRenderer<'a> {
             header_spans.push(Span::raw("│ "));
             for (i, cell) in header_row.iter().enumerate() {
                 let width = col_widths[i];
-                let alignment = alignments.get(i).unwrap_or(&TableAlignment::None);
+                let alignment = alignments.get(i).copied().unwrap_or_default();
                 header_spans.push(Span::styled(
-                    pad_to_width(cell, width, alignment),
+                    alignment.pad(cell, width),
                     Style::default()
                         .fg(self.theme.md_heading.to_ratatui())
                         .add_modifier(Modifier::BOLD),
                 ));
                 header_spans.push(Span::raw(" │ "));
             }
+            context.set_line(lines.len(), row_sources[0]);
             lines.push(Line::from(header_spans));
 
             // Separator line
@@ -667,21 +836,24 @@ impl<'a> MarkdownRenderer<'a> {
                 }
             }
             sep_spans.push(Span::raw("─┤"));
+            // The delimiter row sits directly below the header in the source
+            context.set_line(lines.len(), row_sources[0] + 1);
             lines.push(Line::from(sep_spans));
 
             // Render data rows (skip header)
-            for row in rows.iter().skip(1) {
+            for (row, &source) in rows.iter().zip(&row_sources).skip(1) {
                 let mut row_spans = Vec::new();
                 row_spans.push(Span::raw("│ "));
                 for (i, cell) in row.iter().enumerate() {
                     let width = col_widths[i];
-                    let alignment = alignments.get(i).unwrap_or(&TableAlignment::None);
+                    let alignment = alignments.get(i).copied().unwrap_or_default();
                     row_spans.push(Span::styled(
-                        pad_to_width(cell, width, alignment),
+                        alignment.pad(cell, width),
                         Style::default().fg(self.theme.fg_primary.to_ratatui()),
                     ));
                     row_spans.push(Span::raw(" │ "));
                 }
+                context.set_line(lines.len(), source);
                 lines.push(Line::from(row_spans));
             }
         }
@@ -709,6 +881,7 @@ mod tests {
     use super::*;
     use crate::Theme;
     use comrak::{parse_document, Arena, Options};
+    use patina_core::MarkdownParser;
 
     fn render_markdown(md: &str) -> Vec<StyledLine> {
         let arena = Arena::new();
@@ -758,6 +931,131 @@ mod tests {
         assert!(!lines.is_empty());
     }
 
+    fn render_text(md: &str, options: PreviewOptions) -> Vec<String> {
+        let arena = Arena::new();
+        let root = MarkdownParser::new().parse(&arena, md);
+        let theme = Theme::default();
+        MarkdownRenderer::new(&theme, 60)
+            .options(options)
+            .render(root)
+            .iter()
+            .map(|line| {
+                line.spans
+                    .iter()
+                    .map(|span| span.content.as_ref())
+                    .collect()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_mermaid_block_renders_diagram() {
+        let md = "```mermaid\ngraph TD\n  A --> B\n```";
+        let lines = render_text(md, PreviewOptions::default());
+        assert_eq!(lines[0], "▸ mermaid");
+        assert!(lines.iter().any(|line| line.contains("│ A │")));
+        assert!(!lines.iter().any(|line| line.contains("-->")));
+
+        // The diagram maps back to the opening fence
+        let map = source_map(&format!("Intro\n\n{}", md));
+        let last = map.len() - 1;
+        assert_eq!(map.source_line(last), Some(3));
+    }
+
+    #[test]
+    fn test_mermaid_disabled_or_invalid_shows_source() {
+        let md = "```mermaid\ngraph TD\n  A --> B\n```";
+        let lines = render_text(
+            md,
+            PreviewOptions {
+                mermaid: false,
+                ..PreviewOptions::default()
+            },
+        );
+        assert!(lines.iter().any(|line| line.contains("A --> B")));
+
+        let lines = render_text(
+            "```mermaid\ngraph TD\n  A -->\n```",
+            PreviewOptions::default(),
+        );
+        assert!(lines.iter().any(|line| line.contains("A -->")));
+        assert!(lines.iter().any(|line| line.starts_with("  ⚠ Line 2")));
+    }
+
+    #[test]
+    fn test_display_math_is_laid_out() {
+        let lines = render_text("$$\n\\frac{a}{b+1}\n$$", PreviewOptions::default());
+        let rows: Vec<&str> = lines.iter().map(|line| line.trim()).collect();
+        assert_eq!(rows[..3], ["a", "───────", "b + 1"]);
+
+        let lines = render_text("```math\n\\sqrt{x}\n```", PreviewOptions::default());
+        let rows: Vec<&str> = lines.iter().map(|line| line.trim()).collect();
+        assert_eq!(rows[..2], ["__", "╲╱ x"]);
+
+        // Math inside other text stays on its line
+        let lines = render_text("So $$x^2$$ here", PreviewOptions::default());
+        assert_eq!(lines[0], "So  x²  here");
+    }
+
+    #[test]
+    fn test_dollars_outside_math_are_text() {
+        let options = PreviewOptions::default();
+        let lines = render_text(
+            "It costs $5 and $10.\n\n`$x$` is code, $\\alpha$ is math",
+            options.clone(),
+        );
+        assert_eq!(lines[0], "It costs $5 and $10.");
+        assert_eq!(lines[2], " $x$  is code, α is math");
+
+        let options = PreviewOptions {
+            math: false,
+            ..PreviewOptions::default()
+        };
+        let lines = render_text("$\\alpha$ and $$\n\\beta\n$$", options);
+        assert_eq!(lines[0], "$\\alpha$ and $$ \\beta $$");
+    }
+
+    fn source_map(md: &str) -> SourceMap {
+        let arena = Arena::new();
+        let root = parse_document(&arena, md, &Options::default());
+        let theme = Theme::default();
+        MarkdownRenderer::new(&theme, 80)
+            .render_with_source_map(root)
+            .1
+    }
+
+    #[test]
+    fn test_source_map_blocks() {
+        let map = source_map("# Title\n\nFirst paragraph.\n\n## Next\n");
+        // Heading renders as blank, text, blank
+        assert_eq!(map.source_line(1), Some(1));
+        assert_eq!(map.source_line(3), Some(3));
+        assert_eq!(map.preview_line(3), 3);
+        assert_eq!(map.preview_line(5), 5);
+        // Lines between blocks resolve to the preceding block
+        assert_eq!(map.preview_line(4), 3);
+    }
+
+    #[test]
+    fn test_source_map_code_lines() {
+        let map = source_map("Intro\n\n```rust\nlet a = 1;\nlet b = 2;\n```\n");
+        // Paragraph + blank, then label, two code lines, trailing blank
+        assert_eq!(map.source_line(2), Some(3));
+        assert_eq!(map.source_line(3), Some(4));
+        assert_eq!(map.source_line(4), Some(5));
+        assert_eq!(map.preview_line(5), 4);
+    }
+
+    #[test]
+    fn test_source_map_prepend() {
+        let mut map = source_map("text");
+        let len = map.len();
+        map.prepend(2);
+        assert_eq!(map.len(), len + 2);
+        assert_eq!(map.preview_line(0), 0);
+        assert_eq!(map.preview_line(1), 2);
+    }
+
     #[test]
     fn test_empty_document() {
         let lines = render_markdown("");
@@ -4,8 +4,6 @@
 //! - StatusBar
 //! - TabBar
 //! - FileTree
-//! - Minimap
-//! - Outline
 //! - SearchBar
 
 #[allow(dead_code)]
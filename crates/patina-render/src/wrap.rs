@@ -0,0 +1,467 @@
+//! Soft-wrap layout for the source editor.
+//!
+//! Splits logical buffer lines into visual rows, breaking at word boundaries
+//! and measuring text in terminal cells (double-width characters, tabs expanded
+//! to the next tab stop). The resulting mapping is shared by the editor widget
+//! for drawing and by the application for moving the cursor by visual rows.
+
+use patina_core::Buffer;
+use unicode_width::UnicodeWidthChar;
+
+/// Options controlling how lines are wrapped
+#[derive(Debug, Clone, Copy)]
+pub struct WrapOptions {
+    /// Available width in terminal cells
+    pub width: usize,
+    /// Tab stop interval in cells
+    pub tab_size: usize,
+    /// Wrap long lines (when false, each line is a single row)
+    pub soft_wrap: bool,
+    /// Indent continuation rows to line up with list/blockquote content
+    pub indent_continuation: bool,
+}
+
+impl WrapOptions {
+    /// Create options for the given width with default settings
+    pub fn new(width: usize) -> Self {
+        Self {
+            width,
+            tab_size: 4,
+            soft_wrap: true,
+            indent_continuation: true,
+        }
+    }
+
+    /// Set the tab size
+    pub fn tab_size(mut self, tab_size: usize) -> Self {
+        self.tab_size = tab_size.max(1);
+        self
+    }
+
+    /// Enable or disable soft wrap
+    pub fn soft_wrap(mut self, wrap: bool) -> Self {
+        self.soft_wrap = wrap;
+        self
+    }
+
+    /// Enable or disable continuation indent
+    pub fn indent_continuation(mut self, indent: bool) -> Self {
+        self.indent_continuation = indent;
+        self
+    }
+}
+
+/// A single visual row of a wrapped line
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct VisualRow {
+    /// Char index (within the line) where the row starts
+    pub start: usize,
+    /// Char index one past the last char on the row
+    pub end: usize,
+    /// Leading indent in cells (non-zero only for continuation rows)
+    pub indent: usize,
+}
+
+/// Visual layout of one logical line
+#[derive(Debug, Clone)]
+pub struct LineLayout {
+    chars: Vec<char>,
+    rows: Vec<VisualRow>,
+    tab_size: usize,
+}
+
+impl LineLayout {
+    /// Lay out a line (trailing newline is ignored)
+    pub fn new(line: &str, options: &WrapOptions) -> Self {
+        let chars: Vec<char> = line.trim_end_matches(['\n', '\r']).chars().collect();
+        let tab_size = options.tab_size.max(1);
+        let width = options.width.max(1);
+
+        if !options.soft_wrap || chars.is_empty() {
+            return Self {
+                rows: vec![VisualRow {
+                    start: 0,
+                    end: chars.len(),
+                    indent: 0,
+                }],
+                chars,
+                tab_size,
+            };
+        }
+
+        let mut indent = if options.indent_continuation {
+            continuation_indent(&chars, tab_size)
+        } else {
+            0
+        };
+        // A continuation indent that leaves no room for text is worse than none
+        if indent * 2 > width {
+            indent = 0;
+        }
+
+        let mut rows = Vec::new();
+        let mut row_start = 0;
+        let mut row_indent = 0;
+        let mut x = 0;
+        let mut last_break: Option<usize> = None;
+        let mut i = 0;
+
+        while i < chars.len() {
+            let c = chars[i];
+            let w = cell_width(c, x - row_indent, tab_size);
+
+            // Whitespace may hang past the edge so that words break cleanly
+            if x + w > width && i > row_start && !c.is_whitespace() {
+                let brk = last_break.filter(|&b| b > row_start).unwrap_or(i);
+                rows.push(VisualRow {
+                    start: row_start,
+                    end: brk,
+                    indent: row_indent,
+                });
+                row_start = brk;
+                row_indent = indent;
+                last_break = None;
+                x = row_indent;
+                // Re-measure the chars carried over to the new row
+                for &carried in &chars[brk..i] {
+                    x += cell_width(carried, x - row_indent, tab_size);
+                }
+                continue;
+            }
+
+            x += w;
+            if c.is_whitespace() {
+                last_break = Some(i + 1);
+            }
+            i += 1;
+        }
+
+        rows.push(VisualRow {
+            start: row_start,
+            end: chars.len(),
+            indent: row_indent,
+        });
+
+        Self {
+            chars,
+            rows,
+            tab_size,
+        }
+    }
+
+    /// Visual rows of this line (always at least one)
+    pub fn rows(&self) -> &[VisualRow] {
+        &self.rows
+    }
+
+    /// Number of visual rows
+    pub fn row_count(&self) -> usize {
+        self.rows.len()
+    }
+
+    /// Number of chars in the line
+    pub fn len_chars(&self) -> usize {
+        self.chars.len()
+    }
+
+    /// Find the row containing a char column.
+    /// A column at a row boundary belongs to the later row, except at end of line.
+    pub fn row_of_col(&self, col: usize) -> usize {
+        self.rows
+            .iter()
+            .rposition(|row| col >= row.start)
+            .unwrap_or(0)
+    }
+
+    /// Display x (in cells, including indent) of a char column within its row
+    pub fn x_of_col(&self, col: usize) -> usize {
+        let row = self.rows[self.row_of_col(col)];
+        let col = col.min(self.chars.len());
+        let mut x = 0;
+        for &c in &self.chars[row.start..col.max(row.start)] {
+            x += cell_width(c, x, self.tab_size);
+        }
+        row.indent + x
+    }
+
+    /// Char column closest to display x on the given row
+    pub fn col_at_x(&self, row_idx: usize, target_x: usize) -> usize {
+        let row = self.rows[row_idx.min(self.rows.len() - 1)];
+        let is_last = row_idx + 1 >= self.rows.len();
+        let target = target_x.saturating_sub(row.indent);
+
+        let mut x = 0;
+        for (offset, &c) in self.chars[row.start..row.end].iter().enumerate() {
+            let w = cell_width(c, x, self.tab_size);
+            if target < x + w.max(1) {
+                return row.start + offset;
+            }
+            x += w;
+        }
+
+        // Past the end of the row: stay on this row rather than jumping to the next
+        if is_last || row.end == row.start {
+            row.end
+        } else {
+            row.end - 1
+        }
+    }
+
+    /// Chars on a row with their position: (char column, display x including indent, text to draw).
+    /// Tabs are drawn as `→` followed by padding to the next tab stop.
+    pub fn row_glyphs(&self, row_idx: usize) -> Vec<(usize, usize, String)> {
+        let row = self.rows[row_idx];
+        let mut glyphs = Vec::with_capacity(row.end - row.start);
+        let mut x = 0;
+        for (offset, &c) in self.chars[row.start..row.end].iter().enumerate() {
+            let w = cell_width(c, x, self.tab_size);
+            let glyph = if c == '\t' {
+                format!("→{}", " ".repeat(w.saturating_sub(1)))
+            } else {
+                c.to_string()
+            };
+            glyphs.push((row.start + offset, row.indent + x, glyph));
+            x += w;
+        }
+        glyphs
+    }
+
+    /// Text to draw for a row, with indent applied and tabs expanded
+    pub fn row_text(&self, row_idx: usize) -> String {
+        let mut text = " ".repeat(self.rows[row_idx].indent);
+        for (_, _, glyph) in self.row_glyphs(row_idx) {
+            text.push_str(&glyph);
+        }
+        text
+    }
+}
+
+/// Width in cells of a char drawn at display column `x`
+fn cell_width(c: char, x: usize, tab_size: usize) -> usize {
+    if c == '\t' {
+        tab_size - (x % tab_size)
+    } else {
+        c.width().unwrap_or(0)
+    }
+}
+
+/// Width of the list marker / blockquote prefix that continuation rows line up with
+fn continuation_indent(chars: &[char], tab_size: usize) -> usize {
+    let mut i = 0;
+    let mut matched = false;
+
+    loop {
+        let start = i;
+        while i < chars.len() && (chars[i] == ' ' || chars[i] == '\t') {
+            i += 1;
+        }
+        if i < chars.len() && chars[i] == '>' {
+            i += 1;
+            if i < chars.len() && chars[i] == ' ' {
+                i += 1;
+            }
+            matched = true;
+            continue;
+        }
+        i = start;
+        break;
+    }
+
+    let mut j = i;
+    while j < chars.len() && (chars[j] == ' ' || chars[j] == '\t') {
+        j += 1;
+    }
+    let marker_end = if j < chars.len() && matches!(chars[j], '-' | '*' | '+') {
+        Some(j + 1)
+    } else {
+        let digits = chars[j..].iter().take_while(|c| c.is_ascii_digit()).count();
+        let k = j + digits;
+        if digits > 0 && digits <= 9 && k < chars.len() && matches!(chars[k], '.' | ')') {
+            Some(k + 1)
+        } else {
+            None
+        }
+    };
+
+    if let Some(end) = marker_end {
+        if end < chars.len() && chars[end] == ' ' {
+            i = end + 1;
+            // Task list checkbox
+            if chars.len() >= i + 4
+                && chars[i] == '['
+                && matches!(chars[i + 1], ' ' | 'x' | 'X')
+                && chars[i + 2] == ']'
+                && chars[i + 3] == ' '
+            {
+                i += 4;
+            }
+            matched = true;
+        }
+    }
+
+    if !matched {
+        return 0;
+    }
+
+    let mut x = 0;
+    for &c in &chars[..i] {
+        x += cell_width(c, x, tab_size);
+    }
+    x
+}
+
+/// Move the cursor by `delta` visual rows, keeping its display x where possible.
+/// Returns the new (line, col).
+pub fn move_visual(
+    buffer: &Buffer,
+    cursor: (usize, usize),
+    delta: isize,
+    options: &WrapOptions,
+) -> (usize, usize) {
+    let (mut line, col) = cursor;
+    let mut layout = LineLayout::new(&buffer.line(line).unwrap_or_default(), options);
+    let col = col.min(layout.len_chars());
+    let target_x = layout.x_of_col(col);
+    let mut row = layout.row_of_col(col);
+    let last_line = buffer.len_lines().saturating_sub(1);
+
+    for _ in 0..delta.unsigned_abs() {
+        if delta < 0 {
+            if row > 0 {
+                row -= 1;
+            } else if line > 0 {
+                line -= 1;
+                layout = LineLayout::new(&buffer.line(line).unwrap_or_default(), options);
+                row = layout.row_count() - 1;
+            } else {
+                break;
+            }
+        } else if row + 1 < layout.row_count() {
+            row += 1;
+        } else if line < last_line {
+            line += 1;
+            layout = LineLayout::new(&buffer.line(line).unwrap_or_default(), options);
+            row = 0;
+        } else {
+            break;
+        }
+    }
+
+    (line, layout.col_at_x(row, target_x))
+}
+
+/// Count visual rows from `from_line` up to and including the cursor's row
+pub fn rows_until(
+    buffer: &Buffer,
+    from_line: usize,
+    cursor: (usize, usize),
+    options: &WrapOptions,
+) -> usize {
+    let (cursor_line, cursor_col) = cursor;
+    let mut rows = 0;
+    for line in from_line..cursor_line {
+        rows += LineLayout::new(&buffer.line(line).unwrap_or_default(), options).row_count();
+    }
+    let layout = LineLayout::new(&buffer.line(cursor_line).unwrap_or_default(), options);
+    rows + layout.row_of_col(cursor_col) + 1
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rows(line: &str, width: usize) -> Vec<String> {
+        let layout = LineLayout::new(line, &WrapOptions::new(width));
+        (0..layout.row_count())
+            .map(|r| layout.row_text(r))
+            .collect()
+    }
+
+    #[test]
+    fn test_short_line_single_row() {
+        assert_eq!(rows("hello", 10), vec!["hello"]);
+        assert_eq!(rows("", 10), vec![""]);
+    }
+
+    #[test]
+    fn test_breaks_at_word_boundary() {
+        assert_eq!(
+            rows("hello brave new world", 12),
+            vec!["hello brave ", "new world"]
+        );
+    }
+
+    #[test]
+    fn test_long_word_breaks_mid_word() {
+        assert_eq!(rows("abcdefghij", 4), vec!["abcd", "efgh", "ij"]);
+    }
+
+    #[test]
+    fn test_double_width_chars() {
+        // Each CJK char is two cells wide
+        assert_eq!(rows("世界世界世", 4), vec!["世界", "世界", "世"]);
+    }
+
+    #[test]
+    fn test_tabs_expand_to_tab_stop() {
+        let layout = LineLayout::new("\tx", &WrapOptions::new(20).tab_size(4));
+        assert_eq!(layout.row_text(0), "→   x");
+        assert_eq!(layout.x_of_col(1), 4);
+
+        let layout = LineLayout::new("ab\tx", &WrapOptions::new(20).tab_size(4));
+        assert_eq!(layout.x_of_col(3), 4);
+    }
+
+    #[test]
+    fn test_list_continuation_indent() {
+        assert_eq!(rows("- one two three", 9), vec!["- one two ", "  three"]);
+        assert_eq!(
+            rows("> quoted text here", 12),
+            vec!["> quoted ", "  text here"]
+        );
+        assert_eq!(rows("1. alpha beta", 9), vec!["1. alpha ", "   beta"]);
+
+        let layout = LineLayout::new(
+            "- one two three",
+            &WrapOptions::new(9).indent_continuation(false),
+        );
+        assert_eq!(layout.row_text(1), "three");
+    }
+
+    #[test]
+    fn test_no_wrap() {
+        let layout = LineLayout::new("hello world", &WrapOptions::new(4).soft_wrap(false));
+        assert_eq!(layout.row_count(), 1);
+    }
+
+    #[test]
+    fn test_col_mapping_roundtrip() {
+        let layout = LineLayout::new("hello brave new world", &WrapOptions::new(12));
+        assert_eq!(layout.row_of_col(0), 0);
+        assert_eq!(layout.row_of_col(12), 1);
+        assert_eq!(layout.x_of_col(14), 2);
+        assert_eq!(layout.col_at_x(1, 2), 14);
+        // Past the end of the last row lands at end of line
+        assert_eq!(layout.col_at_x(1, 50), 21);
+    }
+
+    #[test]
+    fn test_move_visual_within_wrapped_line() {
+        let buffer = Buffer::from_text("hello brave new world\nnext");
+        let options = WrapOptions::new(12);
+
+        assert_eq!(move_visual(&buffer, (0, 2), 1, &options), (0, 14));
+        assert_eq!(move_visual(&buffer, (0, 14), -1, &options), (0, 2));
+        assert_eq!(move_visual(&buffer, (0, 14), 1, &options), (1, 2));
+        assert_eq!(move_visual(&buffer, (1, 2), -1, &options), (0, 14));
+        assert_eq!(move_visual(&buffer, (0, 2), -1, &options), (0, 2));
+    }
+
+    #[test]
+    fn test_rows_until() {
+        let buffer = Buffer::from_text("hello brave new world\nnext");
+        let options = WrapOptions::new(12);
+        assert_eq!(rows_until(&buffer, 0, (1, 0), &options), 3);
+        assert_eq!(rows_until(&buffer, 0, (0, 13), &options), 2);
+    }
+}
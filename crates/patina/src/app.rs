@@ -1,18 +1,24 @@
 //! Main application logic.
 
 use anyhow::Result;
-use crossterm::event::{self, Event, KeyCode, KeyModifiers};
-use ratatui::{backend::CrosstermBackend, Terminal};
+use crossterm::event::{
+    self, Event, KeyCode, KeyModifiers, MouseButton, MouseEvent, MouseEventKind,
+};
+use ratatui::{backend::CrosstermBackend, layout::Rect, Terminal};
 use std::io;
 use std::path::PathBuf;
 use std::time::{Duration, Instant};
 
-use patina_core::{selection::Position, Document, Edit, Selection};
-use patina_render::tui::App as TuiApp;
-use patina_render::Theme;
+use patina_core::table::{Alignment, TableEditor};
+use patina_core::{selection::Position, Document, Edit, GoToTarget, Selection, SyntaxAssets};
+use patina_render::tui::{
+    App as TuiApp, ColorMode, Completion, PreviewWidget, ThemePicker, ViewMode,
+};
+use patina_render::{wrap, HtmlExporter, Theme, ThemeRegistry, WrapOptions};
 
 use crate::config::Config;
 use crate::ui;
+use crate::watcher::ConfigWatcher;
 
 /// Main application
 pub struct App {
@@ -28,6 +34,16 @@ pub struct App {
     last_auto_save: Instant,
     /// Terminal height (for page sizing)
     terminal_height: u16,
+    /// Terminal width (for soft-wrap layout)
+    terminal_width: u16,
+    /// Whether a mouse drag started on the minimap
+    dragging_minimap: bool,
+    /// Config file being watched for changes
+    config_path: Option<PathBuf>,
+    /// Watcher for the config file and user themes
+    watcher: Option<ConfigWatcher>,
+    /// Built-in, bundled and user themes
+    themes: ThemeRegistry,
 }
 
 impl App {
@@ -37,10 +53,21 @@ impl App {
         let mut tui = TuiApp::new();
 
         // Apply config
-        tui.theme = Theme::by_name(&config.theme);
+        tui.set_theme(Theme::by_name(&config.theme));
+        tui.style = config.editor_style();
+        tui.preview_options = config.preview_options();
+        tui.diagnostics.set_linter(config.linter());
+        match ColorMode::from_name(&config.ui.color_mode) {
+            Some(mode) => tui.color_mode = mode,
+            None => log::warn!("Unknown color mode: {}", config.ui.color_mode),
+        }
+        match ViewMode::from_name(&config.ui.default_view) {
+            Some(mode) => tui.view_mode = mode,
+            None => log::warn!("Unknown default view: {}", config.ui.default_view),
+        }
 
         // Get initial terminal size
-        let terminal_height = terminal.size()?.height;
+        let size = terminal.size()?;
 
         Ok(Self {
             tui,
@@ -48,7 +75,12 @@ impl App {
             config,
             quit_pending: false,
             last_auto_save: Instant::now(),
-            terminal_height,
+            terminal_height: size.height,
+            terminal_width: size.width,
+            dragging_minimap: false,
+            config_path: None,
+            watcher: None,
+            themes: ThemeRegistry::bundled(),
         })
     }
 
@@ -56,7 +88,13 @@ impl App {
     pub fn run(&mut self) -> Result<()> {
         loop {
             // Update terminal size
-            self.terminal_height = self.terminal.size()?.height;
+            let size = self.terminal.size()?;
+            self.terminal_height = size.height;
+            self.terminal_width = size.width;
+
+            // Re-lint the active document if it changed
+            let doc = &self.tui.documents[self.tui.active_doc];
+            self.tui.diagnostics.refresh(doc);
 
             // Draw UI
             self.terminal.draw(|frame| {
@@ -65,11 +103,18 @@ impl App {
 
             // Handle events
             if event::poll(Duration::from_millis(100))? {
-                if let Event::Key(key) = event::read()? {
-                    self.handle_key(key)?;
+                match event::read()? {
+                    Event::Key(key) => self.handle_key(key)?,
+                    Event::Mouse(mouse) => self.handle_mouse(mouse),
+                    _ => {}
                 }
             }
 
+            // Reload config if it or a theme changed on disk
+            if self.watcher.as_mut().is_some_and(|w| w.changed()) {
+                self.reload_config();
+            }
+
             // Auto-save check
             self.check_auto_save()?;
 
@@ -84,6 +129,74 @@ impl App {
         Ok(())
     }
 
+    /// Load user themes from beside the config file, then watch both for changes
+    pub fn watch_config(&mut self, path: PathBuf) {
+        match ConfigWatcher::new(&path) {
+            Ok(watcher) => self.watcher = Some(watcher),
+            Err(e) => log::warn!("Cannot watch config {}: {}", path.display(), e),
+        }
+        self.config_path = Some(path);
+
+        self.reload_themes();
+        let name = self.config.theme.clone();
+        self.set_theme(&name);
+    }
+
+    /// Re-read user themes, `.tmTheme` files and syntaxes, reporting the first
+    /// broken file in the status bar
+    fn reload_themes(&mut self) {
+        let Some(config_dir) = self.config_path.as_ref().and_then(|p| p.parent()) else {
+            return;
+        };
+        let themes_dir = config_dir.join("themes");
+
+        let (themes, theme_errors) = ThemeRegistry::load(&themes_dir);
+        self.themes = themes;
+        let (assets, syntax_errors) = SyntaxAssets::load(&themes_dir, &config_dir.join("syntaxes"));
+        self.tui.set_syntax_assets(assets);
+
+        let errors = theme_errors
+            .iter()
+            .map(ToString::to_string)
+            .chain(syntax_errors.iter().map(ToString::to_string))
+            .collect::<Vec<_>>();
+        for error in &errors {
+            log::warn!("Theme error: {}", error);
+        }
+        if let Some(error) = errors.first() {
+            self.tui.set_status(format!("✗ Theme error: {}", error));
+        }
+    }
+
+    /// Re-read the config file, reporting parse errors in the status bar
+    fn reload_config(&mut self) {
+        match Config::load(self.config_path.as_deref()) {
+            Ok(config) => {
+                self.tui.set_status("✓ Configuration reloaded");
+                self.reload_themes();
+
+                // Only a changed theme name replaces one chosen on the command line;
+                // otherwise re-apply the current theme in case its file changed
+                let name = if config.theme != self.config.theme {
+                    config.theme.clone()
+                } else {
+                    self.tui.theme.name.clone()
+                };
+                self.set_theme(&name);
+                if config.ui.color_mode != self.config.ui.color_mode {
+                    self.set_color_mode(&config.ui.color_mode);
+                }
+                self.tui.style = config.editor_style();
+                self.tui.preview_options = config.preview_options();
+                self.tui.diagnostics.set_linter(config.linter());
+                self.config = config;
+            }
+            Err(e) => {
+                self.tui.set_status(format!("✗ Config error: {:#}", e));
+            }
+        }
+    }
+
     /// Check and perform auto-save if needed
     fn check_auto_save(&mut self) -> Result<()> {
         let auto_save_secs = self.config.editor.auto_save;
@@ -117,6 +230,32 @@ impl App {
             return self.handle_input_mode(key);
         }
 
+        // The theme picker is modal while open
+        if self.tui.theme_picker.is_some() {
+            self.handle_theme_picker_key(key);
+            return Ok(());
+        }
+
+        // The focused outline takes keys until it hands focus back
+        if self.tui.outline.focused {
+            self.handle_outline_key(key);
+            return Ok(());
+        }
+
+        // So does the focused problems panel
+        if self.tui.diagnostics.focused {
+            self.handle_problems_key(key);
+            return Ok(());
+        }
+
+        // An open completion popup takes navigation keys; anything else
+        // closes it and is handled as usual
+        if self.tui.completion.is_some() && self.handle_completion_key(key) {
+            self.ensure_cursor_visible();
+            return Ok(());
+        }
+        let completing = self.tui.completion.take().is_some();
+
         let ctrl = key.modifiers.contains(KeyModifiers::CONTROL);
         let shift = key.modifiers.contains(KeyModifiers::SHIFT);
         let alt = key.modifiers.contains(KeyModifiers::ALT);
@@ -128,6 +267,17 @@ impl App {
             self.tui.clear_status();
         }
 
+        // Any key other than a preview scroll puts the preview back in sync with the editor
+        let preview_scroll = self.tui.preview_scroll.take();
+        let page_size = self.terminal_height.saturating_sub(3) as isize;
+        let rendered = self.tui.view_mode == ViewMode::Rendered;
+
+        // In a table, keys edit cells and keep the columns aligned
+        if self.handle_table_key(key) {
+            self.ensure_cursor_visible();
+            return Ok(());
+        }
+
         match key.code {
             // === Application Commands ===
 
@@ -154,6 +304,11 @@ impl App {
                 self.save_document()?;
             }
 
+            // Export to HTML next to the file
+            KeyCode::Char('e') if ctrl => {
+                self.export_html();
+            }
+
             // Open
             KeyCode::Char('o') if ctrl => {
                 self.tui.start_open_prompt();
@@ -171,11 +326,13 @@ impl App {
 
             // Next tab (Ctrl+Tab, might not work in all terminals)
             KeyCode::Tab if ctrl => {
+                self.record_jump();
                 self.tui.next_document();
             }
 
             // Previous tab (Ctrl+Shift+Tab, might not work in all terminals)
             KeyCode::BackTab if ctrl => {
+                self.record_jump();
                 self.tui.prev_document();
             }
 
@@ -194,6 +351,50 @@ impl App {
                 self.redo();
             }
 
+            // Go to line, heading or symbol
+            KeyCode::Char('g') if ctrl => {
+                self.tui.start_goto_prompt();
+            }
+
+            // Jump back / forward (Alt+, / Alt+.)
+            KeyCode::Char(',') if alt => {
+                let current = self.tui.current_jump();
+                if let Some(jump) = self.tui.jumps.back(current) {
+                    self.tui.jump_to(jump);
+                }
+            }
+            KeyCode::Char('.') if alt => {
+                if let Some(jump) = self.tui.jumps.forward() {
+                    self.tui.jump_to(jump);
+                }
+            }
+
+            // Pick a theme (Alt+T)
+            KeyCode::Char('t') if alt => {
+                let names = self.themes.names().into_iter().map(String::from).collect();
+                self.tui.theme_picker = Some(ThemePicker::new(names, &self.tui.theme));
+            }
+
+            // Toggle outline sidebar
+            KeyCode::Char('t') if ctrl => {
+                self.toggle_outline();
+            }
+
+            // Toggle problems panel
+            KeyCode::Char('l') if ctrl => {
+                self.toggle_problems();
+            }
+
+            // Format the document (Alt+F)
+            KeyCode::Char('f') if alt => {
+                let status = if self.format_document() {
+                    "✓ Formatted"
+                } else {
+                    "Already formatted"
+                };
+                self.tui.set_status(status);
+            }
+
             // Cycle view mode (Ctrl+\ or Ctrl+P)
             KeyCode::Char('\\') if ctrl => {
                 self.tui.cycle_view_mode();
@@ -205,38 +406,51 @@ impl App {
 
             // Next tab (Alt+Right, Ctrl+PageDown, or Ctrl+N)
             KeyCode::Right if alt => {
+                self.record_jump();
                 self.tui.next_document();
             }
             KeyCode::PageDown if ctrl => {
+                self.record_jump();
                 self.tui.next_document();
             }
 
             // Previous tab (Alt+Left, Ctrl+PageUp, or Ctrl+B)
             KeyCode::Left if alt => {
+                self.record_jump();
                 self.tui.prev_document();
             }
             KeyCode::PageUp if ctrl => {
+                self.record_jump();
                 self.tui.prev_document();
             }
 
+            // === Preview Scrolling ===
+            // Alt+arrows scroll the preview; in rendered-only mode plain navigation does too
+            KeyCode::Up if alt || rendered => {
+                self.scroll_preview(preview_scroll, -1);
+            }
+            KeyCode::Down if alt || rendered => {
+                self.scroll_preview(preview_scroll, 1);
+            }
+            KeyCode::PageUp if alt || rendered => {
+                self.scroll_preview(preview_scroll, -page_size);
+            }
+            KeyCode::PageDown if alt || rendered => {
+                self.scroll_preview(preview_scroll, page_size);
+            }
+
             // === Navigation ===
+            // Up/Down move by visual rows so wrapped lines can be traversed
             KeyCode::Up => {
+                let options = self.wrap_options();
                 let doc = self.tui.active_document_mut();
-                if doc.cursor.0 > 0 {
-                    doc.cursor.0 -= 1;
-                    // Clamp column to new line length
-                    doc.cursor.1 = doc.cursor.1.min(Self::line_length(doc, doc.cursor.0));
-                }
+                doc.cursor = wrap::move_visual(&doc.buffer, doc.cursor, -1, &options);
             }
 
             KeyCode::Down => {
+                let options = self.wrap_options();
                 let doc = self.tui.active_document_mut();
-                let max_line = doc.buffer.len_lines().saturating_sub(1);
-                if doc.cursor.0 < max_line {
-                    doc.cursor.0 += 1;
-                    // Clamp column to new line length
-                    doc.cursor.1 = doc.cursor.1.min(Self::line_length(doc, doc.cursor.0));
-                }
+                doc.cursor = wrap::move_visual(&doc.buffer, doc.cursor, 1, &options);
             }
 
             KeyCode::Left => {
@@ -297,6 +511,7 @@ impl App {
             KeyCode::Char(c) if !ctrl && !alt => {
                 // Only insert regular characters, not Ctrl/Alt combinations
                 self.insert_char(c);
+                self.refresh_completion();
             }
 
             KeyCode::Enter => {
@@ -305,9 +520,9 @@ impl App {
 
             KeyCode::Tab => {
                 // Insert tab character or spaces based on config
-                if self.config.editor.use_spaces {
+                if self.tui.style.insert_spaces {
                     // Insert configured number of spaces
-                    let spaces = " ".repeat(self.config.editor.tab_size);
+                    let spaces = " ".repeat(self.tui.style.tab_size);
                     for ch in spaces.chars() {
                         self.insert_char(ch);
                     }
@@ -319,6 +534,9 @@ impl App {
 
             KeyCode::Backspace => {
                 self.delete_backward();
+                if completing {
+                    self.refresh_completion();
+                }
             }
 
             KeyCode::Delete => {
@@ -334,14 +552,387 @@ impl App {
         Ok(())
     }
 
-    /// Get the length of a line (excluding newline character)
+    /// Handle a mouse event (click or drag on the minimap moves the cursor there)
+    fn handle_mouse(&mut self, mouse: MouseEvent) {
+        let frame_area = Rect::new(0, 0, self.terminal_width, self.terminal_height);
+        let Some(area) = ui::minimap_area(&self.tui, frame_area) else {
+            self.dragging_minimap = false;
+            return;
+        };
+
+        match mouse.kind {
+            MouseEventKind::Down(MouseButton::Left) => {
+                let inside = (area.x..area.x + area.width).contains(&mouse.column)
+                    && (area.y..area.y + area.height).contains(&mouse.row);
+                self.dragging_minimap = inside;
+                if inside {
+                    self.record_jump();
+                }
+            }
+            MouseEventKind::Drag(MouseButton::Left) if self.dragging_minimap => {}
+            MouseEventKind::Up(MouseButton::Left) => {
+                self.dragging_minimap = false;
+                return;
+            }
+            _ => return,
+        }
+        if !self.dragging_minimap {
+            return;
+        }
+
+        // Center the editor on the line under the pointer
+        let row = mouse
+            .row
+            .clamp(area.y, area.y + area.height.saturating_sub(1))
+            - area.y;
+        let line = ui::minimap_widget(&self.tui, area.height).line_at(row, area.height);
+        self.tui.preview_scroll = None;
+        let doc = self.tui.active_document_mut();
+        doc.cursor = (line, 0);
+        doc.scroll_offset = line.saturating_sub(area.height as usize / 2);
+    }
+
+    /// Get the length of a line in characters (excluding newline character)
     fn line_length(doc: &Document, line_idx: usize) -> usize {
         doc.buffer
             .line(line_idx)
-            .map(|l| l.trim_end_matches('\n').len())
+            .map(|l| l.trim_end_matches('\n').chars().count())
             .unwrap_or(0)
     }
 
+    /// Handle a key while the theme picker is open, previewing the highlighted theme
+    fn handle_theme_picker_key(&mut self, key: event::KeyEvent) {
+        let Some(picker) = self.tui.theme_picker.as_mut() else {
+            return;
+        };
+
+        match key.code {
+            KeyCode::Up => picker.move_selection(-1),
+            KeyCode::Down => picker.move_selection(1),
+            KeyCode::Enter => {
+                let name = picker.selected_name().map(str::to_string);
+                self.tui.theme_picker = None;
+                if let Some(name) = name {
+                    self.tui.set_status(format!("✓ Theme: {}", name));
+                }
+                return;
+            }
+            KeyCode::Esc => {
+                let original = picker.original.clone();
+                self.tui.theme_picker = None;
+                self.tui.set_theme(original);
+                return;
+            }
+            _ => return,
+        }
+
+        if let Some(theme) = picker
+            .selected_name()
+            .and_then(|name| self.themes.get(name))
+        {
+            self.tui.set_theme(theme.clone());
+        }
+    }
+
+    /// Handle a key while the completion popup is open; returns whether
+    /// the key was used
+    fn handle_completion_key(&mut self, key: event::KeyEvent) -> bool {
+        let Some(completion) = self.tui.completion.as_mut() else {
+            return false;
+        };
+        match key.code {
+            KeyCode::Up => completion.move_selection(-1),
+            KeyCode::Down => completion.move_selection(1),
+            KeyCode::Tab | KeyCode::Enter => self.accept_completion(),
+            KeyCode::Esc => self.tui.completion = None,
+            _ => return false,
+        }
+        true
+    }
+
+    /// Handle a key with the cursor in a table: cells are edited and
+    /// navigated as a whole and the table is re-aligned after every change.
+    /// Returns false for keys that edit as usual.
+    fn handle_table_key(&mut self, key: event::KeyEvent) -> bool {
+        let ctrl = key.modifiers.contains(KeyModifiers::CONTROL);
+        let shift = key.modifiers.contains(KeyModifiers::SHIFT);
+        let alt = key.modifiers.contains(KeyModifiers::ALT);
+        let table_key = match key.code {
+            KeyCode::Tab
+            | KeyCode::BackTab
+            | KeyCode::Enter
+            | KeyCode::Backspace
+            | KeyCode::Delete => !alt,
+            KeyCode::Char(c) => !alt || matches!(c, 'r' | 'R' | 'c' | 'C' | 'a'),
+            KeyCode::Up | KeyCode::Down | KeyCode::Left | KeyCode::Right => alt && shift,
+            _ => false,
+        };
+        if ctrl || !table_key || !self.config.editor.table_editing {
+            return false;
+        }
+        if self.tui.view_mode == ViewMode::Rendered {
+            return false;
+        }
+        let doc = self.tui.active_document();
+        let Some(mut table) = TableEditor::at(doc, doc.cursor) else {
+            return false;
+        };
+
+        match key.code {
+            KeyCode::Tab => table.next_cell(),
+            KeyCode::BackTab => table.prev_cell(),
+            KeyCode::Enter => table.insert_row(true),
+            // At the edge of a cell these join cells or lines as usual
+            KeyCode::Backspace if !table.delete_backward() => return false,
+            KeyCode::Delete if !table.delete_forward() => return false,
+            KeyCode::Char(c) if !alt => table.insert(&c.to_string()),
+            KeyCode::Char('r') => table.insert_row(false),
+            KeyCode::Char('R') if !table.remove_row() => {
+                self.tui.set_status("The header row can't be removed");
+            }
+            KeyCode::Char('c') => table.insert_column(),
+            KeyCode::Char('C') if !table.remove_column() => {
+                self.tui.set_status("A table needs at least one column");
+            }
+            KeyCode::Char('a') => {
+                let alignment = match table.cycle_alignment() {
+                    Alignment::None => "default",
+                    Alignment::Left => "left",
+                    Alignment::Center => "center",
+                    Alignment::Right => "right",
+                };
+                self.tui
+                    .set_status(format!("Column alignment: {}", alignment));
+            }
+            KeyCode::Up => {
+                table.move_row(-1);
+            }
+            KeyCode::Down => {
+                table.move_row(1);
+            }
+            KeyCode::Left => {
+                table.move_column(-1);
+            }
+            KeyCode::Right => {
+                table.move_column(1);
+            }
+            _ => {}
+        }
+
+        let doc = self.tui.active_document_mut();
+        let last = table.start + table.len - 1;
+        let start = doc.buffer.line_col_to_char(table.start, 0);
+        let end = doc
+            .buffer
+            .line_col_to_char(last, Self::line_length(doc, last));
+        let old = doc.buffer.slice(start, end);
+        Self::replace_text(doc, start, &old, &table.source().join("\n"), table.cursor());
+
+        if matches!(key.code, KeyCode::Char(_)) && !alt {
+            self.refresh_completion();
+        }
+        true
+    }
+
+    /// Open, update or close the completion popup for the text before the
+    /// cursor
+    fn refresh_completion(&mut self) {
+        let enabled = self.config.editor.autocomplete && self.tui.view_mode != ViewMode::Rendered;
+        self.tui.completion = if enabled {
+            Completion::at_cursor(self.tui.active_document())
+        } else {
+            None
+        };
+    }
+
+    /// Replace the completed text with the highlighted candidate
+    fn accept_completion(&mut self) {
+        let Some(completion) = self.tui.completion.take() else {
+            return;
+        };
+        let Some(item) = completion.selected_item() else {
+            return;
+        };
+        let doc = self.tui.active_document_mut();
+        if doc.cursor.0 != completion.line || doc.cursor.1 < completion.start {
+            return;
+        }
+        let cursor_before = Self::cursor_selection(doc);
+        let start = doc
+            .buffer
+            .line_col_to_char(completion.line, completion.start);
+        let end = doc.buffer.line_col_to_char(doc.cursor.0, doc.cursor.1);
+        let deleted = doc.buffer.slice(start, end);
+
+        doc.buffer.replace(start, end, &item.insert);
+        doc.invalidate_from_line(completion.line);
+        doc.cursor.1 = completion.start + item.insert.chars().count();
+
+        let cursor_after = Self::cursor_selection(doc);
+        doc.history.record(Edit::replace(
+            start,
+            deleted,
+            item.insert.clone(),
+            cursor_before,
+            cursor_after,
+        ));
+
+        // Keep completing into a directory
+        if item.insert.ends_with('/') {
+            self.refresh_completion();
+        }
+    }
+
+    /// Remember the current location before a large cursor movement
+    fn record_jump(&mut self) {
+        let from = self.tui.current_jump();
+        self.tui.jumps.record(from);
+    }
+
+    /// Move the cursor to a go-to prompt target
+    fn go_to(&mut self, input: &str) {
+        let Some(target) = GoToTarget::parse(input) else {
+            self.tui
+                .set_status(format!("✗ Invalid go-to target: {}", input.trim()));
+            return;
+        };
+        match target.resolve(self.tui.active_document()) {
+            Some(cursor) => {
+                self.record_jump();
+                self.tui.preview_scroll = None;
+                self.tui.active_document_mut().cursor = cursor;
+                self.ensure_cursor_visible();
+            }
+            None => self
+                .tui
+                .set_status(format!("✗ Not found: {}", input.trim())),
+        }
+    }
+
+    /// Show and focus the outline, or hide it when it already has focus
+    fn toggle_outline(&mut self) {
+        self.tui.outline.toggle();
+        if self.tui.outline.focused {
+            let doc = self.tui.active_document();
+            let (headings, cursor_line) = (doc.headings(), doc.cursor.0);
+            self.tui.outline.select_section(&headings, cursor_line);
+        }
+    }
+
+    /// Handle a key while the outline sidebar has focus
+    fn handle_outline_key(&mut self, key: event::KeyEvent) {
+        let ctrl = key.modifiers.contains(KeyModifiers::CONTROL);
+        let headings = self.tui.active_document().headings();
+        let page_size = self.terminal_height.saturating_sub(5) as isize;
+        let outline = &mut self.tui.outline;
+
+        match key.code {
+            KeyCode::Char('t') if ctrl => self.toggle_outline(),
+            KeyCode::Esc if !outline.filter.is_empty() => {
+                outline.filter.clear();
+                outline.selected = 0;
+            }
+            KeyCode::Esc => outline.unfocus(),
+            KeyCode::Up => outline.move_selection(&headings, -1),
+            KeyCode::Down => outline.move_selection(&headings, 1),
+            KeyCode::PageUp => outline.move_selection(&headings, -page_size),
+            KeyCode::PageDown => outline.move_selection(&headings, page_size),
+            KeyCode::Backspace => outline.pop_filter(),
+            KeyCode::Char(c) if !ctrl => outline.push_filter(c),
+            KeyCode::Enter => {
+                let target = outline.selected_heading(&headings).map(|h| h.line);
+                outline.unfocus();
+                if let Some(line) = target {
+                    self.tui.preview_scroll = None;
+                    self.record_jump();
+                    let doc = self.tui.active_document_mut();
+                    doc.cursor = (line.saturating_sub(1), 0);
+                    self.ensure_cursor_visible();
+                }
+            }
+            _ => {}
+        }
+    }
+
+    /// Show and focus the problems panel, or hide it when it already has focus
+    fn toggle_problems(&mut self) {
+        self.tui.diagnostics.toggle();
+        if self.tui.diagnostics.focused {
+            let cursor_line = self.tui.active_document().cursor.0;
+            self.tui.diagnostics.select_from_line(cursor_line);
+        }
+    }
+
+    /// Handle a key while the problems panel has focus
+    fn handle_problems_key(&mut self, key: event::KeyEvent) {
+        let ctrl = key.modifiers.contains(KeyModifiers::CONTROL);
+        let page_size = ui::problems_height(&self.tui).saturating_sub(2).max(1) as isize;
+        let problems = &mut self.tui.diagnostics;
+
+        match key.code {
+            KeyCode::Char('l') if ctrl => self.toggle_problems(),
+            KeyCode::Esc => problems.unfocus(),
+            KeyCode::Up => problems.move_selection(-1),
+            KeyCode::Down => problems.move_selection(1),
+            KeyCode::PageUp => problems.move_selection(-page_size),
+            KeyCode::PageDown => problems.move_selection(page_size),
+            KeyCode::Enter => {
+                let target = problems.selected_item().map(|d| (d.line, d.column));
+                problems.unfocus();
+                if let Some((line, column)) = target {
+                    self.tui.preview_scroll = None;
+                    self.record_jump();
+                    let doc = self.tui.active_document_mut();
+                    let line = line
+                        .saturating_sub(1)
+                        .min(doc.buffer.len_lines().saturating_sub(1));
+                    doc.cursor = (
+                        line,
+                        column.saturating_sub(1).min(Self::line_length(doc, line)),
+                    );
+                    self.ensure_cursor_visible();
+                }
+            }
+            _ => {}
+        }
+    }
+
+    /// Wrap options matching how the editor pane is currently laid out
+    fn wrap_options(&self) -> WrapOptions {
+        ui::editor_wrap_options(&self.tui, self.terminal_width)
+    }
+
+    /// Scroll the preview by `delta` lines and move the editor to the source shown at the top
+    fn scroll_preview(&mut self, from: Option<usize>, delta: isize) {
+        let width = ui::preview_pane_width(&self.tui, self.terminal_width);
+        let doc = self.tui.active_document();
+        let map = PreviewWidget::new(doc, &self.tui.theme, 0)
+            .code_highlighter(&self.tui.code_highlighter)
+            .options(self.tui.preview_options.clone())
+            .diagram_cache(&self.tui.diagram_cache)
+            .source_map_cache(&self.tui.source_map_cache)
+            .source_map(width);
+
+        // Start from where the preview currently is, synced or not
+        let current = from.unwrap_or_else(|| {
+            let (source_line, rows_above) = ui::preview_anchor(&self.tui, self.terminal_width);
+            map.preview_line(source_line).saturating_sub(rows_above)
+        });
+        let offset = current
+            .saturating_add_signed(delta)
+            .min(map.len().saturating_sub(1));
+        self.tui.preview_scroll = Some(offset);
+
+        if let Some(source_line) = map.source_line(offset) {
+            let doc = self.tui.active_document_mut();
+            let line = source_line
+                .saturating_sub(1)
+                .min(doc.buffer.len_lines().saturating_sub(1));
+            doc.cursor = (line, 0);
+            doc.scroll_offset = line;
+        }
+    }
+
     /// Get cursor as Selection
     fn cursor_selection(doc: &Document) -> Selection {
         Selection::cursor(Position::new(doc.cursor.0, doc.cursor.1))
@@ -349,22 +940,32 @@ impl App {
 
     /// Ensure cursor is visible by adjusting scroll offset
     fn ensure_cursor_visible(&mut self) {
+        let options = self.wrap_options();
+        let chrome = 3 + ui::problems_height(&self.tui);
         let doc = self.tui.active_document_mut();
         let cursor_line = doc.cursor.0;
 
         // Calculate visible area (terminal height minus UI elements)
-        let visible_lines = (self.terminal_height.saturating_sub(3)) as usize;
+        let visible_lines = (self.terminal_height.saturating_sub(chrome)).max(1) as usize;
 
         // If cursor is above visible area, scroll up
         if cursor_line < doc.scroll_offset {
             doc.scroll_offset = cursor_line;
         }
 
-        // If cursor is below visible area, scroll down
+        // Every line takes at least one row, so skip ahead cheaply first
         let bottom_visible_line = doc.scroll_offset + visible_lines.saturating_sub(1);
         if cursor_line > bottom_visible_line {
             doc.scroll_offset = cursor_line.saturating_sub(visible_lines.saturating_sub(1));
         }
+
+        // Then scroll by wrapped rows until the cursor's row fits on screen
+        while doc.scroll_offset < cursor_line
+            && wrap::rows_until(&doc.buffer, doc.scroll_offset, doc.cursor, &options)
+                > visible_lines
+        {
+            doc.scroll_offset += 1;
+        }
     }
 
     /// Insert a character at cursor
@@ -375,6 +976,7 @@ impl App {
 
         let text = c.to_string();
         doc.buffer.insert(pos, &text);
+        doc.invalidate_from_line(doc.cursor.0);
         doc.cursor.1 += 1;
 
         let cursor_after = Self::cursor_selection(doc);
@@ -389,6 +991,7 @@ impl App {
         let pos = doc.buffer.line_col_to_char(doc.cursor.0, doc.cursor.1);
 
         doc.buffer.insert(pos, "\n");
+        doc.invalidate_from_line(doc.cursor.0);
         doc.cursor.0 += 1;
         doc.cursor.1 = 0;
 
@@ -420,6 +1023,7 @@ impl App {
                 doc.cursor.0 -= 1;
                 doc.cursor.1 = Self::line_length(doc, doc.cursor.0);
             }
+            doc.invalidate_from_line(doc.cursor.0);
 
             let cursor_after = Self::cursor_selection(doc);
             doc.history
@@ -438,6 +1042,7 @@ impl App {
             let deleted = doc.buffer.slice(pos, pos + 1);
 
             doc.buffer.delete(pos, pos + 1);
+            doc.invalidate_from_line(doc.cursor.0);
 
             // Cursor stays in same position
             let cursor_after = Self::cursor_selection(doc);
@@ -450,11 +1055,14 @@ impl App {
     fn undo(&mut self) {
         let doc = self.tui.active_document_mut();
         if let Some(edit) = doc.history.undo() {
+            let edit_line = doc.buffer.char_to_line_col(edit.position).0;
+            doc.invalidate_from_line(edit_line);
+
             // Reverse the edit
             if !edit.inserted.is_empty() {
                 // Was an insertion, so delete
                 doc.buffer
-                    .delete(edit.position, edit.position + edit.inserted.len());
+                    .delete(edit.position, edit.position + edit.inserted.chars().count());
             }
             if !edit.deleted.is_empty() {
                 // Was a deletion, so insert
@@ -469,11 +1077,14 @@ impl App {
     fn redo(&mut self) {
         let doc = self.tui.active_document_mut();
         if let Some(edit) = doc.history.redo() {
+            let edit_line = doc.buffer.char_to_line_col(edit.position).0;
+            doc.invalidate_from_line(edit_line);
+
             // Reapply the edit
             if !edit.deleted.is_empty() {
                 // Was a deletion, so delete again
                 doc.buffer
-                    .delete(edit.position, edit.position + edit.deleted.len());
+                    .delete(edit.position, edit.position + edit.deleted.chars().count());
             }
             if !edit.inserted.is_empty() {
                 // Was an insertion, so insert again
@@ -509,8 +1120,70 @@ impl App {
         self.tui.open_document(Document::new());
     }
 
+    /// Format the active document as one undoable edit, returning whether
+    /// anything changed
+    fn format_document(&mut self) -> bool {
+        let formatter = self.config.formatter();
+        let doc = self.tui.active_document_mut();
+        let old = doc.buffer.text();
+        let new = formatter.format_body(&old);
+        let cursor = doc.cursor;
+        Self::replace_text(doc, 0, &old, &new, cursor)
+    }
+
+    /// Replace `old`, the text at char `start`, with `new` as one undoable
+    /// edit and move the cursor to `cursor`. Only the span that differs is
+    /// replaced. Returns whether anything changed.
+    fn replace_text(
+        doc: &mut Document,
+        start: usize,
+        old: &str,
+        new: &str,
+        cursor: (usize, usize),
+    ) -> bool {
+        let old: Vec<char> = old.chars().collect();
+        let new: Vec<char> = new.chars().collect();
+        let prefix = old.iter().zip(&new).take_while(|(a, b)| a == b).count();
+        let suffix = old[prefix..]
+            .iter()
+            .rev()
+            .zip(new[prefix..].iter().rev())
+            .take_while(|(a, b)| a == b)
+            .count();
+        let (end_old, end_new) = (old.len() - suffix, new.len() - suffix);
+        if prefix == end_old && prefix == end_new {
+            doc.cursor = cursor;
+            return false;
+        }
+
+        let cursor_before = Self::cursor_selection(doc);
+        let position = start + prefix;
+        let deleted: String = old[prefix..end_old].iter().collect();
+        let inserted: String = new[prefix..end_new].iter().collect();
+        doc.buffer.replace(position, start + end_old, &inserted);
+        let line = doc.buffer.char_to_line_col(position).0;
+        doc.invalidate_from_line(line);
+
+        let last_line = doc.buffer.len_lines().saturating_sub(1);
+        doc.cursor.0 = cursor.0.min(last_line);
+        doc.cursor.1 = cursor.1.min(Self::line_length(doc, doc.cursor.0));
+
+        let cursor_after = Self::cursor_selection(doc);
+        doc.history.record(Edit::replace(
+            position,
+            deleted,
+            inserted,
+            cursor_before,
+            cursor_after,
+        ));
+        true
+    }
+
     /// Save the active document
     fn save_document(&mut self) -> Result<()> {
+        if self.config.format.on_save {
+            self.format_document();
+        }
         let doc = self.tui.active_document_mut();
         if let Some(path) = doc.path.clone() {
             doc.save()?;
@@ -527,9 +1200,47 @@ impl App {
         Ok(())
     }
 
+    /// Write the active document as a standalone HTML page beside its file
+    fn export_html(&mut self) {
+        let doc = self.tui.active_document();
+        let Some(path) = doc.path.as_ref().map(|path| path.with_extension("html")) else {
+            self.tui.set_status("Save the document before exporting");
+            return;
+        };
+        let html = HtmlExporter::new(&self.tui.theme)
+            .code_highlighter(&self.tui.code_highlighter)
+            .options(self.tui.preview_options.clone())
+            .sanitize(self.config.export.sanitize)
+            .export(doc);
+        let filename = path
+            .file_name()
+            .and_then(|n| n.to_str())
+            .unwrap_or("file")
+            .to_string();
+        match std::fs::write(&path, html) {
+            Ok(()) => self.tui.set_status(format!("✓ Exported: {}", filename)),
+            Err(e) => self
+                .tui
+                .set_status(format!("Export failed: {}: {}", filename, e)),
+        }
+    }
+
     /// Set the theme
     pub fn set_theme(&mut self, theme: &str) {
-        self.tui.theme = Theme::by_name(theme);
+        match self.themes.get(theme) {
+            Some(theme) => self.tui.set_theme(theme.clone()),
+            None => self.tui.set_status(format!("✗ Unknown theme: {}", theme)),
+        }
+    }
+
+    /// Set the terminal color mode by name (`auto` detects it)
+    pub fn set_color_mode(&mut self, name: &str) {
+        match ColorMode::from_name(name) {
+            Some(mode) => self.tui.color_mode = mode,
+            None => self
+                .tui
+                .set_status(format!("✗ Unknown color mode: {}", name)),
+        }
     }
 
     /// Toggle Zen mode
@@ -572,6 +1283,7 @@ impl App {
                                 self.tui.set_status(format!("✓ Saved: {}", filename));
                             }
                         }
+                        InputMode::GoTo => self.go_to(&input),
                         InputMode::Normal => {}
                     }
                 }
@@ -0,0 +1,268 @@
+//! Headless subcommands: render, export and check.
+
+use anyhow::{bail, Context, Result};
+use comrak::nodes::NodeValue;
+use comrak::Arena;
+use std::io::{IsTerminal, Write};
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+
+use patina_core::check::{self, body_line_offset};
+use patina_core::{Diagnostic, Document, Highlighter, Severity, SyntaxAssets};
+use patina_extensions::{mermaid::MermaidError, MermaidRenderer};
+use patina_render::tui::{to_ansi, to_plain, ColorMode, MarkdownRenderer};
+use patina_render::{code_highlighter, HtmlExporter, Theme, ThemeRegistry};
+
+use crate::config::Config;
+
+/// Width used when stdout isn't a terminal
+const DEFAULT_WIDTH: u16 = 80;
+
+/// Formats `patina export` can write
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum ExportFormat {
+    /// Standalone HTML page
+    Html,
+    /// Plain text, as shown in the preview
+    Txt,
+}
+
+/// Theme and highlighter chosen by the config, with user themes loaded
+pub struct Setup {
+    config: Config,
+    theme: Theme,
+    highlighter: Highlighter,
+}
+
+impl Setup {
+    /// Load user themes and syntaxes from beside the config file, then pick
+    /// `theme` or the configured one
+    pub fn new(config: Config, config_path: Option<&Path>, theme: Option<&str>) -> Self {
+        let config_dir = config_path.and_then(Path::parent);
+        let (themes, assets) = match config_dir {
+            Some(dir) => {
+                let themes_dir = dir.join("themes");
+                let (themes, errors) = ThemeRegistry::load(&themes_dir);
+                for error in errors {
+                    log::warn!("Theme error: {}", error);
+                }
+                let (assets, errors) = SyntaxAssets::load(&themes_dir, &dir.join("syntaxes"));
+                for error in errors {
+                    log::warn!("Theme error: {}", error);
+                }
+                (themes, assets)
+            }
+            None => (ThemeRegistry::bundled(), SyntaxAssets::default()),
+        };
+
+        let name = theme.unwrap_or(&config.theme);
+        let theme = themes.get(name).cloned().unwrap_or_else(|| {
+            log::warn!("Unknown theme: {}", name);
+            Theme::by_name(name)
+        });
+        let highlighter = code_highlighter(&theme, &assets);
+        Self {
+            config,
+            theme,
+            highlighter,
+        }
+    }
+
+    /// Preview lines of a document at the given width
+    fn render_lines(&self, doc: &Document, width: u16) -> Vec<ratatui::text::Line<'static>> {
+        let arena = Arena::new();
+        let root = doc.parse(&arena);
+        MarkdownRenderer::new(&self.theme, width)
+            .options(self.config.preview_options())
+            .code_highlighter(&self.highlighter)
+            .render(root)
+    }
+}
+
+/// Read a markdown file, or stdin for `-`
+fn read_document(path: &Path) -> Result<Document> {
+    if path == Path::new("-") {
+        let mut content = String::new();
+        std::io::Read::read_to_string(&mut std::io::stdin(), &mut content)?;
+        return Ok(Document::from_content(&content));
+    }
+    Document::from_file(path.to_path_buf())
+        .with_context(|| format!("cannot read {}", path.display()))
+}
+
+/// `patina render`: print the styled preview, through a pager on a terminal
+pub fn render(
+    setup: &Setup,
+    file: &Path,
+    width: Option<u16>,
+    color: Option<&str>,
+    paging: bool,
+) -> Result<()> {
+    let doc = read_document(file)?;
+    let stdout = std::io::stdout();
+    let terminal = stdout.is_terminal();
+    let (columns, rows) = crossterm::terminal::size().unwrap_or((DEFAULT_WIDTH, u16::MAX));
+    let width = width.unwrap_or(if terminal { columns } else { DEFAULT_WIDTH });
+
+    let name = color.unwrap_or(&setup.config.ui.color_mode);
+    let Some(mode) = ColorMode::from_name(name) else {
+        bail!("unknown color mode: {}", name);
+    };
+
+    let lines = setup.render_lines(&doc, width);
+    let text = to_ansi(&lines, mode, &setup.theme);
+    if paging && terminal && lines.len() >= rows as usize && page(&text).is_ok() {
+        return Ok(());
+    }
+    stdout.lock().write_all(text.as_bytes())?;
+    Ok(())
+}
+
+/// Show text in `$PAGER`, or `less -R`
+fn page(text: &str) -> Result<()> {
+    let pager = std::env::var("PAGER")
+        .ok()
+        .filter(|p| !p.trim().is_empty())
+        .unwrap_or_else(|| "less -R".to_string());
+    let mut words = pager.split_whitespace();
+    let program = words.next().unwrap_or("less");
+    let mut child = Command::new(program)
+        .args(words)
+        .stdin(Stdio::piped())
+        .spawn()?;
+    if let Some(mut stdin) = child.stdin.take() {
+        // The pager closing early (`q`) is not an error
+        let _ = stdin.write_all(text.as_bytes());
+    }
+    child.wait()?;
+    Ok(())
+}
+
+/// `patina export`: write a page or plain text to `output`, or stdout
+pub fn export(
+    setup: &Setup,
+    file: &Path,
+    format: ExportFormat,
+    output: Option<&Path>,
+    sanitize: bool,
+    width: Option<u16>,
+) -> Result<()> {
+    let mut doc = read_document(file)?;
+    if doc.path.is_none() {
+        // Title and relative links of stdin input go by the output file
+        doc.path = output.map(Path::to_path_buf);
+    }
+    let text = match format {
+        ExportFormat::Html => HtmlExporter::new(&setup.theme)
+            .code_highlighter(&setup.highlighter)
+            .options(setup.config.preview_options())
+            .sanitize(sanitize || setup.config.export.sanitize)
+            .export(&doc),
+        ExportFormat::Txt => to_plain(&setup.render_lines(&doc, width.unwrap_or(DEFAULT_WIDTH))),
+    };
+    match output {
+        Some(path) => std::fs::write(path, text)
+            .with_context(|| format!("cannot write {}", path.display()))?,
+        None => std::io::stdout().lock().write_all(text.as_bytes())?,
+    }
+    Ok(())
+}
+
+/// `patina check`: print problems and lint findings in each file as
+/// `file:line:col: ...`.
+///
+/// Returns whether the files passed: no errors, and no warnings either when
+/// `strict`.
+pub fn check(setup: &Setup, files: &[PathBuf], strict: bool) -> Result<bool> {
+    let mut errors = 0;
+    let mut warnings = 0;
+    let linter = setup.config.linter();
+    for file in files {
+        let content = std::fs::read_to_string(file)
+            .with_context(|| format!("cannot read {}", file.display()))?;
+        let mut diagnostics = check::check(&content, Some(file));
+        if let Some(linter) = &linter {
+            let offset = body_line_offset(&content);
+            let doc = Document::from_content(&content);
+            diagnostics.extend(linter.lint(&doc).into_iter().map(|mut diagnostic| {
+                diagnostic.line += offset;
+                diagnostic
+            }));
+        }
+        if setup.config.markdown.enable_mermaid {
+            diagnostics.extend(check_diagrams(&content));
+        }
+        diagnostics.sort_by_key(|d| (d.line, d.column));
+        for diagnostic in &diagnostics {
+            match diagnostic.severity {
+                Severity::Error => errors += 1,
+                Severity::Warning => warnings += 1,
+            }
+            println!("{}:{}", file.display(), diagnostic);
+        }
+    }
+
+    if errors + warnings > 0 {
+        eprintln!(
+            "{} error{}, {} warning{} in {} file{}",
+            errors,
+            plural(errors),
+            warnings,
+            plural(warnings),
+            files.len(),
+            plural(files.len())
+        );
+    }
+    Ok(errors == 0 && (!strict || warnings == 0))
+}
+
+fn plural(count: usize) -> &'static str {
+    if count == 1 {
+        ""
+    } else {
+        "s"
+    }
+}
+
+/// Mermaid fences the preview can't draw
+fn check_diagrams(content: &str) -> Vec<Diagnostic> {
+    let doc = Document::from_content(content);
+    let offset = body_line_offset(content);
+    let arena = Arena::new();
+    let root = doc.parse(&arena);
+    let renderer = MermaidRenderer::new();
+
+    let mut diagnostics = Vec::new();
+    for node in root.descendants() {
+        let ast = node.data.borrow();
+        let NodeValue::CodeBlock(block) = &ast.value else {
+            continue;
+        };
+        let lang = block.info.split_whitespace().next().unwrap_or("");
+        if !lang.eq_ignore_ascii_case("mermaid") {
+            continue;
+        }
+        let start = ast.sourcepos.start;
+        let diagnostic = match renderer.render(&block.literal) {
+            Ok(_) => continue,
+            Err(MermaidError::ParseError {
+                line,
+                column,
+                message,
+            }) => Diagnostic::error(
+                start.line + offset + line,
+                start.column + column - 1,
+                "mermaid",
+                message,
+            ),
+            Err(MermaidError::UnsupportedDiagram) => Diagnostic::warning(
+                start.line + offset,
+                start.column,
+                "mermaid",
+                "unsupported diagram type".to_string(),
+            ),
+        };
+        diagnostics.push(diagnostic);
+    }
+    diagnostics
+}
@@ -2,7 +2,11 @@
 
 use anyhow::Result;
 use directories::ProjectDirs;
+use patina_core::lint::{self, Linter};
+use patina_core::{Formatter, Severity};
+use patina_render::{EditorStyle, PreviewOptions};
 use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
 use std::path::{Path, PathBuf};
 
 /// Application configuration
@@ -23,6 +27,15 @@ pub struct Config {
 
     /// Markdown settings
     pub markdown: MarkdownConfig,
+
+    /// HTML export settings
+    pub export: ExportConfig,
+
+    /// Lint settings
+    pub lint: LintConfig,
+
+    /// Formatter settings
+    pub format: FormatConfig,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -38,6 +51,10 @@ pub struct EditorConfig {
     pub auto_save: u64,
     /// Auto-close brackets
     pub auto_close_brackets: bool,
+    /// Suggest emoji, heading anchors, paths and footnotes while typing
+    pub autocomplete: bool,
+    /// Edit tables cell by cell, keeping their columns aligned
+    pub table_editing: bool,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -45,6 +62,8 @@ pub struct EditorConfig {
 pub struct UiConfig {
     /// Show line numbers
     pub line_numbers: bool,
+    /// Number lines relative to the cursor line
+    pub relative_line_numbers: bool,
     /// Show minimap
     pub minimap: bool,
     /// Highlight current line
@@ -53,6 +72,8 @@ pub struct UiConfig {
     pub indent_guides: bool,
     /// Default view mode
     pub default_view: String,
+    /// Terminal colors: auto, truecolor, 256, 16 or mono
+    pub color_mode: String,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -68,6 +89,59 @@ pub struct MarkdownConfig {
     pub enable_mermaid: bool,
 }
 
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ExportConfig {
+    /// Escape raw HTML and drop script links in exported pages
+    pub sanitize: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct LintConfig {
+    /// Check the document while editing
+    pub enabled: bool,
+    /// Maximum line width for the `line-length` rule
+    pub line_length: usize,
+    /// Level of individual rules, like `line-length = "off"`
+    pub rules: BTreeMap<String, RuleLevel>,
+}
+
+/// How a lint rule is reported
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum RuleLevel {
+    Off,
+    Warning,
+    Error,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct FormatConfig {
+    /// Format the document before saving
+    pub on_save: bool,
+    /// Wrap paragraphs at this width (0 to keep line breaks)
+    pub width: usize,
+    /// Bullet list marker: `-`, `*` or `+`
+    pub list_marker: char,
+    /// Emphasis delimiter: `*` or `_`
+    pub emphasis: char,
+    /// Strong emphasis delimiter: `*` or `_`
+    pub strong: char,
+    /// Heading style
+    pub heading_style: HeadingStyle,
+}
+
+/// How the formatter writes headings
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum HeadingStyle {
+    #[default]
+    Atx,
+    Setext,
+}
+
 #[derive(Debug, Clone, Copy, Serialize, Deserialize, Default)]
 #[serde(rename_all = "lowercase")]
 pub enum KeybindingMode {
@@ -85,6 +159,9 @@ impl Default for Config {
             ui: UiConfig::default(),
             keybindings: KeybindingMode::default(),
             markdown: MarkdownConfig::default(),
+            export: ExportConfig::default(),
+            lint: LintConfig::default(),
+            format: FormatConfig::default(),
         }
     }
 }
@@ -97,6 +174,8 @@ impl Default for EditorConfig {
             soft_wrap: true,
             auto_save: 0,
             auto_close_brackets: true,
+            autocomplete: true,
+            table_editing: true,
         }
     }
 }
@@ -105,10 +184,12 @@ impl Default for UiConfig {
     fn default() -> Self {
         Self {
             line_numbers: true,
+            relative_line_numbers: false,
             minimap: false,
             highlight_line: true,
             indent_guides: true,
             default_view: "split".to_string(),
+            color_mode: "auto".to_string(),
         }
     }
 }
@@ -124,10 +205,33 @@ impl Default for MarkdownConfig {
     }
 }
 
+impl Default for LintConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            line_length: lint::DEFAULT_LINE_LENGTH,
+            rules: BTreeMap::new(),
+        }
+    }
+}
+
+impl Default for FormatConfig {
+    fn default() -> Self {
+        Self {
+            on_save: false,
+            width: 0,
+            list_marker: '-',
+            emphasis: '*',
+            strong: '*',
+            heading_style: HeadingStyle::Atx,
+        }
+    }
+}
+
 impl Config {
     /// Load configuration from file
     pub fn load(path: Option<&Path>) -> Result<Self> {
-        let config_path = path.map(PathBuf::from).or_else(Self::default_path);
+        let config_path = Self::resolve_path(path);
 
         if let Some(path) = config_path {
             if path.exists() {
@@ -143,7 +247,7 @@ impl Config {
     /// Save configuration to file (used in v0.2+ for settings UI)
     #[allow(dead_code)]
     pub fn save(&self, path: Option<&Path>) -> Result<()> {
-        let config_path = path.map(PathBuf::from).or_else(Self::default_path);
+        let config_path = Self::resolve_path(path);
 
         if let Some(path) = config_path {
             if let Some(parent) = path.parent() {
@@ -156,6 +260,77 @@ impl Config {
         Ok(())
     }
 
+    /// Editor rendering options described by this configuration
+    pub fn editor_style(&self) -> EditorStyle {
+        EditorStyle {
+            line_numbers: self.ui.line_numbers,
+            relative_line_numbers: self.ui.relative_line_numbers,
+            minimap: self.ui.minimap,
+            highlight_line: self.ui.highlight_line,
+            indent_guides: self.ui.indent_guides,
+            tab_size: self.editor.tab_size,
+            insert_spaces: self.editor.use_spaces,
+            soft_wrap: self.editor.soft_wrap,
+            ..EditorStyle::default()
+        }
+    }
+
+    /// Preview rendering options described by this configuration
+    pub fn preview_options(&self) -> PreviewOptions {
+        PreviewOptions {
+            math: self.markdown.enable_math,
+            mermaid: self.markdown.enable_mermaid,
+        }
+    }
+
+    /// Linter described by this configuration, if linting is enabled
+    pub fn linter(&self) -> Option<Linter> {
+        if !self.lint.enabled {
+            return None;
+        }
+        let mut linter = Linter::new().line_length(self.lint.line_length);
+        for (rule, level) in &self.lint.rules {
+            if !lint::RULES.iter().any(|(name, _)| name == rule) {
+                log::warn!("Unknown lint rule: {}", rule);
+            }
+            let severity = match level {
+                RuleLevel::Off => None,
+                RuleLevel::Warning => Some(Severity::Warning),
+                RuleLevel::Error => Some(Severity::Error),
+            };
+            linter = linter.rule(rule, severity);
+        }
+        Some(linter)
+    }
+
+    /// Formatter described by this configuration
+    pub fn formatter(&self) -> Formatter {
+        let format = &self.format;
+        if !matches!(format.list_marker, '-' | '*' | '+') {
+            log::warn!("Invalid list marker: {}", format.list_marker);
+        }
+        for delimiter in [format.emphasis, format.strong] {
+            if !matches!(delimiter, '*' | '_') {
+                log::warn!("Invalid emphasis delimiter: {}", delimiter);
+            }
+        }
+        let heading_style = match format.heading_style {
+            HeadingStyle::Atx => patina_core::HeadingStyle::Atx,
+            HeadingStyle::Setext => patina_core::HeadingStyle::Setext,
+        };
+        Formatter::new()
+            .width(format.width)
+            .list_marker(format.list_marker)
+            .emphasis(format.emphasis)
+            .strong(format.strong)
+            .heading_style(heading_style)
+    }
+
+    /// Config file to use: the given path, or the default location
+    pub fn resolve_path(path: Option<&Path>) -> Option<PathBuf> {
+        path.map(PathBuf::from).or_else(Self::default_path)
+    }
+
     /// Get the default config path
     fn default_path() -> Option<PathBuf> {
         ProjectDirs::from("com", "patina", "patina")
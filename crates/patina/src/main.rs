@@ -3,21 +3,28 @@
 //! TUI (Terminal User Interface) version
 
 use anyhow::Result;
-use clap::Parser;
+use clap::{Parser, Subcommand};
 use std::path::PathBuf;
 
 mod app;
+mod commands;
 mod config;
 mod input;
 mod ui;
+mod watcher;
 
 use app::App;
+use commands::{ExportFormat, Setup};
 
 /// Patina - A fast, lightweight Markdown editor
 #[derive(Parser, Debug)]
 #[command(name = "patina")]
 #[command(author, version, about, long_about = None)]
+#[command(args_conflicts_with_subcommands = true)]
 struct Cli {
+    #[command(subcommand)]
+    command: Option<Commands>,
+
     /// Files to open
     #[arg(value_name = "FILE")]
     files: Vec<PathBuf>,
@@ -27,22 +34,78 @@ struct Cli {
     workspace: Option<PathBuf>,
 
     /// Use a specific config file
-    #[arg(short, long, value_name = "FILE")]
+    #[arg(short, long, value_name = "FILE", global = true)]
     config: Option<PathBuf>,
 
     /// Set the color theme
-    #[arg(short, long)]
+    #[arg(short, long, global = true)]
     theme: Option<String>,
 
+    /// Terminal colors: auto, truecolor, 256, 16 or mono
+    #[arg(long, value_name = "MODE", global = true)]
+    color: Option<String>,
+
     /// Start in Zen mode
     #[arg(long)]
     zen: bool,
 
     /// Enable debug logging
-    #[arg(long)]
+    #[arg(long, global = true)]
     debug: bool,
 }
 
+/// Commands that run without the editor
+#[derive(Subcommand, Debug)]
+enum Commands {
+    /// Print the rendered preview of a file with terminal colors
+    Render {
+        /// Markdown file, or `-` for stdin
+        file: PathBuf,
+
+        /// Wrap width (default: terminal width, or 80)
+        #[arg(short, long)]
+        width: Option<u16>,
+
+        /// Print directly instead of through `$PAGER`
+        #[arg(long)]
+        no_pager: bool,
+    },
+
+    /// Convert a file to a standalone HTML page or plain text
+    Export {
+        /// Markdown file, or `-` for stdin
+        file: PathBuf,
+
+        /// Output format
+        #[arg(long, value_enum, default_value = "html")]
+        to: ExportFormat,
+
+        /// Output file (default: stdout)
+        #[arg(short, long, value_name = "FILE")]
+        output: Option<PathBuf>,
+
+        /// Escape raw HTML and drop script links
+        #[arg(long)]
+        sanitize: bool,
+
+        /// Wrap width for text output
+        #[arg(short, long)]
+        width: Option<u16>,
+    },
+
+    /// Check files for invalid frontmatter, broken links and diagram errors,
+    /// exiting with status 1 if any are found
+    Check {
+        /// Markdown files
+        #[arg(required = true)]
+        files: Vec<PathBuf>,
+
+        /// Fail on warnings too
+        #[arg(long)]
+        strict: bool,
+    },
+}
+
 fn main() -> Result<()> {
     let cli = Cli::parse();
 
@@ -58,13 +121,46 @@ fn main() -> Result<()> {
     // Load configuration
     let config = config::Config::load(cli.config.as_deref())?;
 
+    if let Some(command) = cli.command {
+        let config_path = config::Config::resolve_path(cli.config.as_deref());
+        let setup = Setup::new(config, config_path.as_deref(), cli.theme.as_deref());
+        return match command {
+            Commands::Render {
+                file,
+                width,
+                no_pager,
+            } => commands::render(&setup, &file, width, cli.color.as_deref(), !no_pager),
+            Commands::Export {
+                file,
+                to,
+                output,
+                sanitize,
+                width,
+            } => commands::export(&setup, &file, to, output.as_deref(), sanitize, width),
+            Commands::Check { files, strict } => {
+                if !commands::check(&setup, &files, strict)? {
+                    std::process::exit(1);
+                }
+                Ok(())
+            }
+        };
+    }
+
     // Create and run the app
     let mut app = App::new(config)?;
 
+    // Load user themes and pick up config and theme edits while running
+    if let Some(path) = config::Config::resolve_path(cli.config.as_deref()) {
+        app.watch_config(path);
+    }
+
     // Apply CLI options
     if let Some(theme) = cli.theme {
         app.set_theme(&theme);
     }
+    if let Some(mode) = cli.color {
+        app.set_color_mode(&mode);
+    }
     if cli.zen {
         app.toggle_zen_mode();
     }
@@ -8,7 +8,11 @@ use ratatui::{
     Frame,
 };
 
-use patina_render::tui::{App, EditorWidget, PreviewWidget, ViewMode};
+use patina_render::tui::{
+    App, CompletionWidget, DiagnosticsWidget, EditorWidget, MinimapWidget, OutlineWidget,
+    PreviewWidget, ThemePickerWidget, ViewMode,
+};
+use patina_render::{wrap, WrapOptions};
 
 /// Draw the entire UI
 pub fn draw(frame: &mut Frame, app: &App) {
@@ -17,18 +21,36 @@ pub fn draw(frame: &mut Frame, app: &App) {
     } else {
         draw_normal_mode(frame, app);
     }
+
+    if let Some(picker) = &app.theme_picker {
+        frame.render_widget(ThemePickerWidget::new(picker, &app.theme), frame.area());
+    }
+
+    // Downsample to what the terminal can show
+    app.color_mode.apply(frame.buffer_mut(), &app.theme);
 }
 
-/// Draw normal mode UI
-fn draw_normal_mode(frame: &mut Frame, app: &App) {
-    let chunks = Layout::default()
+/// Width of the minimap column
+const MINIMAP_WIDTH: u16 = 10;
+
+/// Most rows the problems panel takes, borders included
+const PROBLEMS_MAX_HEIGHT: u16 = 8;
+
+/// Split the frame into tab bar, editor area and status bar
+fn normal_mode_chunks(area: Rect) -> std::rc::Rc<[Rect]> {
+    Layout::default()
         .direction(Direction::Vertical)
         .constraints([
             Constraint::Length(1), // Tab bar
             Constraint::Min(1),    // Editor area
             Constraint::Length(1), // Status bar
         ])
-        .split(frame.area());
+        .split(area)
+}
+
+/// Draw normal mode UI
+fn draw_normal_mode(frame: &mut Frame, app: &App) {
+    let chunks = normal_mode_chunks(frame.area());
 
     draw_tab_bar(frame, chunks[0], app);
     draw_editor_area(frame, chunks[1], app);
@@ -40,7 +62,7 @@ fn draw_zen_mode(frame: &mut Frame, app: &App) {
     let area = frame.area();
 
     // Center the content
-    let zen_width = 80.min(area.width.saturating_sub(4));
+    let zen_width = (app.style.zen_width as u16).min(area.width.saturating_sub(4));
     let x_offset = (area.width.saturating_sub(zen_width)) / 2;
 
     let zen_area = Rect {
@@ -50,10 +72,158 @@ fn draw_zen_mode(frame: &mut Frame, app: &App) {
         height: area.height.saturating_sub(2),
     };
 
+    frame.render_widget(editor_widget(app), zen_area);
+    draw_completion(frame, app, zen_area);
+}
+
+/// Build the editor widget for the active document as it is drawn in the current mode
+pub fn editor_widget(app: &App) -> EditorWidget<'_> {
+    EditorWidget::new(app.active_document(), &app.theme)
+        .style(&app.style)
+        .code_highlighter(&app.code_highlighter)
+        .diagnostics(&app.diagnostics.items)
+        .line_numbers(app.style.line_numbers && !app.zen_mode)
+}
+
+/// Rows the problems panel takes under the editor area (0 when hidden)
+pub fn problems_height(app: &App) -> u16 {
+    if !app.diagnostics.visible || app.zen_mode {
+        return 0;
+    }
+    (app.diagnostics.items.len().max(1) as u16 + 2).min(PROBLEMS_MAX_HEIGHT)
+}
+
+/// Screen areas of the panes inside the editor area
+struct Panes {
+    problems: Option<Rect>,
+    outline: Option<Rect>,
+    editor: Option<Rect>,
+    minimap: Option<Rect>,
+    preview: Option<Rect>,
+}
+
+/// Split the editor area into outline sidebar, editor and preview panes
+fn layout_panes(app: &App, area: Rect) -> Panes {
+    // The problems panel runs along the bottom, under every pane
+    let (area, problems) = match problems_height(app) {
+        0 => (area, None),
+        height => {
+            let chunks = Layout::default()
+                .direction(Direction::Vertical)
+                .constraints([Constraint::Min(1), Constraint::Length(height)])
+                .split(area);
+            (chunks[0], Some(chunks[1]))
+        }
+    };
+
+    let (outline, main) = if app.outline.visible {
+        let width = 30.min(area.width / 3);
+        let chunks = Layout::default()
+            .direction(Direction::Horizontal)
+            .constraints([Constraint::Length(width), Constraint::Min(1)])
+            .split(area);
+        (Some(chunks[0]), chunks[1])
+    } else {
+        (None, area)
+    };
+
+    let (editor, preview) = match app.view_mode {
+        ViewMode::Raw => (Some(main), None),
+        ViewMode::Rendered => (None, Some(main)),
+        ViewMode::Split => {
+            let chunks = Layout::default()
+                .direction(Direction::Horizontal)
+                .constraints([Constraint::Percentage(50), Constraint::Percentage(50)])
+                .split(main);
+            (Some(chunks[0]), Some(chunks[1]))
+        }
+    };
+
+    // The minimap takes a column on the right of the editor
+    let (editor, minimap) = match editor {
+        Some(editor) if app.style.minimap => {
+            let width = MINIMAP_WIDTH.min(editor.width / 4);
+            let chunks = Layout::default()
+                .direction(Direction::Horizontal)
+                .constraints([Constraint::Min(1), Constraint::Length(width)])
+                .split(editor);
+            (Some(chunks[0]), Some(chunks[1]))
+        }
+        editor => (editor, None),
+    };
+
+    Panes {
+        problems,
+        outline,
+        editor,
+        minimap,
+        preview,
+    }
+}
+
+/// Screen area of the minimap for a frame of the given size, if one is shown
+pub fn minimap_area(app: &App, frame_area: Rect) -> Option<Rect> {
+    if app.zen_mode {
+        return None;
+    }
+    layout_panes(app, normal_mode_chunks(frame_area)[1]).minimap
+}
+
+/// Build the minimap for the active document with the editor viewport marked
+pub fn minimap_widget(app: &App, editor_height: u16) -> MinimapWidget<'_> {
     let doc = app.active_document();
-    let editor = EditorWidget::new(doc, &app.theme).line_numbers(false);
+    MinimapWidget::new(doc, &app.theme)
+        .tab_size(app.style.tab_size)
+        .viewport(doc.scroll_offset, editor_height as usize)
+}
 
-    frame.render_widget(editor, zen_area);
+/// Width of the editor pane for a terminal of the given width
+pub fn editor_pane_width(app: &App, terminal_width: u16) -> u16 {
+    if app.zen_mode {
+        return (app.style.zen_width as u16).min(terminal_width.saturating_sub(4));
+    }
+    let panes = layout_panes(app, Rect::new(0, 0, terminal_width, 1));
+    // In preview-only mode the cursor still moves as if the editor filled the preview
+    panes
+        .editor
+        .or(panes.preview)
+        .map_or(terminal_width, |r| r.width)
+}
+
+/// Wrap options matching how the editor pane is laid out for a terminal of the given width
+pub fn editor_wrap_options(app: &App, terminal_width: u16) -> WrapOptions {
+    let editor = editor_widget(app);
+    editor.wrap_options(editor.text_width(editor_pane_width(app, terminal_width)))
+}
+
+/// Width of the preview pane for a terminal of the given width
+pub fn preview_pane_width(app: &App, terminal_width: u16) -> u16 {
+    let panes = layout_panes(app, Rect::new(0, 0, terminal_width, 1));
+    panes.preview.map_or(terminal_width, |r| r.width)
+}
+
+/// Source line (1-indexed) the preview should follow, and the rows above it in the editor
+pub fn preview_anchor(app: &App, terminal_width: u16) -> (usize, usize) {
+    let doc = app.active_document();
+    let options = editor_wrap_options(app, terminal_width);
+    let rows = wrap::rows_until(&doc.buffer, doc.scroll_offset, doc.cursor, &options);
+    (doc.cursor.0 + 1, rows.saturating_sub(1))
+}
+
+/// Build the preview widget, following the editor unless the preview was scrolled directly
+fn preview_widget(app: &App, terminal_width: u16) -> PreviewWidget<'_> {
+    let doc = app.active_document();
+    match app.preview_scroll {
+        Some(offset) => PreviewWidget::new(doc, &app.theme, offset),
+        None => {
+            let (source_line, rows_above) = preview_anchor(app, terminal_width);
+            PreviewWidget::new(doc, &app.theme, 0).follow_source(source_line, rows_above)
+        }
+    }
+    .code_highlighter(&app.code_highlighter)
+    .options(app.preview_options.clone())
+    .diagram_cache(&app.diagram_cache)
+    .source_map_cache(&app.source_map_cache)
 }
 
 /// Draw the tab bar
@@ -91,33 +261,57 @@ fn draw_tab_bar(frame: &mut Frame, area: Rect, app: &App) {
 
 /// Draw the editor area
 fn draw_editor_area(frame: &mut Frame, area: Rect, app: &App) {
-    let doc = app.active_document();
+    let terminal_width = frame.area().width;
+    let panes = layout_panes(app, area);
 
-    match app.view_mode {
-        ViewMode::Raw => {
-            let editor = EditorWidget::new(doc, &app.theme);
-            frame.render_widget(editor, area);
-        }
-        ViewMode::Rendered => {
-            // Preview only view
-            let preview = PreviewWidget::new(doc, &app.theme, doc.scroll_offset);
-            frame.render_widget(preview, area);
+    if let Some(outline_area) = panes.outline {
+        let doc = app.active_document();
+        let headings = doc.headings();
+        let outline = OutlineWidget::new(&headings, &app.outline, &app.theme, doc.cursor.0);
+        frame.render_widget(outline, outline_area);
+    }
+    if let Some(editor_area) = panes.editor {
+        frame.render_widget(editor_widget(app), editor_area);
+        if let Some(minimap_area) = panes.minimap {
+            frame.render_widget(minimap_widget(app, editor_area.height), minimap_area);
         }
-        ViewMode::Split => {
-            let chunks = Layout::default()
-                .direction(Direction::Horizontal)
-                .constraints([Constraint::Percentage(50), Constraint::Percentage(50)])
-                .split(area);
+        draw_completion(frame, app, editor_area);
+    }
+    if let Some(preview_area) = panes.preview {
+        frame.render_widget(preview_widget(app, terminal_width), preview_area);
+    }
+    if let Some(problems_area) = panes.problems {
+        let problems = DiagnosticsWidget::new(&app.diagnostics, &app.theme);
+        frame.render_widget(problems, problems_area);
+    }
+}
 
-            // Left: Raw editor
-            let editor = EditorWidget::new(doc, &app.theme);
-            frame.render_widget(editor, chunks[0]);
+/// Draw the completion popup, if open, under the start of the completed text
+/// in the editor pane
+fn draw_completion(frame: &mut Frame, app: &App, editor_area: Rect) {
+    let Some(completion) = &app.completion else {
+        return;
+    };
+    let doc = app.active_document();
+    let editor = editor_widget(app);
+    let options = editor.wrap_options(editor.text_width(editor_area.width));
+    let line = doc.buffer.line(completion.line).unwrap_or_default();
+    let layout = wrap::LineLayout::new(&line, &options);
 
-            // Right: Preview
-            let preview = PreviewWidget::new(doc, &app.theme, doc.scroll_offset);
-            frame.render_widget(preview, chunks[1]);
-        }
-    }
+    let position = (completion.line, completion.start);
+    let row = wrap::rows_until(&doc.buffer, doc.scroll_offset, position, &options);
+    let x = editor_area.x as usize
+        + editor.line_number_width() as usize
+        + layout.x_of_col(completion.start);
+    let y = editor_area.y as usize + row.saturating_sub(1);
+    let anchor = (
+        (x as u16).min(editor_area.right().saturating_sub(1)),
+        (y as u16).min(editor_area.bottom().saturating_sub(1)),
+    );
+    frame.render_widget(
+        CompletionWidget::new(completion, &app.theme, anchor),
+        frame.area(),
+    );
 }
 
 /// Draw the status bar
@@ -166,12 +360,18 @@ fn draw_status_bar(frame: &mut Frame, area: Rect, app: &App) {
             .and_then(|n| n.to_str())
             .unwrap_or("Untitled");
 
+        let problems = match app.diagnostics.counts() {
+            (0, 0) => String::new(),
+            (errors, warnings) => format!("✗ {} ⚠ {} │ ", errors, warnings),
+        };
+
         format!(
-            " {} {} │ Ln {}, Col {} │ {} │ {} ",
+            " {} {} │ Ln {}, Col {} │ {}{} │ {} ",
             if doc.is_modified() { "●" } else { "○" },
             filename,
             doc.cursor.0 + 1,
             doc.cursor.1 + 1,
+            problems,
             mode,
             app.theme.name,
         )
@@ -0,0 +1,143 @@
+//! Watching the configuration file and user themes for changes.
+
+use anyhow::Result;
+use notify::{Event, EventKind, RecommendedWatcher, RecursiveMode, Watcher};
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{channel, Receiver};
+
+/// Watches the config file and the `themes` and `syntaxes` directories next to it
+pub struct ConfigWatcher {
+    watcher: RecommendedWatcher,
+    events: Receiver<notify::Result<Event>>,
+    dir: PathBuf,
+    config_path: PathBuf,
+    themes_dir: PathBuf,
+    syntaxes_dir: PathBuf,
+    /// Directories currently watched
+    watched: Vec<PathBuf>,
+}
+
+impl ConfigWatcher {
+    /// Start watching `config_path` and the `themes` and `syntaxes`
+    /// directories beside it.
+    ///
+    /// The config's parent directory is watched rather than the file itself,
+    /// so editors that save by replacing the file are still noticed. Until it
+    /// exists its nearest existing ancestor is watched instead, and the
+    /// subdirectories are watched once they appear. No directories are
+    /// created.
+    pub fn new(config_path: &Path) -> Result<Self> {
+        let dir = config_path
+            .parent()
+            .filter(|p| !p.as_os_str().is_empty())
+            .unwrap_or(Path::new("."));
+        let dir = absolute_dir(dir)?;
+
+        let (tx, events) = channel();
+        let mut watcher = Self {
+            watcher: notify::recommended_watcher(tx)?,
+            events,
+            config_path: dir.join(config_path.file_name().unwrap_or_default()),
+            themes_dir: dir.join("themes"),
+            syntaxes_dir: dir.join("syntaxes"),
+            dir,
+            watched: Vec::new(),
+        };
+        watcher.update_watches()?;
+        Ok(watcher)
+    }
+
+    /// Drain pending events, returning whether the config or a theme changed
+    pub fn changed(&mut self) -> bool {
+        let mut changed = false;
+        let mut any = false;
+        while let Ok(event) = self.events.try_recv() {
+            any = true;
+            match event {
+                Ok(event) if self.is_relevant(&event) => changed = true,
+                Ok(_) => {}
+                Err(e) => log::warn!("Config watch error: {}", e),
+            }
+        }
+
+        // Directories may have come or gone. Files written into a new one
+        // before its watch started are only seen by reloading.
+        if any {
+            match self.update_watches() {
+                Ok(added) => {
+                    changed |= added.iter().any(|dir| {
+                        *dir == self.themes_dir
+                            || *dir == self.syntaxes_dir
+                            || (*dir == self.dir && self.config_path.exists())
+                    });
+                }
+                Err(e) => log::warn!("Config watch error: {}", e),
+            }
+        }
+        changed
+    }
+
+    /// The config directory with its `themes` and `syntaxes` directories, or
+    /// the nearest existing ancestor while the config directory is missing
+    fn wanted(&self) -> Vec<PathBuf> {
+        if !self.dir.is_dir() {
+            let ancestor = self.dir.ancestors().find(|p| p.is_dir());
+            return ancestor.map(Path::to_path_buf).into_iter().collect();
+        }
+        let subdirs = [&self.themes_dir, &self.syntaxes_dir]
+            .into_iter()
+            .filter(|sub| sub.is_dir())
+            .cloned();
+        std::iter::once(self.dir.clone()).chain(subdirs).collect()
+    }
+
+    /// Watch the wanted directories and stop watching the rest, returning the
+    /// newly watched ones
+    fn update_watches(&mut self) -> Result<Vec<PathBuf>> {
+        let wanted = self.wanted();
+        for old in self.watched.iter().filter(|dir| !wanted.contains(dir)) {
+            // Removed directories are no longer watched anyway
+            let _ = self.watcher.unwatch(old);
+        }
+        let mut added = Vec::new();
+        for dir in &wanted {
+            if !self.watched.contains(dir) {
+                self.watcher.watch(dir, RecursiveMode::NonRecursive)?;
+                added.push(dir.clone());
+            }
+        }
+        self.watched = wanted;
+        Ok(added)
+    }
+
+    /// Whether an event modifies the config file, a theme file or a syntax definition
+    fn is_relevant(&self, event: &Event) -> bool {
+        if !matches!(
+            event.kind,
+            EventKind::Create(_) | EventKind::Modify(_) | EventKind::Remove(_)
+        ) {
+            return false;
+        }
+        event.paths.iter().any(|path| {
+            let has_extension = |extensions: &[&str]| {
+                path.extension()
+                    .is_some_and(|ext| extensions.iter().any(|e| ext == *e))
+            };
+            *path == self.config_path
+                || (path.starts_with(&self.themes_dir) && has_extension(&["toml", "tmTheme"]))
+                || (path.starts_with(&self.syntaxes_dir) && has_extension(&["sublime-syntax"]))
+        })
+    }
+}
+
+/// `dir` as an absolute path. Events carry canonical paths, so the part that
+/// exists is canonicalized and the missing rest appended to it.
+fn absolute_dir(dir: &Path) -> Result<PathBuf> {
+    let dir = std::path::absolute(dir)?;
+    let existing = dir
+        .ancestors()
+        .find(|p| p.exists())
+        .unwrap_or(Path::new("/"));
+    let missing = dir.strip_prefix(existing)?;
+    Ok(existing.canonicalize()?.join(missing))
+}